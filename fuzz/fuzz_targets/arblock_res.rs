@@ -0,0 +1,13 @@
+#![no_main]
+
+// the protocol module is shared with the crate, which is a binary only
+#[allow(dead_code, unused_imports)]
+#[path = "../../src/protocol/mod.rs"]
+mod protocol;
+
+use libfuzzer_sys::fuzz_target;
+use protocol::{ArBlockRes, Decode};
+
+fuzz_target!(|datagram: &[u8]| {
+    let _ = ArBlockRes::from_bytes(datagram);
+});
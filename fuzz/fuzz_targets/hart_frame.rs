@@ -0,0 +1,16 @@
+#![no_main]
+
+// the protocol module is shared with the crate, which is a binary only
+#[allow(dead_code, unused_imports)]
+#[path = "../../src/protocol/mod.rs"]
+mod protocol;
+
+use libfuzzer_sys::fuzz_target;
+use protocol::{Decode, HartFrame};
+
+fuzz_target!(|datagram: &[u8]| {
+    if let Ok(frame) = HartFrame::from_bytes(datagram) {
+        let _ = frame.command_number();
+        let _ = HartFrame::split_expansion(&frame.data);
+    }
+});
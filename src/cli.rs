@@ -1,15 +1,18 @@
 use anyhow::anyhow;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::{path::PathBuf, str::FromStr};
 
 #[derive(Parser, Debug)]
 pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
     /// mode for working, see enum WorkingMode
     #[clap(short, long)]
     #[clap(default_value = "local")]
     pub mode: String,
 
-    /// config file path, used in local mode
+    /// config file path, used in local and simulator mode
     #[clap(short, long)]
     pub config: Option<PathBuf>,
 
@@ -18,6 +21,18 @@ pub struct Cli {
     #[clap(default_value_t = 30)]
     pub interval: u8,
 
+    /// maximum number of stations polled at the same time, the requests to
+    /// a station are always sent one after another
+    #[clap(long)]
+    #[clap(default_value_t = 16)]
+    pub concurrency: u16,
+
+    /// interval in seconds for sending the I&M identification of the
+    /// stations and modules, 0 to disable
+    #[clap(long)]
+    #[clap(default_value_t = 0)]
+    pub identification_interval: u16,
+
     /// connection string of the Iot Edge module,
     /// used in iotedge mode, leave empty to use Iot Edge
     /// environment variables
@@ -30,12 +45,56 @@ pub struct Cli {
     #[clap(short, long)]
     #[clap(default_value = "")]
     pub src_ip_address: String,
+
+    /// station to capture the traffic of into a pcapng file, given by its
+    /// ip address or name_of_station, can be repeated
+    #[clap(long)]
+    pub capture: Vec<String>,
+
+    /// directory the captures are written to, one file per station
+    #[clap(long)]
+    #[clap(default_value = "capture")]
+    pub capture_dir: PathBuf,
+
+    /// size in MB a capture file is rotated at
+    #[clap(long)]
+    #[clap(default_value_t = 10)]
+    pub capture_file_size: u64,
+
+    /// number of capture files kept per station, the current one included
+    #[clap(long)]
+    #[clap(default_value_t = 5)]
+    pub capture_files: usize,
+
+    /// network interface the stations are connected to, for example `eth0`,
+    /// used to resolve name_of_station through DCP and in scan mode
+    #[clap(long)]
+    pub interface: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// decode a datagram with the parsers of the gateway and print it as a
+    /// tree, down to the HART frame of the PNIO record
+    Decode {
+        /// hex dump of a datagram, or path of a pcap or pcapng file
+        input: String,
+
+        /// print JSON instead of a tree
+        #[clap(long)]
+        json: bool,
+    },
 }
 
 #[derive(Clone, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum WorkingMode {
     IotEdgeMode,
     LocalMode,
+    /// SimulatorMode runs a simulated IO station instead of collecting data
+    SimulatorMode,
+    /// ScanMode lists the stations answering DCP Identify All and exits
+    ScanMode,
 }
 
 impl FromStr for WorkingMode {
@@ -45,7 +104,11 @@ impl FromStr for WorkingMode {
         match s {
             "iotedge" => Ok(Self::IotEdgeMode),
             "local" => Ok(Self::LocalMode),
-            _ => Err(anyhow!("only iotedge and local mode are supported")),
+            "simulator" => Ok(Self::SimulatorMode),
+            "scan" => Ok(Self::ScanMode),
+            _ => Err(anyhow!(
+                "only iotedge, local, simulator and scan mode are supported"
+            )),
         }
     }
 }
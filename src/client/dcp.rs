@@ -0,0 +1,154 @@
+use crate::protocol::{
+    DcpIdentifyRequest, DcpIdentifyResponse, DcpStation, Decode, Encode, DCP_RESPONSE_DELAY_FACTOR,
+};
+use crate::transport::{is_timeout, FrameTransport};
+use anyhow::anyhow;
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+/// DcpClient discovers the stations of the network segment with DCP Identify,
+/// i.e. their NameOfStation, vendor and device id and ip address.
+pub struct DcpClient {
+    transport: Box<dyn FrameTransport>,
+    xid: Cell<u32>,
+    /// response_window is how long the responses are collected, the stations
+    /// spread their responses over the response delay of the request
+    pub response_window: Duration,
+}
+
+impl DcpClient {
+    pub fn new(transport: Box<dyn FrameTransport>) -> Self {
+        Self {
+            transport,
+            xid: Cell::new(0),
+            response_window: Duration::from_millis(DCP_RESPONSE_DELAY_FACTOR as u64 * 10 + 500),
+        }
+    }
+
+    /// identify_all returns every station answering Identify All
+    pub fn identify_all(&self) -> anyhow::Result<Vec<DcpStation>> {
+        self.identify(None)
+    }
+
+    /// identify_by_name returns the station with the NameOfStation, the first
+    /// response is taken as the name is unique within the network segment
+    pub fn identify_by_name(&self, name_of_station: &str) -> anyhow::Result<DcpStation> {
+        self.identify(Some(name_of_station))?
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("no station answered to name `{name_of_station}`"))
+    }
+
+    fn identify(&self, name_of_station: Option<&str>) -> anyhow::Result<Vec<DcpStation>> {
+        self.xid.set(self.xid.get().wrapping_add(1));
+        let xid = self.xid.get();
+
+        let request = DcpIdentifyRequest::new(self.transport.mac_address(), xid, name_of_station);
+        self.transport.send_frame(&request.to_bytes()?)?;
+
+        let mut stations: Vec<DcpStation> = vec![];
+        let deadline = Instant::now() + self.response_window;
+        while Instant::now() < deadline {
+            let frame = match self.transport.receive_frame() {
+                Ok(f) => f,
+                Err(err) if is_timeout(&err) => continue,
+                Err(err) => return Err(err),
+            };
+
+            // the socket receives all the PNIO frames, e.g. the responses
+            // of an earlier request or of another controller
+            let response = match DcpIdentifyResponse::from_bytes(&frame) {
+                Ok(r) if r.xid == xid && r.dst_mac == self.transport.mac_address() => r,
+                _ => continue,
+            };
+            log::debug!("DCP station: {:?}", response.station);
+
+            let matches_name = name_of_station
+                .is_none_or(|n| response.station.name_of_station.eq_ignore_ascii_case(n));
+            if !matches_name {
+                continue;
+            }
+            if name_of_station.is_some() {
+                return Ok(vec![response.station]);
+            }
+            if !stations
+                .iter()
+                .any(|s| s.mac_address == response.station.mac_address)
+            {
+                stations.push(response.station);
+            }
+        }
+
+        Ok(stations)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transport::MemoryFrameTransport;
+    use std::net::Ipv4Addr;
+
+    const MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+
+    fn get_station(i: u8) -> DcpStation {
+        DcpStation {
+            mac_address: [0x28, 0x63, 0x36, 0x00, 0x00, i],
+            name_of_station: format!("et200sp-{i}"),
+            type_of_station: "ET200SP".to_string(),
+            vendor_id: 0x002a,
+            device_id: 0x0313,
+            ip_address: Ipv4Addr::new(192, 168, 0, i),
+            subnet_mask: Ipv4Addr::new(255, 255, 255, 0),
+            gateway: Ipv4Addr::UNSPECIFIED,
+        }
+    }
+
+    // two stations answering like real ones, i.e. Identify All and their own
+    // name only, plus a stale response of another transaction
+    fn get_client() -> DcpClient {
+        let transport = MemoryFrameTransport::new(
+            MAC,
+            Box::new(|frame| {
+                let request = DcpIdentifyRequest::from_bytes(frame).unwrap();
+                let mut stale = DcpIdentifyRequest::from_bytes(frame).unwrap();
+                stale.xid -= 1;
+
+                let mut responses = vec![DcpIdentifyResponse::new(&stale, get_station(9))];
+                for station in [get_station(1), get_station(2)] {
+                    if request
+                        .name_of_station
+                        .as_ref()
+                        .is_none_or(|n| *n == station.name_of_station)
+                    {
+                        responses.push(DcpIdentifyResponse::new(&request, station));
+                    }
+                }
+                responses.iter().map(|r| r.to_bytes().unwrap()).collect()
+            }),
+        );
+
+        let mut client = DcpClient::new(Box::new(transport));
+        client.response_window = Duration::from_millis(50);
+        client
+    }
+
+    #[test]
+    fn identify_all_should_return_every_station() {
+        let client = get_client();
+
+        let stations = client.identify_all().unwrap();
+        assert_eq!(stations, vec![get_station(1), get_station(2)]);
+    }
+
+    #[test]
+    fn identify_by_name_should_return_the_station() {
+        let client = get_client();
+
+        let station = client.identify_by_name("et200sp-2").unwrap();
+        assert_eq!(station.ip_address, Ipv4Addr::new(192, 168, 0, 2));
+        assert!(client.identify_by_name("et200sp-3").is_err());
+    }
+}
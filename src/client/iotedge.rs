@@ -17,6 +17,11 @@ pub struct IotEdge {
     pub config: RwLock<Vec<Config>>,
 }
 
+// the handle is one of the convenience layer of the IoT Hub SDK, which locks
+// it on every call, the messages may be sent from the egress thread of the
+// worker
+unsafe impl Sync for IotEdge {}
+
 impl IotEdge {
     pub fn new(conn_str: Option<String>) -> anyhow::Result<Self> {
         // TODO: to support more transport protocols
@@ -1,25 +1,252 @@
 use super::sender::Sender;
-use crate::config::Config;
-use std::sync::RwLock;
+use crate::config::{Config, KafkaAcks, KafkaConfig, KafkaPartitionKey, LocalConfig};
+use anyhow::anyhow;
+use kafka::producer::{Producer, Record, RequiredAcks};
+use std::{
+    sync::{Mutex, RwLock},
+    time::Duration,
+};
 
-pub struct Kafka {}
+/// KafkaProducer is the part of the kafka client actually used by `Kafka`,
+/// it allows to swap the real producer with an in-process stand-in.
+pub trait KafkaProducer {
+    fn produce(&mut self, topic: &str, key: Option<&str>, value: &[u8]) -> anyhow::Result<()>;
+}
+
+impl KafkaProducer for Producer {
+    fn produce(&mut self, topic: &str, key: Option<&str>, value: &[u8]) -> anyhow::Result<()> {
+        match key {
+            Some(k) => self.send(&Record::from_key_value(topic, k.as_bytes(), value))?,
+            None => self.send(&Record::from_value(topic, value))?,
+        };
+
+        Ok(())
+    }
+}
+
+pub struct Kafka {
+    settings: KafkaConfig,
+    // the producer is created on setup (or on the first send), creating it
+    // requires the brokers to be reachable
+    producer: Mutex<Option<Box<dyn KafkaProducer + Send>>>,
+    pub config: RwLock<Vec<Config>>,
+}
 
 impl Kafka {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(local_config: LocalConfig) -> Self {
+        Self {
+            settings: local_config.kafka,
+            producer: Mutex::new(None),
+            config: RwLock::new(local_config.config),
+        }
+    }
+
+    /// with_producer creates the client on top of an already created producer,
+    /// for example an in-process stand-in used in tests.
+    pub fn with_producer(
+        local_config: LocalConfig,
+        producer: Box<dyn KafkaProducer + Send>,
+    ) -> Self {
+        Self {
+            settings: local_config.kafka,
+            producer: Mutex::new(Some(producer)),
+            config: RwLock::new(local_config.config),
+        }
+    }
+
+    fn create_producer(&self) -> anyhow::Result<Producer> {
+        let required_acks = match self.settings.acks {
+            KafkaAcks::None => RequiredAcks::None,
+            KafkaAcks::One => RequiredAcks::One,
+            KafkaAcks::All => RequiredAcks::All,
+        };
+
+        let producer = Producer::from_hosts(self.settings.brokers.clone())
+            .with_ack_timeout(Duration::from_millis(self.settings.ack_timeout_ms))
+            .with_required_acks(required_acks)
+            .create()?;
+
+        Ok(producer)
+    }
+
+    fn produce(&self, key: Option<&str>, data: String) -> anyhow::Result<()> {
+        let mut producer = self
+            .producer
+            .lock()
+            .map_err(|err| anyhow!("kafka producer lock poisoned: {err}"))?;
+
+        if producer.is_none() {
+            *producer = Some(Box::new(self.create_producer()?));
+        }
+
+        let key = match self.settings.partition_key {
+            KafkaPartitionKey::DeviceUniqueName => key,
+            KafkaPartitionKey::None => None,
+        };
+
+        log::info!("sending message to kafka topic `{}`", self.settings.topic);
+        log::debug!("↑ message: {:?}", data);
+
+        // producer is always set at this point
+        if let Some(p) = producer.as_mut() {
+            p.produce(self.settings.topic.as_str(), key, data.as_bytes())?;
+        }
+
+        Ok(())
     }
 }
 
 impl Sender for Kafka {
+    /// setup connects to the brokers, the gateway keeps on polling if they're
+    /// unreachable and the first send connects again
     fn setup(&self) -> anyhow::Result<()> {
-        todo!()
+        let mut producer = self
+            .producer
+            .lock()
+            .map_err(|err| anyhow!("kafka producer lock poisoned: {err}"))?;
+
+        if producer.is_none() {
+            log::info!("connecting to kafka brokers {:?}", self.settings.brokers);
+            match self.create_producer() {
+                Ok(p) => *producer = Some(Box::new(p)),
+                Err(err) => log::error!(
+                    "failed to connect to kafka brokers {:?}, retrying on the first send: {err}",
+                    self.settings.brokers
+                ),
+            }
+        }
+
+        Ok(())
     }
 
     fn send(&self, data: String) -> anyhow::Result<()> {
-        todo!()
+        self.produce(None, data)
+    }
+
+    fn send_with_key(&self, key: &str, data: String) -> anyhow::Result<()> {
+        self.produce(Some(key), data)
     }
 
     fn get_config(&self) -> &RwLock<Vec<Config>> {
-        todo!()
+        &self.config
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    type Records = Arc<Mutex<Vec<(String, Option<String>, Vec<u8>)>>>;
+
+    struct InMemoryProducer {
+        records: Records,
+    }
+
+    impl KafkaProducer for InMemoryProducer {
+        fn produce(&mut self, topic: &str, key: Option<&str>, value: &[u8]) -> anyhow::Result<()> {
+            self.records.lock().unwrap().push((
+                topic.to_string(),
+                key.map(|k| k.to_string()),
+                value.to_vec(),
+            ));
+            Ok(())
+        }
+    }
+
+    fn get_local_config(partition_key: &str) -> LocalConfig {
+        let content = format!(
+            r#"
+kafka:
+  brokers: ["localhost:9092"]
+  topic: "pnio_hart"
+  acks: all
+  partition_key: {partition_key}
+config:
+  - ip_address: "10.0.1.231"
+    port: 34964
+    device_name: "6ES7 155-6AU01-0BN0"
+    hart_devices:
+    - slot_number: 1
+      subslot_number: 1
+      hart_commands:
+        - number: 0
+      request_data_record_number: 80
+      response_data_record_number: 81
+      hart_device_name: "7MF4233-1FA10-2AB6-Z"
+"#
+        );
+
+        LocalConfig::deserialize(content.as_str()).unwrap()
+    }
+
+    #[test]
+    fn deserialize_local_config_should_ok() {
+        let local_config = get_local_config("device_unique_name");
+
+        assert_eq!(local_config.kafka.brokers, vec!["localhost:9092"]);
+        assert_eq!(local_config.kafka.acks, KafkaAcks::All);
+        assert_eq!(local_config.kafka.ack_timeout_ms, 1000);
+        assert_eq!(local_config.config.len(), 1);
+    }
+
+    #[test]
+    fn send_with_key_should_use_device_unique_name_as_key() {
+        let records: Records = Default::default();
+        let producer = InMemoryProducer {
+            records: records.clone(),
+        };
+        let kafka =
+            Kafka::with_producer(get_local_config("device_unique_name"), Box::new(producer));
+
+        kafka
+            .send_with_key("10.0.1.231-1-1", "{}".to_string())
+            .unwrap();
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].0, "pnio_hart");
+        assert_eq!(records[0].1.as_deref(), Some("10.0.1.231-1-1"));
+        assert_eq!(records[0].2, b"{}");
+        assert_eq!(kafka.get_config().read().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn send_with_key_should_skip_key_when_disabled() {
+        let records: Records = Default::default();
+        let producer = InMemoryProducer {
+            records: records.clone(),
+        };
+        let kafka = Kafka::with_producer(get_local_config("none"), Box::new(producer));
+
+        kafka
+            .send_with_key("10.0.1.231-1-1", "{}".to_string())
+            .unwrap();
+
+        assert_eq!(records.lock().unwrap()[0].1, None);
+    }
+
+    #[test]
+    fn setup_should_leave_unreachable_brokers_to_the_first_send() {
+        let mut local_config = get_local_config("device_unique_name");
+        // nothing listens on port 1
+        local_config.kafka.brokers = vec!["127.0.0.1:1".to_string()];
+        let kafka = Kafka::new(local_config);
+
+        kafka.setup().unwrap();
+        assert!(kafka.producer.lock().unwrap().is_none());
+        assert!(kafka
+            .send_with_key("10.0.1.231-1-1", "{}".to_string())
+            .is_err());
+    }
+
+    #[test]
+    #[ignore = "requires a kafka broker listening on localhost:9092"]
+    fn send_to_local_broker() {
+        let kafka = Kafka::new(get_local_config("device_unique_name"));
+        kafka.setup().unwrap();
+        kafka
+            .send_with_key("10.0.1.231-1-1", "{}".to_string())
+            .unwrap();
     }
 }
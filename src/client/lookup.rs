@@ -1,91 +1,172 @@
-use crate::device::pnio_device::PnioDevice;
+use super::dcp::DcpClient;
+use crate::device::station_session::StationSession;
 use crate::protocol::{
-    DceRpcEpmRequest, DceRpcEpmResponse, DceRpcPacket, InterfaceVersion, OpNum, Packet, PacketType,
-    TowerFloorProtocol, INTERFACE,
+    DceRpcEpmRequest, DceRpcEpmResponse, DceRpcPacket, Decode, Encode, InterfaceVersion, OpNum,
+    PacketType, INTERFACE,
 };
-use crate::transport::{TransportClient, UdpClient};
+use crate::transport::{CaptureClient, CaptureSettings, TransportClient, UdpClient};
 use anyhow::anyhow;
 use std::cell::Cell;
 use std::net::Ipv4Addr;
-use std::thread;
-use std::time;
 use uuid::Uuid;
 
 #[derive(Debug)]
 pub struct LookupClient {
     dcerpc_seq_num: Cell<u32>,
+    /// capture is set to write the traffic of the station into a pcapng file
+    pub capture: Option<CaptureSettings>,
 }
 
 pub type TargetDeviceUniqueName<'a> = &'a str;
 pub type TargetIpAddr<'a> = &'a str;
 pub type TargetLookupPort = u16;
-pub type TargetSlotNum = u16;
-pub type TargetSubslotNum = u16;
-pub type TargetRequestDataRecordNumber = u16;
-pub type TargetResponseDataRecordNumber = u16;
-pub type TargetHartDeviceName<'a> = &'a str;
 
 impl<'a> LookupClient {
-    pub const MAX_RETRY: u8 = 10;
+    /// MAX_LOOKUP_REQUESTS bounds the enumeration in case the endpoint mapper
+    /// never reports the end of the list
+    pub const MAX_LOOKUP_REQUESTS: u8 = 64;
 
     pub fn new() -> Self {
         LookupClient {
             dcerpc_seq_num: Cell::new(0),
+            capture: None,
         }
     }
 
-    /// lookup looks for the pnio device which able to meet target arguments, then
-    /// parse response and then create pnio_device containing neccessary information
-    /// for subsequent profinet hart requests.
-    pub fn lookup(
+    /// lookup looks for the station which able to meet target arguments, then
+    /// parse response and then create the station session containing neccessary
+    /// information for subsequent profinet hart requests of all its channels.
+    pub async fn lookup(
         &self,
         src_ip: Ipv4Addr,
         target: (
             TargetDeviceUniqueName<'a>,
             TargetIpAddr<'a>,
             TargetLookupPort,
-            TargetSlotNum,
-            TargetSubslotNum,
-            TargetRequestDataRecordNumber,
-            TargetResponseDataRecordNumber,
-            TargetHartDeviceName,
         ),
-    ) -> anyhow::Result<PnioDevice> {
-        let mut retry = 0;
-        let mut dcerpc_epm_response: DceRpcEpmResponse;
-        let mut dcerpc_response: DceRpcPacket;
-
-        // initial handle is 0, real handle will be set after performed DCE/RPC
-        // endpoint mapper request
-        let mut default_handle: [u8; 20] = [0x00; 20];
-
+    ) -> anyhow::Result<StationSession> {
         // destination ip
         let dest_ip = target.1.parse::<Ipv4Addr>()?;
 
         // TODO: abstract the udp client
-        let udp_client = UdpClient::new(src_ip, dest_ip, target.2)?;
+        let udp_client = UdpClient::new(src_ip, dest_ip, target.2).await?;
         log::debug!("{:?}", udp_client);
+        let transport_client: Box<dyn TransportClient> = match &self.capture {
+            Some(capture) => {
+                let src_addr = udp_client.local_addr()?;
+                Box::new(CaptureClient::new(
+                    Box::new(udp_client),
+                    src_addr,
+                    capture.writer(target.1)?,
+                )?)
+            }
+            None => Box::new(udp_client),
+        };
 
-        let target_device = format!("{}-{}-{}", target.1, target.3, target.4);
+        self.lookup_with(transport_client, target).await
+    }
 
-        loop {
-            log::debug!("try counter: {retry}, looking up device {target_device}");
+    /// lookup_with looks up the station through the transport, which is then
+    /// owned by the station session, e.g. to replay the traffic of a station.
+    pub async fn lookup_with(
+        &self,
+        transport_client: Box<dyn TransportClient>,
+        target: (
+            TargetDeviceUniqueName<'a>,
+            TargetIpAddr<'a>,
+            TargetLookupPort,
+        ),
+    ) -> anyhow::Result<StationSession> {
+        let dest_ip = target.1.parse::<Ipv4Addr>()?;
+        let target_station = target.1;
 
-            if retry >= Self::MAX_RETRY {
-                return Err(anyhow!(
-                    "failed for device {target_device}, ignoring this device"
-                ));
-            }
+        log::debug!("looking up station {target_station}");
+        let dcerpc_epm_responses = self.enumerate(transport_client.as_ref()).await?;
+
+        // TODO: how do I know if an interface is a PNIO interface?
+        // the workaround is if the response entry's object is [0x00; 16]
+        // then consider this entry is not pnio, which should be skipped.
+        let (handle, entry) = dcerpc_epm_responses
+            .iter()
+            .flat_map(|r| r.entries.iter().map(move |e| (&r.handle, e)))
+            .filter(|(_, e)| e.object != Uuid::from_bytes([0x00; 16]))
+            // match the device_name
+            .find(|(_, e)| e.tower_pointer.annotation.contains(target.0))
+            .ok_or(anyhow!(
+                "failed for station {target_station}, no interface matches `{}`, ignoring this station",
+                target.0
+            ))?;
+
+        // TODO: there will be multiple floors, some of the floors has UUID,
+        // how do I know which floor has the PNIO interface?
+        // for now assume floor 1 is always PNIO.
+        let interface_uuid = entry
+            .interface_uuid()
+            .ok_or(anyhow!("interface uuid cannot be found"))?;
+        let port = entry.udp_port().ok_or(anyhow!("port cannot be found"))?;
+
+        // update the destination port
+        transport_client.update_dest(dest_ip, port)?;
+
+        // create the station session to be used in the subsequent operation
+        let station_session = StationSession::new(
+            handle.clone(),
+            entry.object,
+            interface_uuid,
+            transport_client.get_dst_conn_details().unwrap().0,
+            port,
+            transport_client,
+        );
+
+        log::debug!("found station `{target_station}`, proceed...");
+
+        Ok(station_session)
+    }
+
+    /// resolve looks for the station with the NameOfStation through DCP and
+    /// returns its ip address, to be looked up with `lookup` then.
+    pub fn resolve(
+        &self,
+        dcp_client: &DcpClient,
+        name_of_station: &str,
+    ) -> anyhow::Result<Ipv4Addr> {
+        let station = dcp_client.identify_by_name(name_of_station)?;
+        if station.ip_address.is_unspecified() {
+            return Err(anyhow!("station `{name_of_station}` has no ip address set"));
+        }
+
+        log::debug!(
+            "resolved station `{name_of_station}` to {}",
+            station.ip_address
+        );
+        Ok(station.ip_address)
+    }
+
+    /// enumerate reads all the entries registered in the endpoint mapper,
+    /// following the context handle until the end of the list.
+    pub async fn enumerate(
+        &self,
+        transport_client: &dyn TransportClient,
+    ) -> anyhow::Result<Vec<DceRpcEpmResponse>> {
+        let mut dcerpc_epm_responses: Vec<DceRpcEpmResponse> = vec![];
+
+        // initial handle is 0, real handle will be set after performed DCE/RPC
+        // endpoint mapper request
+        let mut handle: [u8; 20] = [0x00; 20];
+        // all the requests of an enumeration belong to the same activity
+        let activity = Uuid::new_v4();
+
+        for request in 0..Self::MAX_LOOKUP_REQUESTS {
+            log::debug!("lookup request {request}, handle: {:?}", handle);
 
             // DCE/RPC endpoint mapper packet
-            let dcerpc_epm_request = DceRpcEpmRequest::new(default_handle);
+            let dcerpc_epm_request = DceRpcEpmRequest::new(handle);
             let packet_type = PacketType::Request;
             let obj_uuid = Uuid::from_slice(&[0x00; 16]).unwrap();
             let interface = Uuid::parse_str(INTERFACE).unwrap();
             let interface_ver = InterfaceVersion::Lookup;
-            let activity = Uuid::new_v4();
             let opnum = OpNum::Read;
-            let data = dcerpc_epm_request.concat()?.into_boxed_slice();
+            let data = dcerpc_epm_request.to_bytes()?.into_boxed_slice();
             // DCE/RPC packet
             let dcerpc_packet = DceRpcPacket::new(
                 packet_type,
@@ -97,105 +178,115 @@ impl<'a> LookupClient {
                 opnum,
                 data,
             );
+            self.dcerpc_seq_num.set(self.dcerpc_seq_num.get() + 1);
 
-            log::debug!("sending dcerpc packet to {target_device}");
-            udp_client.send(dcerpc_packet.concat()?.into_boxed_slice())?;
-
-            let raw_response = udp_client.receive()?;
-            log::debug!("raw response: {:?}", raw_response);
-            let raw_response_vec = raw_response.into_vec();
-            dcerpc_response = TryInto::<DceRpcPacket>::try_into(raw_response_vec)?;
+            let dcerpc_response = transport_client.call_dcerpc(&dcerpc_packet).await?;
             log::debug!("dcerpc response: {:?}", dcerpc_response);
 
-            let dcerpc_epm_response_arr = dcerpc_response.data;
-            dcerpc_epm_response =
-                TryInto::<DceRpcEpmResponse>::try_into(&*dcerpc_epm_response_arr)?;
+            let dcerpc_epm_response = DceRpcEpmResponse::from_bytes(&dcerpc_response.data)?;
             log::debug!("dcerpc_epm response: {:?}", dcerpc_epm_response);
 
-            default_handle =
-                TryInto::<[u8; 20]>::try_into(hex::decode(&dcerpc_epm_response.handle)?).unwrap();
-            log::debug!("default_handle: {:?}", default_handle);
+            let end_of_list = dcerpc_epm_response.is_end_of_list();
+            handle = dcerpc_epm_response.handle_bytes()?;
+            dcerpc_epm_responses.push(dcerpc_epm_response);
 
-            // TODO: how do I know if an interface is a PNIO interface?
-            // the workaround is if the response entry's object is [0x00; 16]
-            // then consider this entry is not pnio, which should be skipped.
-            if dcerpc_epm_response.entry.object == Uuid::from_bytes([0x00; 16]) {
-                self.next(&mut retry);
-                continue;
+            if end_of_list {
+                return Ok(dcerpc_epm_responses);
             }
+        }
+
+        log::warn!(
+            "endpoint mapper did not end the list after {} requests",
+            Self::MAX_LOOKUP_REQUESTS
+        );
 
-            let tower_pointer = dcerpc_epm_response.entry.tower_pointer;
+        Ok(dcerpc_epm_responses)
+    }
+}
 
-            // match the device_name
-            let annotation = tower_pointer.annotation;
-            if annotation.contains(target.0) {
-                // TODO: there will be multiple floors, some of the floors has UUID,
-                // how do I know which floor has the PNIO interface?
-                // for now assume floor 1 is always PNIO.
-                let interface_uuid = match tower_pointer.floors[0].uuid {
-                    Some(i) => i,
-                    None => {
-                        log::error!("interface uuid cannot be found");
-                        self.next(&mut retry);
-                        continue;
-                    }
-                };
-
-                let port = match tower_pointer
-                    .floors
-                    .iter()
-                    .filter(|floor| matches!(floor.protocol, TowerFloorProtocol::Udp))
-                    .last()
-                    .and_then(|floor| floor.udp_port)
-                {
-                    Some(p) => p,
-                    None => {
-                        log::error!("port cannot be found");
-                        self.next(&mut retry);
-                        continue;
-                    }
-                };
-
-                // update the destination port
-                if let Err(err) = udp_client.update_dest(dest_ip, port) {
-                    log::error!("failed to update udp client's destination: {err}");
-                    continue;
-                };
-
-                // create pnio_device to be used in the subsequent operation
-                let pnio_device = PnioDevice::new(
-                    dcerpc_epm_response.handle,
-                    dcerpc_epm_response.entry.object,
-                    interface_uuid,
-                    port,
-                    // default device_id, to be set after performed HART command 0
-                    [0x00; 5],
-                    udp_client.get_dst_conn_details().unwrap().0,
-                    Box::new(udp_client),
-                    target.3,
-                    target.4,
-                    0x04,
-                    target.5,
-                    target.6,
-                    target.7.to_string(),
-                );
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::simulator::{SimulatedInterface, Simulator, SimulatorConfig};
 
-                log::debug!("found pnio device `{target_device}`, proceed...");
+    #[tokio::test]
+    async fn lookup_should_enumerate_all_interfaces() {
+        // more interfaces than a single lookup request returns, the target is the last one
+        let mut interfaces: Vec<SimulatedInterface> = (0..20)
+            .map(|i| SimulatedInterface {
+                object_uuid: Uuid::from_u128(0xdea00000_6c97_11d1_8271_000000000000 + i),
+                annotation: format!("OTHER STATION {i}"),
+                ..Default::default()
+            })
+            .collect();
+        interfaces[19].annotation = "TARGET STATION".to_string();
+        let simulator = Simulator::start(SimulatorConfig {
+            bind_address: "127.0.0.2:0".to_string(),
+            interfaces,
+            ..Default::default()
+        })
+        .unwrap();
+        let port = simulator.local_addr().port();
 
-                break Ok(pnio_device);
-            }
+        let client = LookupClient::new();
+        let udp_client = UdpClient::new(
+            Ipv4Addr::new(127, 0, 0, 2),
+            Ipv4Addr::new(127, 0, 0, 2),
+            port,
+        )
+        .await
+        .unwrap();
+        let responses = client.enumerate(&udp_client).await.unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses.iter().map(|r| r.entries.len()).sum::<usize>(), 20);
+        drop(udp_client);
 
-            self.next(&mut retry);
-        }
+        let station_session = client
+            .lookup(
+                Ipv4Addr::new(127, 0, 0, 2),
+                ("TARGET STATION", "127.0.0.2", port),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            station_session.object_uuid,
+            Uuid::from_u128(0xdea00000_6c97_11d1_8271_000000000013)
+        );
+        assert_eq!(station_session.port, port);
     }
 
-    fn next(&self, retry: &mut u8) {
-        log::debug!(
-            "try counter: {}, looking up device not found, wait for next try",
-            retry
-        );
-        thread::sleep(time::Duration::from_secs(1));
-        self.dcerpc_seq_num.set(self.dcerpc_seq_num.get() + 1);
-        *retry += 1;
+    #[tokio::test]
+    async fn read_implicit_should_not_need_ar() {
+        let simulator = Simulator::start(SimulatorConfig {
+            bind_address: "127.0.0.5:0".to_string(),
+            interfaces: vec![SimulatedInterface {
+                annotation: "TARGET STATION".to_string(),
+                ..Default::default()
+            }],
+            channels: vec![Default::default()],
+            records: vec![],
+        })
+        .unwrap();
+        let port = simulator.local_addr().port();
+
+        let station_session = LookupClient::new()
+            .lookup(
+                Ipv4Addr::new(127, 0, 0, 5),
+                ("TARGET STATION", "127.0.0.5", port),
+            )
+            .await
+            .unwrap();
+
+        // HART response record, nothing requested yet
+        let record = station_session.read_implicit_req(1, 1, 81).await.unwrap();
+        assert_eq!(&record[..], &[0x00, 0x00]);
+        assert_eq!(simulator.ar_uuid(), None);
+
+        // unknown record index
+        let err = station_session
+            .read_implicit_req(1, 1, 0x1234)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid index"));
     }
 }
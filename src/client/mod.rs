@@ -1,4 +1,5 @@
-mod lookup;
+pub mod dcp;
+pub mod lookup;
 pub mod worker;
 pub mod sender;
 pub mod iotedge;
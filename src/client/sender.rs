@@ -1,8 +1,15 @@
-use std::sync::RwLock;
 use crate::config::Config;
+use std::sync::RwLock;
 
-pub trait Sender {
+/// Sender is the output of the messages, it's shared with the egress thread
+/// of the worker so that a slow output does not hold up the polling
+pub trait Sender: Sync {
     fn setup(&self) -> anyhow::Result<()>;
     fn send(&self, data: String) -> anyhow::Result<()>;
+    /// send_with_key sends the data together with the key of the device it belongs
+    /// to, outputs without the notion of a key just send the data.
+    fn send_with_key(&self, _key: &str, data: String) -> anyhow::Result<()> {
+        self.send(data)
+    }
     fn get_config(&self) -> &RwLock<Vec<Config>>;
 }
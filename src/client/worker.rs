@@ -1,36 +1,185 @@
-use super::{lookup::LookupClient, sender::Sender};
+use super::{dcp::DcpClient, lookup::LookupClient, sender::Sender};
 use crate::{
-    config::HartCommand,
-    device::pnio_device::PnioDevice,
-    dto::{iotedge_message::IotedgeMessageDto, temp::Temp},
+    config::{Config, HartCommand},
+    device::{health::DeviceHealth, pnio_device::PnioDevice, station_session::StationSession},
+    dto::{
+        error_message::ErrorMessageDto,
+        hart_message::HartMessageDto,
+        identification_message::IdentificationMessageDto,
+        iotedge_message::IotedgeMessageDto,
+        write_audit_message::{WriteAuditMessageDto, WriteOutcome},
+    },
+    error::{self, Error},
+    hart::{DeviceTypeCode, HartDecoderRegistry, HartWrite},
+    protocol::Encode,
+    protocol::Identification,
+    transport::CaptureSettings,
 };
 use anyhow::anyhow;
-use std::{collections::HashMap, net::Ipv4Addr};
+use futures::{future, stream, StreamExt};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    future::Future,
+    net::{IpAddr, Ipv4Addr},
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::runtime::{self, Runtime};
 
 type HartCommands = Vec<HartCommand>;
 type PnioDeviceWithCommands = (PnioDevice, HartCommands);
 /// Name is the unique program variable to identify each
 /// hart device stored inside the program store, the name
-/// consists of ip_address, slot_number and subslot_number
+/// consists of ip_address (or name_of_station if the ip address
+/// is resolved through DCP), slot_number and subslot_number
 /// joined by a `dash`.
 type Name = String;
+/// StationAddress is the ip_address of the station, or its name_of_station
+/// if the ip address is resolved through DCP.
+type StationAddress = String;
+/// EgressMessage is a message to the output and the device it belongs to
+type EgressMessage = (Name, String);
+
+/// CycleStats tells how long a read cycle took, to be compared with the
+/// polling interval
+#[derive(Debug)]
+pub struct CycleStats {
+    pub devices: usize,
+    /// time taken by each station, its devices are polled one after another
+    pub stations: Vec<(IpAddr, Duration)>,
+    pub duration: Duration,
+    /// packets dropped as they don't belong to the requests sent, counted
+    /// since the sessions were opened
+    pub dropped_packets: u64,
+}
+
+impl CycleStats {
+    pub fn slowest_station(&self) -> Option<&(IpAddr, Duration)> {
+        self.stations.iter().max_by_key(|(_, duration)| *duration)
+    }
+}
 
 // TODO: this information is storing in memory at the moment,
 // should we store this into a local db?
 pub struct Worker<'a> {
     sender: &'a dyn Sender,
     pub store: HashMap<Name, PnioDeviceWithCommands>,
+    /// sessions are shared by the devices of the same station, a session is
+    /// released once there's no device of its station left
+    pub sessions: HashMap<StationAddress, Rc<StationSession>>,
+    decoders: HartDecoderRegistry,
+    // resolves name_of_station to the ip address, not set if there's no
+    // network interface to send DCP requests on
+    dcp_client: Option<DcpClient>,
+    // drives the requests of all the devices, the stations are polled
+    // concurrently on this single thread
+    runtime: Runtime,
+    // maximum number of stations polled at the same time
+    concurrency: usize,
+    // where the traffic of the stations to be captured is written
+    capture: Option<CaptureSettings>,
+    // stations captured in addition to those with `capture` in their config
+    captured_stations: Vec<StationAddress>,
+    // queue of the egress thread while the devices are polled, the sender
+    // blocks until the message is out and would hold up every station
+    egress: RefCell<Option<mpsc::Sender<EgressMessage>>>,
 }
 
 impl<'a> Worker<'a> {
+    pub const DEFAULT_CONCURRENCY: usize = 16;
+
     pub fn new(sender: &'a dyn Sender) -> Self {
         let config_len = (*sender).get_config().read().unwrap().len();
         Self {
             sender,
             store: HashMap::with_capacity(config_len),
+            sessions: HashMap::with_capacity(config_len),
+            decoders: HartDecoderRegistry::default(),
+            dcp_client: None,
+            runtime: runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to build the tokio runtime"),
+            concurrency: Self::DEFAULT_CONCURRENCY,
+            capture: None,
+            captured_stations: vec![],
+            egress: RefCell::new(None),
         }
     }
 
+    /// block_on runs the future on the runtime of the worker, the requests of
+    /// the devices in the store must run on it
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    // block_on_egress runs the future on the runtime while its messages to
+    // the output are sent by a thread of their own, it returns once they're
+    // all sent
+    fn block_on_egress<F: Future>(&self, future: F) -> F::Output {
+        let (egress_tx, egress_rx) = mpsc::channel::<EgressMessage>();
+        let sender = self.sender;
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                for (device_unique_name, message) in egress_rx {
+                    if let Err(err) = sender.send_with_key(&device_unique_name, message) {
+                        log::error!(
+                            "failed to egress message to output for device `{device_unique_name}`: {err}"
+                        );
+                    }
+                }
+            });
+
+            self.egress.replace(Some(egress_tx));
+            let output = panic::catch_unwind(AssertUnwindSafe(|| self.block_on(future)));
+            // the thread stops once the queue is closed and drained
+            self.egress.take();
+            output.unwrap_or_else(|err| panic::resume_unwind(err))
+        })
+    }
+
+    // egress queues the message to the egress thread, it's sent right away
+    // if there's none
+    fn egress(&self, device_unique_name: &str, message: String) -> anyhow::Result<()> {
+        match self.egress.borrow().as_ref() {
+            Some(egress) => egress
+                .send((device_unique_name.to_string(), message))
+                .map_err(|_| anyhow!("egress thread stopped")),
+            None => self.sender.send_with_key(device_unique_name, message),
+        }
+    }
+
+    /// set_dcp_client enables the stations configured with name_of_station
+    /// only, their ip address is resolved through DCP
+    pub fn set_dcp_client(&mut self, dcp_client: DcpClient) {
+        self.dcp_client = Some(dcp_client);
+    }
+
+    /// set_concurrency limits the number of stations polled at the same time
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.concurrency = concurrency.max(1);
+    }
+
+    /// set_capture enables capturing the traffic of the stations configured
+    /// with `capture` and of the `stations` given by their ip_address or
+    /// name_of_station, the capture starts with their next session
+    pub fn set_capture(&mut self, capture: CaptureSettings, stations: Vec<StationAddress>) {
+        self.capture = Some(capture);
+        self.captured_stations = stations;
+    }
+
+    // the station is captured if it's configured so or given in the command line
+    fn is_captured(&self, config: &Config) -> bool {
+        config.capture
+            || self.captured_stations.iter().any(|station| {
+                *station == config.ip_address || Some(station) == config.name_of_station.as_ref()
+            })
+    }
+
     /// evaluate if the pnio_device exists in the memory store, perform lookup if
     /// it's not in the memory store yet.
     pub fn evaluate(&mut self, src_ip_address: Ipv4Addr) {
@@ -42,57 +191,68 @@ impl<'a> Worker<'a> {
         log::debug!("config: {:?}", configs);
 
         for config in configs.iter() {
+            // the station is known by its name until its ip address is resolved
+            let station_address = match &config.name_of_station {
+                Some(name) if config.ip_address.is_empty() => name.as_str(),
+                _ => config.ip_address.as_str(),
+            };
+            // opened once for all the hart devices of the station
+            let mut session_failed = false;
+
             for config_hart_device in config.hart_devices.iter() {
                 let device_unique_name = format!(
                     "{}-{}-{}",
-                    config.ip_address,
+                    station_address,
                     config_hart_device.slot_number,
                     config_hart_device.subslot_number
                 );
 
                 // default value, skip looking up this device
-                if config.ip_address == *"127.0.0.1" {
+                if config.is_default() {
                     continue;
                 }
 
                 if self.store.get(&device_unique_name).is_none() {
                     // those configured configs were not available in
-                    // the memory then perform lookup
-                    let target = (
-                        config.device_name.as_str(),
-                        config.ip_address.as_str(),
-                        config.port,
+                    // the memory then use the session of the station,
+                    // perform lookup if there's none yet
+                    let session = match self.sessions.get(station_address) {
+                        Some(session) => session.clone(),
+                        None if session_failed => continue,
+                        None => match self.open_session(src_ip_address, config) {
+                            Ok(session) => {
+                                let session = Rc::new(session);
+                                self.sessions
+                                    .insert(station_address.to_string(), session.clone());
+                                session
+                            }
+                            Err(err) => {
+                                log::error!(
+                                    "failed to open session of station `{station_address}`: {err:#}"
+                                );
+                                session_failed = true;
+                                continue;
+                            }
+                        },
+                    };
+
+                    let mut pnio_device = PnioDevice::new(
+                        session,
                         config_hart_device.slot_number,
                         config_hart_device.subslot_number,
+                        0x04,
                         config_hart_device.request_data_record_number,
                         config_hart_device.response_data_record_number,
-                        config_hart_device.hart_device_name.as_str(),
+                        config_hart_device.hart_device_name.clone(),
+                    );
+                    pnio_device.set_addressing(&config_hart_device.addressing);
+                    pnio_device.configure_writes(
+                        &config_hart_device.write_policy,
+                        &config_hart_device.hart_writes,
+                        &config_hart_device.hart_commands,
                     );
-
-                    let lookup_client = LookupClient::new();
-                    let pnio_device = match lookup_client.lookup(src_ip_address, target) {
-                        Ok(pd) => pd,
-                        Err(err) => {
-                            log::error!(
-                                "failed when performing lookup device `{device_unique_name}`: {err}"
-                            );
-                            continue;
-                        }
-                    };
-
                     log::debug!("pnio_device: {:?}", &pnio_device);
 
-                    // connect to the device
-                    match pnio_device.connect_req() {
-                        Ok(_) => log::debug!("pnio_device `{device_unique_name}` connected"),
-                        Err(err) => {
-                            log::error!(
-                                "failed to connect to pnio_device `{device_unique_name}`: {err}"
-                            );
-                            continue;
-                        }
-                    }
-
                     self.store.insert(
                         device_unique_name.clone(),
                         (pnio_device, config_hart_device.hart_commands.clone()),
@@ -109,6 +269,14 @@ impl<'a> Worker<'a> {
                             config_hart_device.request_data_record_number;
                         pnio_device_with_commands.0.response_data_record_number =
                             config_hart_device.response_data_record_number;
+                        pnio_device_with_commands
+                            .0
+                            .set_addressing(&config_hart_device.addressing);
+                        pnio_device_with_commands.0.configure_writes(
+                            &config_hart_device.write_policy,
+                            &config_hart_device.hart_writes,
+                            &config_hart_device.hart_commands,
+                        );
                         // HartCommands
                         pnio_device_with_commands.1 = config_hart_device.hart_commands.clone();
                     };
@@ -132,120 +300,554 @@ impl<'a> Worker<'a> {
         for name_to_be_deleted in names_to_be_deleted.iter() {
             self.store.remove(name_to_be_deleted);
         }
+        // the stations without any device left are released
+        let (sessions, sessions_to_be_released): (HashMap<_, _>, HashMap<_, _>) =
+            std::mem::take(&mut self.sessions)
+                .into_iter()
+                .partition(|(_, session)| Rc::strong_count(session) > 1);
+        self.sessions = sessions;
+        for (station_address, session) in sessions_to_be_released.iter() {
+            self.block_on(Self::release_session(station_address, session));
+        }
+        self.reconnect_sessions();
 
         log::debug!("the program memory store: {:?}", self.store);
     }
 
-    pub fn read(&mut self) {
-        for (device_unique_name, (pnio_device, hart_commands)) in self.store.iter() {
-            if *pnio_device.device_id.borrow() == [0x00; 5] {
-                // hart command 0 (without specifying device address) request
-                // and this should be the first issued before any other hart command
-                // this is a special hart command, its reponse is handled
-                // directly in this application, see `send_common_read_req` implementation.
-                log::info!("sending write request command 0 to device `{device_unique_name}`");
-                if let Some(err) = pnio_device
-                    .send_common_write_req(pnio_device.request_data_record_number, 0, None)
-                    .err()
-                {
-                    log::error!(
-                        "failed to send request command 0 to device `{device_unique_name}`: {err}"
-                    );
-                    continue;
-                };
-
-                log::info!("sending read request command 0 to device `{device_unique_name}`");
-                if let Some(err) = pnio_device
-                    .send_common_read_req(pnio_device.response_data_record_number, 0)
-                    .err()
-                {
-                    log::error!(
-                        "failed to send response command 0 to device `{device_unique_name}`: {err}"
-                    );
-                    continue;
+    // reconnect_sessions establishes the application relationship of the
+    // stations again which lost it or stopped answering, e.g. they restarted,
+    // the devices keep their state
+    fn reconnect_sessions(&self) {
+        let reconnects = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| session.is_stale())
+            .map(|(station_address, session)| async move {
+                match session.connect_req().await {
+                    Ok(_) => log::info!("station `{station_address}` reconnected"),
+                    Err(err) => {
+                        log::error!("failed to reconnect station `{station_address}`: {err}")
+                    }
                 }
-            } else {
-                // other hart command, send the response bytes to the output
-                for hart_command in hart_commands.iter() {
-                    // send write request
-                    log::info!(
-                        "sending write request command {} to device `{device_unique_name}`",
-                        hart_command.number
-                    );
-                    let command_payload = hart_command.data.as_deref();
-                    if let Some(err) = pnio_device
-                        .send_common_write_req(
-                            pnio_device.request_data_record_number,
-                            hart_command.number,
-                            command_payload,
-                        )
-                        .err()
-                    {
-                        log::error!(
-                            "failed to send request command {} to device `{device_unique_name}`: {err}", 
-                            hart_command.number
-                        );
-                        continue;
-                    };
+            });
+        self.block_on(
+            stream::iter(reconnects)
+                .buffer_unordered(self.concurrency)
+                .collect::<Vec<_>>(),
+        );
+    }
 
-                    // send read request
-                    log::info!(
-                        "sending read request command {} to device `{device_unique_name}`",
-                        hart_command.number
+    // open_session looks up the station and establishes the application
+    // relationship to be shared by its devices
+    fn open_session(
+        &self,
+        src_ip_address: Ipv4Addr,
+        config: &Config,
+    ) -> anyhow::Result<StationSession> {
+        let mut lookup_client = LookupClient::new();
+        if self.is_captured(config) {
+            lookup_client.capture = self.capture.clone();
+        }
+        let ip_address = self.resolve_ip_address(&lookup_client, config)?;
+        let target = (
+            config.device_name.as_str(),
+            ip_address.as_str(),
+            config.port,
+        );
+
+        let session = self
+            .block_on(lookup_client.lookup(src_ip_address, target))
+            .map_err(|err| err.context("failed when performing lookup"))?;
+        log::debug!("station session: {:?}", &session);
+
+        // connect to the station
+        self.block_on(session.connect_req())
+            .map_err(|err| anyhow::Error::new(err).context("failed to connect"))?;
+        log::debug!("station `{ip_address}` connected");
+
+        Ok(session)
+    }
+
+    // the configured ip address, or the one of the station with the
+    // name_of_station if it's left empty
+    fn resolve_ip_address(
+        &self,
+        lookup_client: &LookupClient,
+        config: &Config,
+    ) -> anyhow::Result<String> {
+        if !config.ip_address.is_empty() {
+            return Ok(config.ip_address.clone());
+        }
+
+        let name_of_station = config
+            .name_of_station
+            .as_deref()
+            .ok_or(anyhow!("either ip_address or name_of_station is required"))?;
+        let dcp_client = self.dcp_client.as_ref().ok_or(anyhow!(
+            "no network interface to resolve `{name_of_station}` through DCP"
+        ))?;
+
+        Ok(lookup_client
+            .resolve(dcp_client, name_of_station)?
+            .to_string())
+    }
+
+    /// release the application relationships of all the devices, e.g. before
+    /// shutting down
+    pub fn release(&mut self) {
+        self.store.clear();
+        let sessions = std::mem::take(&mut self.sessions);
+        let releases = sessions
+            .iter()
+            .map(|(station_address, session)| Self::release_session(station_address, session));
+        self.block_on(future::join_all(releases));
+    }
+
+    async fn release_session(station_address: &str, session: &StationSession) {
+        match session.release_req().await {
+            Ok(_) => log::debug!("station `{station_address}` released"),
+            Err(err) => {
+                log::error!("failed to release station `{station_address}`: {err}")
+            }
+        }
+    }
+
+    /// read polls the HART commands of all the devices, the stations are polled
+    /// concurrently up to the concurrency limit while there's only one request
+    /// outstanding per station
+    pub fn read(&mut self) -> CycleStats {
+        let started_at = Instant::now();
+
+        let stations = self.block_on_egress(
+            stream::iter(self.stations())
+                .map(|(ip_address, devices)| self.read_station(ip_address, devices))
+                .buffer_unordered(self.concurrency)
+                .collect::<Vec<_>>(),
+        );
+
+        CycleStats {
+            devices: self.store.len(),
+            stations,
+            duration: started_at.elapsed(),
+            dropped_packets: self
+                .sessions
+                .values()
+                .map(|session| session.transport_client.dropped_packets().get())
+                .sum(),
+        }
+    }
+
+    // stations groups the devices of the store by their station
+    fn stations(&self) -> HashMap<IpAddr, Vec<(&Name, &PnioDeviceWithCommands)>> {
+        let mut stations: HashMap<IpAddr, Vec<(&Name, &PnioDeviceWithCommands)>> = HashMap::new();
+        for (device_unique_name, device) in self.store.iter() {
+            stations
+                .entry(device.0.session.ip_address)
+                .or_default()
+                .push((device_unique_name, device));
+        }
+
+        stations
+    }
+
+    async fn read_station(
+        &self,
+        ip_address: IpAddr,
+        mut devices: Vec<(&Name, &PnioDeviceWithCommands)>,
+    ) -> (IpAddr, Duration) {
+        let started_at = Instant::now();
+        // the station lost the AR, it's connected again by the next evaluate
+        if devices
+            .first()
+            .is_some_and(|(_, (pnio_device, _))| !pnio_device.session.connected.get())
+        {
+            log::warn!("skipping station {ip_address}, it's not connected");
+            return (ip_address, started_at.elapsed());
+        }
+        devices.sort_by_key(|(device_unique_name, _)| *device_unique_name);
+        for (device_unique_name, (pnio_device, hart_commands)) in devices {
+            self.read_device(device_unique_name, pnio_device, hart_commands)
+                .await;
+        }
+
+        (ip_address, started_at.elapsed())
+    }
+
+    async fn read_device(
+        &self,
+        device_unique_name: &str,
+        pnio_device: &PnioDevice,
+        hart_commands: &[HartCommand],
+    ) {
+        if *pnio_device.device_id.borrow() == [0x00; 5] {
+            // the identity command, i.e. command 0 to the polling address or
+            // command 11/21 to the broadcast address, should be the first
+            // issued before any other hart command, its reponse is handled
+            // directly in this application, see `send_common_read_req` implementation.
+            let command = pnio_device.addressing.identity_command();
+            log::info!(
+                "sending command {command} to device `{device_unique_name}` by {}",
+                pnio_device.addressing
+            );
+            let result = pnio_device.discover().await;
+            Self::update_health(device_unique_name, pnio_device, &result);
+            if let Err(err) = result {
+                log::error!(
+                    "failed to send command {command} to device `{device_unique_name}`: {err}"
+                );
+                self.egress_error(device_unique_name, pnio_device, command, &err);
+            }
+            return;
+        }
+
+        // the configuration writes go before the commands polled
+        if !self.write_device(device_unique_name, pnio_device).await {
+            return;
+        }
+
+        // other hart command, send the response bytes to the output
+        for hart_command in hart_commands.iter() {
+            // a command that is not a read is sent once among the writes
+            if !pnio_device.write_policy.is_read(hart_command.number) {
+                continue;
+            }
+
+            log::info!(
+                "sending command {} to device `{device_unique_name}`",
+                hart_command.number
+            );
+            let result = pnio_device
+                .send_command(hart_command.number, hart_command.data.as_deref())
+                .await;
+            Self::update_health(device_unique_name, pnio_device, &result);
+            match result {
+                Ok(response) => {
+                    log::debug!(
+                        "response for hart command {} for device {device_unique_name} - bytes length: {}",
+                        hart_command.number,
+                        response.0,
                     );
-                    match pnio_device.send_common_read_req(
-                        pnio_device.response_data_record_number,
+                    // hart command response message, a response that can't
+                    // be decoded is reported like a failed command
+                    match self.egress_hart_command_response(
+                        device_unique_name,
+                        pnio_device.hart_device_name.as_str(),
+                        pnio_device.device_type_code(),
                         hart_command.number,
+                        response.0,
+                        &response.1,
                     ) {
-                        Ok(response) => {
-                            log::debug!(
-                                "response for hart command {} for device {device_unique_name} - bytes length: {}",
+                        Ok(()) => {}
+                        Err(err @ Error::Decode(_)) => {
+                            self.egress_error(
+                                device_unique_name,
+                                pnio_device,
                                 hart_command.number,
-                                response.0,
+                                &err,
                             );
-                            // hart command response message
-                            if self
-                                .egress_hart_command_response(
-                                    device_unique_name,
-                                    pnio_device.hart_device_name.as_str(),
-                                    hart_command.number,
-                                    response.0,
-                                    &response.1,
-                                )
-                                .is_err()
-                            {
-                                continue;
-                            };
-
-                            // general message
-                            // every hart command response returned contains 2 bytes
-                            // i.e. response code and device status, which are parsed
-                            // and become pnio_device's FieldDeviceCommStatus and FieldDeviceStatus
-                            // field respectively, send this message out as well
-                            // if self.egress_hart_device_statuses().is_err() {
-                            //     continue;
-                            // };
-                        }
-                        Err(err) => {
-                            log::error!("failed to send response command {} to device `{device_unique_name}`: {err}",
-                                hart_command.number);
+                            continue;
                         }
+                        Err(_) => continue,
+                    };
+
+                    // general message
+                    // every hart command response returned contains 2 bytes
+                    // i.e. response code and device status, which are parsed
+                    // and become pnio_device's FieldDeviceCommStatus and FieldDeviceStatus
+                    // field respectively, send this message out as well
+                    // if self.egress_hart_device_statuses().is_err() {
+                    //     continue;
+                    // };
+                }
+                Err(err) => {
+                    log::error!(
+                        "failed to send command {} to device `{device_unique_name}`: {err}",
+                        hart_command.number
+                    );
+                    self.egress_error(device_unique_name, pnio_device, hart_command.number, &err);
+                    // the other commands would wait for the station in vain
+                    if pnio_device.health.get() == DeviceHealth::Unreachable {
+                        break;
                     }
                 }
             }
         }
     }
 
+    // write_device sends the pending writes of the device, a write is kept
+    // pending only if it didn't reach the device. It tells if the device is
+    // still reachable after the writes.
+    async fn write_device(&self, device_unique_name: &str, pnio_device: &PnioDevice) -> bool {
+        let pending_writes = pnio_device.pending_writes.take();
+        let mut writes = pending_writes.iter();
+        let mut kept: Vec<HartWrite> = vec![];
+        let mut reachable = true;
+
+        for write in writes.by_ref() {
+            let (outcome, err) = match write.to_bytes() {
+                Ok(payload) => {
+                    self.write_command(
+                        device_unique_name,
+                        pnio_device,
+                        write.command(),
+                        &payload,
+                        write.typed(),
+                    )
+                    .await
+                }
+                Err(err) => {
+                    let err = Error::from(err);
+                    self.audit_write(
+                        device_unique_name,
+                        pnio_device,
+                        write.command(),
+                        write.typed(),
+                        &[],
+                        WriteOutcome::Invalid,
+                        None,
+                        Some(&err),
+                    );
+                    (WriteOutcome::Invalid, None)
+                }
+            };
+            if outcome == WriteOutcome::Failed
+                && pnio_device.health.get() == DeviceHealth::Unreachable
+            {
+                // a device that does not answer may have executed the write,
+                // it's not sent again
+                if !matches!(err, Some(Error::Hart(_))) {
+                    kept.push(write.clone());
+                }
+                reachable = false;
+                break;
+            }
+        }
+        // the writes after the device became unreachable are not sent
+        kept.extend(writes.cloned());
+        pnio_device.pending_writes.replace(kept);
+
+        reachable
+    }
+
+    // write_command sends the write if the write policy of the device allows
+    // it, the outcome is audited and returned with the error of a failed write
+    async fn write_command(
+        &self,
+        device_unique_name: &str,
+        pnio_device: &PnioDevice,
+        command: u16,
+        payload: &[u8],
+        write: Option<&HartWrite>,
+    ) -> (WriteOutcome, Option<Error>) {
+        if !pnio_device.write_policy.allows(command) {
+            self.audit_write(
+                device_unique_name,
+                pnio_device,
+                command,
+                write,
+                payload,
+                WriteOutcome::Denied,
+                None,
+                None,
+            );
+            return (WriteOutcome::Denied, None);
+        }
+
+        let result = pnio_device.send_command(command, Some(payload)).await;
+        Self::update_health(device_unique_name, pnio_device, &result);
+        let (outcome, response_code, err) = match result {
+            Ok((_, response)) => match response.first() {
+                Some(&code) if HartWrite::is_rejected(code) => {
+                    (WriteOutcome::Rejected, Some(code), None)
+                }
+                code => (WriteOutcome::Written, code.copied(), None),
+            },
+            Err(err) => (WriteOutcome::Failed, None, Some(err)),
+        };
+        self.audit_write(
+            device_unique_name,
+            pnio_device,
+            command,
+            write,
+            payload,
+            outcome,
+            response_code,
+            err.as_ref(),
+        );
+
+        (outcome, err)
+    }
+
+    /// audit_write logs the write and sends its audit record to the output
+    #[allow(clippy::too_many_arguments)]
+    fn audit_write(
+        &self,
+        device_unique_name: &str,
+        pnio_device: &PnioDevice,
+        hart_command: u16,
+        write: Option<&HartWrite>,
+        payload: &[u8],
+        outcome: WriteOutcome,
+        response_code: Option<u8>,
+        error: Option<&Error>,
+    ) {
+        let data = hex::encode(payload);
+        let reason = error.map(|err| format!(", {err}")).unwrap_or_default();
+        let level = match outcome {
+            WriteOutcome::Written => log::Level::Info,
+            _ => log::Level::Warn,
+        };
+        log::log!(
+            level,
+            "write of command {hart_command} with data `{data}` to device `{device_unique_name}`: {outcome}{reason}"
+        );
+
+        let now = format!("{:?}", chrono::Utc::now());
+        let message = WriteAuditMessageDto {
+            timestamp: now.as_str(),
+            device_unique_name,
+            hart_device_name: pnio_device.hart_device_name.as_str(),
+            hart_command,
+            write,
+            data: data.as_str(),
+            outcome,
+            response_code,
+            error,
+        };
+
+        let result = serde_json::to_string(&message)
+            .map_err(|err| anyhow!(err))
+            .and_then(|m| self.egress(device_unique_name, m));
+        if let Err(err) = result {
+            log::error!(
+                "failed to egress write audit to output for device `{device_unique_name}`: {err}"
+            );
+        }
+    }
+
+    // update_health logs the health of the device when it changes
+    fn update_health<T>(
+        device_unique_name: &str,
+        pnio_device: &PnioDevice,
+        result: &error::Result<T>,
+    ) {
+        if let Some(previous) = pnio_device.update_health(result) {
+            log::warn!(
+                "device `{device_unique_name}` is {} (was {previous})",
+                pnio_device.health.get()
+            );
+        }
+    }
+
+    /// identify reads the I&M records of the station and of the module of each
+    /// device and sends them to the output, the records are read implicitly
+    /// so that the polling of the HART commands is not affected
+    pub fn identify(&self) {
+        self.block_on_egress(
+            stream::iter(self.stations())
+                .map(|(_, devices)| self.identify_station(devices))
+                .buffer_unordered(self.concurrency)
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    async fn identify_station(&self, mut devices: Vec<(&Name, &PnioDeviceWithCommands)>) {
+        // slot 0 subslot 1 is the interface module of the station
+        const STATION_SLOT_NUMBER: u16 = 0;
+        const STATION_SUBSLOT_NUMBER: u16 = 1;
+
+        devices.sort_by_key(|(device_unique_name, _)| *device_unique_name);
+        let Some((device_unique_name, (pnio_device, _))) = devices.first() else {
+            return;
+        };
+        // the station is the same for all its devices, it's read once
+        log::info!(
+            "reading identification of station {}",
+            pnio_device.session.ip_address
+        );
+        let station = Self::read_identification(
+            device_unique_name,
+            pnio_device,
+            STATION_SLOT_NUMBER,
+            STATION_SUBSLOT_NUMBER,
+        )
+        .await;
+
+        for (device_unique_name, (pnio_device, _)) in devices {
+            self.identify_device(device_unique_name, pnio_device, station.as_ref())
+                .await;
+        }
+    }
+
+    async fn identify_device(
+        &self,
+        device_unique_name: &str,
+        pnio_device: &PnioDevice,
+        station: Option<&Identification>,
+    ) {
+        log::info!("reading identification of device `{device_unique_name}`");
+        let module = Self::read_identification(
+            device_unique_name,
+            pnio_device,
+            pnio_device.slot_num,
+            pnio_device.subslot_num,
+        )
+        .await;
+        if station.is_none() && module.is_none() {
+            return;
+        }
+
+        let now = format!("{:?}", chrono::Utc::now());
+        let message = IdentificationMessageDto {
+            timestamp: now.as_str(),
+            device_unique_name,
+            hart_device_name: pnio_device.hart_device_name.as_str(),
+            station,
+            module: module.as_ref(),
+        };
+
+        let result = serde_json::to_string(&message)
+            .map_err(|err| anyhow!(err))
+            .and_then(|m| self.egress(device_unique_name, m));
+        if let Err(err) = result {
+            log::error!(
+                "failed to egress identification to output for device `{device_unique_name}`: {err}"
+            );
+        }
+    }
+
+    async fn read_identification(
+        device_unique_name: &str,
+        pnio_device: &PnioDevice,
+        slot_num: u16,
+        subslot_num: u16,
+    ) -> Option<Identification> {
+        match pnio_device
+            .session
+            .read_identification(slot_num, subslot_num)
+            .await
+        {
+            Ok(identification) => Some(identification),
+            Err(err) => {
+                log::error!(
+                    "failed to read identification of slot {slot_num} subslot {subslot_num} of device `{device_unique_name}`: {err}"
+                );
+                None
+            }
+        }
+    }
+
     /// egress_hart_command_response construct the message to be sent to output,
     /// for example the Azure IoT Hub message
     fn egress_hart_command_response(
         &self,
         device_unique_name: &str,
         hart_device_name: &str,
-        hart_command: u8,
+        device_type_code: Option<DeviceTypeCode>,
+        hart_command: u16,
         length: u8,
         bytes: &[u8],
-    ) -> anyhow::Result<()> {
+    ) -> error::Result<()> {
         // construct the iot hub message
         let now = format!("{:?}", chrono::Utc::now());
         // TODO: testing, this is hard coded for the sack of quick presentation only.
@@ -267,36 +869,79 @@ impl<'a> Worker<'a> {
         //     bytes,
         // };
 
-        // TODO: testing, remove this in production
-        let data = Temp::map_to_string(hart_command, bytes).unwrap();
-        let message = Temp {
+        // the byte count covers the statuses and the data, the remaining
+        // bytes of the data record are not part of the HART response
+        let status_and_data = bytes.get(..length as usize).unwrap_or(bytes);
+        let response = match self
+            .decoders
+            .decode(hart_command, device_type_code, status_and_data)
+        {
+            Ok(r) => r,
+            Err(err) => {
+                log::error!(
+                    "failed to decode response command {hart_command} of device `{device_unique_name}`: {err}"
+                );
+                return Err(Error::Decode(err));
+            }
+        };
+        let message = HartMessageDto {
             timestamp: now.as_str(),
             device_unique_name,
             hart_device_name,
+            manufacturer: device_type_code.map(|d| d[0].into()),
             hart_command,
             length,
-            data: data.as_str(),
+            response: &response,
         };
 
         let message = match serde_json::to_string(&message) {
             Ok(m) => m,
             Err(err) => {
                 log::error!("failed to serialize the message{}", err);
-                return Err(anyhow!(err));
+                return Err(Error::Other(anyhow!(err)));
             }
         };
 
         log::info!("sending message of `{device_unique_name}` to output");
-        if let Err(err) = self.sender.send(message) {
+        if let Err(err) = self.egress(device_unique_name, message) {
             log::error!(
                 "failed to egress message to output for device `{device_unique_name}`: {err}"
             );
-            return Err(anyhow!(err));
+            return Err(Error::Other(err));
         };
 
         Ok(())
     }
 
+    /// egress_error sends the error of the command together with the health
+    /// of the device to the output, see `Error` for its serialization
+    fn egress_error(
+        &self,
+        device_unique_name: &str,
+        pnio_device: &PnioDevice,
+        hart_command: u16,
+        error: &error::Error,
+    ) {
+        let now = format!("{:?}", chrono::Utc::now());
+        let message = ErrorMessageDto {
+            timestamp: now.as_str(),
+            device_unique_name,
+            hart_device_name: pnio_device.hart_device_name.as_str(),
+            hart_command,
+            health: pnio_device.health.get(),
+            error,
+        };
+
+        let result = serde_json::to_string(&message)
+            .map_err(|err| anyhow!(err))
+            .and_then(|m| self.egress(device_unique_name, m));
+        if let Err(err) = result {
+            log::error!(
+                "failed to egress error message to output for device `{device_unique_name}`: {err}"
+            );
+        }
+    }
+
     fn egress_hart_device_statuses(&self) -> anyhow::Result<()> {
         let _message = HashMap::<&str, &str>::new();
 
@@ -1,8 +1,16 @@
+use crate::hart::{HartAddressing, HartWrite};
 use serde::{self, Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Config {
+    /// ip_address of the station, resolved from name_of_station through DCP
+    /// if left empty
+    #[serde(default)]
     pub ip_address: String,
+    /// name_of_station is the PROFINET NameOfStation of the station, for
+    /// example `et200sp-1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_of_station: Option<String>,
     /// port is the lookup port, actual port being used to communicate
     /// with the device is obtained through the program
     pub port: u16,
@@ -10,6 +18,9 @@ pub struct Config {
     // device_name is the profinet device name i.e. the model used for searching the
     // device when performing the lookup, for example, `6ES7 155-6AU01-0BN0`
     pub device_name: String,
+    /// capture writes the traffic of the station into a pcapng file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub capture: bool,
 }
 
 impl Config {
@@ -22,12 +33,20 @@ impl Config {
         let config = serde_yaml::from_str::<Vec<Self>>(content)?;
         Ok(config)
     }
+
+    /// is_default tells whether this is the placeholder config written by
+    /// `Config::default`, a station on the loopback address is only a real
+    /// one when it has a lookup port, e.g. the simulator
+    pub fn is_default(&self) -> bool {
+        self.ip_address == *"127.0.0.1" && self.port == 0
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             ip_address: "127.0.0.1".to_string(),
+            name_of_station: None,
             port: 0,
             hart_devices: vec![ConfigHartDevice {
                 slot_number: 0,
@@ -36,8 +55,12 @@ impl Default for Config {
                 request_data_record_number: 80,
                 response_data_record_number: 81,
                 hart_device_name: "hart_device_name".to_string(),
+                hart_writes: vec![],
+                write_policy: WritePolicy::default(),
+                addressing: HartAddressing::default(),
             }],
             device_name: "device_name".to_string(),
+            capture: false,
         }
     }
 }
@@ -57,11 +80,150 @@ pub struct ConfigHartDevice {
     pub response_data_record_number: u16,
     /// hart_device_name is the hart device model
     pub hart_device_name: String,
+    /// hart_writes are the configuration writes sent once the device is found,
+    /// they're sent again only if they change, see `write_policy`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hart_writes: Vec<HartWrite>,
+    /// write_policy tells which commands other than the reads may be sent to
+    /// the device, both `hart_writes` and `hart_commands` are checked against it
+    #[serde(default, skip_serializing_if = "WritePolicy::is_default")]
+    pub write_policy: WritePolicy,
+    /// addressing tells how the device is found, by its polling address (0
+    /// by default), its tag or its long tag
+    #[serde(default)]
+    pub addressing: HartAddressing,
+}
+
+/// WritePolicy is the allow-list of the commands of a device other than the
+/// reads (see `HartWrite::READ_COMMANDS`), the device is read-only if it's
+/// empty.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct WritePolicy {
+    #[serde(default)]
+    pub allowed_commands: Vec<u16>,
+    /// read_commands are the device specific commands known to be reads,
+    /// they're allowed and polled like the universal reads
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub read_commands: Vec<u16>,
+}
+
+impl WritePolicy {
+    pub fn is_read_only(&self) -> bool {
+        self.allowed_commands.is_empty()
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// is_read tells if the command is known to leave the device as it is
+    pub fn is_read(&self, command: u16) -> bool {
+        HartWrite::is_read_command(command) || self.read_commands.contains(&command)
+    }
+
+    /// allows tells if the command may be sent, the reads are always allowed,
+    /// any other command only if it's listed
+    pub fn allows(&self, command: u16) -> bool {
+        self.is_read(command) || self.allowed_commands.contains(&command)
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct HartCommand {
-    pub number: u8,
+    pub number: u16,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<Box<[u8]>>,
 }
+
+/// LocalConfig is the content of the config file used in local mode, it carries
+/// the kafka output settings alongside the same config list the module twin holds.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LocalConfig {
+    pub kafka: KafkaConfig,
+    pub config: Vec<Config>,
+}
+
+impl LocalConfig {
+    pub fn deserialize(content: &str) -> anyhow::Result<Self> {
+        let local_config = serde_yaml::from_str::<Self>(content)?;
+        Ok(local_config)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct KafkaConfig {
+    /// brokers is the bootstrap broker list, for example `localhost:9092`
+    pub brokers: Vec<String>,
+    pub topic: String,
+    #[serde(default)]
+    pub acks: KafkaAcks,
+    /// ack_timeout_ms is how long the broker waits for the required acks
+    #[serde(default = "KafkaConfig::default_ack_timeout_ms")]
+    pub ack_timeout_ms: u64,
+    #[serde(default)]
+    pub partition_key: KafkaPartitionKey,
+}
+
+impl KafkaConfig {
+    fn default_ack_timeout_ms() -> u64 {
+        1000
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaAcks {
+    None,
+    #[default]
+    One,
+    All,
+}
+
+/// KafkaPartitionKey decides the key attached to each record, records with the
+/// same key always land in the same partition and so keep their order.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaPartitionKey {
+    #[default]
+    DeviceUniqueName,
+    None,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn is_default_should_keep_loopback_stations_with_a_port() {
+        assert!(Config::default().is_default());
+
+        let simulator = Config {
+            port: 34964,
+            ..Config::default()
+        };
+        assert!(!simulator.is_default());
+    }
+
+    #[test]
+    fn allows_should_deny_commands_other_than_reads() {
+        let read_only = WritePolicy::default();
+        assert!(read_only.is_read_only());
+        for command in [0, 1, 3, 9, 48] {
+            assert!(read_only.allows(command));
+        }
+        // writes, resets, loop current and trim commands, device specific ones
+        for command in [6, 34, 36, 37, 38, 40, 42, 45, 46, 1024] {
+            assert!(!read_only.allows(command));
+        }
+
+        let policy = WritePolicy {
+            allowed_commands: vec![40],
+            read_commands: vec![1024],
+        };
+        assert!(policy.allows(9));
+        assert!(policy.allows(40));
+        assert!(!policy.allows(42));
+        assert!(policy.is_read(1024));
+        assert!(policy.allows(1024));
+    }
+}
@@ -0,0 +1,521 @@
+use crate::{
+    hart::HartDecoderRegistry,
+    protocol::{
+        format_mac, BlockHeaderType, DceRpcEpmResponse, DceRpcPacket, Decode, HartFrame, OpNum,
+        PacketType, Pnio, PnioHeaderEnum, INTERFACE,
+    },
+    transport::read_datagrams,
+};
+use anyhow::anyhow;
+use serde::Serialize;
+use serde_json::Value;
+use std::{fmt, fs, path::Path};
+use uuid::Uuid;
+
+/// Node is a field of a decoded datagram, or a packet or a block with its
+/// fields as children
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Node {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: None,
+            children: vec![],
+        }
+    }
+
+    fn field(name: &str, value: impl Into<Value>) -> Self {
+        Self {
+            value: Some(value.into()),
+            ..Self::new(name)
+        }
+    }
+
+    fn hex(name: &str, bytes: &[u8]) -> Self {
+        Self::field(name, hex::encode(bytes))
+    }
+
+    fn error(err: impl fmt::Display) -> Self {
+        Self::field("error", err.to_string())
+    }
+
+    fn push(&mut self, child: Node) {
+        self.children.push(child);
+    }
+
+    /// from_value turns serialized data, e.g. a `HartResponse`, into nodes, an
+    /// array of numbers is kept as a single value
+    fn from_value(name: &str, value: Value) -> Self {
+        match value {
+            Value::Object(fields) => Self {
+                children: fields
+                    .into_iter()
+                    .map(|(k, v)| Self::from_value(&k, v))
+                    .collect(),
+                ..Self::new(name)
+            },
+            Value::Array(items) if !items.iter().all(Value::is_number) => Self {
+                children: items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, v)| Self::from_value(&i.to_string(), v))
+                    .collect(),
+                ..Self::new(name)
+            },
+            value => Self::field(name, value),
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}{}", "", self.name, indent = depth * 2)?;
+        match &self.value {
+            Some(Value::String(s)) => writeln!(f, ": {s}")?,
+            Some(value) => writeln!(f, ": {value}")?,
+            None => writeln!(f)?,
+        }
+        for child in self.children.iter() {
+            child.fmt_indented(f, depth + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// decode decodes the input of the `decode` subcommand, the hex dump of a
+/// datagram or the path of a pcap or pcapng file, with the parsers of the
+/// gateway
+pub fn decode(input: &str) -> anyhow::Result<Vec<Node>> {
+    let decoders = HartDecoderRegistry::default();
+    if !Path::new(input).is_file() {
+        return Ok(vec![decode_datagram(&parse_hex(input)?, &decoders)]);
+    }
+
+    let content = fs::read(input)?;
+    let datagrams = match read_datagrams(&content) {
+        Ok(d) => d,
+        // a file holding the hex dump
+        Err(err) => match std::str::from_utf8(&content).map(parse_hex) {
+            Ok(Ok(datagram)) => return Ok(vec![decode_datagram(&datagram, &decoders)]),
+            _ => return Err(err),
+        },
+    };
+
+    Ok(datagrams
+        .iter()
+        .map(|datagram| {
+            let mut node = Node::field("datagram", format!("{} -> {}", datagram.src, datagram.dst));
+            node.push(decode_datagram(&datagram.payload, &decoders));
+            node
+        })
+        .collect())
+}
+
+// the hex dump may be split with whitespaces or colons, e.g. as copied out of
+// Wireshark
+fn parse_hex(input: &str) -> anyhow::Result<Vec<u8>> {
+    let hex: String = input
+        .trim()
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':')
+        .collect();
+    hex::decode(hex).map_err(|err| anyhow!("input is neither a file nor hex: {err}"))
+}
+
+/// decode_datagram decodes a DCE/RPC datagram down to the HART frame of the
+/// PNIO record it carries, what can't be decoded is left as hex
+pub fn decode_datagram(datagram: &[u8], decoders: &HartDecoderRegistry) -> Node {
+    let packet = match DceRpcPacket::from_bytes(datagram) {
+        Ok(p) => p,
+        Err(err) => {
+            let mut node = Node::new("datagram");
+            node.push(Node::error(err));
+            node.push(Node::hex("data", datagram));
+            return node;
+        }
+    };
+
+    let packet_type = packet.get_packet_type();
+    let interface = Uuid::from_bytes_le(packet.interface);
+    let is_epm = interface == Uuid::parse_str(INTERFACE).unwrap();
+    let opnum = u16::from_le_bytes(packet.opnum);
+
+    let mut node = Node::new("DceRpcPacket");
+    node.push(match packet_type {
+        Some(t) => Node::field("packet_type", format!("{t:?}")),
+        None => Node::field("packet_type", packet.packet_type[0]),
+    });
+    node.push(Node::field("flags1", format!("{:#04x}", packet.flags1[0])));
+    node.push(Node::field("flags2", format!("{:#04x}", packet.flags2[0])));
+    node.push(Node::field(
+        "object",
+        Uuid::from_bytes_le(packet.obj_uuid).to_string(),
+    ));
+    node.push(Node::field("interface", interface.to_string()));
+    node.push(Node::field(
+        "activity",
+        Uuid::from_bytes_le(packet.activity).to_string(),
+    ));
+    node.push(Node::field(
+        "server_boot_time",
+        u32::from_le_bytes(packet.server_boot_time),
+    ));
+    node.push(Node::field(
+        "interface_version",
+        u32::from_le_bytes(packet.interface_ver),
+    ));
+    node.push(Node::field("seq_num", u32::from_le_bytes(packet.seq_num)));
+    node.push(match OpNum::from_u16(opnum) {
+        Some(o) if !is_epm => Node::field("opnum", format!("{o:?}")),
+        _ => Node::field("opnum", opnum),
+    });
+    node.push(Node::field(
+        "fragment_num",
+        u16::from_le_bytes(packet.fragment_num),
+    ));
+    node.push(Node::field(
+        "fragment_len",
+        u16::from_le_bytes(packet.fragment_len),
+    ));
+
+    if packet.data.is_empty() {
+        return node;
+    }
+    let body = match packet_type {
+        _ if packet.is_fragment() => Node::hex("fragment", &packet.data),
+        Some(PacketType::Reject | PacketType::Fault) => match packet.get_status() {
+            Some(status) => Node::field("status", format!("{status:?}")),
+            None => Node::hex("data", &packet.data),
+        },
+        Some(PacketType::Response) if is_epm => decode_epm_response(&packet.data),
+        Some(PacketType::Request | PacketType::Response) if !is_epm => {
+            decode_pnio(&packet.data, decoders)
+        }
+        _ => Node::hex("data", &packet.data),
+    };
+    node.push(body);
+
+    node
+}
+
+fn decode_epm_response(data: &[u8]) -> Node {
+    let response = match DceRpcEpmResponse::from_bytes(data) {
+        Ok(r) => r,
+        Err(err) => {
+            let mut node = Node::new("DceRpcEpmResponse");
+            node.push(Node::error(err));
+            node.push(Node::hex("data", data));
+            return node;
+        }
+    };
+
+    let mut node = Node::new("DceRpcEpmResponse");
+    node.push(Node::field("handle", response.handle.clone()));
+    node.push(Node::field("status", format!("{:#010x}", response.status)));
+    for entry in response.entries.iter() {
+        let mut entry_node = Node::field("entry", entry.object.to_string());
+        entry_node.push(Node::field(
+            "annotation",
+            entry.tower_pointer.annotation.clone(),
+        ));
+        let mut tower = Node::field("tower", entry.tower_pointer.num_of_floors);
+        for (i, floor) in entry.tower_pointer.floors.iter().enumerate() {
+            let mut floor_node =
+                Node::field(&format!("floor {}", i + 1), format!("{:?}", floor.protocol));
+            if let Some(uuid) = floor.uuid {
+                floor_node.push(Node::field("uuid", uuid.to_string()));
+            }
+            if let Some(port) = floor.udp_port {
+                floor_node.push(Node::field("udp_port", port));
+            }
+            if let Some(ip) = floor.ipv4 {
+                floor_node.push(Node::field("ipv4", ip.to_string()));
+            }
+            tower.push(floor_node);
+        }
+        entry_node.push(tower);
+        node.push(entry_node);
+    }
+
+    node
+}
+
+fn decode_pnio(data: &[u8], decoders: &HartDecoderRegistry) -> Node {
+    let pnio = match Pnio::from_bytes(data) {
+        Ok(p) => p,
+        Err(err) => {
+            let mut node = Node::new("Pnio");
+            node.push(Node::error(err));
+            node.push(Node::hex("data", data));
+            return node;
+        }
+    };
+
+    let mut node = Node::new("Pnio");
+    if let Some(status) = pnio.status {
+        let mut status_node = Node::hex("status", &status);
+        if let Some(err) = pnio.error() {
+            status_node.push(Node::error(err));
+        }
+        node.push(status_node);
+    }
+    if let Some(args_max) = pnio.args_max {
+        node.push(Node::field("args_max", args_max));
+    }
+    node.push(Node::field(
+        "args_length",
+        u32::from_le_bytes(pnio.args_length),
+    ));
+    node.push(Node::field("max_count", u32::from_le_bytes(pnio.max_count)));
+    node.push(Node::field("offset", u32::from_le_bytes(pnio.offset)));
+    node.push(Node::field(
+        "actual_count",
+        u32::from_le_bytes(pnio.actual_count),
+    ));
+
+    let uuid = |bytes: [u8; 16]| Uuid::from_bytes(bytes).to_string();
+    let block_type = |bytes: [u8; 2]| {
+        let block_type = u16::from_be_bytes(bytes);
+        match BlockHeaderType::from_u16(block_type) {
+            Some(t) => Node::field("block_type", format!("{t:?}")),
+            None => Node::field("block_type", block_type),
+        }
+    };
+    // the valid bytes of the record
+    let mut record_data_len: Option<u32> = None;
+    let header = match &pnio.pnio_header {
+        PnioHeaderEnum::ArBlockReq(h) => {
+            let mut n = Node::new("ArBlockReq");
+            n.push(block_type(h.block_header_type));
+            n.push(Node::field("ar_type", u16::from_be_bytes(h.ar_type)));
+            n.push(Node::field("ar_uuid", uuid(h.ar_uuid)));
+            n.push(Node::field(
+                "session_key",
+                u16::from_be_bytes(h.session_key),
+            ));
+            n.push(Node::field(
+                "cm_initiator_mac",
+                format_mac(&h.cm_initiator_mac),
+            ));
+            n.push(Node::field(
+                "cm_initiator_obj_uuid",
+                uuid(h.cm_initiator_obj_uuid),
+            ));
+            n.push(Node::field(
+                "cm_initiator_station_name",
+                String::from_utf8_lossy(&h.cm_initiator_station_name).to_string(),
+            ));
+            n
+        }
+        PnioHeaderEnum::ArBlockRes(h) => {
+            let mut n = Node::new("ArBlockRes");
+            n.push(block_type(h.block_header_type));
+            n.push(Node::field("ar_type", u16::from_be_bytes(h.ar_type)));
+            n.push(Node::field("ar_uuid", uuid(h.ar_uuid)));
+            n.push(Node::field(
+                "session_key",
+                u16::from_be_bytes(h.session_key),
+            ));
+            n.push(Node::field(
+                "cm_responder_mac",
+                format_mac(&h.cm_responder_mac_address),
+            ));
+            n.push(Node::field(
+                "cm_responder_udp_port",
+                u16::from_be_bytes(h.cm_responder_udpport),
+            ));
+            n
+        }
+        PnioHeaderEnum::IodReq(h) => {
+            let mut n = Node::new("IodReq");
+            n.push(block_type(h.block_header_type));
+            n.push(Node::field("seq_num", u16::from_be_bytes(h.seq_num)));
+            n.push(Node::field("ar_uuid", uuid(h.ar_uuid)));
+            n.push(Node::field("api", u32::from_be_bytes(h.api)));
+            n.push(Node::field("slot", u16::from_be_bytes(h.slot_num)));
+            n.push(Node::field("subslot", u16::from_be_bytes(h.subslot_num)));
+            n.push(Node::field("index", u16::from_be_bytes(h.index)));
+            n.push(Node::field(
+                "record_data_len",
+                u32::from_be_bytes(h.record_data_len),
+            ));
+            record_data_len = Some(u32::from_be_bytes(h.record_data_len));
+            n
+        }
+        PnioHeaderEnum::IodRes(h) => {
+            let mut n = Node::new("IodRes");
+            n.push(block_type(h.block_header_type));
+            n.push(Node::field("seq_num", u16::from_be_bytes(h.seq_num)));
+            n.push(Node::field("ar_uuid", uuid(h.ar_uuid)));
+            n.push(Node::field("api", u32::from_be_bytes(h.api)));
+            n.push(Node::field("slot", u16::from_be_bytes(h.slot_num)));
+            n.push(Node::field("subslot", u16::from_be_bytes(h.subslot_num)));
+            n.push(Node::field("index", u16::from_be_bytes(h.index)));
+            n.push(Node::field(
+                "record_data_len",
+                u32::from_be_bytes(h.record_data_len),
+            ));
+            n.push(Node::hex("status", &h.status));
+            record_data_len = Some(u32::from_be_bytes(h.record_data_len));
+            n
+        }
+        PnioHeaderEnum::ReleaseBlock(h) => {
+            let mut n = Node::new("ReleaseBlock");
+            n.push(block_type(h.block_header_type));
+            n.push(Node::field("ar_uuid", uuid(h.ar_uuid)));
+            n.push(Node::field(
+                "session_key",
+                u16::from_be_bytes(h.session_key),
+            ));
+            n.push(Node::field(
+                "control_command",
+                format!("{:#06x}", u16::from_be_bytes(h.control_command)),
+            ));
+            n
+        }
+    };
+    node.push(header);
+
+    if let Some(record) = pnio.pnio_data.as_deref() {
+        let len = record_data_len.map_or(record.len(), |l| (l as usize).min(record.len()));
+        node.push(decode_record(&record[..len], decoders));
+    }
+
+    node
+}
+
+// the record is taken for a HART request or response record if it carries a
+// frame with a valid checksum, e.g. not for the I&M records
+fn decode_record(record: &[u8], decoders: &HartDecoderRegistry) -> Node {
+    let frame = record
+        .get(HartFrame::RECORD_OFFSET..)
+        .and_then(|f| HartFrame::from_bytes(f).ok())
+        .filter(|f| f.is_checksum_valid());
+    let mut node = Node::hex("record", record);
+    let Some(frame) = frame else {
+        return node;
+    };
+
+    let mut frame_node = Node::new("HartFrame");
+    frame_node.push(Node::field(
+        "delimiter",
+        format!("{:#04x}", frame.delimiter),
+    ));
+    frame_node.push(Node::hex("address", &frame.address));
+    frame_node.push(Node::field("command", frame.command));
+    if frame.command == HartFrame::EXPANSION_COMMAND {
+        frame_node.push(match frame.command_number() {
+            Ok(command) => Node::field("expanded_command", command),
+            Err(err) => Node::error(err),
+        });
+    }
+    frame_node.push(Node::field("byte_count", frame.byte_count));
+    frame_node.push(Node::hex("data", &frame.data));
+    frame_node.push(Node::field("checksum", format!("{:#04x}", frame.checksum)));
+    if frame.is_response() {
+        // the expansion bytes of command 31 are not part of the response
+        let status_and_data = match frame.command {
+            HartFrame::EXPANSION_COMMAND => HartFrame::split_expansion(&frame.data),
+            command => Ok((command.into(), frame.data.clone())),
+        };
+        let response = status_and_data
+            .and_then(|(command, status_and_data)| {
+                decoders.decode(command, frame.device_type_code(), &status_and_data)
+            })
+            .map_err(anyhow::Error::from)
+            .and_then(|r| Ok(serde_json::to_value(r)?));
+        frame_node.push(match response {
+            Ok(value) => Node::from_value("HartResponse", value),
+            Err(err) => Node::error(err),
+        });
+    }
+    node.push(frame_node);
+
+    node
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::{Encode, InterfaceVersion};
+
+    #[test]
+    fn decode_should_decode_hart_response_record() {
+        // command 9 response of the device 2a0b0c7805, value 1.5
+        let mut frame = vec![0x86, 0x2a, 0x0b, 0x0c, 0x78, 0x05, 0x09, 0x0f];
+        frame.extend([
+            0x00, 0x00, 0x00, 0x00, 0x41, 0x08, 0x3f, 0xc0, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00,
+            0x00,
+        ]);
+        frame.push(frame.iter().fold(0, |acc, b| acc ^ b));
+        let mut record = vec![0x04, 0x00];
+        record.extend(frame);
+
+        let node = decode_record(&record, &HartDecoderRegistry::default());
+        let tree = node.to_string();
+        assert!(tree.contains("  HartFrame\n"));
+        assert!(tree.contains("    command: 9\n"));
+        assert!(tree.contains("HartResponse"));
+        assert!(tree.contains("1.5"));
+
+        // JSON keeps the values typed
+        let json = serde_json::to_value(&node).unwrap();
+        assert_eq!(json["children"][0]["children"][2]["value"], 9);
+
+        // command 1024 carried by command 31, the response is decoded without
+        // the expansion bytes
+        let mut frame = vec![0x86, 0x2a, 0x0b, 0x0c, 0x78, 0x05, 0x1f, 0x05];
+        frame.extend([0x00, 0x00, 0x04, 0x00, 0x01]);
+        frame.push(frame.iter().fold(0, |acc, b| acc ^ b));
+        let mut record = vec![0x04, 0x00];
+        record.extend(frame);
+
+        let tree = decode_record(&record, &HartDecoderRegistry::default()).to_string();
+        assert!(tree.contains("    command: 31\n"));
+        assert!(tree.contains("    expanded_command: 1024\n"));
+        assert!(tree.contains("      command: 1024\n"));
+
+        // not a HART record
+        let node = decode_record(&[0x00, 0x01], &HartDecoderRegistry::default());
+        assert!(node.children.is_empty());
+    }
+
+    #[test]
+    fn decode_should_decode_hex_of_dcerpc_packet() {
+        let err = decode("0x0102zz").unwrap_err();
+        assert!(err.to_string().contains("neither a file nor hex"));
+
+        let datagram = DceRpcPacket::new(
+            PacketType::Request,
+            Uuid::nil(),
+            Uuid::nil(),
+            InterfaceVersion::ReadWrite,
+            Uuid::nil(),
+            7,
+            OpNum::Write,
+            Box::new([]),
+        )
+        .to_bytes()
+        .unwrap();
+        let nodes = decode(&hex::encode(datagram)).unwrap();
+        assert_eq!(nodes.len(), 1);
+        let tree = nodes[0].to_string();
+        assert!(tree.starts_with("DceRpcPacket\n  packet_type: Request\n"));
+        assert!(tree.contains("  seq_num: 7\n"));
+        assert!(tree.contains("  opnum: Write\n"));
+    }
+}
@@ -0,0 +1,29 @@
+use serde::Serialize;
+use std::fmt;
+
+/// DeviceHealth is the state of a device as told by its last request, see
+/// `Error::health`
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceHealth {
+    #[default]
+    Healthy,
+    /// the device answers with errors which may go away, e.g. busy
+    Degraded,
+    /// the station of the device, or the device itself, does not answer
+    Unreachable,
+    /// the requests fail whenever they are sent, e.g. an invalid record index
+    Failed,
+}
+
+impl fmt::Display for DeviceHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Self::Healthy => "healthy",
+            Self::Degraded => "degraded",
+            Self::Unreachable => "unreachable",
+            Self::Failed => "failed",
+        };
+        write!(f, "{text}")
+    }
+}
@@ -27,9 +27,9 @@ impl Metadata {
             self.number_of_preemble_bytes_in_response.set(*h);
         }
 
-        if let Some(h) = hart_response.get(14..16) {
+        if let Some(&[high, low]) = hart_response.get(14..16) {
             self.configuration_change_counter
-                .set(u16::from_be_bytes(TryInto::<[u8; 2]>::try_into(h).unwrap()));
+                .set(u16::from_be_bytes([high, low]));
         }
 
         Ok(())
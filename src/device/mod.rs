@@ -1,4 +1,6 @@
+pub mod health;
 pub mod pnio_device;
 pub mod spec_comm_status;
 pub mod spec_status;
+pub mod station_session;
 mod metadata;
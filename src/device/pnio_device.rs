@@ -1,32 +1,27 @@
 use super::{
-    metadata::Metadata, spec_comm_status::FieldDeviceCommStatus, spec_status::FieldDeviceStatus,
+    health::DeviceHealth, metadata::Metadata, spec_comm_status::FieldDeviceCommStatus,
+    spec_status::FieldDeviceStatus, station_session::StationSession,
 };
 use crate::{
-    protocol::{
-        ArBlockReq, BlockHeaderType, DceRpcPacket, HartCommand, InterfaceVersion, IodReq, OpNum,
-        Packet, PacketType, Pnio, PnioHeaderEnum,
-    },
-    transport::TransportClient,
+    config::{self, WritePolicy},
+    error::{self, Error, HartError},
+    hart::{DeviceTypeCode, HartAddressing, HartWrite},
+    protocol::{Cursor, Decode, HartAddress, HartCommand, HartFrame, ProtocolError},
 };
-use anyhow::anyhow;
-use core::time;
 use std::{
     cell::{Cell, RefCell},
-    net::IpAddr,
-    thread,
+    rc::Rc,
+    time::Duration,
 };
-use uuid::Uuid;
+use tokio::time;
 
+/// PnioDevice is a HART field device connected to a channel of an AI module,
+/// the requests are sent through the session of its station.
 #[derive(Debug)]
 pub struct PnioDevice {
-    pub handle: String,
-    pub object_uuid: Uuid,
-    pub interface_uuid: Uuid,
+    pub session: Rc<StationSession>,
     pub device_id: RefCell<[u8; 5]>,
 
-    pub ip_address: IpAddr,
-    pub port: u16,
-
     // TODO: maybe remove this for clarity
     pub comm_status: FieldDeviceCommStatus,
     // TODO: maybe remove this for clarity
@@ -34,13 +29,6 @@ pub struct PnioDevice {
     // TODO: maybe remove this for clarity
     pub metadata: Metadata,
 
-    pub transport_client: Box<dyn TransportClient>,
-    pub ar_uuid: RefCell<Uuid>,
-
-    pub activity: RefCell<Uuid>,
-    pub dcerpc_seq_num: Cell<u32>,
-    pub pnio_seq_num: Cell<u16>,
-
     pub slot_num: u16,
     pub subslot_num: u16,
 
@@ -48,6 +36,9 @@ pub struct PnioDevice {
     /// coming back in the first byte of PNIO packet's user specified data
     /// refer to the AI module documentation
     pub data_ready_flag: u8,
+    /// data_ready_interval is how long to wait before the response record
+    /// is read again while the AI module has no response to the command yet
+    pub data_ready_interval: Duration,
 
     /// request_data_record_number indicates the pnio data record for request each
     /// channel for the AI module, see manual for specific AI module for more info.
@@ -57,18 +48,24 @@ pub struct PnioDevice {
     pub response_data_record_number: u16,
     /// hart_device_name is the hart device model
     pub hart_device_name: String,
+    /// health is told by the last command sent to the device
+    pub health: Cell<DeviceHealth>,
+    /// addressing tells how the device_id is found
+    pub addressing: HartAddressing,
+    /// write_policy tells which commands other than the reads may be sent to
+    /// the device
+    pub write_policy: WritePolicy,
+    /// hart_writes are the configured writes followed by the raw ones,
+    /// pending_writes those of them not sent yet
+    pub hart_writes: Vec<HartWrite>,
+    pub pending_writes: RefCell<Vec<HartWrite>>,
 }
 
 impl PnioDevice {
-    #[allow(clippy::too_many_arguments)]
+    pub const DATA_READY_INTERVAL: Duration = Duration::from_secs(1);
+
     pub fn new(
-        handle: String,
-        object_uuid: Uuid,
-        interface_uuid: Uuid,
-        port: u16,
-        device_id: [u8; 5],
-        ip_address: IpAddr,
-        transport_client: Box<dyn TransportClient>,
+        session: Rc<StationSession>,
         slot_num: u16,
         subslot_num: u16,
         data_ready_flag: u8,
@@ -77,23 +74,21 @@ impl PnioDevice {
         hart_device_name: String,
     ) -> Self {
         PnioDevice {
-            handle,
-            object_uuid,
-            interface_uuid,
-            port,
-            device_id: device_id.into(),
-            ip_address,
-            ar_uuid: RefCell::new(Uuid::new_v4()),
-            transport_client,
+            session,
+            // default device_id, to be set after performed HART command 0
+            device_id: [0x00; 5].into(),
             slot_num,
             subslot_num,
-            activity: RefCell::new(Uuid::new_v4()),
-            dcerpc_seq_num: Cell::new(0),
-            pnio_seq_num: Cell::new(0),
             data_ready_flag,
+            data_ready_interval: Self::DATA_READY_INTERVAL,
             request_data_record_number,
             response_data_record_number,
             hart_device_name,
+            health: Cell::default(),
+            addressing: HartAddressing::default(),
+            write_policy: WritePolicy::default(),
+            hart_writes: vec![],
+            pending_writes: RefCell::default(),
             comm_status: FieldDeviceCommStatus {
                 buffer_overflow: false.into(),
                 communication_failure: false.into(),
@@ -123,152 +118,182 @@ impl PnioDevice {
         }
     }
 
-    fn construct_pnio_req(
-        &self,
-        is_read: bool,
-        pnio_header: PnioHeaderEnum,
-        data: Option<Box<[u8]>>,
-    ) -> anyhow::Result<Pnio> {
-        let status: Option<[u8; 4]> = None;
-        let mut pnio_data = data;
-
-        if is_read {
-            pnio_data = None;
+    /// device_type_code is the manufacturer id and device type taken from the
+    /// device_id, `None` until the identity command has been answered
+    pub fn device_type_code(&self) -> Option<DeviceTypeCode> {
+        let device_id = self.device_id.borrow();
+        match *device_id == [0x00; 5] {
+            true => None,
+            false => Some([device_id[0], device_id[1]]),
+        }
+    }
+
+    /// set_addressing sets how the device_id is found, it's found again if
+    /// the addressing is changed
+    pub fn set_addressing(&mut self, addressing: &HartAddressing) {
+        if self.addressing != *addressing {
+            self.addressing = addressing.clone();
+            self.device_id.replace([0x00; 5]);
         }
+    }
 
-        let pnio = Pnio::new(status, pnio_header, pnio_data);
+    /// address is the unique identifier of the device once it's found, the
+    /// address of the identity command otherwise
+    pub fn address(&self) -> HartAddress {
+        let device_id = *self.device_id.borrow();
+        match &self.addressing {
+            _ if device_id != [0x00; 5] => HartAddress::Unique(device_id),
+            HartAddressing::PollingAddress { polling_address } => {
+                HartAddress::Polling(*polling_address)
+            }
+            HartAddressing::Tag { .. } | HartAddressing::LongTag { .. } => HartAddress::BROADCAST,
+        }
+    }
 
-        Ok(pnio)
+    /// discover sends the identity command of the addressing, the device_id
+    /// is taken from its response, see `send_common_read_req`
+    pub async fn discover(&self) -> error::Result<()> {
+        let payload = self.addressing.identity_payload()?;
+        self.send_command(self.addressing.identity_command(), payload.as_deref())
+            .await
+            .map(|_| ())
     }
 
-    fn construct_dcerpc_req(&self, opnum: OpNum, data: Box<[u8]>) -> anyhow::Result<DceRpcPacket> {
-        let packet_type = PacketType::Request;
-        let interface_ver = InterfaceVersion::ReadWrite;
-
-        let request_dcerpc_packet = DceRpcPacket::new(
-            packet_type,
-            self.object_uuid,
-            self.interface_uuid,
-            interface_ver,
-            *self.activity.borrow(),
-            self.dcerpc_seq_num.get(),
-            opnum,
-            data,
-        );
-
-        Ok(request_dcerpc_packet)
+    /// configure_writes sets the write policy and the writes of the device,
+    /// the commands other than the reads count as raw writes. The writes are
+    /// sent again only if they're changed.
+    pub fn configure_writes(
+        &mut self,
+        write_policy: &WritePolicy,
+        hart_writes: &[HartWrite],
+        hart_commands: &[config::HartCommand],
+    ) {
+        self.write_policy = write_policy.clone();
+        let raw_writes = hart_commands
+            .iter()
+            .filter(|c| !write_policy.is_read(c.number))
+            .map(|c| HartWrite::Raw {
+                command: c.number,
+                data: c.data.clone().unwrap_or_default(),
+            });
+        let hart_writes: Vec<HartWrite> = hart_writes.iter().cloned().chain(raw_writes).collect();
+        if self.hart_writes != hart_writes {
+            self.pending_writes.replace(hart_writes.clone());
+            self.hart_writes = hart_writes;
+        }
     }
 
-    fn construct_iod_header_req(
+    /// send_command writes the HART command into the request record and reads
+    /// its response, the command is sent again while the field device answers
+    /// with an error that may go away (see `Error::backoff`), the errors of
+    /// the station are retried by the requests themselves. A command without
+    /// response is not sent again, the device may have executed it.
+    pub async fn send_command(
         &self,
-        is_read: bool,
-        index: u16,
-        data: &Option<Box<[u8]>>,
-    ) -> anyhow::Result<IodReq> {
-        let mut block_header_type = BlockHeaderType::IodWriteReqType;
-        let record_data_len: u32 = match data.as_ref() {
-            Some(d) => d.len().try_into()?,
-            None => 65520,
-        };
+        command: u16,
+        command_payload: Option<&[u8]>,
+    ) -> error::Result<(u8, Box<[u8]>)> {
+        const RETRY_MAX: u32 = 3;
+        let mut retry = 0;
 
-        if is_read {
-            block_header_type = BlockHeaderType::IodReadReqType;
+        loop {
+            let result = match self
+                .send_common_write_req(self.request_data_record_number, command, command_payload)
+                .await
+            {
+                Ok(()) => {
+                    self.send_common_read_req(self.response_data_record_number, command)
+                        .await
+                }
+                Err(err) => Err(err),
+            };
+            let err = match result {
+                Err(err @ Error::Hart(_)) => err,
+                result => return result,
+            };
+            match err.backoff(retry) {
+                Some(backoff) if retry < RETRY_MAX => {
+                    log::debug!("retry counter: {retry}, {err}, send command again");
+                    retry += 1;
+                    time::sleep(backoff).await;
+                }
+                _ => return Err(err),
+            }
         }
-
-        let iod_write_req_header = IodReq::new(
-            block_header_type,
-            self.pnio_seq_num.get(),
-            *self.ar_uuid.borrow(),
-            self.slot_num,
-            self.subslot_num,
-            index,
-            record_data_len,
-        );
-
-        Ok(iod_write_req_header)
     }
 
-    fn next_request(&self) {
-        self.dcerpc_seq_num.set(self.dcerpc_seq_num.get() + 1);
-        self.pnio_seq_num.set(self.pnio_seq_num.get() + 1);
-    }
+    /// update_health sets the health told by the result of a command, the
+    /// health it had before is returned if it changed
+    pub fn update_health<T>(&self, result: &error::Result<T>) -> Option<DeviceHealth> {
+        let health = match result {
+            Ok(_) => DeviceHealth::Healthy,
+            Err(err) => err.health(),
+        };
+        let previous = self.health.replace(health);
 
-    pub fn connect_req(&self) -> anyhow::Result<()> {
-        // PNIO ARBlockReq
-        let session_key: u16 = 1;
-        self.ar_uuid.replace(Uuid::new_v4());
-        let pnio_header_arblock =
-            ArBlockReq::new(*self.ar_uuid.borrow(), session_key, self.object_uuid);
-        let pnio_data = None;
-        // PNIO
-        let pnio = self.construct_pnio_req(
-            false,
-            PnioHeaderEnum::ArBlockReq(pnio_header_arblock),
-            pnio_data,
-        )?;
-        // DCE/RPC packet
-        let req_dcerpc_packet =
-            self.construct_dcerpc_req(OpNum::Connect, pnio.concat()?.into_boxed_slice())?;
-        // send connect request
-        self.transport_client
-            .send(req_dcerpc_packet.concat()?.into_boxed_slice())?;
-        // receive connect request's response
-        self.transport_client.receive()?;
-
-        Ok(())
+        (previous != health).then_some(previous)
     }
 
-    pub fn send_common_write_req(
+    // send write request carrying the HART command, a write the station is too
+    // busy for is sent again, other errors are returned by their kind
+    pub async fn send_common_write_req(
         &self,
         data_record_num: u16,
-        command: u8,
+        command: u16,
         command_payload: Option<&[u8]>,
-    ) -> anyhow::Result<()> {
-        let device_id = *self.device_id.borrow();
-        let user_specified_data =
-            HartCommand::construct_write_request(device_id, command, command_payload)?;
-        let pnio_data = Some(user_specified_data);
-
-        let iod_write_req_header =
-            self.construct_iod_header_req(false, data_record_num, &pnio_data)?;
-
-        let pnio = self.construct_pnio_req(
-            false,
-            PnioHeaderEnum::IodReq(iod_write_req_header),
-            pnio_data,
-        )?;
-
-        let req_dcerpc_packet =
-            self.construct_dcerpc_req(OpNum::Write, pnio.concat()?.into_boxed_slice())?;
-
-        if let Err(err) = self
-            .transport_client
-            .send(req_dcerpc_packet.concat()?.into_boxed_slice())
-        {
-            return Err(anyhow!("failed to send dcerpc packet: {err}"));
-        };
+    ) -> error::Result<()> {
+        const RETRY_MAX: u32 = 3;
+        let mut retry = 0;
 
-        // receive write request's response
-        let buffer = self.transport_client.receive()?;
-        let res_dcerpc_packet = TryInto::<DceRpcPacket>::try_into(buffer.to_vec())?;
-        let res_pnio_packet = TryInto::<Pnio>::try_into(res_dcerpc_packet.data.to_vec())?;
-        if res_pnio_packet.status == Some([0x00; 4]) {
-            return Ok(());
+        loop {
+            let err = match self
+                .write_req(data_record_num, command, command_payload)
+                .await
+            {
+                Err(err) => err,
+                result => return result,
+            };
+            match station_backoff(&err, retry) {
+                Some(backoff) if retry < RETRY_MAX => {
+                    log::debug!("retry counter: {retry}, {err}, send request again");
+                    retry += 1;
+                    time::sleep(backoff).await;
+                }
+                _ => return Err(err),
+            }
         }
-        Err(anyhow!("{:?}", res_pnio_packet.status.unwrap()))
+    }
+
+    async fn write_req(
+        &self,
+        data_record_num: u16,
+        command: u16,
+        command_payload: Option<&[u8]>,
+    ) -> error::Result<()> {
+        let user_specified_data =
+            HartCommand::construct_write_request(self.address(), command, command_payload)?;
+
+        self.session
+            .write_req(
+                self.slot_num,
+                self.subslot_num,
+                data_record_num,
+                user_specified_data,
+            )
+            .await
     }
 
     // send read request to read the response,
     // return HART statuses 2 bytes (all commands have this),
     // and the rest is command specific response,
     // check HART specification for relevant HART command
-    pub fn send_common_read_req(
+    pub async fn send_common_read_req(
         &self,
         data_record_number: u16,
-        command: u8, // this is just to verify whether the response of the request
-                     // is indeed the correct corresponds
-    ) -> anyhow::Result<(u8, Box<[u8]>)> {
-        const RETRY_MAX: u8 = 10;
+        command: u16, // this is just to verify whether the response of the request
+                      // is indeed the correct corresponds
+    ) -> error::Result<(u8, Box<[u8]>)> {
+        const RETRY_MAX: u32 = 10;
         let mut read_again = true;
         let mut retry = 0;
         // first byte is the response code, second byte is device status
@@ -278,84 +303,77 @@ impl PnioDevice {
         // number the valid bytes, starting from the statuses
         // TODO: this data_length include statuses or not?
         let mut data_length: u8 = Default::default();
-        let mut final_device_id: [u8; 5] = [0x00; 5];
 
         while read_again {
             if retry >= RETRY_MAX {
-                return Err(anyhow!("failed to get valid data after 10 tries"));
+                return Err(HartError::NotReady { command }.into());
             }
 
-            // increment the request's sequence number
-            self.next_request();
-
-            // PNIO IODReadReqHeader
-            let iod_read_req_header =
-                self.construct_iod_header_req(true, data_record_number, &None)?;
-            // PNIO packet
-            let pnio_data = None;
-            let pnio = self.construct_pnio_req(
-                true,
-                PnioHeaderEnum::IodReq(iod_read_req_header),
-                pnio_data,
-            )?;
-            // DCE/RPC packet
-            let req_dcerpc_packet =
-                self.construct_dcerpc_req(OpNum::Read, pnio.concat()?.into_boxed_slice())?;
-
-            // send read request
-            self.transport_client
-                .send(req_dcerpc_packet.concat()?.into_boxed_slice())?;
-            // receive read request's response
-            let buffer = self.transport_client.receive()?;
-            // DCE/RPC response packet
-            let res_dcerpc_packet = TryInto::<DceRpcPacket>::try_into(buffer.to_vec())?;
-            // PNIO response packet
-            let res_pnio_packet = TryInto::<Pnio>::try_into(res_dcerpc_packet.data.to_vec())?;
-            // handle first command 0 to find the device_id
+            // send read request and receive its response, the station may be
+            // too busy to answer, read again then
+            let pnio_data = match self
+                .session
+                .read_req(self.slot_num, self.subslot_num, data_record_number)
+                .await
+            {
+                Ok(pnio_data) => pnio_data,
+                Err(err) => match station_backoff(&err, retry) {
+                    Some(backoff) => {
+                        log::debug!("retry counter: {retry}, {err}, send request again");
+                        retry += 1;
+                        time::sleep(backoff).await;
+                        continue;
+                    }
+                    None => return Err(err),
+                },
+            };
+            // handle the identity command (0, 11 or 21) to find the device_id
             // retrieve the device id from PNIO response packet's payload
-            if command == 0 && *self.device_id.borrow() == [0x00; 5] {
-                read_again = res_pnio_packet.pnio_data.is_some_and(|pnio_data| {
+            if *self.device_id.borrow() == [0x00; 5] {
+                read_again = pnio_data.is_some_and(|pnio_data| {
                     if pnio_data
                         .first()
                         .is_some_and(|v| *v == self.data_ready_flag)
                     {
-                        // TODO:
-                        // to get the device_type_code in order to form the device_id,
-                        // it's uncertain that it would work for all kind of hart devices,
-                        // but it certainly works on the device that i am working on.
-                        let device_type_code_result =
-                            TryInto::<[u8; 2]>::try_into(pnio_data.get(9..11).unwrap()).map_err(
-                                |_| "failed to parse device_type_code from the command 0 response",
-                            );
-
-                        if device_type_code_result.is_err() {
-                            log::error!("{:?}", device_type_code_result.err());
-                            // read again
-                            return true;
+                        let frame = match identity_frame(&pnio_data) {
+                            Ok(frame) => frame,
+                            Err(err) => {
+                                log::error!(
+                                    "failed to parse the command {command} response: {err}"
+                                );
+                                // read again
+                                return true;
+                            }
+                        };
+                        // the statuses of a device not executing the command
+                        // are checked below
+                        if let Some(&[response_code, device_status]) = frame.data.get(..2) {
+                            if HartError::from_statuses(command, [response_code, device_status])
+                                .is_none()
+                            {
+                                // TODO:
+                                // to get the device_type_code in order to form the device_id,
+                                // it's uncertain that it would work for all kind of hart devices,
+                                // but it certainly works on the device that i am working on.
+                                match identity_device_id(&frame.data) {
+                                    // got device_id for this hart device
+                                    Ok(device_id) => {
+                                        self.device_id.replace(device_id);
+                                    }
+                                    Err(err) => {
+                                        log::error!(
+                                            "failed to parse device_id from the command {command} response: {err}"
+                                        );
+                                        // read again
+                                        return true;
+                                    }
+                                }
+                            }
                         }
+                        data_length = frame.byte_count;
+                        status_and_hart_response = frame.data;
 
-                        let device_id_result = TryInto::<[u8; 3]>::try_into(
-                            pnio_data.get(17..20).unwrap(),
-                        )
-                        .map_err(|_| "failed to parse device_id from the command 0 response");
-
-                        if device_id_result.is_err() {
-                            log::error!("{:?}", device_id_result.err());
-                            // read again
-                            return true;
-                        }
-
-                        let device_type_code = device_type_code_result.unwrap();
-                        let device_id = device_id_result.unwrap();
-                        final_device_id[0] = device_type_code[0];
-                        final_device_id[1] = device_type_code[1];
-                        final_device_id[2] = device_id[0];
-                        final_device_id[3] = device_id[1];
-                        final_device_id[4] = device_id[2];
-                        // got device_id for this hart device
-                        self.device_id.replace(final_device_id);
-
-                        true
+                        false
                     } else {
                         // read again
                         true
@@ -364,12 +382,22 @@ impl PnioDevice {
             } else {
                 // handle common responseo other than command 0, just parse the
                 // payload and return the bytes
-                read_again = res_pnio_packet.pnio_data.is_some_and(|data| {
+                read_again = pnio_data.is_some_and(|data| {
                     if data.first().is_some_and(|v| *v == self.data_ready_flag) {
-                        data_length = data[9];
-                        let hart_only = &data[10..];
-                        status_and_hart_response = hart_only.to_vec().into_boxed_slice();
-                        false
+                        match common_response(&data) {
+                            Ok((len, hart_only)) => {
+                                data_length = len;
+                                status_and_hart_response = hart_only.into();
+                                false
+                            }
+                            Err(err) => {
+                                log::error!(
+                                    "failed to parse the command {command} response: {err}"
+                                );
+                                // read again
+                                true
+                            }
+                        }
                     } else {
                         // read again
                         true
@@ -386,9 +414,70 @@ impl PnioDevice {
             );
             retry += 1;
 
-            thread::sleep(time::Duration::from_secs(1));
+            time::sleep(self.data_ready_interval).await;
+        }
+
+        // the field device may answer without executing the command
+        if let Some(&[response_code, device_status]) = status_and_hart_response.get(..2) {
+            if let Some(err) = HartError::from_statuses(command, [response_code, device_status]) {
+                return Err(err.into());
+            }
+        }
+
+        // the response of command 31 echoes the number of the command
+        if HartFrame::expand(command).1.is_some() {
+            let (expanded_command, status_and_data) =
+                HartFrame::split_expansion(&status_and_hart_response)?;
+            if expanded_command != command {
+                return Err(ProtocolError::Invalid {
+                    field: "HART expanded command",
+                    value: expanded_command.into(),
+                }
+                .into());
+            }
+            data_length = data_length.saturating_sub(2);
+            status_and_hart_response = status_and_data;
         }
 
         Ok((data_length, status_and_hart_response))
     }
 }
+
+// station_backoff tells how long to wait before the request the station failed
+// is sent again, the errors of the field device are left to `send_command`
+fn station_backoff(err: &Error, retry: u32) -> Option<Duration> {
+    match err {
+        Error::Hart(_) => None,
+        _ => err.backoff(retry),
+    }
+}
+
+// identity_frame decodes the HART frame of the response record
+fn identity_frame(record: &[u8]) -> Result<HartFrame, ProtocolError> {
+    let mut cursor = Cursor::new(record);
+    cursor.skip(HartFrame::RECORD_OFFSET, "response record")?;
+    HartFrame::decode(&mut cursor)
+}
+
+// identity_device_id forms the unique identifier from the device type code and
+// the device id of the statuses and the data of the identity response, the
+// data of command 11 and 21 is the one of command 0
+fn identity_device_id(status_and_data: &[u8]) -> Result<[u8; 5], ProtocolError> {
+    let mut cursor = Cursor::new(status_and_data);
+    cursor.skip(3, "statuses and expansion code")?;
+    let [manufacturer_id, device_type] = cursor.array("device type code")?;
+    cursor.skip(6, "identity response")?;
+    let [a, b, c] = cursor.array("device id")?;
+
+    Ok([manufacturer_id, device_type, a, b, c])
+}
+
+// common_response splits the response record into the data length and the
+// statuses followed by the data
+fn common_response(record: &[u8]) -> Result<(u8, &[u8]), ProtocolError> {
+    let mut cursor = Cursor::new(record);
+    cursor.skip(9, "response record")?;
+    let data_length = cursor.u8("HART data length")?;
+
+    Ok((data_length, cursor.rest()))
+}
@@ -1,4 +1,3 @@
-use anyhow::anyhow;
 use serde::Serialize;
 use std::cell::Cell;
 
@@ -31,11 +30,8 @@ impl FieldDeviceCommStatus {
         }
     }
 
-    pub fn map_to_comm_status(&self, hart_statuses: [u8; 2]) -> anyhow::Result<()> {
-        let comm_statuses = match hart_statuses.first() {
-            Some(d) => d,
-            None => return Err(anyhow!("failed to get comm statuses")),
-        };
+    pub fn map_to_comm_status(&self, hart_statuses: [u8; 2]) {
+        let comm_statuses = hart_statuses[0];
 
         self.buffer_overflow.set((comm_statuses & 0x02) == 0x02);
         self.communication_failure
@@ -47,7 +43,5 @@ impl FieldDeviceCommStatus {
         self.vertical_parity_error
             .set((comm_statuses & 0x40) == 0x40);
         self.communication_error.set((comm_statuses & 0x80) == 0x80);
-
-        Ok(())
     }
 }
@@ -1,4 +1,3 @@
-use anyhow::anyhow;
 use serde::Serialize;
 use std::cell::Cell;
 
@@ -32,11 +31,8 @@ impl FieldDeviceStatus {
         }
     }
 
-    pub fn map_to_device_status(&self, hart_statuses: [u8; 2]) -> anyhow::Result<()> {
-        let device_statuses = match hart_statuses.get(1) {
-            Some(d) => d,
-            None => return Err(anyhow!("failed to get device statuses")),
-        };
+    pub fn map_to_device_status(&self, hart_statuses: [u8; 2]) {
+        let device_statuses = hart_statuses[1];
 
         self.primary_variable_out_of_limits
             .set((device_statuses & 0x01) == 0x01);
@@ -53,7 +49,5 @@ impl FieldDeviceStatus {
             .set((device_statuses & 0x40) == 0x40);
         self.device_malfunction
             .set((device_statuses & 0x80) == 0x80);
-
-        Ok(())
     }
 }
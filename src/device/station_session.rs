@@ -0,0 +1,382 @@
+use crate::{
+    error,
+    protocol::{
+        length, ArBlockReq, BlockHeaderType, DceRpcPacket, Decode, Encode, Identification, Im0,
+        InterfaceVersion, IodReq, OpNum, PacketType, Pnio, PnioHeaderEnum, ProtocolError,
+        ReleaseBlock, IM0_INDEX, IM1_INDEX, IM2_INDEX, IM3_INDEX, IM4_INDEX, READ_RECORD_DATA_LEN,
+    },
+    transport::TransportClient,
+};
+use anyhow::anyhow;
+use std::{
+    cell::{Cell, RefCell},
+    net::IpAddr,
+};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// StationSession is shared by all the HART channels of an IO station, it owns
+/// the endpoint found by the lookup, the socket, the application relationship
+/// and the sequence numbers. A single call is outstanding at a time, its
+/// response is told apart from the others by the activity and the sequence
+/// number (see `TransportClient::call_dcerpc`).
+#[derive(Debug)]
+pub struct StationSession {
+    pub handle: String,
+    pub object_uuid: Uuid,
+    pub interface_uuid: Uuid,
+
+    pub ip_address: IpAddr,
+    pub port: u16,
+
+    pub transport_client: Box<dyn TransportClient>,
+    pub ar_uuid: RefCell<Uuid>,
+    pub session_key: Cell<u16>,
+    /// connected tells if the application relationship is established
+    pub connected: Cell<bool>,
+    /// unanswered tells the last call got no response, the station may have
+    /// dropped the application relationship meanwhile
+    pub unanswered: Cell<bool>,
+
+    pub activity: RefCell<Uuid>,
+    pub dcerpc_seq_num: Cell<u32>,
+    pub pnio_seq_num: Cell<u16>,
+
+    // held from sending the request until its response is received
+    call_lock: Mutex<()>,
+}
+
+impl StationSession {
+    pub fn new(
+        handle: String,
+        object_uuid: Uuid,
+        interface_uuid: Uuid,
+        ip_address: IpAddr,
+        port: u16,
+        transport_client: Box<dyn TransportClient>,
+    ) -> Self {
+        Self {
+            handle,
+            object_uuid,
+            interface_uuid,
+            ip_address,
+            port,
+            transport_client,
+            ar_uuid: RefCell::new(Uuid::new_v4()),
+            session_key: Cell::new(0),
+            connected: Cell::new(false),
+            unanswered: Cell::new(false),
+            activity: RefCell::new(Uuid::new_v4()),
+            dcerpc_seq_num: Cell::new(0),
+            pnio_seq_num: Cell::new(0),
+            call_lock: Mutex::new(()),
+        }
+    }
+
+    /// is_stale tells the application relationship has to be established
+    /// again, the station lost it or stopped answering
+    pub fn is_stale(&self) -> bool {
+        !self.connected.get() || self.unanswered.get()
+    }
+
+    fn next_request(&self) {
+        self.dcerpc_seq_num
+            .set(self.dcerpc_seq_num.get().wrapping_add(1));
+        self.pnio_seq_num
+            .set(self.pnio_seq_num.get().wrapping_add(1));
+    }
+
+    // call sends the PNIO request built with the PNIO sequence number of the
+    // call and returns the PNIO response, every call takes new sequence numbers
+    async fn call<F>(&self, opnum: OpNum, build: F) -> error::Result<Pnio>
+    where
+        F: FnOnce(u16) -> Pnio,
+    {
+        let _call = self.call_lock.lock().await;
+        self.next_request();
+
+        let pnio = build(self.pnio_seq_num.get());
+        let req_dcerpc_packet = DceRpcPacket::new(
+            PacketType::Request,
+            self.object_uuid,
+            self.interface_uuid,
+            InterfaceVersion::ReadWrite,
+            *self.activity.borrow(),
+            self.dcerpc_seq_num.get(),
+            opnum,
+            pnio.to_bytes()?.into_boxed_slice(),
+        );
+
+        let res_dcerpc_packet = match self.transport_client.call_dcerpc(&req_dcerpc_packet).await {
+            Ok(packet) => packet,
+            Err(err) => {
+                self.unanswered.set(err.is_timeout());
+                return Err(err);
+            }
+        };
+        self.unanswered.set(false);
+        let res_pnio_packet = Pnio::from_bytes(&res_dcerpc_packet.data)?;
+        // the response must not be taken for the data of another record
+        if let Some(mismatch) = res_pnio_packet.mismatch(&pnio) {
+            self.transport_client
+                .drop_packet(&format!("{mismatch} of the response does not match"));
+            return Err(
+                anyhow!("{opnum:?} response does not match the request: {mismatch}").into(),
+            );
+        }
+        // the station lost the AR, e.g. it restarted, it has to be connected again
+        if res_pnio_packet
+            .error()
+            .is_some_and(|err| err.is_ar_unknown())
+        {
+            log::warn!("AR {} is unknown to the station", self.ar_uuid.borrow());
+            self.connected.set(false);
+        }
+
+        Ok(res_pnio_packet)
+    }
+
+    /// connect_req establishes the application relationship, the PNIO error
+    /// the station answers with is returned as `Error::Pnio`
+    pub async fn connect_req(&self) -> error::Result<()> {
+        // release the application relationship still open before reconnecting,
+        // the station would keep it until it times out otherwise
+        if self.connected.get() {
+            if let Err(err) = self.release_req().await {
+                log::warn!("failed to release AR {}: {err}", self.ar_uuid.borrow());
+            }
+        }
+
+        // PNIO ARBlockReq
+        let session_key = self.session_key.get().wrapping_add(1);
+        self.session_key.set(session_key);
+        self.ar_uuid.replace(Uuid::new_v4());
+        let ar_block_req = ArBlockReq::new(*self.ar_uuid.borrow(), session_key, self.object_uuid);
+        // send connect request and receive its response
+        let res_pnio_packet = self
+            .call(OpNum::Connect, |_| {
+                Pnio::new(None, PnioHeaderEnum::ArBlockReq(ar_block_req), None)
+            })
+            .await?;
+        if let Some(err) = res_pnio_packet.error() {
+            return Err(err.into());
+        }
+        self.connected.set(true);
+
+        Ok(())
+    }
+
+    /// release_req releases the application relationship, nothing is sent if
+    /// it has not been established
+    pub async fn release_req(&self) -> error::Result<()> {
+        if !self.connected.get() {
+            return Ok(());
+        }
+        // the AR is gone for this side whatever the station answers
+        self.connected.set(false);
+
+        // PNIO IODReleaseReq
+        let release_block = ReleaseBlock::new(
+            BlockHeaderType::ReleaseBlockReqType,
+            *self.ar_uuid.borrow(),
+            self.session_key.get(),
+        );
+        let res_pnio_packet = self
+            .call(OpNum::Release, |_| {
+                Pnio::new(None, PnioHeaderEnum::ReleaseBlock(release_block), None)
+            })
+            .await?;
+        if let Some(err) = res_pnio_packet.error() {
+            return Err(err.into());
+        }
+
+        log::debug!("AR {} released", self.ar_uuid.borrow());
+        Ok(())
+    }
+
+    /// write_req writes the record of the slot/subslot within the application
+    /// relationship, the PNIO error the station answers with is returned as
+    /// `Error::Pnio`
+    pub async fn write_req(
+        &self,
+        slot_num: u16,
+        subslot_num: u16,
+        index: u16,
+        data: Box<[u8]>,
+    ) -> error::Result<()> {
+        let ar_uuid = *self.ar_uuid.borrow();
+        let record_data_len: u32 = length(data.len(), "record data")?;
+        let res_pnio_packet = self
+            .call(OpNum::Write, |pnio_seq_num| {
+                let iod_write_req_header = IodReq::new(
+                    BlockHeaderType::IodWriteReqType,
+                    pnio_seq_num,
+                    ar_uuid,
+                    slot_num,
+                    subslot_num,
+                    index,
+                    record_data_len,
+                );
+                Pnio::new(
+                    None,
+                    PnioHeaderEnum::IodReq(iod_write_req_header),
+                    Some(data),
+                )
+            })
+            .await?;
+
+        match (res_pnio_packet.error(), res_pnio_packet.status) {
+            (Some(err), _) => Err(err.into()),
+            (None, Some(_)) => Ok(()),
+            (None, None) => Err(ProtocolError::Missing {
+                field: "PNIO status",
+            }
+            .into()),
+        }
+    }
+
+    /// read_req reads the record of the slot/subslot within the application
+    /// relationship, the PNIO error the station answers with is returned as
+    /// `Error::Pnio`
+    pub async fn read_req(
+        &self,
+        slot_num: u16,
+        subslot_num: u16,
+        index: u16,
+    ) -> error::Result<Option<Box<[u8]>>> {
+        let ar_uuid = *self.ar_uuid.borrow();
+        let res_pnio_packet = self
+            .read(OpNum::Read, ar_uuid, slot_num, subslot_num, index)
+            .await?;
+
+        Ok(res_pnio_packet.pnio_data)
+    }
+
+    /// read_implicit_req reads a record without an application relationship,
+    /// i.e. IODReadReq with a NIL AR UUID, there's no need for `connect_req`.
+    /// The slot and subslot are those of the request, e.g. slot 0 for the
+    /// records of the interface module.
+    pub async fn read_implicit_req(
+        &self,
+        slot_num: u16,
+        subslot_num: u16,
+        index: u16,
+    ) -> error::Result<Box<[u8]>> {
+        let res_pnio_packet = self
+            .read(
+                OpNum::ReadImplicit,
+                Uuid::nil(),
+                slot_num,
+                subslot_num,
+                index,
+            )
+            .await?;
+
+        // the record data length tells the valid bytes of the record
+        let record_data_len = match &res_pnio_packet.pnio_header {
+            PnioHeaderEnum::IodRes(iod_res) => u32::from_be_bytes(iod_res.record_data_len),
+            _ => {
+                return Err(ProtocolError::Missing {
+                    field: "IODReadResHeader",
+                }
+                .into())
+            }
+        };
+        let mut data = res_pnio_packet.pnio_data.unwrap_or_default().into_vec();
+        data.truncate(record_data_len as usize);
+
+        Ok(data.into_boxed_slice())
+    }
+
+    async fn read(
+        &self,
+        opnum: OpNum,
+        ar_uuid: Uuid,
+        slot_num: u16,
+        subslot_num: u16,
+        index: u16,
+    ) -> error::Result<Pnio> {
+        let res_pnio_packet = self
+            .call(opnum, |pnio_seq_num| {
+                let iod_read_req_header = IodReq::new(
+                    BlockHeaderType::IodReadReqType,
+                    pnio_seq_num,
+                    ar_uuid,
+                    slot_num,
+                    subslot_num,
+                    index,
+                    READ_RECORD_DATA_LEN,
+                );
+                Pnio::new(None, PnioHeaderEnum::IodReq(iod_read_req_header), None)
+            })
+            .await?;
+        if let Some(err) = res_pnio_packet.error() {
+            return Err(err.into());
+        }
+
+        Ok(res_pnio_packet)
+    }
+
+    /// read_identification reads the I&M records of the slot/subslot, e.g.
+    /// slot 0 subslot 1 for the station or the device's own slot/subslot for
+    /// the module. I&M0 is mandatory, I&M1 to I&M4 are only read if I&M0 tells
+    /// they are supported and are left out if they can't be read.
+    pub async fn read_identification(
+        &self,
+        slot_num: u16,
+        subslot_num: u16,
+    ) -> error::Result<Identification> {
+        let im0 = Im0::from_bytes(
+            &self
+                .read_implicit_req(slot_num, subslot_num, IM0_INDEX)
+                .await?,
+        )?;
+
+        Ok(Identification {
+            slot_number: slot_num,
+            subslot_number: subslot_num,
+            im1: self
+                .read_optional_im(&im0, slot_num, subslot_num, 1, IM1_INDEX)
+                .await,
+            im2: self
+                .read_optional_im(&im0, slot_num, subslot_num, 2, IM2_INDEX)
+                .await,
+            im3: self
+                .read_optional_im(&im0, slot_num, subslot_num, 3, IM3_INDEX)
+                .await,
+            im4: self
+                .read_optional_im(&im0, slot_num, subslot_num, 4, IM4_INDEX)
+                .await,
+            im0,
+        })
+    }
+
+    // read I&M`n` if it's supported, failures are only logged
+    async fn read_optional_im<T>(
+        &self,
+        im0: &Im0,
+        slot_num: u16,
+        subslot_num: u16,
+        n: u16,
+        index: u16,
+    ) -> Option<T>
+    where
+        T: Decode,
+    {
+        if !im0.supports(n) {
+            return None;
+        }
+
+        let result = self
+            .read_implicit_req(slot_num, subslot_num, index)
+            .await
+            .and_then(|record| Ok(T::from_bytes(&record)?));
+        match result {
+            Ok(im) => Some(im),
+            Err(err) => {
+                log::warn!("failed to read I&M{n} of slot {slot_num} subslot {subslot_num}: {err}");
+                None
+            }
+        }
+    }
+}
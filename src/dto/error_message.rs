@@ -0,0 +1,12 @@
+use crate::{device::health::DeviceHealth, error::Error};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ErrorMessageDto<'a> {
+    pub timestamp: &'a str,
+    pub device_unique_name: &'a str,
+    pub hart_device_name: &'a str,
+    pub hart_command: u16,
+    pub health: DeviceHealth,
+    pub error: &'a Error,
+}
@@ -0,0 +1,14 @@
+use crate::hart::{HartResponse, ManufacturerId};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct HartMessageDto<'a> {
+    pub timestamp: &'a str,
+    pub device_unique_name: &'a str,
+    pub hart_device_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<ManufacturerId>,
+    pub hart_command: u16,
+    pub length: u8,
+    pub response: &'a HartResponse,
+}
@@ -0,0 +1,16 @@
+use crate::protocol::Identification;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct IdentificationMessageDto<'a> {
+    pub timestamp: &'a str,
+    pub device_unique_name: &'a str,
+    pub hart_device_name: &'a str,
+    /// station is the identification of the interface module, i.e. slot 0
+    /// subslot 1, left out if it can't be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub station: Option<&'a Identification>,
+    /// module is the identification of the slot/subslot of the HART device
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module: Option<&'a Identification>,
+}
@@ -5,7 +5,7 @@ pub struct IotedgeMessageDto<'a> {
     pub timestamp: &'a str,
     pub device_unique_name: &'a str,
     pub hart_device_name: &'a str,
-    pub hart_command: u8,
+    pub hart_command: u16,
     pub length: u8,
     pub bytes: &'a [u8],
 }
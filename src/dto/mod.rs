@@ -1,2 +1,5 @@
+pub mod error_message;
+pub mod hart_message;
+pub mod identification_message;
 pub mod iotedge_message;
-pub mod temp;
+pub mod write_audit_message;
@@ -0,0 +1,51 @@
+use crate::{error::Error, hart::HartWrite};
+use serde::Serialize;
+use std::fmt;
+
+/// WriteAuditMessageDto records a write to the configuration of a device,
+/// whether it was sent or not.
+#[derive(Serialize)]
+pub struct WriteAuditMessageDto<'a> {
+    pub timestamp: &'a str,
+    pub device_unique_name: &'a str,
+    pub hart_device_name: &'a str,
+    pub hart_command: u16,
+    /// write is the typed write, `None` for a write configured as raw bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write: Option<&'a HartWrite>,
+    /// data is the payload of the write in hex
+    pub data: &'a str,
+    pub outcome: WriteOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_code: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a Error>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteOutcome {
+    /// the device executed the write, maybe with a warning
+    Written,
+    /// the device answered with a command specific error
+    Rejected,
+    /// the write policy of the device doesn't allow the command
+    Denied,
+    /// the payload doesn't pass the validation
+    Invalid,
+    /// the write didn't get through, see the error
+    Failed,
+}
+
+impl fmt::Display for WriteOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match self {
+            Self::Written => "written",
+            Self::Rejected => "rejected",
+            Self::Denied => "denied",
+            Self::Invalid => "invalid",
+            Self::Failed => "failed",
+        };
+        write!(f, "{outcome}")
+    }
+}
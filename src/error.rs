@@ -0,0 +1,411 @@
+use crate::{
+    device::health::DeviceHealth,
+    protocol::{DceRpcError, PnioError, ProtocolError},
+};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::{fmt, io, net::SocketAddr, time::Duration};
+
+// the backoff doubles with every retry up to this
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error is what a request to a station or to one of its HART devices fails
+/// with, its kind drives the retries, the backoff, the health of the device
+/// and the error message sent to the output
+#[derive(Debug)]
+pub enum Error {
+    Transport(TransportError),
+    /// the station refused or failed the call
+    DceRpc(DceRpcError),
+    /// the station answered the request with a PNIO status other than ok
+    Pnio(PnioError),
+    /// the field device answered the HART command with an error
+    Hart(HartError),
+    /// the datagram can't be decoded or the packet can't be encoded
+    Decode(ProtocolError),
+    /// anything else, e.g. a response to another record
+    Other(anyhow::Error),
+}
+
+/// TransportError is a failure of the socket
+#[derive(Debug)]
+pub enum TransportError {
+    /// nothing received in time
+    Timeout,
+    Bind {
+        address: SocketAddr,
+        source: io::Error,
+    },
+    Io(io::Error),
+}
+
+/// HartError is a HART response telling the command has not been executed,
+/// see the 1st status byte in the HART specification (TS20099)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HartError {
+    /// the command specific response code of an error
+    ResponseCode { command: u16, code: u8 },
+    /// the field device did not get the command right, the status holds the
+    /// parity, overrun, framing and buffer overflow bits
+    CommStatus { command: u16, status: u8 },
+    /// the AI module has no response to the command yet
+    NotReady { command: u16 },
+}
+
+// response codes of the errors with the same meaning for every command
+const RESPONSE_CODE_BUSY: u8 = 32;
+const RESPONSE_CODE_DR_INITIATE: u8 = 33;
+const RESPONSE_CODE_DR_RUNNING: u8 = 34;
+const RESPONSE_CODE_DR_DEAD: u8 = 35;
+const RESPONSE_CODE_DR_CONFLICT: u8 = 36;
+const RESPONSE_CODE_NOT_IMPLEMENTED: u8 = 64;
+
+impl HartError {
+    /// from_statuses tells the error of the 2 status bytes of a response, the
+    /// command specific response codes are left to the decoder of the command
+    pub fn from_statuses(command: u16, statuses: [u8; 2]) -> Option<Self> {
+        let [code, _] = statuses;
+        if code & 0x80 == 0x80 {
+            return Some(Self::CommStatus {
+                command,
+                status: code,
+            });
+        }
+
+        match code {
+            RESPONSE_CODE_BUSY..=RESPONSE_CODE_DR_CONFLICT | RESPONSE_CODE_NOT_IMPLEMENTED => {
+                Some(Self::ResponseCode { command, code })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn command(&self) -> u16 {
+        match *self {
+            Self::ResponseCode { command, .. }
+            | Self::CommStatus { command, .. }
+            | Self::NotReady { command } => command,
+        }
+    }
+
+    /// is_retryable tells if the command may succeed when it's sent again,
+    /// e.g. the device is busy or the frame got garbled on the loop. A device
+    /// that does not answer is not, it may have executed the command anyway.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::ResponseCode { code, .. } => matches!(
+                *code,
+                RESPONSE_CODE_BUSY | RESPONSE_CODE_DR_INITIATE | RESPONSE_CODE_DR_RUNNING
+            ),
+            Self::CommStatus { .. } => true,
+            Self::NotReady { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for HartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ResponseCode { command, code } => {
+                let text = match *code {
+                    RESPONSE_CODE_BUSY => "busy",
+                    RESPONSE_CODE_DR_INITIATE => "delayed response initiated",
+                    RESPONSE_CODE_DR_RUNNING => "delayed response running",
+                    RESPONSE_CODE_DR_DEAD => "delayed response dead",
+                    RESPONSE_CODE_DR_CONFLICT => "delayed response conflict",
+                    RESPONSE_CODE_NOT_IMPLEMENTED => "command not implemented",
+                    _ => "error",
+                };
+                write!(f, "HART command {command}: {text} (response code {code})")
+            }
+            Self::CommStatus { command, status } => write!(
+                f,
+                "HART command {command}: communication error (status {status:#04x})"
+            ),
+            Self::NotReady { command } => {
+                write!(f, "HART command {command}: no response from the device")
+            }
+        }
+    }
+}
+
+/// ErrorKind tells the errors apart in the messages sent to the output
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Timeout,
+    Bind,
+    Io,
+    DceRpc,
+    Pnio,
+    Hart,
+    Decode,
+    Other,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Transport(TransportError::Timeout) => ErrorKind::Timeout,
+            Self::Transport(TransportError::Bind { .. }) => ErrorKind::Bind,
+            Self::Transport(TransportError::Io(_)) => ErrorKind::Io,
+            Self::DceRpc(_) => ErrorKind::DceRpc,
+            Self::Pnio(_) => ErrorKind::Pnio,
+            Self::Hart(_) => ErrorKind::Hart,
+            Self::Decode(_) => ErrorKind::Decode,
+            Self::Other(_) => ErrorKind::Other,
+        }
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self,
+            Self::Transport(TransportError::Timeout) | Self::DceRpc(DceRpcError::Timeout)
+        )
+    }
+
+    /// is_retryable tells if the same request may succeed later on. A station
+    /// that does not answer though it's been pinged is not, it's left until
+    /// the next cycle.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::DceRpc(DceRpcError::Reject(status) | DceRpcError::Fault(status)) => {
+                status.is_transient()
+            }
+            Self::DceRpc(DceRpcError::Nocall) => true,
+            Self::Pnio(err) => err.is_retryable(),
+            Self::Hart(err) => err.is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// backoff is how long to wait before the request is sent again after its
+    /// `retry`th failure, `None` if it's not worth sending it again
+    pub fn backoff(&self, retry: u32) -> Option<Duration> {
+        if !self.is_retryable() {
+            return None;
+        }
+
+        // the field device is answered by the AI module in a few hundred
+        // milliseconds while a busy station takes longer
+        let base = match self {
+            Self::Hart(_) => Duration::from_millis(500),
+            _ => Duration::from_secs(1),
+        };
+        Some(
+            base.saturating_mul(2u32.saturating_pow(retry))
+                .min(MAX_BACKOFF),
+        )
+    }
+
+    /// health is what the error tells about the device the request was sent to
+    pub fn health(&self) -> DeviceHealth {
+        match self {
+            Self::Transport(_) | Self::DceRpc(_) => DeviceHealth::Unreachable,
+            Self::Hart(HartError::NotReady { .. }) => DeviceHealth::Unreachable,
+            // the device answers, if only with an error
+            Self::Hart(_) => DeviceHealth::Degraded,
+            _ if self.is_retryable() => DeviceHealth::Degraded,
+            _ => DeviceHealth::Failed,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(TransportError::Timeout) => write!(f, "no response in time"),
+            Self::Transport(TransportError::Bind { address, source }) => {
+                write!(f, "failed to bind {address}: {source}")
+            }
+            Self::Transport(TransportError::Io(err)) => write!(f, "transport failed: {err}"),
+            Self::DceRpc(err) => err.fmt(f),
+            Self::Pnio(err) => err.fmt(f),
+            Self::Hart(err) => err.fmt(f),
+            Self::Decode(err) => write!(f, "failed to decode: {err}"),
+            Self::Other(err) => write!(f, "{err:#}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(TransportError::Bind { source, .. }) => Some(source),
+            Self::Transport(TransportError::Io(err)) => Some(err),
+            Self::DceRpc(err) => Some(err),
+            Self::Pnio(err) => Some(err),
+            Self::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Error", 5)?;
+        s.serialize_field("kind", &self.kind())?;
+        s.serialize_field("text", &self.to_string())?;
+        s.serialize_field("retryable", &self.is_retryable())?;
+        match self {
+            Self::Pnio(err) => s.serialize_field("status", &hex::encode(err.status()))?,
+            Self::Hart(HartError::ResponseCode { code, .. }) => {
+                s.serialize_field("response_code", code)?
+            }
+            Self::Hart(HartError::CommStatus { status, .. }) => {
+                s.serialize_field("response_code", status)?
+            }
+            _ => s.skip_field("status")?,
+        }
+        s.end()
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                Self::Transport(TransportError::Timeout)
+            }
+            _ => Self::Transport(TransportError::Io(err)),
+        }
+    }
+}
+
+impl From<TransportError> for Error {
+    fn from(err: TransportError) -> Self {
+        Self::Transport(err)
+    }
+}
+
+impl From<DceRpcError> for Error {
+    fn from(err: DceRpcError) -> Self {
+        Self::DceRpc(err)
+    }
+}
+
+impl From<PnioError> for Error {
+    fn from(err: PnioError) -> Self {
+        Self::Pnio(err)
+    }
+}
+
+impl From<HartError> for Error {
+    fn from(err: HartError) -> Self {
+        Self::Hart(err)
+    }
+}
+
+impl From<ProtocolError> for Error {
+    fn from(err: ProtocolError) -> Self {
+        Self::Decode(err)
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    /// from keeps the kind of an error passed through anyhow
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<Error>() {
+            Ok(err) => err,
+            Err(err) => Self::Other(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::NcaStatus;
+
+    #[test]
+    fn hart_error_should_be_told_by_the_statuses() {
+        assert_eq!(HartError::from_statuses(9, [0x00, 0x40]), None);
+        // a command specific warning is left to the decoder
+        assert_eq!(HartError::from_statuses(9, [0x08, 0x00]), None);
+
+        let busy = HartError::from_statuses(9, [0x20, 0x00]).unwrap();
+        assert_eq!(
+            busy,
+            HartError::ResponseCode {
+                command: 9,
+                code: 0x20
+            }
+        );
+        assert!(busy.is_retryable());
+        assert_eq!(busy.to_string(), "HART command 9: busy (response code 32)");
+
+        let comm = HartError::from_statuses(9, [0x90, 0x00]).unwrap();
+        assert!(matches!(comm, HartError::CommStatus { status: 0x90, .. }));
+        assert!(comm.is_retryable());
+
+        assert!(!HartError::from_statuses(9, [0x40, 0x00])
+            .unwrap()
+            .is_retryable());
+        assert!(!HartError::NotReady { command: 9 }.is_retryable());
+    }
+
+    #[test]
+    fn error_kind_should_drive_backoff_and_health() {
+        let busy = Error::from(PnioError::from_status([0xdf, 0x80, 0xc2, 0x00]).unwrap());
+        assert_eq!(busy.kind(), ErrorKind::Pnio);
+        assert_eq!(busy.backoff(0), Some(Duration::from_secs(1)));
+        assert_eq!(busy.backoff(2), Some(Duration::from_secs(4)));
+        assert_eq!(busy.backoff(10), Some(MAX_BACKOFF));
+        assert_eq!(busy.health(), DeviceHealth::Degraded);
+
+        let invalid_index = Error::from(PnioError::from_status([0xdf, 0x80, 0xb0, 0x00]).unwrap());
+        assert_eq!(invalid_index.backoff(0), None);
+        assert_eq!(invalid_index.health(), DeviceHealth::Failed);
+
+        let timeout = Error::from(io::Error::from(io::ErrorKind::WouldBlock));
+        assert!(timeout.is_timeout());
+        assert_eq!(timeout.backoff(0), None);
+        assert_eq!(timeout.health(), DeviceHealth::Unreachable);
+
+        let too_busy = Error::from(DceRpcError::Reject(NcaStatus::ServerTooBusy));
+        assert_eq!(too_busy.kind(), ErrorKind::DceRpc);
+        assert!(too_busy.is_retryable());
+
+        let comm = Error::from(HartError::CommStatus {
+            command: 1,
+            status: 0x88,
+        });
+        assert_eq!(comm.backoff(1), Some(Duration::from_secs(1)));
+        assert_eq!(comm.health(), DeviceHealth::Degraded);
+
+        let not_ready = Error::from(HartError::NotReady { command: 1 });
+        assert_eq!(not_ready.backoff(0), None);
+        assert_eq!(not_ready.health(), DeviceHealth::Unreachable);
+
+        assert_eq!(
+            Error::from(ProtocolError::Missing { field: "a" }).health(),
+            DeviceHealth::Failed
+        );
+    }
+
+    #[test]
+    fn error_should_keep_its_kind_through_anyhow() {
+        let err = anyhow::Error::new(Error::from(HartError::NotReady { command: 3 }));
+        assert_eq!(Error::from(err).kind(), ErrorKind::Hart);
+        assert_eq!(
+            Error::from(anyhow::anyhow!("response does not match")).kind(),
+            ErrorKind::Other
+        );
+    }
+
+    #[test]
+    fn error_should_serialize_its_kind() {
+        assert_eq!(
+            serde_json::to_string(&Error::from(
+                PnioError::from_status([0xde, 0x80, 0xa7, 0x01]).unwrap()
+            ))
+            .unwrap(),
+            r#"{"kind":"pnio","text":"IODReadRes: application: busy (status de80a701)","retryable":true,"status":"de80a701"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Error::from(TransportError::Timeout)).unwrap(),
+            r#"{"kind":"timeout","text":"no response in time","retryable":false}"#
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use super::response::{write_latin1, write_packed_ascii};
+use crate::protocol::{HartAddress, ProtocolError};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+
+/// HartAddressing tells how the unique identifier of a field device is found,
+/// the device answers the identity command with the same data as command 0
+/// and is addressed by its unique identifier afterwards.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum HartAddressing {
+    /// command 0 sent to the polling address, 0-63. The address is the one
+    /// configured, the polling addresses are not scanned.
+    PollingAddress {
+        #[serde(deserialize_with = "deserialize_polling_address")]
+        polling_address: u8,
+    },
+    /// command 11 sent to the broadcast address, only the device with the tag
+    /// answers, e.g. one of multidropped devices
+    Tag { tag: String },
+    /// command 21 sent to the broadcast address, only the device with the
+    /// long tag answers
+    LongTag { long_tag: String },
+}
+
+impl Default for HartAddressing {
+    fn default() -> Self {
+        Self::PollingAddress { polling_address: 0 }
+    }
+}
+
+impl HartAddressing {
+    /// identity_command is the command answered with the identity of the
+    /// device
+    pub fn identity_command(&self) -> u16 {
+        match self {
+            Self::PollingAddress { .. } => 0,
+            Self::Tag { .. } => 11,
+            Self::LongTag { .. } => 21,
+        }
+    }
+
+    /// identity_payload is the data of the identity command, the tag as
+    /// packed ASCII or the long tag as ISO Latin-1
+    pub fn identity_payload(&self) -> Result<Option<Vec<u8>>, ProtocolError> {
+        let mut buf: Vec<u8> = vec![];
+        match self {
+            Self::PollingAddress { .. } => return Ok(None),
+            Self::Tag { tag } => write_packed_ascii(&mut buf, tag, 6, "tag")?,
+            Self::LongTag { long_tag } => write_latin1(&mut buf, long_tag, 32, "long tag")?,
+        }
+
+        Ok(Some(buf))
+    }
+}
+
+fn deserialize_polling_address<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let polling_address = u8::deserialize(deserializer)?;
+    if polling_address > HartAddress::MAX_POLLING_ADDRESS {
+        return Err(serde::de::Error::custom(format!(
+            "polling address {polling_address} is out of range 0-{}",
+            HartAddress::MAX_POLLING_ADDRESS
+        )));
+    }
+
+    Ok(polling_address)
+}
+
+impl fmt::Display for HartAddressing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PollingAddress { polling_address } => {
+                write!(f, "polling address {polling_address}")
+            }
+            Self::Tag { tag } => write!(f, "tag `{tag}`"),
+            Self::LongTag { long_tag } => write!(f, "long tag `{long_tag}`"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identity_payload_should_encode_the_tag() {
+        assert_eq!(HartAddressing::default().identity_command(), 0);
+        assert_eq!(HartAddressing::default().identity_payload(), Ok(None));
+
+        let tag = HartAddressing::Tag {
+            tag: "pt-101".to_string(),
+        };
+        assert_eq!(tag.identity_command(), 11);
+        assert_eq!(
+            hex::encode(tag.identity_payload().unwrap().unwrap()),
+            "414b71c31820"
+        );
+
+        let long_tag = HartAddressing::LongTag {
+            long_tag: "inlet pressure".to_string(),
+        };
+        let payload = long_tag.identity_payload().unwrap().unwrap();
+        assert_eq!(long_tag.identity_command(), 21);
+        assert_eq!(payload.len(), 32);
+        assert_eq!(&payload[..14], b"inlet pressure");
+
+        let too_long = HartAddressing::Tag {
+            tag: "PT-1010-A".to_string(),
+        };
+        assert!(too_long.identity_payload().is_err());
+    }
+
+    #[test]
+    fn deserialize_should_read_the_mode() {
+        let addressing: HartAddressing = serde_yaml::from_str("mode: tag\ntag: PT-101").unwrap();
+        assert_eq!(
+            addressing,
+            HartAddressing::Tag {
+                tag: "PT-101".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_should_reject_polling_address_above_63() {
+        let addressing = |polling_address: u8| {
+            serde_yaml::from_str::<HartAddressing>(&format!(
+                "mode: polling_address\npolling_address: {polling_address}"
+            ))
+        };
+
+        assert_eq!(
+            addressing(63).unwrap(),
+            HartAddressing::PollingAddress {
+                polling_address: 63
+            }
+        );
+        let err = addressing(64).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("polling address 64 is out of range"));
+    }
+}
@@ -0,0 +1,226 @@
+use super::{
+    response::{read_bytes, HartCommandData, HartResponse, RawResponse},
+    siemens::{SitransPDsCommand48Decoder, SITRANS_P_DS_III},
+    universal::*,
+};
+use crate::{
+    device::{spec_comm_status::FieldDeviceCommStatus, spec_status::FieldDeviceStatus},
+    protocol::ProtocolError,
+};
+use std::collections::HashMap;
+
+/// DeviceTypeCode is the manufacturer id followed by the device type,
+/// i.e. the first 2 bytes of the long address.
+pub type DeviceTypeCode = [u8; 2];
+
+/// HartDecoder decodes the command specific data of a HART response, i.e. the
+/// bytes following the 2 status bytes.
+pub trait HartDecoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError>;
+}
+
+/// RawDecoder passes the data through for commands without a decoder.
+pub struct RawDecoder;
+
+impl HartDecoder for RawDecoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::Raw(RawResponse {
+            bytes: data.to_vec(),
+        }))
+    }
+}
+
+/// HartDecoderRegistry holds the decoders by command, a decoder registered for
+/// a device type takes precedence over the one registered for all devices.
+pub struct HartDecoderRegistry {
+    decoders: HashMap<(u16, Option<DeviceTypeCode>), Box<dyn HartDecoder>>,
+    raw: RawDecoder,
+}
+
+impl HartDecoderRegistry {
+    pub fn new() -> Self {
+        Self {
+            decoders: HashMap::new(),
+            raw: RawDecoder,
+        }
+    }
+
+    /// register the decoder of a command, for all devices if device_type is `None`
+    pub fn register(
+        &mut self,
+        command: u16,
+        device_type: Option<DeviceTypeCode>,
+        decoder: Box<dyn HartDecoder>,
+    ) {
+        self.decoders.insert((command, device_type), decoder);
+    }
+
+    pub fn get(&self, command: u16, device_type: Option<DeviceTypeCode>) -> &dyn HartDecoder {
+        device_type
+            .and_then(|d| self.decoders.get(&(command, Some(d))))
+            .or_else(|| self.decoders.get(&(command, None)))
+            .map(|d| d.as_ref())
+            .unwrap_or(&self.raw)
+    }
+
+    /// decode the statuses and the command specific data of a response, the
+    /// bytes must not include anything beyond the byte count of the response.
+    pub fn decode(
+        &self,
+        command: u16,
+        device_type: Option<DeviceTypeCode>,
+        status_and_data: &[u8],
+    ) -> Result<HartResponse, ProtocolError> {
+        let statuses: [u8; 2] = read_bytes(status_and_data, 0, "statuses")?;
+        let data = &status_and_data[2..];
+
+        let response_code = statuses[0];
+        let comm_status = match response_code & 0x80 == 0x80 {
+            true => {
+                let comm_status = FieldDeviceCommStatus::new();
+                comm_status.map_to_comm_status(statuses);
+                Some(comm_status)
+            }
+            false => None,
+        };
+        let field_device_status = FieldDeviceStatus::new();
+        field_device_status.map_to_device_status(statuses);
+
+        let data = match self.get(command, device_type).decode(data) {
+            Ok(d) => d,
+            // a device reporting an error may leave out the data, keep the bytes
+            // as they are instead of failing the whole response
+            Err(_) if response_code != 0 => self.raw.decode(data)?,
+            Err(err) => return Err(err),
+        };
+
+        Ok(HartResponse {
+            command,
+            response_code,
+            comm_status,
+            field_device_status,
+            data,
+        })
+    }
+}
+
+impl Default for HartDecoderRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(0, None, Box::new(Command0Decoder));
+        registry.register(1, None, Box::new(Command1Decoder));
+        registry.register(2, None, Box::new(Command2Decoder));
+        registry.register(3, None, Box::new(Command3Decoder));
+        registry.register(6, None, Box::new(LoopConfigurationDecoder));
+        registry.register(7, None, Box::new(LoopConfigurationDecoder));
+        registry.register(8, None, Box::new(Command8Decoder));
+        registry.register(9, None, Box::new(Command9Decoder));
+        registry.register(11, None, Box::new(Command0Decoder));
+        registry.register(12, None, Box::new(MessageDecoder));
+        registry.register(13, None, Box::new(TagDescriptorDateDecoder));
+        registry.register(14, None, Box::new(Command14Decoder));
+        registry.register(15, None, Box::new(Command15Decoder));
+        registry.register(16, None, Box::new(FinalAssemblyNumberDecoder));
+        registry.register(17, None, Box::new(MessageDecoder));
+        registry.register(18, None, Box::new(TagDescriptorDateDecoder));
+        registry.register(19, None, Box::new(FinalAssemblyNumberDecoder));
+        registry.register(20, None, Box::new(LongTagDecoder));
+        registry.register(21, None, Box::new(Command0Decoder));
+        registry.register(22, None, Box::new(LongTagDecoder));
+        registry.register(38, None, Box::new(Command38Decoder));
+        registry.register(48, None, Box::new(Command48Decoder));
+        registry.register(
+            48,
+            Some(SITRANS_P_DS_III),
+            Box::new(SitransPDsCommand48Decoder),
+        );
+        registry
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn registry_should_prefer_device_specific_decoder() {
+        let registry = HartDecoderRegistry::default();
+        let mut bytes = vec![0x00, 0x00];
+        bytes.extend([0x00; 25]);
+
+        let response = registry.decode(48, Some(SITRANS_P_DS_III), &bytes).unwrap();
+        assert!(matches!(
+            response.data,
+            HartCommandData::SitransPDsCommand48(_)
+        ));
+
+        let response = registry.decode(48, Some([0x26, 0x01]), &bytes).unwrap();
+        assert!(matches!(response.data, HartCommandData::Command48(_)));
+
+        let response = registry.decode(48, None, &bytes).unwrap();
+        assert!(matches!(response.data, HartCommandData::Command48(_)));
+    }
+
+    #[test]
+    fn registry_should_fall_back_to_raw_decoder() {
+        let registry = HartDecoderRegistry::default();
+
+        let response = registry
+            .decode(128, None, &[0x00, 0x00, 0x01, 0x02])
+            .unwrap();
+        let HartCommandData::Raw(raw) = response.data else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(raw.bytes, vec![0x01, 0x02]);
+    }
+
+    #[test]
+    fn registry_should_decode_extended_command() {
+        struct DiagnosticsDecoder;
+        impl HartDecoder for DiagnosticsDecoder {
+            fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+                Ok(HartCommandData::Raw(RawResponse {
+                    bytes: data.iter().rev().copied().collect(),
+                }))
+            }
+        }
+
+        let mut registry = HartDecoderRegistry::default();
+        registry.register(1024, Some([0x26, 0x01]), Box::new(DiagnosticsDecoder));
+
+        let response = registry
+            .decode(1024, Some([0x26, 0x01]), &[0x00, 0x00, 0x01, 0x02])
+            .unwrap();
+        assert_eq!(response.command, 1024);
+        let HartCommandData::Raw(raw) = response.data else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(raw.bytes, vec![0x02, 0x01]);
+    }
+
+    #[test]
+    fn registry_should_return_error_on_short_response() {
+        let registry = HartDecoderRegistry::default();
+
+        assert_eq!(
+            registry.decode(9, None, &[0x00]).unwrap_err(),
+            ProtocolError::Truncated {
+                field: "statuses",
+                needed: 2,
+                remaining: 1
+            }
+        );
+        assert_eq!(
+            registry.decode(9, None, &[0x00, 0x00, 0x00]).unwrap_err(),
+            ProtocolError::Missing {
+                field: "device variable"
+            }
+        );
+
+        // communication error, the device does not send any data
+        let response = registry.decode(9, None, &[0x88, 0x00]).unwrap();
+        assert!(response.is_comm_error());
+        assert!(response.comm_status.is_some());
+        assert!(matches!(response.data, HartCommandData::Raw(_)));
+    }
+}
@@ -0,0 +1,15 @@
+mod addressing;
+mod decoder;
+mod response;
+mod siemens;
+mod tables;
+mod universal;
+mod write;
+
+pub use addressing::*;
+pub use decoder::*;
+pub use response::*;
+pub use siemens::*;
+pub use tables::*;
+pub use universal::*;
+pub use write::*;
@@ -0,0 +1,218 @@
+use super::{siemens::SitransPDsCommand48Response, universal::*};
+use crate::device::{spec_comm_status::FieldDeviceCommStatus, spec_status::FieldDeviceStatus};
+use crate::protocol::ProtocolError;
+use serde::Serialize;
+
+/// HartResponse is a decoded HART command response, the statuses are common
+/// to all commands while data is command specific.
+#[derive(Debug, Serialize)]
+pub struct HartResponse {
+    pub command: u16,
+    /// response_code is the 1st status byte, it's either a command response code
+    /// or, if the most significant bit is set, the communication status
+    pub response_code: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comm_status: Option<FieldDeviceCommStatus>,
+    pub field_device_status: FieldDeviceStatus,
+    pub data: HartCommandData,
+}
+
+impl HartResponse {
+    /// is_comm_error tells if the field device reported a communication error,
+    /// in that case there is no command specific data
+    pub fn is_comm_error(&self) -> bool {
+        self.response_code & 0x80 == 0x80
+    }
+}
+
+/// HartCommandData is the command specific data of a response.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum HartCommandData {
+    Command0(Command0Response),
+    Command1(Command1Response),
+    Command2(Command2Response),
+    Command3(Command3Response),
+    LoopConfiguration(LoopConfigurationResponse),
+    Command8(Command8Response),
+    Command9(Command9Response),
+    Message(MessageResponse),
+    TagDescriptorDate(TagDescriptorDateResponse),
+    Command14(Command14Response),
+    Command15(Command15Response),
+    FinalAssemblyNumber(FinalAssemblyNumberResponse),
+    LongTag(LongTagResponse),
+    Command38(Command38Response),
+    Command48(Command48Response),
+    SitransPDsCommand48(SitransPDsCommand48Response),
+    Raw(RawResponse),
+}
+
+/// RawResponse is used for commands without a decoder, the bytes are
+/// passed through as they are.
+#[derive(Debug, Serialize)]
+pub struct RawResponse {
+    pub bytes: Vec<u8>,
+}
+
+// helpers for decoders, a response shorter than expected is an error --------
+
+pub(super) fn read_bytes<const N: usize>(
+    data: &[u8],
+    start: usize,
+    name: &'static str,
+) -> Result<[u8; N], ProtocolError> {
+    data.get(start..start + N)
+        .and_then(|b| TryInto::<[u8; N]>::try_into(b).ok())
+        .ok_or(truncated(data, start, N, name))
+}
+
+pub(super) fn read_u8(data: &[u8], start: usize, name: &'static str) -> Result<u8, ProtocolError> {
+    Ok(read_bytes::<1>(data, start, name)?[0])
+}
+
+pub(super) fn read_u16(
+    data: &[u8],
+    start: usize,
+    name: &'static str,
+) -> Result<u16, ProtocolError> {
+    Ok(u16::from_be_bytes(read_bytes(data, start, name)?))
+}
+
+pub(super) fn read_u24(
+    data: &[u8],
+    start: usize,
+    name: &'static str,
+) -> Result<u32, ProtocolError> {
+    let b = read_bytes::<3>(data, start, name)?;
+    Ok(u32::from_be_bytes([0x00, b[0], b[1], b[2]]))
+}
+
+pub(super) fn read_f32(
+    data: &[u8],
+    start: usize,
+    name: &'static str,
+) -> Result<f32, ProtocolError> {
+    Ok(f32::from_be_bytes(read_bytes(data, start, name)?))
+}
+
+// truncated is the error of a field reaching beyond the end of the response
+fn truncated(data: &[u8], start: usize, len: usize, name: &'static str) -> ProtocolError {
+    ProtocolError::Truncated {
+        field: name,
+        needed: len,
+        remaining: data.len().saturating_sub(start),
+    }
+}
+
+/// read_packed_ascii reads HART packed ASCII, 4 characters of 6 bits are
+/// packed into 3 bytes, the trailing spaces are removed.
+pub(super) fn read_packed_ascii(
+    data: &[u8],
+    start: usize,
+    len: usize,
+    name: &'static str,
+) -> Result<String, ProtocolError> {
+    let bytes = data
+        .get(start..start + len)
+        .ok_or(truncated(data, start, len, name))?;
+
+    let mut text = String::with_capacity(len / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let mut bits = [0x00; 3];
+        bits[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0x00, bits[0], bits[1], bits[2]]);
+        for shift in [18, 12, 6, 0] {
+            let c = ((bits >> shift) & 0x3f) as u8;
+            // 0x00-0x1f map to 0x40-0x5f, 0x20-0x3f are unchanged
+            text.push((c | ((!c & 0x20) << 1)) as char);
+        }
+    }
+
+    Ok(text.trim_end().to_string())
+}
+
+/// read_latin1 reads an ISO Latin-1 string padded with null characters.
+pub(super) fn read_latin1(
+    data: &[u8],
+    start: usize,
+    len: usize,
+    name: &'static str,
+) -> Result<String, ProtocolError> {
+    let bytes = data
+        .get(start..start + len)
+        .ok_or(truncated(data, start, len, name))?;
+
+    Ok(bytes
+        .iter()
+        .take_while(|b| **b != 0x00)
+        .map(|b| *b as char)
+        .collect::<String>()
+        .trim_end()
+        .to_string())
+}
+
+/// write_packed_ascii writes the text as HART packed ASCII of `len` bytes,
+/// padded with spaces, lowercase letters are written as uppercase.
+pub(super) fn write_packed_ascii(
+    buf: &mut Vec<u8>,
+    text: &str,
+    len: usize,
+    field: &'static str,
+) -> Result<(), ProtocolError> {
+    let capacity = len / 3 * 4;
+    if text.chars().count() > capacity {
+        return Err(ProtocolError::InvalidLength {
+            field,
+            len: text.chars().count(),
+        });
+    }
+
+    let mut codes = Vec::with_capacity(capacity);
+    for c in text.chars().map(|c| c.to_ascii_uppercase()) {
+        // 0x40-0x5f map to 0x00-0x1f, 0x20-0x3f are unchanged
+        if !(' '..='_').contains(&c) {
+            return Err(ProtocolError::Invalid {
+                field,
+                value: c.into(),
+            });
+        }
+        codes.push(c as u8 & 0x3f);
+    }
+    codes.resize(capacity, b' ');
+
+    for chunk in codes.chunks(4) {
+        let bits = chunk
+            .iter()
+            .fold(0u32, |bits, code| (bits << 6) | *code as u32);
+        buf.extend(&bits.to_be_bytes()[1..]);
+    }
+    Ok(())
+}
+
+/// write_latin1 writes the text as ISO Latin-1 of `len` bytes, padded with
+/// null characters.
+pub(super) fn write_latin1(
+    buf: &mut Vec<u8>,
+    text: &str,
+    len: usize,
+    field: &'static str,
+) -> Result<(), ProtocolError> {
+    let start = buf.len();
+    for c in text.chars() {
+        let b = u8::try_from(c).map_err(|_| ProtocolError::Invalid {
+            field,
+            value: c.into(),
+        })?;
+        buf.push(b);
+    }
+    if buf.len() - start > len {
+        return Err(ProtocolError::InvalidLength {
+            field,
+            len: buf.len() - start,
+        });
+    }
+
+    buf.resize(start + len, 0x00);
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use super::{
+    decoder::{DeviceTypeCode, HartDecoder},
+    response::{read_u8, HartCommandData},
+    universal::{Command48Decoder, Command48Response},
+};
+use crate::protocol::ProtocolError;
+use serde::Serialize;
+
+/// SITRANS_P_DS_III is the device type code of the Siemens SITRANS P DS III
+/// pressure transmitter, i.e. manufacturer id 0x2a followed by device type 0x0b.
+pub const SITRANS_P_DS_III: DeviceTypeCode = [0x2a, 0x0b];
+
+// command 48 - read additional device status ---------------------------------
+
+/// SitransPDsCommand48Response adds the device specific status bits of the
+/// SITRANS P DS III to the generic command 48 response,
+/// see the device manual, chapter "HART commands"
+#[derive(Debug, Serialize)]
+pub struct SitransPDsCommand48Response {
+    #[serde(flatten)]
+    pub common: Command48Response,
+    pub hw_fw_error: bool,
+    pub diag_alarm: bool,
+    pub diag_warn: bool,
+    pub sim_mode: bool,
+    pub sensor_break_0: bool,
+    pub ram_failure: bool,
+    pub rom_failure: bool,
+    pub sim_pressure: bool,
+    pub sim_sensor_temperature: bool,
+    pub sim_el_temperature: bool,
+    pub watchdog_failed: bool,
+    pub watchdog_triggered: bool,
+    pub service_alarm: bool,
+}
+
+pub struct SitransPDsCommand48Decoder;
+
+impl HartDecoder for SitransPDsCommand48Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        let byte0 = read_u8(data, 0, "device specific status 0")?;
+        let byte1 = read_u8(data, 1, "device specific status 1")?;
+        let byte3 = read_u8(data, 3, "device specific status 3")?;
+        let byte6 = read_u8(data, 6, "extended device status")?;
+        let byte22 = read_u8(data, 22, "device specific status 22")?;
+
+        Ok(HartCommandData::SitransPDsCommand48(
+            SitransPDsCommand48Response {
+                common: Command48Decoder::decode_response(data)?,
+                hw_fw_error: (byte0 & 0x01) == 0x01,
+                diag_alarm: (byte0 & 0x02) == 0x02,
+                diag_warn: (byte0 & 0x04) == 0x04,
+                sim_mode: (byte0 & 0x08) == 0x08,
+                sensor_break_0: (byte0 & 0x10) == 0x10,
+                ram_failure: (byte1 & 0x01) == 0x01,
+                rom_failure: (byte1 & 0x02) == 0x02,
+                sim_pressure: (byte22 & 0x01) == 0x01,
+                sim_sensor_temperature: (byte22 & 0x02) == 0x02,
+                sim_el_temperature: (byte22 & 0x04) == 0x04,
+                watchdog_failed: (byte3 & 0x08) == 0x08,
+                watchdog_triggered: (byte3 & 0x10) == 0x10,
+                service_alarm: (byte6 & 0x01) == 0x01,
+            },
+        ))
+    }
+}
@@ -0,0 +1,13 @@
+common_table! {
+    /// AlarmSelectionCode is the loop current action on a device failure,
+    /// common table 6.
+    pub enum AlarmSelectionCode: u8 {
+        High = 0, "high";
+        Low = 1, "low";
+        HoldLastOutputValue = 239, "hold last output value";
+        NotUsed = 250, "not used";
+        None = 251, "none";
+        Unknown = 252, "unknown";
+        Special = 253, "special";
+    }
+}
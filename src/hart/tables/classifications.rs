@@ -0,0 +1,39 @@
+common_table! {
+    /// DeviceVariableClassification tells what a device variable measures,
+    /// it determines the set of valid units, common table 21.
+    pub enum DeviceVariableClassification: u8 {
+        NotClassified = 0, "not classified";
+        Temperature = 64, "temperature";
+        Pressure = 65, "pressure";
+        VolumetricFlow = 66, "volumetric flow";
+        Velocity = 67, "velocity";
+        Volume = 68, "volume";
+        Length = 69, "length";
+        Time = 70, "time";
+        Mass = 71, "mass";
+        MassFlow = 72, "mass flow";
+        MassPerVolume = 73, "mass per volume";
+        Viscosity = 74, "viscosity";
+        AngularVelocity = 75, "angular velocity";
+        Area = 76, "area";
+        Energy = 77, "energy";
+        Force = 78, "force";
+        Power = 79, "power";
+        Frequency = 80, "frequency";
+        Analytical = 81, "analytical";
+        Capacitance = 82, "capacitance";
+        Emf = 83, "emf";
+        Current = 84, "current";
+        Resistance = 85, "resistance";
+        Angle = 86, "angle";
+        Conductance = 87, "conductance";
+        VolumePerVolume = 88, "volume per volume";
+        VolumePerMass = 89, "volume per mass";
+        Concentration = 90, "concentration";
+        ValveActuator = 91, "valve/actuator";
+        Level = 92, "level";
+        VortexFlow = 93, "vortex flow";
+        MagFlow = 94, "mag flow";
+        CoriolisFlow = 95, "coriolis flow";
+    }
+}
@@ -0,0 +1,148 @@
+common_table! {
+    /// ManufacturerId identifies the manufacturer of a field device, it's the
+    /// first byte of the long address before HART 7, common table 8.
+    pub enum ManufacturerId: u16 {
+        Acromag = 0x0001, "Acromag";
+        AllenBradley = 0x0002, "Allen Bradley";
+        Ametek = 0x0003, "Ametek";
+        AnalogDevices = 0x0004, "Analog Devices";
+        ElsagBailey = 0x0005, "Elsag Bailey";
+        Beckman = 0x0006, "Beckman";
+        BellMicrosensor = 0x0007, "Bell Microsensor";
+        Bourns = 0x0008, "Bourns";
+        BristolBabcock = 0x0009, "Bristol Babcock";
+        BrooksInstrument = 0x000a, "Brooks Instrument";
+        Chessell = 0x000b, "Chessell";
+        CombustionEngineering = 0x000c, "Combustion Engineering";
+        DanielIndustries = 0x000d, "Daniel Industries";
+        Delta = 0x000e, "Delta";
+        DieterichStandard = 0x000f, "Dieterich Standard";
+        Dohrmann = 0x0010, "Dohrmann";
+        EndressHauser = 0x0011, "Endress+Hauser";
+        FischerPorter = 0x0012, "Fischer & Porter";
+        FisherControls = 0x0013, "Fisher Controls";
+        Foxboro = 0x0014, "Foxboro";
+        Fuji = 0x0015, "Fuji";
+        AbbAutomation = 0x0016, "ABB Automation";
+        Honeywell = 0x0017, "Honeywell";
+        IttBarton = 0x0018, "ITT Barton";
+        KayRaySensall = 0x0019, "Kay Ray/Sensall";
+        Abb = 0x001a, "ABB";
+        LeedsNorthrup = 0x001b, "Leeds & Northrup";
+        Leslie = 0x001c, "Leslie";
+        MSystem = 0x001d, "M-System";
+        Measurex = 0x001e, "Measurex";
+        MicroMotion = 0x001f, "Micro Motion";
+        MooreIndustries = 0x0020, "Moore Industries";
+        MooreProducts = 0x0021, "Moore Products";
+        OhkuraElectric = 0x0022, "Ohkura Electric";
+        Paine = 0x0023, "Paine";
+        RochesterInstrumentSystems = 0x0024, "Rochester Instrument Systems";
+        Ronan = 0x0025, "Ronan";
+        Rosemount = 0x0026, "Rosemount";
+        Peek = 0x0027, "Peek Measurement";
+        Schlumberger = 0x0028, "Schlumberger";
+        Sensall = 0x0029, "Sensall";
+        Siemens = 0x002a, "Siemens";
+        Weed = 0x002b, "Weed";
+        Toshiba = 0x002c, "Toshiba";
+        Transmation = 0x002d, "Transmation";
+        RosemountAnalytic = 0x002e, "Rosemount Analytic";
+        MetsoAutomation = 0x002f, "Metso Automation";
+        Flowserve = 0x0030, "Flowserve";
+        Varec = 0x0031, "Varec";
+        Viatran = 0x0032, "Viatran";
+        DeltaWeed = 0x0033, "Delta/Weed";
+        Westinghouse = 0x0034, "Westinghouse";
+        Xomox = 0x0035, "Xomox";
+        Yamatake = 0x0036, "Yamatake";
+        Yokogawa = 0x0037, "Yokogawa";
+        NuovoPignone = 0x0038, "Nuovo Pignone";
+        Promac = 0x0039, "Promac";
+        Exac = 0x003a, "Exac Corporation";
+        MeggittMobrey = 0x003b, "Meggitt Mobrey";
+        ArcomControlSystem = 0x003c, "Arcom Control System";
+        Princo = 0x003d, "Princo";
+        Smar = 0x003e, "Smar";
+        Eckardt = 0x003f, "Eckardt";
+        MeasurementTechnology = 0x0040, "Measurement Technology";
+        AppliedSystemTechnologies = 0x0041, "Applied System Technologies";
+        Samson = 0x0042, "Samson";
+        SparlingInstruments = 0x0043, "Sparling Instruments";
+        Fireye = 0x0044, "Fireye";
+        Krohne = 0x0045, "Krohne";
+        Betz = 0x0046, "Betz";
+        Druck = 0x0047, "Druck";
+        Sor = 0x0048, "SOR";
+        ElconInstruments = 0x0049, "Elcon Instruments";
+        Emco = 0x004a, "EMCO";
+        Termiflex = 0x004b, "Termiflex";
+        VafInstruments = 0x004c, "VAF Instruments";
+        WestlockControls = 0x004d, "Westlock Controls";
+        Drexelbrook = 0x004e, "Drexelbrook";
+        SaabTankControl = 0x004f, "Saab Tank Control";
+        KTek = 0x0050, "K-TEK";
+        Flowdata = 0x0051, "Flowdata";
+        Draeger = 0x0052, "Draeger";
+        Raytek = 0x0053, "Raytek";
+        MeridianInstruments = 0x0054, "Meridian Instruments";
+        Btg = 0x0055, "BTG";
+        Magnetrol = 0x0056, "Magnetrol";
+        MetsoFieldSystems = 0x0057, "Metso Field Systems";
+        Milltronics = 0x0058, "Milltronics";
+        Helios = 0x0059, "HELIOS";
+        AndersonInstrument = 0x005a, "Anderson Instrument Company";
+        Inor = 0x005b, "INOR";
+        Robertshaw = 0x005c, "Robertshaw";
+        PepperlFuchs = 0x005d, "Pepperl+Fuchs";
+        Accutech = 0x005e, "Accutech";
+        FlowMeasurement = 0x005f, "Flow Measurement";
+        Kamstrup = 0x0060, "Kamstrup";
+        Knick = 0x0061, "Knick";
+        Vega = 0x0062, "VEGA";
+        MtsSystems = 0x0063, "MTS Systems";
+        Oval = 0x0064, "Oval";
+        MasoneilanDresser = 0x0065, "Masoneilan-Dresser";
+        Besta = 0x0066, "BESTA";
+        Ohmart = 0x0067, "Ohmart";
+        HaroldBeck = 0x0068, "Harold Beck and Sons";
+        Rittmeyer = 0x0069, "Rittmeyer Instrumentation";
+        RosselMesstechnik = 0x006a, "Rossel Messtechnik";
+        Wika = 0x006b, "WIKA";
+        BoppReutherHeinrichs = 0x006c, "Bopp & Reuther Heinrichs";
+        PrElectronics = 0x006d, "PR Electronics";
+        JordanControls = 0x006e, "Jordan Controls";
+        Valcom = 0x006f, "Valcom";
+        UsElectricMotors = 0x0070, "US Electric Motors";
+        ApparatebauHundsbach = 0x0071, "Apparatebau Hundsbach";
+        Dynisco = 0x0072, "Dynisco";
+        Spriano = 0x0073, "Spriano";
+        DirectMeasurement = 0x0074, "Direct Measurement";
+        KlayInstruments = 0x0075, "Klay Instruments";
+        ActionInstruments = 0x0076, "Action Instruments";
+        MmgAutomatiky = 0x0077, "MMG Automatiky DTR";
+        Buerkert = 0x0078, "Buerkert Fluid Control Systems";
+        Aaliant = 0x0079, "AALIANT Process Mgt";
+        Pondus = 0x007a, "PONDUS Instruments";
+        ZapPonizej = 0x007b, "ZAP S.A. Ponizej";
+        Gli = 0x007c, "GLI";
+        FisherRosemountPerformance = 0x007d, "Fisher-Rosemount Performance Technologies";
+        PaperMachineComponents = 0x007e, "Paper Machine Components";
+        Labom = 0x007f, "LABOM";
+        Danfoss = 0x0080, "Danfoss";
+        Turbo = 0x0081, "Turbo";
+        TokyoKeiso = 0x0082, "Tokyo Keiso";
+        Smc = 0x0083, "SMC";
+        StatusInstruments = 0x0084, "Status Instruments";
+        Huakong = 0x0085, "Huakong";
+        DuonSystem = 0x0086, "Duon System";
+        VortekInstruments = 0x0087, "Vortek Instruments";
+        AgCrosby = 0x0088, "AG Crosby";
+    }
+}
+
+impl From<u8> for ManufacturerId {
+    fn from(code: u8) -> Self {
+        Self::from(code as u16)
+    }
+}
@@ -0,0 +1,118 @@
+// HART common tables, see https://library.fieldcommgroup.org/20183/TS20183
+
+/// common_table defines an enum for a HART common table, each entry has a code,
+/// a name and optionally a symbol. Codes missing from the table are kept as
+/// `Unlisted` so that no information is lost.
+macro_rules! common_table {
+    (
+        $(#[$meta:meta])*
+        pub enum $table:ident: $repr:ty {
+            $($variant:ident = $code:literal, $name:literal $(, $symbol:literal)?;)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $table {
+            $($variant,)*
+            Unlisted($repr),
+        }
+
+        impl $table {
+            pub fn code(&self) -> $repr {
+                match self {
+                    $(Self::$variant => $code,)*
+                    Self::Unlisted(code) => *code,
+                }
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name,)*
+                    Self::Unlisted(_) => "unlisted",
+                }
+            }
+
+            /// symbol is empty if the table does not define one
+            pub fn symbol(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => common_table!(@symbol $($symbol)?),)*
+                    Self::Unlisted(_) => "",
+                }
+            }
+        }
+
+        impl From<$repr> for $table {
+            fn from(code: $repr) -> Self {
+                match code {
+                    $($code => Self::$variant,)*
+                    _ => Self::Unlisted(code),
+                }
+            }
+        }
+
+        impl serde::Serialize for $table {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::SerializeStruct;
+
+                let symbol = self.symbol();
+                let len = if symbol.is_empty() { 2 } else { 3 };
+                let mut s = serializer.serialize_struct(stringify!($table), len)?;
+                s.serialize_field("code", &self.code())?;
+                s.serialize_field("name", self.name())?;
+                if !symbol.is_empty() {
+                    s.serialize_field("symbol", symbol)?;
+                }
+                s.end()
+            }
+        }
+    };
+    (@symbol) => { "" };
+    (@symbol $symbol:literal) => { $symbol };
+}
+
+mod alarm_selection;
+mod classifications;
+mod manufacturers;
+mod transfer_functions;
+mod units;
+
+pub use alarm_selection::*;
+pub use classifications::*;
+pub use manufacturers::*;
+pub use transfer_functions::*;
+pub use units::*;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn common_table_should_map_code_name_and_symbol() {
+        let unit = UnitCode::from(0x08);
+        assert_eq!(unit, UnitCode::Millibar);
+        assert_eq!(unit.code(), 0x08);
+        assert_eq!(unit.symbol(), "mbar");
+        assert_eq!(
+            serde_json::to_string(&unit).unwrap(),
+            r#"{"code":8,"name":"millibar","symbol":"mbar"}"#
+        );
+
+        let manufacturer = ManufacturerId::from(0x2a_u8);
+        assert_eq!(manufacturer.name(), "Siemens");
+        assert_eq!(
+            serde_json::to_string(&manufacturer).unwrap(),
+            r#"{"code":42,"name":"Siemens"}"#
+        );
+    }
+
+    #[test]
+    fn common_table_should_keep_unlisted_code() {
+        let unit = UnitCode::from(0xc8);
+        assert_eq!(unit, UnitCode::Unlisted(0xc8));
+        assert_eq!(unit.code(), 0xc8);
+        assert_eq!(
+            serde_json::to_string(&unit).unwrap(),
+            r#"{"code":200,"name":"unlisted"}"#
+        );
+    }
+}
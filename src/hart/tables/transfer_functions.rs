@@ -0,0 +1,26 @@
+common_table! {
+    /// TransferFunctionCode is the relation between the primary variable and
+    /// the loop current, common table 3.
+    pub enum TransferFunctionCode: u8 {
+        Linear = 0, "linear", "lin";
+        SquareRoot = 1, "square root", "sqrt";
+        SquareRootThirdPower = 2, "square root third power", "sqrt3";
+        SquareRootFifthPower = 3, "square root fifth power", "sqrt5";
+        SpecialCurve = 4, "special curve", "curve";
+        Square = 5, "square", "sqr";
+        SquareRootWithCutOff = 6, "square root with cut-off", "sqrtcut";
+        EqualPercentage25 = 10, "equal percentage 1:25", "eq%25";
+        EqualPercentage33 = 11, "equal percentage 1:33", "eq%33";
+        EqualPercentage50 = 12, "equal percentage 1:50", "eq%50";
+        QuickOpen25 = 15, "quick open 1:25", "qo25";
+        QuickOpen33 = 16, "quick open 1:33", "qo33";
+        QuickOpen50 = 17, "quick open 1:50", "qo50";
+        Hyperbolic = 30, "hyperbolic", "hyp";
+        Custom = 31, "custom", "custom";
+        DiscreteSwitch = 100, "discrete/switch", "switch";
+        NotUsed = 250, "not used";
+        None = 251, "none";
+        Unknown = 252, "unknown";
+        Special = 253, "special";
+    }
+}
@@ -0,0 +1,226 @@
+common_table! {
+    /// UnitCode is the engineering unit of a device variable, common table 2.
+    pub enum UnitCode: u8 {
+        // pressure
+        InchesOfWater68F = 1, "inches of water at 68 degrees F", "inH2O (68°F)";
+        InchesOfMercury0C = 2, "inches of mercury at 0 degrees C", "inHg (0°C)";
+        FeetOfWater68F = 3, "feet of water at 68 degrees F", "ftH2O (68°F)";
+        MillimetersOfWater68F = 4, "millimeters of water at 68 degrees F", "mmH2O (68°F)";
+        MillimetersOfMercury0C = 5, "millimeters of mercury at 0 degrees C", "mmHg (0°C)";
+        PoundsPerSquareInch = 6, "pounds per square inch", "psi";
+        Bar = 7, "bar", "bar";
+        Millibar = 8, "millibar", "mbar";
+        GramsPerSquareCentimeter = 9, "grams per square centimeter", "g/cm²";
+        KilogramsPerSquareCentimeter = 10, "kilograms per square centimeter", "kg/cm²";
+        Pascals = 11, "pascals", "Pa";
+        Kilopascals = 12, "kilopascals", "kPa";
+        Torr = 13, "torr", "torr";
+        Atmospheres = 14, "atmospheres", "atm";
+        // volumetric flow
+        CubicFeetPerMinute = 15, "cubic feet per minute", "ft³/min";
+        GallonsPerMinute = 16, "gallons per minute", "gal/min";
+        LitersPerMinute = 17, "liters per minute", "l/min";
+        ImperialGallonsPerMinute = 18, "imperial gallons per minute", "impgal/min";
+        CubicMetersPerHour = 19, "cubic meters per hour", "m³/h";
+        // velocity
+        FeetPerSecond = 20, "feet per second", "ft/s";
+        MetersPerSecond = 21, "meters per second", "m/s";
+        // volumetric flow
+        GallonsPerSecond = 22, "gallons per second", "gal/s";
+        MillionGallonsPerDay = 23, "million gallons per day", "Mgal/d";
+        LitersPerSecond = 24, "liters per second", "l/s";
+        MillionLitersPerDay = 25, "million liters per day", "Ml/d";
+        CubicFeetPerSecond = 26, "cubic feet per second", "ft³/s";
+        CubicFeetPerDay = 27, "cubic feet per day", "ft³/d";
+        CubicMetersPerSecond = 28, "cubic meters per second", "m³/s";
+        CubicMetersPerDay = 29, "cubic meters per day", "m³/d";
+        ImperialGallonsPerHour = 30, "imperial gallons per hour", "impgal/h";
+        ImperialGallonsPerDay = 31, "imperial gallons per day", "impgal/d";
+        // temperature
+        DegreesCelsius = 32, "degrees Celsius", "°C";
+        DegreesFahrenheit = 33, "degrees Fahrenheit", "°F";
+        DegreesRankine = 34, "degrees Rankine", "°R";
+        Kelvin = 35, "kelvin", "K";
+        // electrical
+        Millivolts = 36, "millivolts", "mV";
+        Ohms = 37, "ohms", "Ω";
+        Hertz = 38, "hertz", "Hz";
+        Milliamperes = 39, "milliamperes", "mA";
+        // volume
+        Gallons = 40, "gallons", "gal";
+        Liters = 41, "liters", "l";
+        ImperialGallons = 42, "imperial gallons", "impgal";
+        CubicMeters = 43, "cubic meters", "m³";
+        // length
+        Feet = 44, "feet", "ft";
+        Meters = 45, "meters", "m";
+        // volume
+        Barrels = 46, "barrels", "bbl";
+        // length
+        Inches = 47, "inches", "in";
+        Centimeters = 48, "centimeters", "cm";
+        Millimeters = 49, "millimeters", "mm";
+        // time
+        Minutes = 50, "minutes", "min";
+        Seconds = 51, "seconds", "s";
+        Hours = 52, "hours", "h";
+        Days = 53, "days", "d";
+        // viscosity
+        Centistokes = 54, "centistokes", "cSt";
+        Centipoise = 55, "centipoise", "cP";
+        // conductance
+        Microsiemens = 56, "microsiemens", "µS";
+        // miscellaneous
+        Percent = 57, "percent", "%";
+        Volts = 58, "volts", "V";
+        Ph = 59, "pH", "pH";
+        // mass
+        Grams = 60, "grams", "g";
+        Kilograms = 61, "kilograms", "kg";
+        MetricTons = 62, "metric tons", "t";
+        Pounds = 63, "pounds", "lb";
+        ShortTons = 64, "short tons", "STon";
+        LongTons = 65, "long tons", "LTon";
+        // conductivity
+        MillisiemensPerCentimeter = 66, "millisiemens per centimeter", "mS/cm";
+        MicrosiemensPerCentimeter = 67, "microsiemens per centimeter", "µS/cm";
+        // force and torque
+        Newton = 68, "newton", "N";
+        NewtonMeter = 69, "newton meter", "N m";
+        // mass flow
+        GramsPerSecond = 70, "grams per second", "g/s";
+        GramsPerMinute = 71, "grams per minute", "g/min";
+        GramsPerHour = 72, "grams per hour", "g/h";
+        KilogramsPerSecond = 73, "kilograms per second", "kg/s";
+        KilogramsPerMinute = 74, "kilograms per minute", "kg/min";
+        KilogramsPerHour = 75, "kilograms per hour", "kg/h";
+        KilogramsPerDay = 76, "kilograms per day", "kg/d";
+        MetricTonsPerMinute = 77, "metric tons per minute", "t/min";
+        MetricTonsPerHour = 78, "metric tons per hour", "t/h";
+        MetricTonsPerDay = 79, "metric tons per day", "t/d";
+        PoundsPerSecond = 80, "pounds per second", "lb/s";
+        PoundsPerMinute = 81, "pounds per minute", "lb/min";
+        PoundsPerHour = 82, "pounds per hour", "lb/h";
+        PoundsPerDay = 83, "pounds per day", "lb/d";
+        ShortTonsPerMinute = 84, "short tons per minute", "STon/min";
+        ShortTonsPerHour = 85, "short tons per hour", "STon/h";
+        ShortTonsPerDay = 86, "short tons per day", "STon/d";
+        LongTonsPerHour = 87, "long tons per hour", "LTon/h";
+        LongTonsPerDay = 88, "long tons per day", "LTon/d";
+        // energy
+        Decatherm = 89, "decatherm", "Dth";
+        // density
+        SpecificGravityUnits = 90, "specific gravity units", "SGU";
+        GramsPerCubicCentimeter = 91, "grams per cubic centimeter", "g/cm³";
+        KilogramsPerCubicMeter = 92, "kilograms per cubic meter", "kg/m³";
+        PoundsPerGallon = 93, "pounds per gallon", "lb/gal";
+        PoundsPerCubicFoot = 94, "pounds per cubic foot", "lb/ft³";
+        GramsPerMilliliter = 95, "grams per milliliter", "g/ml";
+        KilogramsPerLiter = 96, "kilograms per liter", "kg/l";
+        GramsPerLiter = 97, "grams per liter", "g/l";
+        PoundsPerCubicInch = 98, "pounds per cubic inch", "lb/in³";
+        ShortTonsPerCubicYard = 99, "short tons per cubic yard", "STon/yd³";
+        DegreesTwaddell = 100, "degrees Twaddell", "°Tw";
+        DegreesBrix = 101, "degrees Brix", "°Bx";
+        DegreesBaumeHeavy = 102, "degrees Baume heavy", "°Bé hv";
+        DegreesBaumeLight = 103, "degrees Baume light", "°Bé lt";
+        DegreesApi = 104, "degrees API", "°API";
+        PercentSolidsPerWeight = 105, "percent solids per weight", "% sol/wt";
+        PercentSolidsPerVolume = 106, "percent solids per volume", "% sol/vol";
+        DegreesBalling = 107, "degrees Balling", "°Balling";
+        ProofPerVolume = 108, "proof per volume", "proof/vol";
+        ProofPerMass = 109, "proof per mass", "proof/mass";
+        // volume
+        Bushels = 110, "bushels", "bu";
+        CubicYards = 111, "cubic yards", "yd³";
+        CubicFeet = 112, "cubic feet", "ft³";
+        CubicInches = 113, "cubic inches", "in³";
+        // velocity
+        InchesPerSecond = 114, "inches per second", "in/s";
+        InchesPerMinute = 115, "inches per minute", "in/min";
+        FeetPerMinute = 116, "feet per minute", "ft/min";
+        // angular velocity
+        DegreesPerSecond = 117, "degrees per second", "°/s";
+        RevolutionsPerSecond = 118, "revolutions per second", "rev/s";
+        RevolutionsPerMinute = 119, "revolutions per minute", "rpm";
+        // velocity
+        MetersPerHour = 120, "meters per hour", "m/h";
+        // volumetric flow
+        NormalCubicMetersPerHour = 121, "normal cubic meters per hour", "Nm³/h";
+        NormalLitersPerHour = 122, "normal liters per hour", "Nl/h";
+        StandardCubicFeetPerMinute = 123, "standard cubic feet per minute", "SCFM";
+        // volume
+        LiquidBarrels = 124, "liquid barrels", "bbl liq";
+        // mass
+        Ounces = 125, "ounces", "oz";
+        // energy and power
+        FootPoundForce = 126, "foot pound force", "ft lbf";
+        Kilowatts = 127, "kilowatts", "kW";
+        KilowattHours = 128, "kilowatt hours", "kWh";
+        Horsepower = 129, "horsepower", "hp";
+        // volumetric flow
+        CubicFeetPerHour = 130, "cubic feet per hour", "ft³/h";
+        CubicMetersPerMinute = 131, "cubic meters per minute", "m³/min";
+        BarrelsPerSecond = 132, "barrels per second", "bbl/s";
+        BarrelsPerMinute = 133, "barrels per minute", "bbl/min";
+        BarrelsPerHour = 134, "barrels per hour", "bbl/h";
+        BarrelsPerDay = 135, "barrels per day", "bbl/d";
+        GallonsPerHour = 136, "gallons per hour", "gal/h";
+        ImperialGallonsPerSecond = 137, "imperial gallons per second", "impgal/s";
+        LitersPerHour = 138, "liters per hour", "l/h";
+        // analytical
+        PartsPerMillion = 139, "parts per million", "ppm";
+        // power
+        MegacaloriesPerHour = 140, "megacalories per hour", "Mcal/h";
+        MegajoulesPerHour = 141, "megajoules per hour", "MJ/h";
+        BritishThermalUnitsPerHour = 142, "british thermal units per hour", "BTU/h";
+        // angle
+        Degrees = 143, "degrees", "°";
+        Radians = 144, "radians", "rad";
+        // pressure
+        InchesOfWater60F = 145, "inches of water at 60 degrees F", "inH2O (60°F)";
+        // analytical
+        MicrogramsPerLiter = 146, "micrograms per liter", "µg/l";
+        MicrogramsPerCubicMeter = 147, "micrograms per cubic meter", "µg/m³";
+        PercentConsistency = 148, "percent consistency", "% Cs";
+        VolumePercent = 149, "volume percent", "vol%";
+        PercentSteamQuality = 150, "percent steam quality", "% stm qual";
+        // length
+        FeetInSixteenths = 151, "feet in sixteenths", "ftin16";
+        // volume per mass
+        CubicFeetPerPound = 152, "cubic feet per pound", "ft³/lb";
+        // capacitance
+        Picofarads = 153, "picofarads", "pF";
+        // analytical
+        MillilitersPerLiter = 154, "milliliters per liter", "ml/l";
+        MicrolitersPerLiter = 155, "microliters per liter", "µl/l";
+        PercentPlato = 160, "percent Plato", "% Plato";
+        PercentLowerExplosionLevel = 161, "percent lower explosion level", "% LEL";
+        // energy
+        Megacalories = 162, "megacalories", "Mcal";
+        // resistance
+        Kiloohms = 163, "kiloohms", "kΩ";
+        // energy
+        Megajoules = 164, "megajoules", "MJ";
+        BritishThermalUnits = 165, "british thermal units", "BTU";
+        // volume
+        NormalCubicMeters = 166, "normal cubic meters", "Nm³";
+        NormalLiters = 167, "normal liters", "Nl";
+        StandardCubicFeet = 168, "standard cubic feet", "SCF";
+        // analytical
+        PartsPerBillion = 169, "parts per billion", "ppb";
+        // volumetric flow
+        GallonsPerDay = 235, "gallons per day", "gal/d";
+        // volume
+        Hectoliters = 236, "hectoliters", "hl";
+        // pressure
+        Megapascals = 237, "megapascals", "MPa";
+        InchesOfWater4C = 238, "inches of water at 4 degrees C", "inH2O (4°C)";
+        MillimetersOfWater4C = 239, "millimeters of water at 4 degrees C", "mmH2O (4°C)";
+        // 240 to 249 are manufacturer specific
+        NotUsed = 250, "not used";
+        None = 251, "none";
+        Unknown = 252, "unknown";
+        Special = 253, "special";
+    }
+}
@@ -0,0 +1,816 @@
+use super::{
+    decoder::HartDecoder,
+    response::{
+        read_bytes, read_f32, read_latin1, read_packed_ascii, read_u16, read_u24, read_u8,
+        HartCommandData,
+    },
+    tables::{
+        AlarmSelectionCode, DeviceVariableClassification, ManufacturerId, TransferFunctionCode,
+        UnitCode,
+    },
+};
+use crate::protocol::ProtocolError;
+use serde::{Deserialize, Serialize};
+
+// the byte offsets below are relative to the command specific data, i.e. the
+// bytes following the response code and the field device status,
+// see https://library.fieldcommgroup.org/20127/TS20127 universal command specification
+
+// extended device status (common table 17) ----------------------------------
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ExtendedDeviceStatus {
+    pub maintenance_required: bool,
+    pub device_variable_alert: bool,
+    pub critical_power_failure: bool,
+    pub failure: bool,
+    pub out_of_specification: bool,
+    pub function_check: bool,
+}
+
+impl From<u8> for ExtendedDeviceStatus {
+    fn from(value: u8) -> Self {
+        Self {
+            maintenance_required: (value & 0x01) == 0x01,
+            device_variable_alert: (value & 0x02) == 0x02,
+            critical_power_failure: (value & 0x04) == 0x04,
+            failure: (value & 0x08) == 0x08,
+            out_of_specification: (value & 0x10) == 0x10,
+            function_check: (value & 0x20) == 0x20,
+        }
+    }
+}
+
+// command 0 - read unique identifier -----------------------------------------
+// command 11 - read unique identifier associated with tag --------------------
+// command 21 - read unique identifier associated with long tag ---------------
+
+#[derive(Debug, Serialize)]
+pub struct Command0Response {
+    /// manufacturer and device_type form the expanded device type in HART 7
+    pub manufacturer: ManufacturerId,
+    pub device_type: u8,
+    pub min_preambles_master_to_slave: u8,
+    pub universal_command_revision: u8,
+    pub device_revision_level: u8,
+    pub software_revision_level: u8,
+    pub hardware_revision_level: u8,
+    pub physical_signaling_code: u8,
+    pub flags: u8,
+    pub device_id: u32,
+
+    // HART 6 onwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_preambles_slave_to_master: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_device_variables: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub configuration_change_counter: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extended_device_status: Option<ExtendedDeviceStatus>,
+
+    // HART 7 onwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer_identification_code: Option<ManufacturerId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private_label_distributor_code: Option<ManufacturerId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_profile: Option<u8>,
+}
+
+/// Command0Decoder decodes command 0, command 11 and command 21, they share
+/// the same response.
+pub struct Command0Decoder;
+
+impl HartDecoder for Command0Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        let hw_rev_and_signaling = read_u8(data, 7, "hardware revision")?;
+        let hart6 = data.len() >= 17;
+        let hart7 = data.len() >= 22;
+
+        Ok(HartCommandData::Command0(Command0Response {
+            manufacturer: read_u8(data, 1, "manufacturer id")?.into(),
+            device_type: read_u8(data, 2, "device type")?,
+            min_preambles_master_to_slave: read_u8(data, 3, "number of preambles")?,
+            universal_command_revision: read_u8(data, 4, "universal command revision")?,
+            device_revision_level: read_u8(data, 5, "device revision level")?,
+            software_revision_level: read_u8(data, 6, "software revision level")?,
+            hardware_revision_level: hw_rev_and_signaling >> 3,
+            physical_signaling_code: hw_rev_and_signaling & 0x07,
+            flags: read_u8(data, 8, "flags")?,
+            device_id: read_u24(data, 9, "device id")?,
+            min_preambles_slave_to_master: hart6.then(|| data[12]),
+            max_device_variables: hart6.then(|| data[13]),
+            configuration_change_counter: match hart6 {
+                true => Some(read_u16(data, 14, "configuration change counter")?),
+                false => None,
+            },
+            extended_device_status: hart6.then(|| data[16].into()),
+            manufacturer_identification_code: match hart7 {
+                true => Some(read_u16(data, 17, "manufacturer identification code")?.into()),
+                false => None,
+            },
+            private_label_distributor_code: match hart7 {
+                true => Some(read_u16(data, 19, "private label distributor code")?.into()),
+                false => None,
+            },
+            device_profile: hart7.then(|| data[21]),
+        }))
+    }
+}
+
+// command 1 - read primary variable ------------------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct Command1Response {
+    pub unit: UnitCode,
+    pub primary_variable: f32,
+}
+
+pub struct Command1Decoder;
+
+impl HartDecoder for Command1Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::Command1(Command1Response {
+            unit: read_u8(data, 0, "primary variable unit code")?.into(),
+            primary_variable: read_f32(data, 1, "primary variable")?,
+        }))
+    }
+}
+
+// command 2 - read loop current and percent of range -------------------------
+
+#[derive(Debug, Serialize)]
+pub struct Command2Response {
+    /// loop_current is in milliamperes
+    pub loop_current: f32,
+    pub percent_of_range: f32,
+}
+
+pub struct Command2Decoder;
+
+impl HartDecoder for Command2Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::Command2(Command2Response {
+            loop_current: read_f32(data, 0, "loop current")?,
+            percent_of_range: read_f32(data, 4, "percent of range")?,
+        }))
+    }
+}
+
+// command 3 - read dynamic variables and loop current ------------------------
+
+#[derive(Debug, Serialize)]
+pub struct Command3Response {
+    /// loop_current is in milliamperes
+    pub loop_current: f32,
+    /// dynamic_variables are the PV, SV, TV and QV in this order, a device
+    /// returns only the ones it supports
+    pub dynamic_variables: Vec<DynamicVariable>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DynamicVariable {
+    pub unit: UnitCode,
+    pub value: f32,
+}
+
+pub struct Command3Decoder;
+
+impl Command3Decoder {
+    const VARIABLE_LEN: usize = 5;
+}
+
+impl HartDecoder for Command3Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        let loop_current = read_f32(data, 0, "loop current")?;
+
+        // at least the primary variable, at most 4 dynamic variables
+        let variable_count = (data.len() - 4) / Self::VARIABLE_LEN;
+        if variable_count == 0 {
+            return Err(ProtocolError::Missing {
+                field: "primary variable",
+            });
+        }
+
+        let dynamic_variables = (0..variable_count.min(4))
+            .map(|i| {
+                let start = 4 + i * Self::VARIABLE_LEN;
+                Ok(DynamicVariable {
+                    unit: read_u8(data, start, "dynamic variable unit code")?.into(),
+                    value: read_f32(data, start + 1, "dynamic variable value")?,
+                })
+            })
+            .collect::<Result<Vec<_>, ProtocolError>>()?;
+
+        Ok(HartCommandData::Command3(Command3Response {
+            loop_current,
+            dynamic_variables,
+        }))
+    }
+}
+
+// command 6 - write polling address ------------------------------------------
+// command 7 - read loop configuration ----------------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct LoopConfigurationResponse {
+    pub polling_address: u8,
+    /// loop_current_mode tells if the loop current signals the primary
+    /// variable (1) or is fixed (0), HART 6 onwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loop_current_mode: Option<u8>,
+}
+
+/// LoopConfigurationDecoder decodes command 6, whose response echoes the
+/// request, and command 7, the loop current mode is only sent by HART 6
+/// onwards devices.
+pub struct LoopConfigurationDecoder;
+
+impl HartDecoder for LoopConfigurationDecoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::LoopConfiguration(
+            LoopConfigurationResponse {
+                polling_address: read_u8(data, 0, "polling address")?,
+                loop_current_mode: data.get(1).copied(),
+            },
+        ))
+    }
+}
+
+// command 8 - read dynamic variable classifications --------------------------
+
+#[derive(Debug, Serialize)]
+pub struct Command8Response {
+    pub primary_variable_classification: DeviceVariableClassification,
+    pub secondary_variable_classification: DeviceVariableClassification,
+    pub tertiary_variable_classification: DeviceVariableClassification,
+    pub quaternary_variable_classification: DeviceVariableClassification,
+}
+
+pub struct Command8Decoder;
+
+impl HartDecoder for Command8Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::Command8(Command8Response {
+            primary_variable_classification: read_u8(data, 0, "pv classification")?.into(),
+            secondary_variable_classification: read_u8(data, 1, "sv classification")?.into(),
+            tertiary_variable_classification: read_u8(data, 2, "tv classification")?.into(),
+            quaternary_variable_classification: read_u8(data, 3, "qv classification")?.into(),
+        }))
+    }
+}
+
+// command 9 - read device variables with status ------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct Command9Response {
+    pub extended_device_status: ExtendedDeviceStatus,
+    pub device_variables: Vec<Command9DeviceVariable>,
+    /// time_stamp is in 1/32 ms since midnight, HART 7 onwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_stamp: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Command9DeviceVariable {
+    pub device_variable_code: u8,
+    pub device_variable_classification: DeviceVariableClassification,
+    pub unit: UnitCode,
+    pub value: f32,
+    pub status: u8,
+}
+
+pub struct Command9Decoder;
+
+impl Command9Decoder {
+    const SLOT_LEN: usize = 8;
+    const TIME_STAMP_LEN: usize = 4;
+}
+
+impl HartDecoder for Command9Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        let extended_device_status = read_u8(data, 0, "extended device status")?;
+
+        // 8 bytes per requested device variable, followed by the time stamp
+        // for HART 7 devices
+        let slots_len = data.len() - 1;
+        let slot_count = slots_len / Self::SLOT_LEN;
+        let time_stamp = match slots_len % Self::SLOT_LEN == Self::TIME_STAMP_LEN {
+            true => Some(u32::from_be_bytes(read_bytes(
+                data,
+                1 + slot_count * Self::SLOT_LEN,
+                "time stamp",
+            )?)),
+            false => None,
+        };
+
+        if slot_count == 0 {
+            return Err(ProtocolError::Missing {
+                field: "device variable",
+            });
+        }
+
+        let device_variables = (0..slot_count)
+            .map(|i| {
+                let start = 1 + i * Self::SLOT_LEN;
+                Ok(Command9DeviceVariable {
+                    device_variable_code: read_u8(data, start, "device variable code")?,
+                    device_variable_classification: read_u8(data, start + 1, "classification")?
+                        .into(),
+                    unit: read_u8(data, start + 2, "unit code")?.into(),
+                    value: read_f32(data, start + 3, "device variable value")?,
+                    status: read_u8(data, start + 7, "device variable status")?,
+                })
+            })
+            .collect::<Result<Vec<_>, ProtocolError>>()?;
+
+        Ok(HartCommandData::Command9(Command9Response {
+            extended_device_status: extended_device_status.into(),
+            device_variables,
+            time_stamp,
+        }))
+    }
+}
+
+// command 12 - read message --------------------------------------------------
+// command 17 - write message -------------------------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct MessageResponse {
+    pub message: String,
+}
+
+/// MessageDecoder decodes command 12 and command 17, whose response echoes
+/// the 32 characters packed ASCII message.
+pub struct MessageDecoder;
+
+impl HartDecoder for MessageDecoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::Message(MessageResponse {
+            message: read_packed_ascii(data, 0, 24, "message")?,
+        }))
+    }
+}
+
+// command 13 - read tag, descriptor, date ------------------------------------
+// command 18 - write tag, descriptor, date -----------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct TagDescriptorDateResponse {
+    pub tag: String,
+    pub descriptor: String,
+    pub date: HartDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HartDate {
+    pub day: u8,
+    pub month: u8,
+    pub year: u16,
+}
+
+/// TagDescriptorDateDecoder decodes command 13 and command 18, whose response
+/// echoes the request.
+pub struct TagDescriptorDateDecoder;
+
+impl HartDecoder for TagDescriptorDateDecoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::TagDescriptorDate(
+            TagDescriptorDateResponse {
+                tag: read_packed_ascii(data, 0, 6, "tag")?,
+                descriptor: read_packed_ascii(data, 6, 12, "descriptor")?,
+                date: HartDate {
+                    day: read_u8(data, 18, "day")?,
+                    month: read_u8(data, 19, "month")?,
+                    // the year is transmitted as offset from 1900
+                    year: 1900 + read_u8(data, 20, "year")? as u16,
+                },
+            },
+        ))
+    }
+}
+
+// command 14 - read primary variable transducer information ------------------
+
+#[derive(Debug, Serialize)]
+pub struct Command14Response {
+    pub transducer_serial_number: u32,
+    pub transducer_limits_unit: UnitCode,
+    pub transducer_upper_limit: f32,
+    pub transducer_lower_limit: f32,
+    pub minimum_span: f32,
+}
+
+pub struct Command14Decoder;
+
+impl HartDecoder for Command14Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::Command14(Command14Response {
+            transducer_serial_number: read_u24(data, 0, "transducer serial number")?,
+            transducer_limits_unit: read_u8(data, 3, "transducer limits unit code")?.into(),
+            transducer_upper_limit: read_f32(data, 4, "upper transducer limit")?,
+            transducer_lower_limit: read_f32(data, 8, "lower transducer limit")?,
+            minimum_span: read_f32(data, 12, "minimum span")?,
+        }))
+    }
+}
+
+// command 15 - read device information ---------------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct Command15Response {
+    pub alarm_selection: AlarmSelectionCode,
+    pub transfer_function: TransferFunctionCode,
+    pub range_values_unit: UnitCode,
+    pub upper_range_value: f32,
+    pub lower_range_value: f32,
+    /// damping_value is in seconds
+    pub damping_value: f32,
+    pub write_protect_code: u8,
+    /// analog_channel_flags tells if the primary variable is an analog input
+    /// or output channel, HART 6 onwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analog_channel_flags: Option<u8>,
+}
+
+pub struct Command15Decoder;
+
+impl HartDecoder for Command15Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        // byte 16 is reserved (250), the distributor code in HART 5
+        Ok(HartCommandData::Command15(Command15Response {
+            alarm_selection: read_u8(data, 0, "alarm selection code")?.into(),
+            transfer_function: read_u8(data, 1, "transfer function code")?.into(),
+            range_values_unit: read_u8(data, 2, "range values unit code")?.into(),
+            upper_range_value: read_f32(data, 3, "upper range value")?,
+            lower_range_value: read_f32(data, 7, "lower range value")?,
+            damping_value: read_f32(data, 11, "damping value")?,
+            write_protect_code: read_u8(data, 15, "write protect code")?,
+            analog_channel_flags: data.get(17).copied(),
+        }))
+    }
+}
+
+// command 16 - read final assembly number ------------------------------------
+// command 19 - write final assembly number -----------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct FinalAssemblyNumberResponse {
+    pub final_assembly_number: u32,
+}
+
+/// FinalAssemblyNumberDecoder decodes command 16 and command 19, whose
+/// response echoes the request.
+pub struct FinalAssemblyNumberDecoder;
+
+impl HartDecoder for FinalAssemblyNumberDecoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::FinalAssemblyNumber(
+            FinalAssemblyNumberResponse {
+                final_assembly_number: read_u24(data, 0, "final assembly number")?,
+            },
+        ))
+    }
+}
+
+// command 20 - read long tag -------------------------------------------------
+// command 22 - write long tag ------------------------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct LongTagResponse {
+    pub long_tag: String,
+}
+
+/// LongTagDecoder decodes command 20 and command 22, whose response echoes
+/// the request, both are HART 6 onwards.
+pub struct LongTagDecoder;
+
+impl HartDecoder for LongTagDecoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::LongTag(LongTagResponse {
+            long_tag: read_latin1(data, 0, 32, "long tag")?,
+        }))
+    }
+}
+
+// command 38 - reset configuration changed flag ------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct Command38Response {
+    /// configuration_change_counter is echoed by HART 6 onwards devices,
+    /// HART 5 devices do not return any data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub configuration_change_counter: Option<u16>,
+}
+
+pub struct Command38Decoder;
+
+impl HartDecoder for Command38Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::Command38(Command38Response {
+            configuration_change_counter: match data.is_empty() {
+                true => None,
+                false => Some(read_u16(data, 0, "configuration change counter")?),
+            },
+        }))
+    }
+}
+
+// command 48 - read additional device status ---------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct Command48Response {
+    /// device_specific_status holds bytes 0-5 and, if available, bytes 14-24,
+    /// their meaning is defined by the device manufacturer
+    pub device_specific_status: Vec<u8>,
+
+    // HART 6 onwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extended_device_status: Option<ExtendedDeviceStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_operating_mode: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub standardized_status_0: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub standardized_status_1: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analog_channel_saturated: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub standardized_status_2: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub standardized_status_3: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analog_channel_fixed: Option<u8>,
+}
+
+pub struct Command48Decoder;
+
+impl Command48Decoder {
+    pub fn decode_response(data: &[u8]) -> Result<Command48Response, ProtocolError> {
+        let mut device_specific_status =
+            read_bytes::<6>(data, 0, "device specific status")?.to_vec();
+        if let Some(more) = data.get(14..25) {
+            device_specific_status.extend(more);
+        }
+
+        Ok(Command48Response {
+            device_specific_status,
+            extended_device_status: data.get(6).map(|b| (*b).into()),
+            device_operating_mode: data.get(7).copied(),
+            standardized_status_0: data.get(8).copied(),
+            standardized_status_1: data.get(9).copied(),
+            analog_channel_saturated: data.get(10).copied(),
+            standardized_status_2: data.get(11).copied(),
+            standardized_status_3: data.get(12).copied(),
+            analog_channel_fixed: data.get(13).copied(),
+        })
+    }
+}
+
+impl HartDecoder for Command48Decoder {
+    fn decode(&self, data: &[u8]) -> Result<HartCommandData, ProtocolError> {
+        Ok(HartCommandData::Command48(Self::decode_response(data)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_command0_hart6_response() {
+        // SITRANS P DS III, captured from the AI module response record
+        let data = hex::decode("fe2a0b050503063800 3fcc78 050c026900".replace(' ', "")).unwrap();
+
+        let HartCommandData::Command0(response) = Command0Decoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.manufacturer, ManufacturerId::Siemens);
+        assert_eq!(response.device_type, 0x0b);
+        assert_eq!(response.universal_command_revision, 5);
+        assert_eq!(response.device_id, 0x3fcc78);
+        assert_eq!(response.configuration_change_counter, Some(0x0269));
+        assert!(response.manufacturer_identification_code.is_none());
+    }
+
+    #[test]
+    fn decode_command0_hart5_response() {
+        let data = hex::decode("fe2a0b05050306380000cc78").unwrap();
+
+        let HartCommandData::Command0(response) = Command0Decoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.device_id, 0x00cc78);
+        assert!(response.configuration_change_counter.is_none());
+    }
+
+    #[test]
+    fn decode_command9_response() {
+        // one device variable (pressure 1.5 mbar) with HART 7 time stamp
+        let data = hex::decode("00 00 41 08 3fc00000 c0 00000010".replace(' ', "")).unwrap();
+
+        let HartCommandData::Command9(response) = Command9Decoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.device_variables.len(), 1);
+        assert_eq!(response.device_variables[0].value, 1.5);
+        assert_eq!(response.device_variables[0].unit, UnitCode::Millibar);
+        assert_eq!(
+            response.device_variables[0].device_variable_classification,
+            DeviceVariableClassification::Pressure
+        );
+        assert_eq!(response.time_stamp, Some(0x10));
+    }
+
+    #[test]
+    fn decode_command1_response() {
+        // 1.5 mbar
+        let data = hex::decode("083fc00000").unwrap();
+
+        let HartCommandData::Command1(response) = Command1Decoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.unit.symbol(), "mbar");
+        assert_eq!(response.primary_variable, 1.5);
+    }
+
+    #[test]
+    fn decode_command2_response() {
+        // 12 mA, 50 %
+        let data = hex::decode("4140000042480000").unwrap();
+
+        let HartCommandData::Command2(response) = Command2Decoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.loop_current, 12.0);
+        assert_eq!(response.percent_of_range, 50.0);
+    }
+
+    #[test]
+    fn decode_command3_response() {
+        // HART 5 device with the primary variable only
+        let data = hex::decode("41400000 08 3fc00000".replace(' ', "")).unwrap();
+        let HartCommandData::Command3(response) = Command3Decoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.loop_current, 12.0);
+        assert_eq!(response.dynamic_variables.len(), 1);
+
+        // pressure, sensor temperature, electronics temperature and pressure in %
+        let data = hex::decode(
+            "41400000 08 3fc00000 20 41b80000 20 42040000 39 42480000".replace(' ', ""),
+        )
+        .unwrap();
+        let HartCommandData::Command3(response) = Command3Decoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.dynamic_variables.len(), 4);
+        assert_eq!(response.dynamic_variables[1].unit, UnitCode::DegreesCelsius);
+        assert_eq!(response.dynamic_variables[1].value, 23.0);
+        assert_eq!(response.dynamic_variables[3].value, 50.0);
+
+        assert!(Command3Decoder.decode(&data[..8]).is_err());
+    }
+
+    #[test]
+    fn decode_loop_configuration_response() {
+        // HART 5 command 6 echoes the polling address only
+        let HartCommandData::LoopConfiguration(response) =
+            LoopConfigurationDecoder.decode(&[0x05]).unwrap()
+        else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.polling_address, 5);
+        assert!(response.loop_current_mode.is_none());
+
+        let HartCommandData::LoopConfiguration(response) =
+            LoopConfigurationDecoder.decode(&[0x00, 0x01]).unwrap()
+        else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.loop_current_mode, Some(1));
+    }
+
+    #[test]
+    fn decode_command8_response() {
+        let HartCommandData::Command8(response) =
+            Command8Decoder.decode(&[0x41, 0x40, 0x40, 0x00]).unwrap()
+        else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(
+            response.primary_variable_classification,
+            DeviceVariableClassification::Pressure
+        );
+        assert_eq!(
+            response.tertiary_variable_classification,
+            DeviceVariableClassification::Temperature
+        );
+    }
+
+    #[test]
+    fn decode_message_response() {
+        let data = hex::decode("4c951204e4e04201138092498104854d3552160820820820").unwrap();
+
+        let HartCommandData::Message(response) = MessageDecoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.message, "SITRANS P DS III PRESSURE");
+    }
+
+    #[test]
+    fn decode_tag_descriptor_date_response() {
+        let data =
+            hex::decode("414c70c60820 4121534d548581404e2e0c60 110a7c".replace(' ', "")).unwrap();
+
+        let HartCommandData::TagDescriptorDate(response) =
+            TagDescriptorDateDecoder.decode(&data).unwrap()
+        else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.tag, "PT101");
+        assert_eq!(response.descriptor, "PRESSURE TANK 1");
+        assert_eq!(
+            response.date,
+            HartDate {
+                day: 17,
+                month: 10,
+                year: 2024
+            }
+        );
+    }
+
+    #[test]
+    fn decode_command15_response() {
+        // HART 5 device, no analog channel flags
+        let data =
+            hex::decode("00 00 08 447a0000 00000000 3f800000 00 fa".replace(' ', "")).unwrap();
+        let HartCommandData::Command15(response) = Command15Decoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.range_values_unit, UnitCode::Millibar);
+        assert_eq!(response.transfer_function, TransferFunctionCode::Linear);
+        assert_eq!(response.alarm_selection, AlarmSelectionCode::High);
+        assert_eq!(response.upper_range_value, 1000.0);
+        assert_eq!(response.damping_value, 1.0);
+        assert!(response.analog_channel_flags.is_none());
+
+        let data =
+            hex::decode("00 00 08 447a0000 00000000 3f800000 00 fa 00".replace(' ', "")).unwrap();
+        let HartCommandData::Command15(response) = Command15Decoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.analog_channel_flags, Some(0));
+    }
+
+    #[test]
+    fn decode_final_assembly_number_response() {
+        let HartCommandData::FinalAssemblyNumber(response) = FinalAssemblyNumberDecoder
+            .decode(&[0x01, 0x23, 0x45])
+            .unwrap()
+        else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.final_assembly_number, 0x012345);
+    }
+
+    #[test]
+    fn decode_long_tag_response() {
+        let mut data = b"PT-101 FEED PUMP DISCHARGE".to_vec();
+        data.resize(32, 0x00);
+
+        let HartCommandData::LongTag(response) = LongTagDecoder.decode(&data).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.long_tag, "PT-101 FEED PUMP DISCHARGE");
+    }
+
+    #[test]
+    fn decode_command38_response() {
+        // HART 5 device does not return any data
+        let HartCommandData::Command38(response) = Command38Decoder.decode(&[]).unwrap() else {
+            panic!("unexpected command data");
+        };
+        assert!(response.configuration_change_counter.is_none());
+
+        let HartCommandData::Command38(response) = Command38Decoder.decode(&[0x02, 0x69]).unwrap()
+        else {
+            panic!("unexpected command data");
+        };
+        assert_eq!(response.configuration_change_counter, Some(0x0269));
+    }
+
+    #[test]
+    fn decode_short_response_should_return_error() {
+        assert!(Command0Decoder.decode(&[0xfe, 0x2a]).is_err());
+        assert!(Command9Decoder.decode(&[0x00, 0x00, 0x41]).is_err());
+        assert!(Command14Decoder.decode(&[]).is_err());
+        assert!(Command48Decoder.decode(&[0x00; 5]).is_err());
+        assert!(Command1Decoder.decode(&[0x08, 0x3f]).is_err());
+        assert!(Command15Decoder.decode(&[0x00; 15]).is_err());
+        assert!(MessageDecoder.decode(&[0x00; 23]).is_err());
+        assert!(TagDescriptorDateDecoder.decode(&[0x00; 20]).is_err());
+        assert!(LongTagDecoder.decode(&[0x00; 31]).is_err());
+    }
+}
@@ -0,0 +1,426 @@
+use super::{
+    response::{write_latin1, write_packed_ascii},
+    tables::UnitCode,
+    universal::HartDate,
+};
+use crate::protocol::{Encode, HartAddress, ProtocolError};
+use serde::{Deserialize, Serialize};
+
+// the write commands change the configuration of the field device, see
+// https://library.fieldcommgroup.org/20127/TS20127 universal command specification
+// and https://library.fieldcommgroup.org/20151/TS20151 common practice command
+// specification
+
+/// HartWrite is a configuration write of a field device, its payload is
+/// validated when encoded, the constructors validate it upfront.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "write", rename_all = "snake_case")]
+pub enum HartWrite {
+    /// command 6, write polling address
+    PollingAddress {
+        polling_address: u8,
+        /// loop_current tells if the loop current still signals the primary
+        /// variable, it's fixed otherwise
+        #[serde(default = "HartWrite::default_loop_current")]
+        loop_current: bool,
+    },
+    /// command 17, write message
+    Message { message: String },
+    /// command 18, write tag, descriptor, date
+    TagDescriptorDate {
+        tag: String,
+        descriptor: String,
+        date: HartDate,
+    },
+    /// command 19, write final assembly number
+    FinalAssemblyNumber { final_assembly_number: u32 },
+    /// command 22, write long tag
+    LongTag { long_tag: String },
+    /// command 34, write primary variable damping value in seconds
+    Damping { damping: f32 },
+    /// command 35, write primary variable range values
+    RangeValues { units: u8, upper: f32, lower: f32 },
+    /// command 44, write primary variable units
+    PvUnits { units: u8 },
+    /// a command of `hart_commands` that is not a read, its data is sent as
+    /// it is and only once like the writes above
+    #[serde(skip)]
+    Raw { command: u16, data: Box<[u8]> },
+}
+
+impl HartWrite {
+    /// READ_COMMANDS are the universal and common practice commands known to
+    /// leave the field device as it is, any other command may change its
+    /// state and is sent only if the write policy of the device allows it
+    pub const READ_COMMANDS: [u16; 36] = [
+        0, 1, 2, 3, 7, 8, 9, 11, 12, 13, 14, 15, 16, 20, 21, 33, 48, 50, 54, 57, 60, 61, 62, 63,
+        70, 74, 76, 80, 81, 84, 86, 94, 95, 105, 115, 512,
+    ];
+
+    const MAX_FINAL_ASSEMBLY_NUMBER: u32 = 0xff_ffff;
+
+    fn default_loop_current() -> bool {
+        true
+    }
+
+    pub fn polling_address(polling_address: u8, loop_current: bool) -> Result<Self, ProtocolError> {
+        Self::PollingAddress {
+            polling_address,
+            loop_current,
+        }
+        .validated()
+    }
+
+    pub fn message(message: &str) -> Result<Self, ProtocolError> {
+        Self::Message {
+            message: message.to_string(),
+        }
+        .validated()
+    }
+
+    pub fn tag_descriptor_date(
+        tag: &str,
+        descriptor: &str,
+        date: HartDate,
+    ) -> Result<Self, ProtocolError> {
+        Self::TagDescriptorDate {
+            tag: tag.to_string(),
+            descriptor: descriptor.to_string(),
+            date,
+        }
+        .validated()
+    }
+
+    pub fn final_assembly_number(final_assembly_number: u32) -> Result<Self, ProtocolError> {
+        Self::FinalAssemblyNumber {
+            final_assembly_number,
+        }
+        .validated()
+    }
+
+    pub fn long_tag(long_tag: &str) -> Result<Self, ProtocolError> {
+        Self::LongTag {
+            long_tag: long_tag.to_string(),
+        }
+        .validated()
+    }
+
+    pub fn damping(damping: f32) -> Result<Self, ProtocolError> {
+        Self::Damping { damping }.validated()
+    }
+
+    pub fn range_values(units: u8, upper: f32, lower: f32) -> Result<Self, ProtocolError> {
+        Self::RangeValues {
+            units,
+            upper,
+            lower,
+        }
+        .validated()
+    }
+
+    pub fn pv_units(units: u8) -> Result<Self, ProtocolError> {
+        Self::PvUnits { units }.validated()
+    }
+
+    fn validated(self) -> Result<Self, ProtocolError> {
+        self.to_bytes()?;
+        Ok(self)
+    }
+
+    pub fn command(&self) -> u16 {
+        match self {
+            Self::PollingAddress { .. } => 6,
+            Self::Message { .. } => 17,
+            Self::TagDescriptorDate { .. } => 18,
+            Self::FinalAssemblyNumber { .. } => 19,
+            Self::LongTag { .. } => 22,
+            Self::Damping { .. } => 34,
+            Self::RangeValues { .. } => 35,
+            Self::PvUnits { .. } => 44,
+            Self::Raw { command, .. } => *command,
+        }
+    }
+
+    /// typed is the write unless it's raw, i.e. what the audit tells about it
+    pub fn typed(&self) -> Option<&Self> {
+        match self {
+            Self::Raw { .. } => None,
+            write => Some(write),
+        }
+    }
+
+    pub fn is_read_command(command: u16) -> bool {
+        Self::READ_COMMANDS.contains(&command)
+    }
+
+    /// is_rejected tells if the response code of a write is a command
+    /// specific error, i.e. the configuration is left unchanged, the other
+    /// codes are warnings
+    pub fn is_rejected(response_code: u8) -> bool {
+        matches!(response_code, 1..=7 | 9..=13 | 15..=23 | 28 | 29 | 65..=95)
+    }
+}
+
+impl Encode for HartWrite {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        match self {
+            Self::PollingAddress {
+                polling_address,
+                loop_current,
+            } => {
+                if *polling_address > HartAddress::MAX_POLLING_ADDRESS {
+                    return Err(ProtocolError::OutOfRange {
+                        field: "polling address",
+                        value: (*polling_address).into(),
+                    });
+                }
+                buf.extend([*polling_address, *loop_current as u8]);
+            }
+            Self::Message { message } => write_packed_ascii(buf, message, 24, "message")?,
+            Self::TagDescriptorDate {
+                tag,
+                descriptor,
+                date,
+            } => {
+                write_packed_ascii(buf, tag, 6, "tag")?;
+                write_packed_ascii(buf, descriptor, 12, "descriptor")?;
+                write_date(buf, date)?;
+            }
+            Self::FinalAssemblyNumber {
+                final_assembly_number,
+            } => {
+                if *final_assembly_number > Self::MAX_FINAL_ASSEMBLY_NUMBER {
+                    return Err(ProtocolError::OutOfRange {
+                        field: "final assembly number",
+                        value: (*final_assembly_number).into(),
+                    });
+                }
+                buf.extend(&final_assembly_number.to_be_bytes()[1..]);
+            }
+            Self::LongTag { long_tag } => write_latin1(buf, long_tag, 32, "long tag")?,
+            Self::Damping { damping } => {
+                if !damping.is_finite() || *damping < 0.0 {
+                    return Err(ProtocolError::OutOfRange {
+                        field: "damping",
+                        value: (*damping).into(),
+                    });
+                }
+                buf.extend(damping.to_be_bytes());
+            }
+            Self::RangeValues {
+                units,
+                upper,
+                lower,
+            } => {
+                for (field, value) in [("upper range value", upper), ("lower range value", lower)] {
+                    if !value.is_finite() {
+                        return Err(ProtocolError::OutOfRange {
+                            field,
+                            value: (*value).into(),
+                        });
+                    }
+                }
+                // an inverted range is fine, an empty one is not
+                if upper == lower {
+                    return Err(ProtocolError::OutOfRange {
+                        field: "range values span",
+                        value: 0.0,
+                    });
+                }
+                buf.push(unit_code(*units)?);
+                buf.extend(upper.to_be_bytes());
+                buf.extend(lower.to_be_bytes());
+            }
+            Self::PvUnits { units } => buf.push(unit_code(*units)?),
+            Self::Raw { data, .. } => buf.extend(data.iter()),
+        }
+
+        Ok(())
+    }
+}
+
+// unit_code accepts the units of common table 2 and the manufacturer specific
+// ones, "not used" and "unknown" can't be written
+fn unit_code(units: u8) -> Result<u8, ProtocolError> {
+    match UnitCode::from(units) {
+        UnitCode::Unlisted(240..=249) => Ok(units),
+        UnitCode::Unlisted(_) | UnitCode::NotUsed | UnitCode::Unknown => {
+            Err(ProtocolError::Invalid {
+                field: "units",
+                value: units.into(),
+            })
+        }
+        _ => Ok(units),
+    }
+}
+
+// write_date writes the day, the month and the year as offset from 1900
+fn write_date(buf: &mut Vec<u8>, date: &HartDate) -> Result<(), ProtocolError> {
+    for (field, value, range) in [
+        ("day", date.day.into(), 1..=31),
+        ("month", date.month.into(), 1..=12),
+        ("year", date.year, 1900..=2155),
+    ] {
+        if !range.contains(&value) {
+            return Err(ProtocolError::OutOfRange {
+                field,
+                value: value.into(),
+            });
+        }
+    }
+
+    buf.extend([date.day, date.month, (date.year - 1900) as u8]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hart::response::{read_latin1, read_packed_ascii};
+
+    #[test]
+    fn encode_should_pack_text_fields() {
+        let message = HartWrite::message("Sitrans p ds iii pressure").unwrap();
+        let bytes = message.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 24);
+        assert_eq!(
+            hex::encode(&bytes),
+            "4c951204e4e04201138092498104854d3552160820820820"
+        );
+
+        let write = HartWrite::tag_descriptor_date(
+            "PT-101",
+            "inlet",
+            HartDate {
+                day: 17,
+                month: 10,
+                year: 2026,
+            },
+        )
+        .unwrap();
+        let bytes = write.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 21);
+        assert_eq!(read_packed_ascii(&bytes, 0, 6, "tag").unwrap(), "PT-101");
+        assert_eq!(
+            read_packed_ascii(&bytes, 6, 12, "descriptor").unwrap(),
+            "INLET"
+        );
+        assert_eq!(&bytes[18..], &[17, 10, 126]);
+
+        let long_tag = HartWrite::long_tag("Zulauf Behälter 3").unwrap();
+        let bytes = long_tag.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(
+            read_latin1(&bytes, 0, 32, "long tag").unwrap(),
+            "Zulauf Behälter 3"
+        );
+    }
+
+    #[test]
+    fn encode_should_write_numbers_big_endian() {
+        let cases = [
+            (HartWrite::polling_address(5, false).unwrap(), "0500"),
+            (
+                HartWrite::final_assembly_number(0x123456).unwrap(),
+                "123456",
+            ),
+            (HartWrite::damping(2.0).unwrap(), "40000000"),
+            (
+                HartWrite::range_values(12, 100.0, 0.0).unwrap(),
+                "0c42c8000000000000",
+            ),
+            (HartWrite::pv_units(7).unwrap(), "07"),
+        ];
+        for (write, payload) in cases {
+            assert_eq!(hex::encode(write.to_bytes().unwrap()), payload);
+            assert!(!HartWrite::is_read_command(write.command()));
+        }
+    }
+
+    #[test]
+    fn constructors_should_validate_the_payload() {
+        assert_eq!(
+            HartWrite::polling_address(64, true),
+            Err(ProtocolError::OutOfRange {
+                field: "polling address",
+                value: 64.0
+            })
+        );
+        assert!(HartWrite::message(&"A".repeat(33)).is_err());
+        assert_eq!(
+            HartWrite::message("~"),
+            Err(ProtocolError::Invalid {
+                field: "message",
+                value: 0x7e
+            })
+        );
+        assert!(HartWrite::long_tag("温度").is_err());
+        assert!(HartWrite::final_assembly_number(0x100_0000).is_err());
+        assert!(HartWrite::damping(-1.0).is_err());
+        assert!(HartWrite::damping(f32::NAN).is_err());
+        assert!(HartWrite::range_values(12, 1.0, 1.0).is_err());
+        assert!(HartWrite::range_values(12, f32::INFINITY, 0.0).is_err());
+        // inverted range
+        assert!(HartWrite::range_values(12, 0.0, 100.0).is_ok());
+        assert!(HartWrite::pv_units(245).is_ok());
+        assert!(HartWrite::pv_units(200).is_err());
+        assert!(HartWrite::pv_units(250).is_err());
+
+        let date = |day, month, year| HartDate { day, month, year };
+        assert!(HartWrite::tag_descriptor_date("", "", date(0, 1, 2000)).is_err());
+        assert!(HartWrite::tag_descriptor_date("", "", date(1, 13, 2000)).is_err());
+        assert!(HartWrite::tag_descriptor_date("", "", date(1, 1, 2156)).is_err());
+    }
+
+    #[test]
+    fn deserialize_should_read_tagged_writes() {
+        let writes: Vec<HartWrite> = serde_yaml::from_str(
+            "
+            - write: damping
+              damping: 1.5
+            - write: polling_address
+              polling_address: 3
+            - write: pv_units
+              units: 12
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            writes,
+            vec![
+                HartWrite::Damping { damping: 1.5 },
+                HartWrite::PollingAddress {
+                    polling_address: 3,
+                    loop_current: true
+                },
+                HartWrite::PvUnits { units: 12 },
+            ]
+        );
+
+        // raw writes come from the commands, not from the writes
+        assert!(serde_yaml::from_str::<HartWrite>("write: raw\ncommand: 40").is_err());
+    }
+
+    #[test]
+    fn encode_should_send_raw_data_as_it_is() {
+        let raw = HartWrite::Raw {
+            command: 40,
+            data: Box::new([0x41, 0x80, 0x00, 0x00]),
+        };
+        assert_eq!(raw.command(), 40);
+        assert_eq!(hex::encode(raw.to_bytes().unwrap()), "41800000");
+        assert_eq!(raw.typed(), None);
+
+        let damping = HartWrite::damping(2.0).unwrap();
+        assert_eq!(damping.typed(), Some(&damping));
+    }
+
+    #[test]
+    fn is_rejected_should_leave_warnings() {
+        // write protected
+        assert!(HartWrite::is_rejected(7));
+        // set to nearest possible value
+        assert!(!HartWrite::is_rejected(8));
+        assert!(!HartWrite::is_rejected(0));
+    }
+}
@@ -7,17 +7,25 @@
 mod cli;
 mod client;
 mod config;
+mod decode;
 mod device;
 mod dto;
+mod error;
+mod hart;
 mod protocol;
+mod simulator;
 mod transport;
 
-use crate::{cli::WorkingMode, client::worker::Worker};
+use crate::{
+    cli::{Command, WorkingMode},
+    client::{dcp::DcpClient, worker::Worker},
+};
 use anyhow::anyhow;
 use clap::Parser;
 use cli::Cli;
 use client::{iotedge::IotEdge, kafka::Kafka, sender::Sender};
-use config::Config;
+use config::LocalConfig;
+use simulator::{Simulator, SimulatorConfig};
 use signal_hook::{
     consts::{SIGHUP, SIGINT, SIGTERM},
     iterator::Signals,
@@ -26,21 +34,39 @@ use std::{
     fs::File,
     io::{BufReader, Read},
     net::Ipv4Addr,
+    path::PathBuf,
     str::FromStr,
-    sync::{Arc, RwLock},
+    sync::{mpsc, Arc, RwLock},
     thread, time,
 };
+use transport::{CaptureSettings, PacketSocket};
 
 fn main() -> anyhow::Result<()> {
     env_logger::init();
 
     let args = Cli::parse();
 
+    if let Some(Command::Decode { input, json }) = &args.command {
+        let nodes = decode::decode(input)?;
+        match json {
+            true => println!("{}", serde_json::to_string_pretty(&nodes)?),
+            false => nodes.iter().for_each(|node| print!("{node}")),
+        }
+
+        return Ok(());
+    }
+
+    // the worker loop is stopped on a signal, so that the application
+    // relationships can be released before exiting
     let mut signals = Signals::new([SIGTERM, SIGINT, SIGHUP])?;
+    let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
     thread::spawn(move || {
-        for _ in signals.forever() {
+        for (i, _) in signals.forever().enumerate() {
             log::info!("received shutdown request");
-            std::process::exit(0);
+            // a second signal does not wait for the release
+            if i > 0 || shutdown_tx.send(()).is_err() {
+                std::process::exit(0);
+            }
         }
     });
 
@@ -70,25 +96,108 @@ fn main() -> anyhow::Result<()> {
                 "config file path is required in local mode, check --help"
             ))?;
 
-            let config_file = File::open(config_file_path)?;
-            let mut buf_reader = BufReader::new(config_file);
-            let mut content = String::new();
-            buf_reader.read_to_string(&mut content)?;
-
-            let local_file_config = Config::deserialize(content.as_str())?;
-            let _config = Arc::new(RwLock::new(local_file_config));
-            kafka_client = Kafka::new();
+            let content = read_config_file(config_file_path)?;
+            let local_file_config = LocalConfig::deserialize(content.as_str())?;
+            kafka_client = Kafka::new(local_file_config);
 
             sender = &kafka_client;
         }
+        WorkingMode::SimulatorMode => {
+            let config_file_path = args.config.ok_or(anyhow!(
+                "config file path is required in simulator mode, check --help"
+            ))?;
+
+            let content = read_config_file(config_file_path)?;
+            let simulator_config = SimulatorConfig::deserialize(content.as_str())?;
+            let simulator = Simulator::start(simulator_config)?;
+            // the simulator is stopped when it's dropped
+            let _ = shutdown_rx.recv();
+            drop(simulator);
+
+            return Ok(());
+        }
+        WorkingMode::ScanMode => {
+            let interface = args.interface.as_deref().ok_or(anyhow!(
+                "network interface is required in scan mode, check --help"
+            ))?;
+
+            let dcp_client = DcpClient::new(Box::new(PacketSocket::new(interface)?));
+            for station in dcp_client.identify_all()? {
+                println!("{}", serde_json::to_string(&station)?);
+            }
+
+            return Ok(());
+        }
     }
 
+    sender.setup()?;
+
     let src_ip_address = args.src_ip_address.parse::<Ipv4Addr>()?;
     let mut worker = Worker::new(sender);
+    worker.set_concurrency(args.concurrency as usize);
+    worker.set_capture(
+        CaptureSettings {
+            dir: args.capture_dir.clone(),
+            max_file_size: args.capture_file_size * 1024 * 1024,
+            max_files: args.capture_files,
+        },
+        args.capture.clone(),
+    );
+    if let Some(interface) = args.interface.as_deref() {
+        let dcp_client = DcpClient::new(Box::new(PacketSocket::new(interface)?));
+        worker.set_dcp_client(dcp_client);
+    }
+    let identification_interval = time::Duration::from_secs(args.identification_interval as u64);
+    let mut identified_at: Option<time::Instant> = None;
+    let interval = time::Duration::from_secs(args.interval as u64);
     loop {
+        let started_at = time::Instant::now();
         worker.evaluate(src_ip_address);
-        worker.read();
-        log::info!("sleep for {} seconds waiting for next loop", args.interval);
-        thread::sleep(time::Duration::from_secs(args.interval as u64));
+
+        let stats = worker.read();
+        log::info!(
+            "read {} devices of {} stations in {:?}, {} packets dropped",
+            stats.devices,
+            stats.stations.len(),
+            stats.duration,
+            stats.dropped_packets
+        );
+        if let Some((ip_address, duration)) = stats.slowest_station() {
+            log::debug!("slowest station {ip_address} took {duration:?}");
+        }
+        if stats.duration > interval {
+            log::warn!(
+                "reading took {:?}, longer than the interval of {} seconds",
+                stats.duration,
+                args.interval
+            );
+        }
+
+        if !identification_interval.is_zero()
+            && identified_at.is_none_or(|i| i.elapsed() >= identification_interval)
+        {
+            worker.identify();
+            identified_at = Some(time::Instant::now());
+        }
+        // the interval is kept from the start of one loop to the next one
+        let remaining = interval.saturating_sub(started_at.elapsed());
+        log::info!("sleep for {remaining:?} waiting for next loop");
+        if shutdown_rx.recv_timeout(remaining).is_ok() {
+            break;
+        }
     }
+
+    log::info!("releasing the devices before shutting down");
+    worker.release();
+
+    Ok(())
+}
+
+fn read_config_file(config_file_path: PathBuf) -> anyhow::Result<String> {
+    let config_file = File::open(config_file_path)?;
+    let mut buf_reader = BufReader::new(config_file);
+    let mut content = String::new();
+    buf_reader.read_to_string(&mut content)?;
+
+    Ok(content)
 }
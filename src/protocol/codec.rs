@@ -0,0 +1,249 @@
+use std::fmt;
+
+/// ProtocolError tells why a datagram can't be decoded or a packet can't be
+/// encoded, the field is the name of the field it's about
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolError {
+    /// the datagram ends before the field
+    Truncated {
+        field: &'static str,
+        needed: usize,
+        remaining: usize,
+    },
+    /// the field holds a value unknown to this side or not allowed here, e.g.
+    /// the block type of a request where a response is expected
+    Invalid { field: &'static str, value: u32 },
+    /// a length disagrees with the data, or does not fit into its field
+    InvalidLength { field: &'static str, len: usize },
+    /// a text field is not UTF-8
+    InvalidText { field: &'static str },
+    /// the packet lacks a field it needs to be encoded
+    Missing { field: &'static str },
+    /// a number is outside of what the field allows
+    OutOfRange { field: &'static str, value: f64 },
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated {
+                field,
+                needed,
+                remaining,
+            } => write!(
+                f,
+                "{field} of {needed} bytes is truncated, {remaining} bytes left"
+            ),
+            Self::Invalid { field, value } => write!(f, "invalid {field} {value:#x}"),
+            Self::InvalidLength { field, len } => write!(f, "invalid length {len} of {field}"),
+            Self::InvalidText { field } => write!(f, "{field} is not UTF-8"),
+            Self::Missing { field } => write!(f, "{field} is missing"),
+            Self::OutOfRange { field, value } => write!(f, "{field} {value} is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// Cursor reads the fields of a datagram in order, a field that doesn't fit
+/// into what is left is `ProtocolError::Truncated` and nothing is consumed
+#[derive(Debug, Copy, Clone)]
+pub struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    /// position is the number of bytes consumed
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    pub fn take(&mut self, len: usize, field: &'static str) -> Result<&'a [u8], ProtocolError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(ProtocolError::Truncated {
+                field,
+                needed: len,
+                remaining: self.remaining(),
+            })?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    /// sub takes the next `len` bytes as a cursor of their own, e.g. for a
+    /// block whose length is known upfront
+    pub fn sub(&mut self, len: usize, field: &'static str) -> Result<Cursor<'a>, ProtocolError> {
+        self.take(len, field).map(Cursor::new)
+    }
+
+    /// rest takes all the bytes left
+    pub fn rest(&mut self) -> &'a [u8] {
+        let bytes = &self.bytes[self.position..];
+        self.position = self.bytes.len();
+        bytes
+    }
+
+    pub fn skip(&mut self, len: usize, field: &'static str) -> Result<(), ProtocolError> {
+        self.take(len, field).map(|_| ())
+    }
+
+    /// align skips the padding up to the next multiple of `n` bytes from the
+    /// start of the cursor, the padding may be cut at the end of the data
+    pub fn align(&mut self, n: usize) {
+        let padding = self.position.next_multiple_of(n) - self.position;
+        self.position += padding.min(self.remaining());
+    }
+
+    pub fn array<const N: usize>(&mut self, field: &'static str) -> Result<[u8; N], ProtocolError> {
+        let mut array = [0x00; N];
+        array.copy_from_slice(self.take(N, field)?);
+        Ok(array)
+    }
+
+    pub fn u8(&mut self, field: &'static str) -> Result<u8, ProtocolError> {
+        self.array::<1>(field).map(|[b]| b)
+    }
+
+    pub fn u16_be(&mut self, field: &'static str) -> Result<u16, ProtocolError> {
+        self.array(field).map(u16::from_be_bytes)
+    }
+
+    pub fn u16_le(&mut self, field: &'static str) -> Result<u16, ProtocolError> {
+        self.array(field).map(u16::from_le_bytes)
+    }
+
+    pub fn u32_be(&mut self, field: &'static str) -> Result<u32, ProtocolError> {
+        self.array(field).map(u32::from_be_bytes)
+    }
+
+    pub fn u32_le(&mut self, field: &'static str) -> Result<u32, ProtocolError> {
+        self.array(field).map(u32::from_le_bytes)
+    }
+}
+
+/// Encode writes the packet as it goes on the wire
+pub trait Encode {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError>;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, ProtocolError> {
+        let mut buf: Vec<u8> = vec![];
+        self.encode(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// Decode reads the packet from the wire, it never panics whatever the
+/// datagram, bytes after the packet are left in the cursor
+pub trait Decode: Sized {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError>;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        Self::decode(&mut Cursor::new(bytes))
+    }
+}
+
+/// length converts the length of a field for its length field, e.g. `u16`
+pub fn length<T: TryFrom<usize>>(len: usize, field: &'static str) -> Result<T, ProtocolError> {
+    T::try_from(len).map_err(|_| ProtocolError::InvalidLength { field, len })
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use proptest::prelude::*;
+    use std::fmt::Debug;
+
+    /// assert_roundtrip checks that the packet is decoded the same as encoded
+    pub fn assert_roundtrip<T: Encode + Decode + PartialEq + Debug>(packet: &T) {
+        let bytes = packet.to_bytes().unwrap();
+        assert_eq!(&T::from_bytes(&bytes).unwrap(), packet);
+    }
+
+    /// assert_decode_never_panics decodes the datagram, every prefix of it
+    /// and the datagram with a byte flipped, the result does not matter
+    pub fn assert_decode_never_panics<T: Decode>(datagram: &[u8], flip: usize) {
+        let _ = T::from_bytes(datagram);
+        for len in 0..datagram.len() {
+            let _ = T::from_bytes(&datagram[..len]);
+        }
+        if !datagram.is_empty() {
+            let mut flipped = datagram.to_vec();
+            let i = flip % flipped.len();
+            flipped[i] = !flipped[i];
+            let _ = T::from_bytes(&flipped);
+        }
+    }
+
+    #[test]
+    fn cursor_should_not_consume_truncated_field() {
+        let mut cursor = Cursor::new(&[0x01, 0x02, 0x03]);
+        assert_eq!(cursor.u16_be("a"), Ok(0x0102));
+        assert_eq!(
+            cursor.u32_le("b"),
+            Err(ProtocolError::Truncated {
+                field: "b",
+                needed: 4,
+                remaining: 1
+            })
+        );
+        // the length overflowing the position
+        assert!(cursor.take(usize::MAX, "c").is_err());
+        assert_eq!(cursor.u8("d"), Ok(0x03));
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn cursor_should_align_from_its_start() {
+        let bytes = [0x00; 10];
+        let mut cursor = Cursor::new(&bytes);
+        cursor.skip(5, "a").unwrap();
+        cursor.align(4);
+        assert_eq!(cursor.position(), 8);
+        cursor.align(4);
+        assert_eq!(cursor.position(), 8);
+        // padding cut at the end
+        cursor.skip(1, "b").unwrap();
+        cursor.align(4);
+        assert_eq!(cursor.position(), 10);
+    }
+
+    #[test]
+    fn length_should_not_truncate() {
+        assert_eq!(length::<u16>(0xffff, "a"), Ok(0xffff));
+        assert_eq!(
+            length::<u16>(0x10000, "a"),
+            Err(ProtocolError::InvalidLength {
+                field: "a",
+                len: 0x10000
+            })
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn cursor_should_read_what_it_is_given(bytes in proptest::collection::vec(any::<u8>(), 0..64)) {
+            let mut cursor = Cursor::new(&bytes);
+            let mut read: Vec<u8> = vec![];
+            while let Ok(b) = cursor.u8("byte") {
+                read.push(b);
+            }
+            prop_assert_eq!(read, bytes);
+        }
+    }
+}
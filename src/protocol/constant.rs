@@ -6,10 +6,14 @@ pub const CM_INITIATOR_MAC: [u8; 6] = [0x00; 6];
 pub const CM_INITIATOR_ACT_TIMEOUT_FACTOR: u16 = 0x006e;
 pub const CM_INITIATOR_UDPRT_PORT: u16 = 0x0000;
 pub const READ_MAX_COUNT: u32 = 65584;
+// the largest record data a read request asks for
+pub const READ_RECORD_DATA_LEN: u32 = 65520;
 // IOD packet
 pub const IOD_PADDING: u8 = 0x00;
 pub const IOD_REQ_API: [u8; 4] = [0x00; 4];
 pub const AR_PROPS: [u8; 4] = [0x00, 0x00, 0x01, 0x11];
+// control block
+pub const CONTROL_COMMAND_RELEASE: u16 = 0x0004;
 // DCE/RPC Endpoint Mapper packet
 pub const DCERPC_EPM_INQUIRY_TYPE: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
 pub const DCERPC_EPM_REF_ID: u32 = 1;
@@ -19,7 +23,8 @@ pub const DCERPC_EPM_INTERFACE_INTERFACE: [u8; 16] = [0x00; 16];
 pub const DCERPC_EPM_INTERFACE_VERSION_MAJOR: u16 = 0;
 pub const DCERPC_EPM_INTERFACE_VERSION_MINOR: u16 = 0;
 pub const DCERPC_EPM_VERSION_OPTION: u32 = 1;
-pub const DCERPC_EPM_MAX_ENTRIES: u32 = 1;
+pub const DCERPC_EPM_MAX_ENTRIES: u32 = 16;
+pub const DCERPC_EPM_STATUS_NOT_REGISTERED: u32 = 0x16c9_a0d6;
 // DCE/RPC Endpoint Mapper response tower floor protocol
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum TowerFloorProtocol {
@@ -49,6 +54,19 @@ pub const DCERPC_SERVER_BOOT_TIME: [u8; 4] = [0x00; 4];
 pub const DCERPC_INTERFACE_HINT: [u8; 2] = [0xff, 0xff];
 pub const DCERPC_ACTIVITY_HINT: [u8; 2] = [0xff, 0xff];
 pub const DCERPC_FRAGMENT_NUM: u16 = 0;
+// flags1 bits of a fragmented body
+pub const DCERPC_FLAGS1_LAST_FRAGMENT: u8 = 0x02;
+pub const DCERPC_FLAGS1_FRAGMENT: u8 = 0x04;
+pub const DCERPC_FLAGS1_NO_FACK: u8 = 0x08;
+// the largest body sent in a single datagram, so that the datagram fits
+// into an ethernet frame, i.e. 1500 - 20 (ip) - 8 (udp) - 80 (DCE/RPC header)
+pub const DCERPC_MAX_FRAGMENT_DATA_LEN: usize = 1392;
+// fack body, see DCE 1.1 RPC chapter 12.5.3.4
+pub const DCERPC_FACK_VERSION: u8 = 0;
+pub const DCERPC_FACK_WINDOW_SIZE: u16 = 8;
+// number of pings in a row sent for a call whose response is overdue, before
+// giving up, a "working" answer starts counting again
+pub const DCERPC_MAX_PINGS: u8 = 2;
 pub const DCERPC_AUTH_PROTO: u8 = 0;
 pub const DCERPC_SERIAL_LOW: u8 = 0;
 // TODO: this seems like a fixed value?
@@ -72,33 +90,53 @@ impl InterfaceVersion {
 // DCE/RPC opnum
 #[derive(Debug, Copy, Clone)]
 pub enum OpNum {
+    ReadImplicit = 0x0005,
     Write = 0x0003,
     Read = 0x0002,
+    Release = 0x0001,
     Connect = 0x0000,
 }
 impl OpNum {
     pub fn from_u16(value: u16) -> Option<Self> {
         match value {
+            0x0005 => Some(Self::ReadImplicit),
             0x0003 => Some(Self::Write),
             0x0002 => Some(Self::Read),
+            0x0001 => Some(Self::Release),
             0x0000 => Some(Self::Connect),
             _ => None,
         }
     }
 }
-// DCE/RPC packet type
+// DCE/RPC packet type, see DCE 1.1 RPC chapter 12.5.2
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum PacketType {
     Request = 0x00,
+    Ping = 0x01,
     Response = 0x02,
+    Fault = 0x03,
+    Working = 0x04,
+    Nocall = 0x05,
     Reject = 0x06,
+    Ack = 0x07,
+    ClCancel = 0x08,
+    Fack = 0x09,
+    CancelAck = 0x0a,
 }
 impl PacketType {
     pub fn from_u8(value: u8) -> Option<Self> {
         match value {
             0x00 => Some(Self::Request),
+            0x01 => Some(Self::Ping),
             0x02 => Some(Self::Response),
+            0x03 => Some(Self::Fault),
+            0x04 => Some(Self::Working),
+            0x05 => Some(Self::Nocall),
             0x06 => Some(Self::Reject),
+            0x07 => Some(Self::Ack),
+            0x08 => Some(Self::ClCancel),
+            0x09 => Some(Self::Fack),
+            0x0a => Some(Self::CancelAck),
             _ => None,
         }
     }
@@ -113,6 +151,8 @@ pub enum BlockHeaderType {
     IodReadResType = 0x8009,
     IodWriteReqType = 0x0008,
     IodWriteResType = 0x8008,
+    ReleaseBlockReqType = 0x0114,
+    ReleaseBlockResType = 0x8114,
 }
 impl BlockHeaderType {
     pub fn from_u16(value: u16) -> Option<Self> {
@@ -123,7 +163,15 @@ impl BlockHeaderType {
             0x0008 => Some(Self::IodWriteReqType),
             0x8009 => Some(Self::IodReadResType),
             0x8008 => Some(Self::IodWriteResType),
+            0x0114 => Some(Self::ReleaseBlockReqType),
+            0x8114 => Some(Self::ReleaseBlockResType),
             _ => None,
         }
     }
+
+    /// is_response tells if the block answers a request, the response bit is
+    /// the highest bit of the block type
+    pub fn is_response(&self) -> bool {
+        *self as u16 & 0x8000 == 0x8000
+    }
 }
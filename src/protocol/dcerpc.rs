@@ -1,9 +1,8 @@
-use super::{constant, Packet};
-use anyhow::anyhow;
-use std::mem;
+use super::{constant, length, Cursor, DceRpcError, Decode, Encode, NcaStatus, ProtocolError};
+use std::{collections::BTreeMap, mem};
 use uuid::Uuid;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DceRpcPacket {
     pub version: [u8; 1],
     pub packet_type: [u8; 1],
@@ -62,7 +61,7 @@ impl DceRpcPacket {
             interface_hint: constant::DCERPC_INTERFACE_HINT,
             activity_hint: constant::DCERPC_ACTIVITY_HINT,
             fragment_len,
-            fragment_num: constant::DCERPC_FRAGMENT_NUM.to_be_bytes(),
+            fragment_num: constant::DCERPC_FRAGMENT_NUM.to_le_bytes(),
             auth_proto: constant::DCERPC_AUTH_PROTO.to_be_bytes(),
             serial_low: constant::DCERPC_SERIAL_LOW.to_be_bytes(),
             data,
@@ -70,112 +69,274 @@ impl DceRpcPacket {
     }
 }
 
-impl Packet for DceRpcPacket {
-    fn concat(&self) -> anyhow::Result<Vec<u8>> {
-        let mut v: Vec<u8> = vec![];
-
-        v.extend(&self.version);
-        v.extend(&self.packet_type);
-        v.extend(&self.flags1);
-        v.extend(&self.flags2);
-        v.extend(&self.data_representation);
-        v.extend(&self.serial_high);
-        v.extend(&self.obj_uuid);
-        v.extend(&self.interface);
-        v.extend(&self.activity);
-        v.extend(&self.server_boot_time);
-        v.extend(&self.interface_ver);
-        v.extend(&self.seq_num);
-        v.extend(&self.opnum);
-        v.extend(&self.interface_hint);
-        v.extend(&self.activity_hint);
-        v.extend(&self.fragment_len);
-        v.extend(&self.fragment_num);
-        v.extend(&self.auth_proto);
-        v.extend(&self.serial_low);
-        v.extend(&*self.data);
-
-        Ok(v)
-    }
-
-    fn size(&self) -> usize {
-        mem::size_of::<Self>()
+impl DceRpcPacket {
+    pub fn get_packet_type(&self) -> Option<constant::PacketType> {
+        constant::PacketType::from_u8(self.packet_type[0])
+    }
+
+    /// get_status returns the status carried in the body of a reject or fault
+    pub fn get_status(&self) -> Option<NcaStatus> {
+        let status = TryInto::<[u8; 4]>::try_into(self.data.get(0..4)?).ok()?;
+        Some(NcaStatus::from_u32(u32::from_le_bytes(status)))
+    }
+
+    /// into_result turns a reject or fault into its error, other packets
+    /// are passed through
+    pub fn into_result(self) -> Result<Self, DceRpcError> {
+        let status = || self.get_status().unwrap_or(NcaStatus::Unknown(0));
+        match self.get_packet_type() {
+            Some(constant::PacketType::Reject) => Err(DceRpcError::Reject(status())),
+            Some(constant::PacketType::Fault) => Err(DceRpcError::Fault(status())),
+            Some(constant::PacketType::Nocall) => Err(DceRpcError::Nocall),
+            _ => Ok(self),
+        }
+    }
+
+    /// ping asks the server about the call of this request
+    pub fn ping(&self) -> Self {
+        let mut ping = self.with_data(Box::new([]));
+        ping.packet_type = [constant::PacketType::Ping as u8];
+        ping.flags1 = constant::DCERPC_FLAGS1;
+        ping.fragment_num = constant::DCERPC_FRAGMENT_NUM.to_le_bytes();
+        ping
+    }
+
+    /// reply answers this packet with a packet of the same call
+    pub fn reply(&self, packet_type: constant::PacketType, data: Box<[u8]>) -> Self {
+        let mut reply = self.with_data(data);
+        reply.packet_type = [packet_type as u8];
+        reply.flags1 = constant::DCERPC_FLAGS1;
+        reply.fragment_num = constant::DCERPC_FRAGMENT_NUM.to_le_bytes();
+        reply.serial_high = [constant::DCERPC_SERIAL_HIGH];
+        reply.serial_low = [constant::DCERPC_SERIAL_LOW];
+        reply
+    }
+
+    pub fn is_fragment(&self) -> bool {
+        self.flags1[0] & constant::DCERPC_FLAGS1_FRAGMENT == constant::DCERPC_FLAGS1_FRAGMENT
+    }
+
+    pub fn is_last_fragment(&self) -> bool {
+        self.flags1[0] & constant::DCERPC_FLAGS1_LAST_FRAGMENT
+            == constant::DCERPC_FLAGS1_LAST_FRAGMENT
+    }
+
+    /// is_no_fack tells if the sender does not want the fragment to be acknowledged
+    pub fn is_no_fack(&self) -> bool {
+        self.flags1[0] & constant::DCERPC_FLAGS1_NO_FACK == constant::DCERPC_FLAGS1_NO_FACK
+    }
+
+    pub fn get_fragment_num(&self) -> u16 {
+        u16::from_le_bytes(self.fragment_num)
+    }
+
+    /// with_data copies the header for another body
+    fn with_data(&self, data: Box<[u8]>) -> Self {
+        DceRpcPacket {
+            fragment_len: u16::try_from(data.len()).unwrap_or(u16::MAX).to_le_bytes(),
+            data,
+            ..*self
+        }
+    }
+
+    /// fragment splits the body into fragments of at most max_data_len bytes,
+    /// the packet is returned as it is if the body fits.
+    pub fn fragment(&self, max_data_len: usize) -> Vec<DceRpcPacket> {
+        if self.data.len() <= max_data_len {
+            return vec![self.with_data(self.data.clone())];
+        }
+
+        let chunks: Vec<&[u8]> = self.data.chunks(max_data_len).collect();
+        let last = chunks.len() - 1;
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let mut fragment = self.with_data((*chunk).into());
+                fragment.flags1[0] |= constant::DCERPC_FLAGS1_FRAGMENT;
+                // the receiver must not acknowledge the fragments in between,
+                // the response acknowledges the whole body
+                match i == last {
+                    true => fragment.flags1[0] |= constant::DCERPC_FLAGS1_LAST_FRAGMENT,
+                    false => fragment.flags1[0] |= constant::DCERPC_FLAGS1_NO_FACK,
+                }
+                fragment.fragment_num = (i as u16).to_le_bytes();
+                fragment.serial_low = [(i & 0xff) as u8];
+                fragment.serial_high = [((i >> 8) & 0xff) as u8];
+                fragment
+            })
+            .collect()
+    }
+
+    /// fack acknowledges this fragment
+    pub fn fack(&self) -> Self {
+        let mut data: Vec<u8> = vec![];
+        data.push(constant::DCERPC_FACK_VERSION);
+        data.push(0x00); // padding
+        data.extend(constant::DCERPC_FACK_WINDOW_SIZE.to_le_bytes());
+        // max_tsdu and max_frag_size
+        data.extend((constant::DCERPC_MAX_FRAGMENT_DATA_LEN as u32).to_le_bytes());
+        data.extend((constant::DCERPC_MAX_FRAGMENT_DATA_LEN as u32).to_le_bytes());
+        // serial number of the fragment
+        data.extend([self.serial_low[0], self.serial_high[0]]);
+        // no selective acknowledgement
+        data.extend(0u16.to_le_bytes());
+
+        let mut fack = self.with_data(data.into_boxed_slice());
+        fack.packet_type = [constant::PacketType::Fack as u8];
+        fack.flags1 = constant::DCERPC_FLAGS1;
+        fack
     }
 }
 
-impl TryFrom<Vec<u8>> for DceRpcPacket {
-    type Error = anyhow::Error;
-
-    fn try_from(value: Vec<u8>) -> anyhow::Result<Self, Self::Error> {
-        let packet_type = match value.get(1..2) {
-            Some(p) => constant::PacketType::from_u8(p[0]).unwrap(),
-            None => return Err(anyhow!("DCE/RPC packet type cannot be created")),
-        };
-
-        let obj_uuid = match value.get(8..24) {
-            Some(o) => TryInto::<&[u8; 16]>::try_into(o).unwrap(),
-            None => return Err(anyhow!("DCE/RPC object UUID cannot be created")),
-        };
-
-        let interface = match value.get(24..40) {
-            Some(i) => TryInto::<&[u8; 16]>::try_into(i).unwrap(),
-            None => return Err(anyhow!("DCE/RPC interface cannot be created")),
-        };
-
-        let activity = match value.get(40..56) {
-            Some(a) => TryInto::<&[u8; 16]>::try_into(a).unwrap(),
-            None => return Err(anyhow!("DCE/RPC activity cannot be created")),
-        };
-
-        let interface_ver = match value.get(60..64) {
-            Some(iv) => {
-                let b = u32::from_le_bytes(TryInto::<[u8; 4]>::try_into(iv).unwrap());
-                constant::InterfaceVersion::from_u32(b).unwrap()
+/// DceRpcFragments collects the fragments of a body until all of them are
+/// received, fragments of another call are dropped.
+#[derive(Debug, Default)]
+pub struct DceRpcFragments {
+    fragments: BTreeMap<u16, DceRpcPacket>,
+    last_fragment_num: Option<u16>,
+}
+
+impl DceRpcFragments {
+    /// push returns the reassembled packet once the last missing fragment is pushed
+    pub fn push(&mut self, packet: DceRpcPacket) -> Option<DceRpcPacket> {
+        if let Some(first) = self.fragments.values().next() {
+            if first.activity != packet.activity || first.seq_num != packet.seq_num {
+                log::debug!("dropping fragment of another call");
+                return None;
             }
-            None => return Err(anyhow!("DCE/RPC interface version cannot be created")),
-        };
-
-        let seq_num = match value.get(64..68) {
-            Some(s) => u32::from_le_bytes(*TryInto::<&[u8; 4]>::try_into(s).unwrap()),
-            None => return Err(anyhow!("DCE/RPC sequence number cannot be created")),
-        };
-
-        let opnum_u16 = match value.get(68..70) {
-            Some(o) => u16::from_le_bytes(*TryInto::<&[u8; 2]>::try_into(o).unwrap()),
-            None => return Err(anyhow!("DCE/RPC opnum cannot be created")),
-        };
-        let opnum = constant::OpNum::from_u16(opnum_u16).unwrap();
-
-        match value.get(74..76) {
-            Some(f) => u16::from_le_bytes(*TryInto::<&[u8; 2]>::try_into(f).unwrap()),
-            None => return Err(anyhow!("DCE/RPC fragment length cannot be created")),
-        };
-
-        // the rest of the bytes are stub data (payload)
-        let payload_start: usize = 80;
-        let payload = match value.get(payload_start..) {
-            Some(p) => p.to_vec().into_boxed_slice(),
-            None => return Err(anyhow!("DCE/RPC stub data cannot be created")),
-        };
+        }
 
-        let dcerpc_packet = DceRpcPacket::new(
+        let fragment_num = packet.get_fragment_num();
+        if packet.is_last_fragment() {
+            self.last_fragment_num = Some(fragment_num);
+        }
+        self.fragments.insert(fragment_num, packet);
+
+        match self.last_fragment_num {
+            // every fragment from 0 to the last one has been received
+            Some(last)
+                if self.fragments.len() == last as usize + 1
+                    && self.fragments.keys().next_back() == Some(&last) =>
+            {
+                Some(Self::reassemble(mem::take(&mut self.fragments)))
+            }
+            _ => None,
+        }
+    }
+
+    fn reassemble(fragments: BTreeMap<u16, DceRpcPacket>) -> DceRpcPacket {
+        let data: Vec<u8> = fragments
+            .values()
+            .flat_map(|f| f.data.iter().copied())
+            .collect();
+
+        let first = &fragments[&0];
+        let mut packet = first.with_data(data.into_boxed_slice());
+        packet.flags1[0] &=
+            !(constant::DCERPC_FLAGS1_FRAGMENT | constant::DCERPC_FLAGS1_LAST_FRAGMENT);
+        packet.fragment_num = constant::DCERPC_FRAGMENT_NUM.to_le_bytes();
+        packet
+    }
+}
+
+impl Encode for DceRpcPacket {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        // the fragment length is the one of the body, whatever the field says
+        let fragment_len = length::<u16>(self.data.len(), "DCE/RPC stub data")?;
+
+        buf.extend(self.version);
+        buf.extend(self.packet_type);
+        buf.extend(self.flags1);
+        buf.extend(self.flags2);
+        buf.extend(self.data_representation);
+        buf.extend(self.serial_high);
+        buf.extend(self.obj_uuid);
+        buf.extend(self.interface);
+        buf.extend(self.activity);
+        buf.extend(self.server_boot_time);
+        buf.extend(self.interface_ver);
+        buf.extend(self.seq_num);
+        buf.extend(self.opnum);
+        buf.extend(self.interface_hint);
+        buf.extend(self.activity_hint);
+        buf.extend(fragment_len.to_le_bytes());
+        buf.extend(self.fragment_num);
+        buf.extend(self.auth_proto);
+        buf.extend(self.serial_low);
+        buf.extend(&*self.data);
+
+        Ok(())
+    }
+}
+
+impl Decode for DceRpcPacket {
+    /// decode keeps the header as it is on the wire, e.g. a reject may well be
+    /// about an interface or an operation unknown to the server
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let version = cursor.array("DCE/RPC version")?;
+        let packet_type = cursor.array("DCE/RPC packet type")?;
+        if constant::PacketType::from_u8(packet_type[0]).is_none() {
+            return Err(ProtocolError::Invalid {
+                field: "DCE/RPC packet type",
+                value: packet_type[0].into(),
+            });
+        }
+        let flags1 = cursor.array("DCE/RPC flags1")?;
+        let flags2 = cursor.array("DCE/RPC flags2")?;
+        let data_representation = cursor.array("DCE/RPC data representation")?;
+        let serial_high = cursor.array("DCE/RPC serial high")?;
+        let obj_uuid = cursor.array("DCE/RPC object UUID")?;
+        let interface = cursor.array("DCE/RPC interface")?;
+        let activity = cursor.array("DCE/RPC activity")?;
+        let server_boot_time = cursor.array("DCE/RPC server boot time")?;
+        let interface_ver = cursor.array("DCE/RPC interface version")?;
+        let seq_num = cursor.array("DCE/RPC sequence number")?;
+        let opnum = cursor.array("DCE/RPC opnum")?;
+        let interface_hint = cursor.array("DCE/RPC interface hint")?;
+        let activity_hint = cursor.array("DCE/RPC activity hint")?;
+        let fragment_len = cursor.array("DCE/RPC fragment length")?;
+        let fragment_num = cursor.array("DCE/RPC fragment number")?;
+        let auth_proto = cursor.array("DCE/RPC auth protocol")?;
+        let serial_low = cursor.array("DCE/RPC serial low")?;
+        // the fragment length tells the number of stub data (payload) bytes
+        let data = cursor
+            .take(
+                u16::from_le_bytes(fragment_len) as usize,
+                "DCE/RPC stub data",
+            )?
+            .into();
+
+        Ok(DceRpcPacket {
+            version,
             packet_type,
-            Uuid::from_slice(obj_uuid).unwrap(),
-            Uuid::from_slice(interface).unwrap(),
+            flags1,
+            flags2,
+            data_representation,
+            serial_high,
+            obj_uuid,
+            interface,
+            activity,
+            server_boot_time,
             interface_ver,
-            Uuid::from_slice(activity).unwrap(),
             seq_num,
             opnum,
-            payload,
-        );
-
-        Ok(dcerpc_packet)
+            interface_hint,
+            activity_hint,
+            fragment_len,
+            fragment_num,
+            auth_proto,
+            serial_low,
+            data,
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::protocol::codec::test::{assert_decode_never_panics, assert_roundtrip};
+    use proptest::{collection, prelude::*};
 
     #[test]
     fn create_write_request_dcerpc_packet_should_ok() {
@@ -229,9 +390,204 @@ mod test {
                       00000000001402800000\
                       82";
         let target = hex::decode(target_bytes).unwrap();
-        let bytes = dcerpc_packet.concat().unwrap();
+        let bytes = dcerpc_packet.to_bytes().unwrap();
 
         assert_eq!(target, bytes);
         assert_eq!(dcerpc_packet.fragment_len, (91 as u16).to_le_bytes());
     }
+
+    #[test]
+    fn decode_dcerpc_packet_should_keep_the_uuids() {
+        let obj_uuid = Uuid::parse_str("dea00000-6c97-11d1-8271-00010313002a").unwrap();
+        let interface = Uuid::parse_str("dea00001-6c97-11d1-8271-00a02442df7d").unwrap();
+        let activity = Uuid::parse_str("401ca514-11a1-1e1e-9ec0-080027e3f4b9").unwrap();
+
+        let dcerpc_packet = DceRpcPacket::new(
+            constant::PacketType::Request,
+            obj_uuid,
+            interface,
+            constant::InterfaceVersion::ReadWrite,
+            activity,
+            4,
+            constant::OpNum::Write,
+            vec![0x00; 20].into_boxed_slice(),
+        );
+
+        let parsed = DceRpcPacket::from_bytes(&dcerpc_packet.to_bytes().unwrap()).unwrap();
+
+        assert_eq!(parsed.obj_uuid, obj_uuid.to_bytes_le());
+        assert_eq!(parsed.interface, interface.to_bytes_le());
+        assert_eq!(parsed.activity, activity.to_bytes_le());
+    }
+
+    #[test]
+    fn fragments_should_reassemble_body_of_same_call() {
+        let packet = DceRpcPacket::new(
+            constant::PacketType::Response,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            constant::InterfaceVersion::ReadWrite,
+            Uuid::new_v4(),
+            1,
+            constant::OpNum::Read,
+            vec![0xab; 25].into_boxed_slice(),
+        );
+        let other = DceRpcPacket::new(
+            constant::PacketType::Response,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            constant::InterfaceVersion::ReadWrite,
+            Uuid::new_v4(),
+            2,
+            constant::OpNum::Read,
+            vec![0xcd; 25].into_boxed_slice(),
+        );
+
+        let fragments = packet.fragment(10);
+        assert_eq!(fragments.len(), 3);
+        assert!(fragments.iter().all(|f| f.is_fragment()));
+        assert!(fragments[2].is_last_fragment());
+        assert!(!fragments[1].is_last_fragment());
+        assert_eq!(fragments[2].data.len(), 5);
+
+        let [first, second, third]: [DceRpcPacket; 3] = fragments.try_into().unwrap();
+        let mut reassembly = DceRpcFragments::default();
+        assert!(reassembly.push(third).is_none());
+        // a fragment of another call is dropped
+        let other = other.fragment(10).into_iter().next().unwrap();
+        assert!(reassembly.push(other).is_none());
+        assert!(reassembly.push(first).is_none());
+        let reassembled = reassembly.push(second).unwrap();
+
+        assert!(!reassembled.is_fragment());
+        assert_eq!(reassembled.get_fragment_num(), 0);
+        assert_eq!(reassembled.data, vec![0xab; 25].into_boxed_slice());
+    }
+
+    #[test]
+    fn small_body_should_not_be_fragmented() {
+        let packet = DceRpcPacket::new(
+            constant::PacketType::Request,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            constant::InterfaceVersion::ReadWrite,
+            Uuid::new_v4(),
+            1,
+            constant::OpNum::Read,
+            vec![0x00; 10].into_boxed_slice(),
+        );
+
+        let fragments = packet.fragment(10);
+        assert_eq!(fragments.len(), 1);
+        assert!(!fragments[0].is_fragment());
+    }
+
+    #[test]
+    fn decode_fault_should_return_typed_error() {
+        let request = DceRpcPacket::new(
+            constant::PacketType::Request,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            constant::InterfaceVersion::ReadWrite,
+            Uuid::new_v4(),
+            1,
+            constant::OpNum::Read,
+            Box::new([]),
+        );
+        let mut fault = request
+            .reply(
+                constant::PacketType::Fault,
+                0x1c01_0002u32.to_le_bytes().into(),
+            )
+            .to_bytes()
+            .unwrap();
+        // an opnum unknown to the client must not panic
+        fault[68] = 0x7f;
+
+        let packet = DceRpcPacket::from_bytes(&fault).unwrap();
+        assert_eq!(packet.opnum, [0x7f, 0x00]);
+        assert_eq!(
+            packet.into_result().unwrap_err(),
+            DceRpcError::Fault(NcaStatus::OpRngError)
+        );
+
+        // unknown packet type
+        fault[1] = 0x0b;
+        assert_eq!(
+            DceRpcPacket::from_bytes(&fault).unwrap_err(),
+            ProtocolError::Invalid {
+                field: "DCE/RPC packet type",
+                value: 0x0b
+            }
+        );
+        // the fragment length exceeds the datagram
+        fault[1] = constant::PacketType::Fault as u8;
+        fault[74] = 0xff;
+        assert!(matches!(
+            DceRpcPacket::from_bytes(&fault).unwrap_err(),
+            ProtocolError::Truncated { .. }
+        ));
+    }
+
+    fn dcerpc_packet() -> impl Strategy<Value = DceRpcPacket> {
+        (
+            0..=0x0au8,
+            any::<[u8; 3]>(),
+            any::<[[u8; 16]; 3]>(),
+            any::<[u32; 3]>(),
+            any::<[u16; 4]>(),
+            collection::vec(any::<u8>(), 0..1500),
+        )
+            .prop_map(
+                |(packet_type, [flags1, flags2, serial], uuids, [boot, ver, seq], words, data)| {
+                    let [opnum, interface_hint, activity_hint, fragment_num] = words;
+                    DceRpcPacket {
+                        packet_type: [packet_type],
+                        flags1: [flags1],
+                        flags2: [flags2],
+                        serial_high: [serial],
+                        obj_uuid: uuids[0],
+                        interface: uuids[1],
+                        activity: uuids[2],
+                        server_boot_time: boot.to_le_bytes(),
+                        interface_ver: ver.to_le_bytes(),
+                        seq_num: seq.to_le_bytes(),
+                        opnum: opnum.to_le_bytes(),
+                        interface_hint: interface_hint.to_le_bytes(),
+                        activity_hint: activity_hint.to_le_bytes(),
+                        fragment_len: (data.len() as u16).to_le_bytes(),
+                        fragment_num: fragment_num.to_le_bytes(),
+                        serial_low: [serial],
+                        data: data.into(),
+                        ..DceRpcPacket::new(
+                            constant::PacketType::Request,
+                            Uuid::nil(),
+                            Uuid::nil(),
+                            constant::InterfaceVersion::ReadWrite,
+                            Uuid::nil(),
+                            0,
+                            constant::OpNum::Connect,
+                            Box::new([]),
+                        )
+                    }
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn dcerpc_packet_should_roundtrip(packet in dcerpc_packet()) {
+            assert_roundtrip(&packet);
+        }
+
+        #[test]
+        fn decode_dcerpc_packet_should_not_panic(
+            packet in dcerpc_packet(),
+            garbage in collection::vec(any::<u8>(), 0..128),
+            flip in any::<usize>(),
+        ) {
+            assert_decode_never_panics::<DceRpcPacket>(&packet.to_bytes().unwrap(), flip);
+            assert_decode_never_panics::<DceRpcPacket>(&garbage, flip);
+        }
+    }
 }
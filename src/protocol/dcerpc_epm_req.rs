@@ -1,8 +1,6 @@
-use std::mem;
+use super::{constant, Cursor, Decode, Encode, ProtocolError};
 
-use super::{constant, Packet};
-
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct DceRpcEpmRequest {
     pub inquiry_type: [u8; 4],
     pub object_reference_id: [u8; 4],
@@ -33,39 +31,52 @@ impl DceRpcEpmRequest {
     }
 }
 
-impl Packet for DceRpcEpmRequest {
-    fn concat(&self) -> anyhow::Result<Vec<u8>> {
-        let mut v: Vec<u8> = vec![];
-
-        v.extend(self.inquiry_type);
-        v.extend(self.object_reference_id);
-        v.extend(self.object_object);
-        v.extend(self.interface_refrence_id);
-        v.extend(self.interface_interface);
-        v.extend(self.interface_version_major);
-        v.extend(self.interface_version_minor);
-        v.extend(self.version_option);
-        v.extend(self.handle);
-        v.extend(self.max_entries);
+impl Encode for DceRpcEpmRequest {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        buf.extend(self.inquiry_type);
+        buf.extend(self.object_reference_id);
+        buf.extend(self.object_object);
+        buf.extend(self.interface_refrence_id);
+        buf.extend(self.interface_interface);
+        buf.extend(self.interface_version_major);
+        buf.extend(self.interface_version_minor);
+        buf.extend(self.version_option);
+        buf.extend(self.handle);
+        buf.extend(self.max_entries);
 
-        Ok(v)
+        Ok(())
     }
+}
 
-    fn size(&self) -> usize {
-        mem::size_of::<Self>()
+impl Decode for DceRpcEpmRequest {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        Ok(DceRpcEpmRequest {
+            inquiry_type: cursor.array("EPM inquiry type")?,
+            object_reference_id: cursor.array("EPM object referent")?,
+            object_object: cursor.array("EPM object")?,
+            interface_refrence_id: cursor.array("EPM interface referent")?,
+            interface_interface: cursor.array("EPM interface")?,
+            interface_version_major: cursor.array("EPM interface version major")?,
+            interface_version_minor: cursor.array("EPM interface version minor")?,
+            version_option: cursor.array("EPM version option")?,
+            handle: cursor.array("EPM handle")?,
+            max_entries: cursor.array("EPM max entries")?,
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::protocol::codec::test::{assert_decode_never_panics, assert_roundtrip};
+    use proptest::{collection, prelude::*};
 
     #[test]
     pub fn construct_packet_successfully() {
         let handle = [0x00; 20];
         let dcerpc_epm_request = DceRpcEpmRequest::new(handle);
 
-        let result = dcerpc_epm_request.concat().unwrap();
+        let result = dcerpc_epm_request.to_bytes().unwrap();
         let target = hex::decode(
             "00000000010000000000\
                  00000000000000000000\
@@ -74,9 +85,24 @@ mod test {
                  00000000000000000100\
                  00000000000000000000\
                  00000000000000000000\
-                 000001000000",
+                 000010000000",
         )
         .unwrap();
         assert_eq!(result, target);
     }
+
+    proptest! {
+        #[test]
+        fn dcerpc_epm_request_should_roundtrip(handle in any::<[u8; 20]>()) {
+            assert_roundtrip(&DceRpcEpmRequest::new(handle));
+        }
+
+        #[test]
+        fn decode_dcerpc_epm_request_should_not_panic(
+            bytes in collection::vec(any::<u8>(), 0..128),
+            flip in any::<usize>(),
+        ) {
+            assert_decode_never_panics::<DceRpcEpmRequest>(&bytes, flip);
+        }
+    }
 }
@@ -1,72 +1,91 @@
-use super::constant;
+use super::{
+    constant::{self, TowerFloorProtocol},
+    Cursor, Decode, ProtocolError,
+};
 use anyhow::anyhow;
 use std::net::Ipv4Addr;
-use std::{mem, str};
+use std::str;
 use uuid::Uuid;
 
 // DCE/RPC Endpoint Mapper response -------------------------------------------
 
 #[derive(Debug)]
-pub struct DceRpcEpmResponse<'a> {
+pub struct DceRpcEpmResponse {
     pub handle: String,
     _num_of_entries: u32,
     _actual_count: u32,
-    pub entry: Box<Entry<'a>>,
+    pub entries: Vec<Entry>,
+    /// status is the lookup status, see `DCERPC_EPM_STATUS_NOT_REGISTERED`
+    pub status: u32,
 }
 
-impl<'a> TryFrom<&'a [u8]> for DceRpcEpmResponse<'a> {
-    type Error = anyhow::Error;
-
-    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
-        // handle occupies 20 bytes
-        let handle_arr = match value.get(0..20) {
-            Some(h) => TryInto::<&[u8; 20]>::try_into(h).unwrap(),
-            None => return Err(anyhow!("handle cannot be created".to_string())),
-        };
-
-        let handle = handle_arr
-            .iter()
-            .map(|h| format!("{:02x?}", h))
-            .collect::<Vec<String>>()
-            .join("");
-
-        // num_of_entries occupies 4 bytes
-        let num_of_entries = match value.get(20..24) {
-            Some(n) => TryInto::<&[u8; 4]>::try_into(n).unwrap(),
-            None => return Err(anyhow!("number of entries cannot be created".to_string())),
-        };
-
-        // max_count occupies 4 bytes, not used, put here for clarity
-        if let Some(_max_count) = value.get(24..28) {};
+impl DceRpcEpmResponse {
+    /// is_end_of_list tells if there are no more entries to be looked up,
+    /// either the status says so or the endpoint mapper returned a nil handle
+    pub fn is_end_of_list(&self) -> bool {
+        self.status == constant::DCERPC_EPM_STATUS_NOT_REGISTERED
+            || self.handle.chars().all(|c| c == '0')
+    }
 
-        // offset occupies 4 bytes, not used, put here for clarity
-        if let Some(_offset) = value.get(28..32) {};
+    pub fn handle_bytes(&self) -> anyhow::Result<[u8; 20]> {
+        TryInto::<[u8; 20]>::try_into(hex::decode(&self.handle)?)
+            .map_err(|_| anyhow!("handle must be 20 bytes"))
+    }
+}
 
-        // actual_count occupies 4 bytes
-        let actual_count = match value.get(32..36) {
-            Some(a) => u32::from_le_bytes(*TryInto::<&[u8; 4]>::try_into(a).unwrap()),
-            None => return Err(anyhow!("actual count cannot be created".to_string())),
+impl Decode for DceRpcEpmResponse {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        // the status is the last 4 bytes, after the handle and the counts
+        let remaining = cursor.remaining();
+        let body_len = match remaining.checked_sub(4) {
+            Some(len) if len >= 36 => len,
+            _ => {
+                return Err(ProtocolError::Truncated {
+                    field: "EPM response",
+                    needed: 40,
+                    remaining,
+                })
+            }
         };
+        let mut body = cursor.sub(body_len, "EPM response")?;
+        let status = cursor.u32_le("EPM status")?;
 
-        // TODO: the entry byte order is not confirmed.
-        // I do not know how the bytes would look like if there were multiple entries.
-        // so for now, only assume there is only one entry in box,
-        // thus considering the remaining bytes belong that specific entry.
-        let entry_arr = match value.get(36..) {
-            Some(e) => TryInto::<&[u8]>::try_into(e).unwrap(),
-            None => return Err(anyhow!("entries cannot be created".to_string())),
-        };
+        // handle occupies 20 bytes
+        let handle = hex::encode(body.array::<20>("EPM handle")?);
+        let num_of_entries = body.u32_le("EPM number of entries")?;
+        // max_count and offset occupy 4 bytes each, not used
+        body.skip(8, "EPM max count and offset")?;
+        let actual_count = body.u32_le("EPM actual count")?;
+
+        // the entries come first, each one with a referent to its tower,
+        // the towers are deferred after all the entries in the same order
+        let mut entries: Vec<Entry> = vec![];
+        let mut tower_referents: Vec<u32> = vec![];
+        for _ in 0..actual_count {
+            let object = Uuid::from_bytes_le(body.array("EPM entry object")?);
+            let (tower_pointer, referent) = TowerPointer::decode_entry(&mut body)?;
+
+            tower_referents.push(referent);
+            entries.push(Entry {
+                object,
+                tower_pointer,
+            });
+        }
 
-        let entry = match Entry::try_from(entry_arr) {
-            Ok(entry) => entry,
-            Err(e) => return Err(e),
-        };
+        for (entry, referent) in entries.iter_mut().zip(tower_referents) {
+            // a null referent has no tower
+            if referent == 0 {
+                continue;
+            }
+            entry.tower_pointer.decode_tower(&mut body)?;
+        }
 
         let dcerpc_epm_response = DceRpcEpmResponse {
             handle,
-            _num_of_entries: u32::from_le_bytes(*num_of_entries),
+            _num_of_entries: num_of_entries,
             _actual_count: actual_count,
-            entry: Box::new(entry),
+            entries,
+            status,
         };
 
         Ok(dcerpc_epm_response)
@@ -76,115 +95,130 @@ impl<'a> TryFrom<&'a [u8]> for DceRpcEpmResponse<'a> {
 // DCE/RPC Endpoint Mapper response entries -----------------------------------
 
 #[derive(Debug, Clone)]
-pub struct Entry<'a> {
+pub struct Entry {
     pub object: Uuid,
-    pub tower_pointer: TowerPointer<'a>,
+    pub tower_pointer: TowerPointer,
 }
 
-impl<'a> TryFrom<&'a [u8]> for Entry<'a> {
-    type Error = anyhow::Error;
-
-    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
-        let object = match value.get(0..16) {
-            Some(o) => TryInto::<&[u8; 16]>::try_into(o).unwrap(),
-            None => return Err(anyhow!("this entry is empty".to_string())),
-        };
-
-        // TODO: unsure how the byte order looks like if there
-        // were multiple entries, so for now, assume only one
-        // entry and treat the rest of bytes as tower_pointer.
-        let tower_pointer_arr = match value.get(16..) {
-            Some(t) => t,
-            None => todo!(),
-        };
-
-        let tower_pointer = match TowerPointer::try_from(tower_pointer_arr) {
-            Ok(t) => t,
-            Err(e) => return Err(e),
-        };
-
-        let entry = Entry {
-            object: Uuid::from_bytes_le(*object),
-            tower_pointer,
-        };
+impl Entry {
+    /// interface_uuid is the uuid of the 1st tower floor
+    pub fn interface_uuid(&self) -> Option<Uuid> {
+        self.tower_pointer.floors.first().and_then(|f| f.uuid)
+    }
 
-        Ok(entry)
+    /// udp_port is the port of the last udp tower floor
+    pub fn udp_port(&self) -> Option<u16> {
+        self.tower_pointer
+            .floors
+            .iter()
+            .rev()
+            .find(|floor| matches!(floor.protocol, TowerFloorProtocol::Udp))
+            .and_then(|floor| floor.udp_port)
     }
 }
 
 // DCE/RPC Endpoint Mapper response entry tower pointer -----------------------
 
 #[derive(Debug, Clone)]
-pub struct TowerPointer<'a> {
+pub struct TowerPointer {
     _annotation_offset: u32,
     _annotation_length: u32,
-    pub annotation: &'a str,
+    /// annotation is without the trailing null character
+    pub annotation: String,
     _length1: u32,
     _length2: u32,
     pub num_of_floors: u16,
     pub floors: Vec<TowerFloor>,
 }
 
-impl<'a> TryFrom<&'a [u8]> for TowerPointer<'a> {
-    type Error = anyhow::Error;
+impl Decode for TowerPointer {
+    /// decode parses a single entry after its object uuid, immediately
+    /// followed by its tower
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let (mut tower_pointer, _) = TowerPointer::decode_entry(cursor)?;
+        tower_pointer.decode_tower(cursor)?;
+
+        Ok(tower_pointer)
+    }
+}
+
+impl TowerPointer {
+    /// decode_entry parses the tower referent and the annotation of an entry,
+    /// returns the referent too
+    fn decode_entry(cursor: &mut Cursor<'_>) -> Result<(Self, u32), ProtocolError> {
+        // tower referent occupies 4 bytes
+        let referent = cursor.u32_le("tower pointer referent")?;
 
-    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
         // annotation_offset occupies 4 bytes
-        let annotation_offset = match value.get(4..8) {
-            // TODO: not sure if this is little endian
-            Some(a) => u32::from_le_bytes(*TryInto::<&[u8; 4]>::try_into(a).unwrap()),
-            None => {
-                return Err(anyhow!(
-                    "tower pointer annotation offset cannot be created".to_string()
-                ))
-            }
-        };
+        let annotation_offset = cursor.u32_le("tower pointer annotation offset")?;
 
         // annotation_length occupies 4 bytes
-        let annotation_length = match value.get(8..12) {
-            Some(a) => u32::from_le_bytes(*TryInto::<&[u8; 4]>::try_into(a).unwrap()),
-            None => {
-                return Err(anyhow!(
-                    "tower pointer annotation length cannot be created".to_string()
-                ))
-            }
-        };
+        let annotation_length = cursor.u32_le("tower pointer annotation length")?;
 
         // annotation dynamically occupies <annotation_length> bytes
-        let annotation_start = 12;
-        let annotation_end = annotation_start + (annotation_length as usize);
-        let annotation = match value.get(annotation_start..annotation_end) {
-            Some(a) => str::from_utf8(a).unwrap(),
-            None => {
-                return Err(anyhow!(
-                    "tower pointer annotation cannot be created".to_string()
-                ))
-            }
-        };
+        let annotation = cursor.take(annotation_length as usize, "tower pointer annotation")?;
+        let annotation = str::from_utf8(annotation)
+            .map_err(|_| ProtocolError::InvalidText {
+                field: "tower pointer annotation",
+            })?
+            .trim_end_matches('\0')
+            .to_string();
+        // NDR aligns every entry and tower to 4 bytes
+        cursor.align(4);
 
-        // length1 occupies 4 bytes
-        let length1_end = annotation_end + 4;
-        let length1 = match value.get(annotation_end..length1_end) {
-            Some(l) => u32::from_le_bytes(*TryInto::<&[u8; 4]>::try_into(l).unwrap()),
-            None => return Err(anyhow!("tower point length1 cannot be created".to_string())),
+        let tower_pointer = TowerPointer {
+            _annotation_offset: annotation_offset,
+            _annotation_length: annotation_length,
+            annotation,
+            _length1: 0,
+            _length2: 0,
+            num_of_floors: 0,
+            floors: vec![],
         };
 
-        // length2 occupies 4 bytes
-        let length2_end = length1_end + 4;
-        let length2 = match value.get(length1_end..length2_end) {
-            Some(l) => u32::from_le_bytes(*TryInto::<&[u8; 4]>::try_into(l).unwrap()),
-            None => return Err(anyhow!("tower point length2 cannot be created".to_string())),
-        };
+        Ok((tower_pointer, referent))
+    }
+
+    /// decode_tower parses the deferred tower
+    fn decode_tower(&mut self, cursor: &mut Cursor<'_>) -> Result<(), ProtocolError> {
+        // length1 (max count) occupies 4 bytes
+        let length1 = cursor.u32_le("tower pointer length1")?;
+
+        // length2 (tower length) occupies 4 bytes
+        let length2 = cursor.u32_le("tower pointer length2")?;
+
+        let mut tower = cursor.sub(length2 as usize, "tower")?;
+        cursor.align(4);
 
         // num of floors occupies 2 bytes
-        let num_of_floors_end = length2_end + 2;
-        let num_of_floors = match value.get(length2_end..num_of_floors_end) {
-            Some(n) => u16::from_le_bytes(*TryInto::<&[u8; 2]>::try_into(n).unwrap()),
-            None => return Err(anyhow!("num of floors cannot be created".to_string())),
-        };
+        let num_of_floors = tower.u16_le("num of floors")?;
+
+        self._length1 = length1;
+        self._length2 = length2;
+        self.num_of_floors = num_of_floors;
+        self.floors = TowerFloor::decode_floors(&mut tower, num_of_floors)?;
+
+        Ok(())
+    }
+}
 
-        // construct floors
-        let mut floors_start = num_of_floors_end;
+// DCE/RPC Endpoint Mapper response entry tower floor -------------------------
+
+#[derive(Debug, Clone)]
+pub struct TowerFloor {
+    _lhs_length: u16,
+    _rhs_length: u16,
+    pub protocol: constant::TowerFloorProtocol,
+    pub uuid: Option<Uuid>,
+    pub udp_port: Option<u16>,
+    pub ipv4: Option<Ipv4Addr>,
+}
+
+impl TowerFloor {
+    fn decode_floors(
+        cursor: &mut Cursor<'_>,
+        num_of_floors: u16,
+    ) -> Result<Vec<Self>, ProtocolError> {
         let mut floors: Vec<TowerFloor> = vec![];
         for i in 0..num_of_floors {
             // uuid type floor (in the order of bytes)
@@ -201,42 +235,21 @@ impl<'a> TryFrom<&'a [u8]> for TowerPointer<'a> {
             // rhs_length (2 bytes)
             // data <-- data with rhs_length
 
-            // lhs_length occupies 2 bytes
-            let lhs_length = match value.get(floors_start..floors_start + 2) {
-                Some(l) => u16::from_le_bytes(*TryInto::<&[u8; 2]>::try_into(l).unwrap()),
-                None => todo!(),
-            };
+            let lhs_length = cursor.u16_le("tower floor lhs length")?;
+            let mut lhs = cursor.sub(lhs_length as usize, "tower floor lhs")?;
+            let rhs_length = cursor.u16_le("tower floor rhs length")?;
+            let mut rhs = cursor.sub(rhs_length as usize, "tower floor rhs")?;
 
             // protocol occupies 1 byte
-            let protocol_start = floors_start + mem::size_of_val(&lhs_length);
-            let protocol_end = protocol_start + 1;
-            let protocol_u8 = match value.get(protocol_start..protocol_end) {
-                Some(p) => u8::from_le_bytes(*TryInto::<&[u8; 1]>::try_into(p).unwrap()),
-                None => return Err(anyhow!("tower floor protocol cannot be created".to_string())),
-            };
+            let protocol_u8 = lhs.u8("tower floor protocol")?;
             let protocol = match constant::TowerFloorProtocol::from_u8(protocol_u8) {
                 Some(p) => p,
                 None => {
-                    let err = format!(
+                    log::debug!(
                         "tower floor \"{}\" protocol \"{}\" not found",
                         i + 1,
                         protocol_u8
                     );
-                    dbg!("{}", err);
-                    continue;
-                }
-            };
-
-            // rhs_length
-            // lhs_length occupies 2 bytes
-            let rhs_start = floors_start + mem::size_of_val(&lhs_length) + (lhs_length as usize);
-            // rhs_length occupies 2 bytes
-            let rhs_end = rhs_start + 2;
-            let rhs_length = match value.get(rhs_start..rhs_end) {
-                Some(r) => u16::from_le_bytes(*TryInto::<&[u8; 2]>::try_into(r).unwrap()),
-                None => {
-                    let err = format!("tower floor \"{}\" rhs length cannot be created", i + 1);
-                    dbg!("{}", err);
                     continue;
                 }
             };
@@ -247,27 +260,16 @@ impl<'a> TryFrom<&'a [u8]> for TowerPointer<'a> {
             let mut ipv4: Option<Ipv4Addr> = None;
             match protocol {
                 constant::TowerFloorProtocol::Uuid => {
-                    let uuid_start = protocol_end;
-                    let uuid_end = protocol_end + 16; // uuid always occupies 16 bytes
-                    let uuid_arr_u8 = value.get(uuid_start..uuid_end).unwrap();
-                    uuid = Some(Uuid::from_bytes_le(
-                        *TryInto::<&[u8; 16]>::try_into(uuid_arr_u8).unwrap(),
-                    ));
+                    // uuid always occupies 16 bytes
+                    uuid = lhs.array("tower floor uuid").ok().map(Uuid::from_bytes_le);
                 }
                 constant::TowerFloorProtocol::Udp => {
-                    let udp_start = rhs_end;
-                    let udp_end = udp_start + 2; // udp port always occupies 2 bytes
-                    let udp_arr_u8 = value.get(udp_start..udp_end).unwrap();
-                    udp_port = Some(u16::from_be_bytes(
-                        *TryInto::<&[u8; 2]>::try_into(udp_arr_u8).unwrap(),
-                    ));
+                    // udp port always occupies 2 bytes
+                    udp_port = rhs.u16_be("tower floor udp port").ok();
                 }
                 constant::TowerFloorProtocol::Ip => {
-                    let ip_start = rhs_end;
-                    let ip_end = ip_start + 4; // ipv4 always occupies 4 bytes
-                    let ipv4_arr_u8 = value.get(ip_start..ip_end).unwrap();
-                    let ipv4_arr_u8_4bytes = TryInto::<&[u8; 4]>::try_into(ipv4_arr_u8).unwrap();
-                    ipv4 = Some(Ipv4Addr::from(*ipv4_arr_u8_4bytes));
+                    // ipv4 always occupies 4 bytes
+                    ipv4 = rhs.array::<4>("tower floor ipv4").ok().map(Ipv4Addr::from);
                 }
                 _ => (),
             };
@@ -280,44 +282,19 @@ impl<'a> TryFrom<&'a [u8]> for TowerPointer<'a> {
                 udp_port,
                 ipv4,
             });
-
-            // iterate next floor
-            // a floor always occupies lhs_length + rhs_length + 2 + 2
-            // where lhs_length and rhs_length occupies 2 bytes respectively
-            floors_start = floors_start + (lhs_length as usize) + (rhs_length as usize) + 2 + 2;
         }
 
-        let tower_pointer = TowerPointer {
-            _annotation_offset: annotation_offset,
-            _annotation_length: annotation_length,
-            annotation,
-            _length1: length1,
-            _length2: length2,
-            num_of_floors,
-            floors,
-        };
-
-        Ok(tower_pointer)
+        Ok(floors)
     }
 }
 
-// DCE/RPC Endpoint Mapper response entry tower floor -------------------------
-
-#[derive(Debug, Clone)]
-pub struct TowerFloor {
-    _lhs_length: u16,
-    _rhs_length: u16,
-    pub protocol: constant::TowerFloorProtocol,
-    pub uuid: Option<Uuid>,
-    pub udp_port: Option<u16>,
-    pub ipv4: Option<Ipv4Addr>,
-}
-
 // test -----------------------------------------------------------------------
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::protocol::codec::test::assert_decode_never_panics;
+    use proptest::{collection, prelude::*};
 
     fn get_et200sp_response_packet() -> Vec<u8> {
         let hex_string = "00000000290000000000\
@@ -356,11 +333,11 @@ mod test {
 
     #[test]
     // correct packet should map to all respective structs
-    fn try_from_tower_pointer_should_return_correctly() {
+    fn decode_tower_pointer_should_return_correctly() {
         let full_packet = &get_et200sp_response_packet()[..];
 
         let tower_pointer_packet = &get_et200sp_response_packet()[52..];
-        let tower_pointer = TowerPointer::try_from(tower_pointer_packet).unwrap();
+        let tower_pointer = TowerPointer::from_bytes(tower_pointer_packet).unwrap();
 
         assert_eq!(5, tower_pointer.floors.len());
         assert_eq!(
@@ -368,7 +345,7 @@ mod test {
             tower_pointer.floors[0].uuid.unwrap()
         );
 
-        let dcerpc_epm_response = DceRpcEpmResponse::try_from(full_packet).unwrap();
+        let dcerpc_epm_response = DceRpcEpmResponse::from_bytes(full_packet).unwrap();
         assert_eq!(
             "0000000029000000000000108000ec1c5d4d5497",
             dcerpc_epm_response.handle
@@ -376,17 +353,77 @@ mod test {
 
         assert_eq!(
             Uuid::parse_str("dea00000-6c97-11d1-8271-00010313002a").unwrap(),
-            dcerpc_epm_response.entry.object
+            dcerpc_epm_response.entries[0].object
+        );
+        assert_eq!(Some(0xc004), dcerpc_epm_response.entries[0].udp_port());
+        assert!(dcerpc_epm_response.entries[0]
+            .tower_pointer
+            .annotation
+            .starts_with("ET200SP"));
+        assert!(!dcerpc_epm_response.is_end_of_list());
+    }
+
+    #[test]
+    // the towers of all entries are deferred after the entries
+    fn decode_multiple_entries_should_return_every_tower() {
+        let packet = get_et200sp_response_packet();
+        let entry = &packet[36..128];
+        let tower = &packet[128..212];
+
+        let mut v = packet[0..20].to_vec();
+        v.extend(2u32.to_le_bytes());
+        v.extend(2u32.to_le_bytes());
+        v.extend(0u32.to_le_bytes());
+        v.extend(2u32.to_le_bytes());
+        v.extend(entry);
+        v.extend(entry);
+        // second object ends with 0x2b instead of 0x2a
+        v[36 + 92 + 15] = 0x2b;
+        v.extend(tower);
+        v.extend(tower);
+        v.extend(0u32.to_le_bytes());
+
+        let dcerpc_epm_response = DceRpcEpmResponse::from_bytes(&v[..]).unwrap();
+        assert_eq!(2, dcerpc_epm_response.entries.len());
+        assert_eq!(
+            Uuid::parse_str("dea00000-6c97-11d1-8271-00010313002b").unwrap(),
+            dcerpc_epm_response.entries[1].object
         );
+        for entry in dcerpc_epm_response.entries.iter() {
+            assert_eq!(5, entry.tower_pointer.floors.len());
+            assert_eq!(Some(0xc004), entry.udp_port());
+        }
+    }
 
-        // TODO: more tests
+    #[test]
+    // no more entries, the endpoint mapper says "not registered"
+    fn decode_empty_response_should_be_end_of_list() {
+        let full_packet = &get_empty_response_packet()[..];
+
+        let dcerpc_epm_response = DceRpcEpmResponse::from_bytes(full_packet).unwrap();
+        assert!(dcerpc_epm_response.entries.is_empty());
+        assert!(dcerpc_epm_response.is_end_of_list());
     }
 
     #[test]
     // if there is error, just return and ignore this packet
     fn test_try_from_dcerpc_epm_response_should_return_error() {
-        let full_packet = &get_empty_response_packet()[..];
+        let full_packet = &get_empty_response_packet()[..30];
+        assert!(DceRpcEpmResponse::from_bytes(full_packet).is_err());
 
-        assert!(DceRpcEpmResponse::try_from(full_packet).is_err());
+        // the entry is cut in the middle of its annotation
+        let full_packet = &get_et200sp_response_packet()[..100];
+        assert!(DceRpcEpmResponse::from_bytes(full_packet).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn decode_dcerpc_epm_response_should_not_panic(
+            garbage in collection::vec(any::<u8>(), 0..256),
+            flip in any::<usize>(),
+        ) {
+            assert_decode_never_panics::<DceRpcEpmResponse>(&get_et200sp_response_packet(), flip);
+            assert_decode_never_panics::<DceRpcEpmResponse>(&garbage, flip);
+        }
     }
 }
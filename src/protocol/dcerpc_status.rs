@@ -0,0 +1,215 @@
+use std::fmt;
+
+/// NcaStatus is the status carried by a DCE/RPC reject or fault,
+/// see DCE 1.1 RPC appendix E
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NcaStatus {
+    FaultIntDivByZero,
+    FaultAddrError,
+    FaultFpDivZero,
+    FaultFpUnderflow,
+    FaultFpOverflow,
+    FaultInvalidTag,
+    FaultInvalidBound,
+    RpcVersionMismatch,
+    UnspecReject,
+    BadActid,
+    WhoAreYouFailed,
+    ManagerNotEntered,
+    FaultCancel,
+    FaultIllInst,
+    FaultFpError,
+    FaultIntOverflow,
+    FaultUnspec,
+    FaultRemoteCommFailure,
+    FaultRemoteNoMemory,
+    InvalidPresContextId,
+    UnsupportedAuthnLevel,
+    InvalidChecksum,
+    InvalidCrc,
+    CommFailure,
+    OpRngError,
+    UnkIf,
+    WrongBootTime,
+    YouCrashed,
+    ProtoError,
+    OutArgsTooBig,
+    ServerTooBusy,
+    UnsupportedType,
+    Unknown(u32),
+}
+
+impl NcaStatus {
+    pub fn from_u32(value: u32) -> Self {
+        match value {
+            0x1c00_0001 => Self::FaultIntDivByZero,
+            0x1c00_0002 => Self::FaultAddrError,
+            0x1c00_0003 => Self::FaultFpDivZero,
+            0x1c00_0004 => Self::FaultFpUnderflow,
+            0x1c00_0005 => Self::FaultFpOverflow,
+            0x1c00_0006 => Self::FaultInvalidTag,
+            0x1c00_0007 => Self::FaultInvalidBound,
+            0x1c00_0008 => Self::RpcVersionMismatch,
+            0x1c00_0009 => Self::UnspecReject,
+            0x1c00_000a => Self::BadActid,
+            0x1c00_000b => Self::WhoAreYouFailed,
+            0x1c00_000c => Self::ManagerNotEntered,
+            0x1c00_000d => Self::FaultCancel,
+            0x1c00_000e => Self::FaultIllInst,
+            0x1c00_000f => Self::FaultFpError,
+            0x1c00_0010 => Self::FaultIntOverflow,
+            0x1c00_0012 => Self::FaultUnspec,
+            0x1c00_0013 => Self::FaultRemoteCommFailure,
+            0x1c00_001b => Self::FaultRemoteNoMemory,
+            0x1c00_001c => Self::InvalidPresContextId,
+            0x1c00_001d => Self::UnsupportedAuthnLevel,
+            0x1c00_001f => Self::InvalidChecksum,
+            0x1c00_0020 => Self::InvalidCrc,
+            0x1c01_0001 => Self::CommFailure,
+            0x1c01_0002 => Self::OpRngError,
+            0x1c01_0003 => Self::UnkIf,
+            0x1c01_0006 => Self::WrongBootTime,
+            0x1c01_0009 => Self::YouCrashed,
+            0x1c01_000b => Self::ProtoError,
+            0x1c01_0013 => Self::OutArgsTooBig,
+            0x1c01_0014 => Self::ServerTooBusy,
+            0x1c01_0017 => Self::UnsupportedType,
+            v => Self::Unknown(v),
+        }
+    }
+
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::FaultIntDivByZero => 0x1c00_0001,
+            Self::FaultAddrError => 0x1c00_0002,
+            Self::FaultFpDivZero => 0x1c00_0003,
+            Self::FaultFpUnderflow => 0x1c00_0004,
+            Self::FaultFpOverflow => 0x1c00_0005,
+            Self::FaultInvalidTag => 0x1c00_0006,
+            Self::FaultInvalidBound => 0x1c00_0007,
+            Self::RpcVersionMismatch => 0x1c00_0008,
+            Self::UnspecReject => 0x1c00_0009,
+            Self::BadActid => 0x1c00_000a,
+            Self::WhoAreYouFailed => 0x1c00_000b,
+            Self::ManagerNotEntered => 0x1c00_000c,
+            Self::FaultCancel => 0x1c00_000d,
+            Self::FaultIllInst => 0x1c00_000e,
+            Self::FaultFpError => 0x1c00_000f,
+            Self::FaultIntOverflow => 0x1c00_0010,
+            Self::FaultUnspec => 0x1c00_0012,
+            Self::FaultRemoteCommFailure => 0x1c00_0013,
+            Self::FaultRemoteNoMemory => 0x1c00_001b,
+            Self::InvalidPresContextId => 0x1c00_001c,
+            Self::UnsupportedAuthnLevel => 0x1c00_001d,
+            Self::InvalidChecksum => 0x1c00_001f,
+            Self::InvalidCrc => 0x1c00_0020,
+            Self::CommFailure => 0x1c01_0001,
+            Self::OpRngError => 0x1c01_0002,
+            Self::UnkIf => 0x1c01_0003,
+            Self::WrongBootTime => 0x1c01_0006,
+            Self::YouCrashed => 0x1c01_0009,
+            Self::ProtoError => 0x1c01_000b,
+            Self::OutArgsTooBig => 0x1c01_0013,
+            Self::ServerTooBusy => 0x1c01_0014,
+            Self::UnsupportedType => 0x1c01_0017,
+            Self::Unknown(v) => *v,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::FaultIntDivByZero => "nca_s_fault_int_div_by_zero",
+            Self::FaultAddrError => "nca_s_fault_addr_error",
+            Self::FaultFpDivZero => "nca_s_fault_fp_div_zero",
+            Self::FaultFpUnderflow => "nca_s_fault_fp_underflow",
+            Self::FaultFpOverflow => "nca_s_fault_fp_overflow",
+            Self::FaultInvalidTag => "nca_s_fault_invalid_tag",
+            Self::FaultInvalidBound => "nca_s_fault_invalid_bound",
+            Self::RpcVersionMismatch => "nca_s_rpc_version_mismatch",
+            Self::UnspecReject => "nca_s_unspec_reject",
+            Self::BadActid => "nca_s_bad_actid",
+            Self::WhoAreYouFailed => "nca_s_who_are_you_failed",
+            Self::ManagerNotEntered => "nca_s_manager_not_entered",
+            Self::FaultCancel => "nca_s_fault_cancel",
+            Self::FaultIllInst => "nca_s_fault_ill_inst",
+            Self::FaultFpError => "nca_s_fault_fp_error",
+            Self::FaultIntOverflow => "nca_s_fault_int_overflow",
+            Self::FaultUnspec => "nca_s_fault_unspec",
+            Self::FaultRemoteCommFailure => "nca_s_fault_remote_comm_failure",
+            Self::FaultRemoteNoMemory => "nca_s_fault_remote_no_memory",
+            Self::InvalidPresContextId => "nca_s_invalid_pres_context_id",
+            Self::UnsupportedAuthnLevel => "nca_s_unsupported_authn_level",
+            Self::InvalidChecksum => "nca_s_invalid_checksum",
+            Self::InvalidCrc => "nca_s_invalid_crc",
+            Self::CommFailure => "nca_s_comm_failure",
+            Self::OpRngError => "nca_s_op_rng_error",
+            Self::UnkIf => "nca_s_unk_if",
+            Self::WrongBootTime => "nca_s_wrong_boot_time",
+            Self::YouCrashed => "nca_s_you_crashed",
+            Self::ProtoError => "nca_s_proto_error",
+            Self::OutArgsTooBig => "nca_s_out_args_too_big",
+            Self::ServerTooBusy => "nca_s_server_too_busy",
+            Self::UnsupportedType => "nca_s_unsupported_type",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+
+    /// is_transient tells if the same call may succeed when it's tried again
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::ServerTooBusy | Self::CommFailure | Self::FaultRemoteCommFailure
+        )
+    }
+}
+
+impl fmt::Display for NcaStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:#010x})", self.name(), self.code())
+    }
+}
+
+/// DceRpcError is the answer of a server that did not execute the call
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DceRpcError {
+    /// the server refused the call, e.g. for an unknown interface or operation
+    Reject(NcaStatus),
+    /// the call failed while it was executed
+    Fault(NcaStatus),
+    /// the server does not know the call, i.e. the request got lost
+    Nocall,
+    /// no response after pinging the server
+    Timeout,
+}
+
+impl fmt::Display for DceRpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reject(status) => write!(f, "DCE/RPC call rejected: {status}"),
+            Self::Fault(status) => write!(f, "DCE/RPC call failed: {status}"),
+            Self::Nocall => write!(f, "DCE/RPC call unknown to the server"),
+            Self::Timeout => write!(f, "DCE/RPC call timed out"),
+        }
+    }
+}
+
+impl std::error::Error for DceRpcError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nca_status_should_roundtrip() {
+        for code in (0x1c00_0000..0x1c00_0030).chain(0x1c01_0000..0x1c01_0020) {
+            assert_eq!(NcaStatus::from_u32(code).code(), code);
+        }
+
+        assert_eq!(NcaStatus::from_u32(0x1c01_0002), NcaStatus::OpRngError);
+        assert_eq!(
+            NcaStatus::from_u32(0x1c01_0003).to_string(),
+            "nca_s_unk_if (0x1c010003)"
+        );
+        assert_eq!(NcaStatus::from_u32(0x1234), NcaStatus::Unknown(0x1234));
+    }
+}
@@ -0,0 +1,523 @@
+use super::{length, Cursor, Decode, Encode, ProtocolError};
+use serde::{Serialize, Serializer};
+use std::net::Ipv4Addr;
+
+// DCP is carried directly in ethernet frames, see IEC 61158-6-10 "DCP"
+pub const ETHER_TYPE_PNIO: u16 = 0x8892;
+const ETHER_TYPE_VLAN: u16 = 0x8100;
+const VLAN_TAG_LEN: usize = 4;
+// frames shorter than this are padded, the frame check sequence not counted
+const ETHERNET_MIN_FRAME_LEN: usize = 60;
+// Identify requests are sent to this multicast address
+pub const DCP_MULTICAST_MAC: [u8; 6] = [0x01, 0x0e, 0xcf, 0x00, 0x00, 0x00];
+const FRAME_ID_IDENTIFY_REQ: u16 = 0xfefe;
+const FRAME_ID_IDENTIFY_RES: u16 = 0xfeff;
+const SERVICE_ID_IDENTIFY: u8 = 0x05;
+const SERVICE_TYPE_REQUEST: u8 = 0x00;
+const SERVICE_TYPE_RESPONSE_SUCCESS: u8 = 0x01;
+// the stations spread their responses over the response delay, in 10ms
+pub const DCP_RESPONSE_DELAY_FACTOR: u16 = 0x0080;
+const BLOCK_INFO_LEN: usize = 2;
+// BlockInfo of the IP parameter, "IP set"
+const BLOCK_INFO_IP_SET: u16 = 0x0001;
+const DEVICE_ROLE_IO_DEVICE: u8 = 0x01;
+
+/// DcpBlockOption is the option and suboption of a DCP block
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DcpBlockOption {
+    IpParameter,
+    TypeOfStation,
+    NameOfStation,
+    DeviceId,
+    DeviceRole,
+    All,
+}
+
+impl DcpBlockOption {
+    pub fn from_u8(option: u8, suboption: u8) -> Option<Self> {
+        match (option, suboption) {
+            (0x01, 0x02) => Some(Self::IpParameter),
+            (0x02, 0x01) => Some(Self::TypeOfStation),
+            (0x02, 0x02) => Some(Self::NameOfStation),
+            (0x02, 0x03) => Some(Self::DeviceId),
+            (0x02, 0x04) => Some(Self::DeviceRole),
+            (0xff, 0xff) => Some(Self::All),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> [u8; 2] {
+        match self {
+            Self::IpParameter => [0x01, 0x02],
+            Self::TypeOfStation => [0x02, 0x01],
+            Self::NameOfStation => [0x02, 0x02],
+            Self::DeviceId => [0x02, 0x03],
+            Self::DeviceRole => [0x02, 0x04],
+            Self::All => [0xff, 0xff],
+        }
+    }
+}
+
+// block of a request, i.e. without BlockInfo, padded to an even length
+fn request_block(option: DcpBlockOption, data: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+    let mut v: Vec<u8> = vec![];
+
+    v.extend(option.code());
+    v.extend(length::<u16>(data.len(), "DCP block")?.to_be_bytes());
+    v.extend(data);
+    if data.len() % 2 == 1 {
+        v.push(0x00);
+    }
+
+    Ok(v)
+}
+
+// block of a response, the length counts the BlockInfo
+fn response_block(
+    option: DcpBlockOption,
+    block_info: u16,
+    data: &[u8],
+) -> Result<Vec<u8>, ProtocolError> {
+    let mut block_data = block_info.to_be_bytes().to_vec();
+    block_data.extend(data);
+
+    request_block(option, &block_data)
+}
+
+fn ethernet_frame(dst_mac: [u8; 6], src_mac: [u8; 6], payload: &[u8]) -> Vec<u8> {
+    let mut v: Vec<u8> = vec![];
+
+    v.extend(dst_mac);
+    v.extend(src_mac);
+    v.extend(ETHER_TYPE_PNIO.to_be_bytes());
+    v.extend(payload);
+    if v.len() < ETHERNET_MIN_FRAME_LEN {
+        v.resize(ETHERNET_MIN_FRAME_LEN, 0x00);
+    }
+
+    v
+}
+
+// split the ethernet frame into destination, source and the PNIO payload,
+// a VLAN tag is skipped
+fn ethernet_payload<'a>(
+    cursor: &mut Cursor<'a>,
+) -> Result<([u8; 6], [u8; 6], Cursor<'a>), ProtocolError> {
+    let dst_mac = cursor.array("ethernet destination")?;
+    let src_mac = cursor.array("ethernet source")?;
+    let mut ether_type = cursor.u16_be("ether type")?;
+    if ether_type == ETHER_TYPE_VLAN {
+        cursor.skip(VLAN_TAG_LEN - 2, "VLAN tag")?;
+        ether_type = cursor.u16_be("ether type")?;
+    }
+    if ether_type != ETHER_TYPE_PNIO {
+        return Err(ProtocolError::Invalid {
+            field: "ether type",
+            value: ether_type.into(),
+        });
+    }
+
+    // the padding of the frame is left in the payload
+    Ok((dst_mac, src_mac, Cursor::new(cursor.rest())))
+}
+
+// the DCP header and the blocks of the payload, as xid, response delay and
+// the blocks
+fn dcp_blocks<'a>(
+    payload: &mut Cursor<'a>,
+    frame_id: u16,
+    service_type: u8,
+) -> Result<(u32, u16, Cursor<'a>), ProtocolError> {
+    let actual_frame_id = payload.u16_be("DCP frame id")?;
+    if actual_frame_id != frame_id {
+        return Err(ProtocolError::Invalid {
+            field: "DCP frame id",
+            value: actual_frame_id.into(),
+        });
+    }
+    let service_id = payload.u8("DCP service id")?;
+    if service_id != SERVICE_ID_IDENTIFY {
+        return Err(ProtocolError::Invalid {
+            field: "DCP service id",
+            value: service_id.into(),
+        });
+    }
+    let actual_service_type = payload.u8("DCP service type")?;
+    if actual_service_type != service_type {
+        return Err(ProtocolError::Invalid {
+            field: "DCP service type",
+            value: actual_service_type.into(),
+        });
+    }
+
+    let xid = payload.u32_be("DCP xid")?;
+    let response_delay = payload.u16_be("DCP response delay")?;
+    let data_len = payload.u16_be("DCP data length")? as usize;
+    let blocks = payload.sub(data_len, "DCP data")?;
+
+    Ok((xid, response_delay, blocks))
+}
+
+// option and data of a block, `None` for an option unknown here
+type DcpBlock<'a> = (Option<DcpBlockOption>, &'a [u8]);
+
+// iterate the blocks as (option, data)
+fn parse_blocks(mut blocks: Cursor<'_>) -> Result<Vec<DcpBlock<'_>>, ProtocolError> {
+    let mut parsed = vec![];
+
+    while blocks.remaining() >= 4 {
+        let [option, suboption] = blocks.array("DCP block option")?;
+        let len = blocks.u16_be("DCP block length")? as usize;
+        let data = blocks.take(len, "DCP block")?;
+        parsed.push((DcpBlockOption::from_u8(option, suboption), data));
+
+        // blocks are padded to an even length
+        blocks.align(2);
+    }
+
+    Ok(parsed)
+}
+
+pub fn format_mac(mac: &[u8; 6]) -> String {
+    mac.iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn serialize_mac<S: Serializer>(value: &[u8; 6], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_mac(value))
+}
+
+/// DcpIdentifyRequest is an Identify All or, with the name of station, an
+/// Identify by NameOfStation
+#[derive(Debug, PartialEq)]
+pub struct DcpIdentifyRequest {
+    pub src_mac: [u8; 6],
+    pub xid: u32,
+    pub response_delay: u16,
+    pub name_of_station: Option<String>,
+}
+
+impl DcpIdentifyRequest {
+    pub fn new(src_mac: [u8; 6], xid: u32, name_of_station: Option<&str>) -> Self {
+        Self {
+            src_mac,
+            xid,
+            response_delay: DCP_RESPONSE_DELAY_FACTOR,
+            name_of_station: name_of_station.map(|n| n.to_string()),
+        }
+    }
+}
+
+impl Encode for DcpIdentifyRequest {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        let blocks = match &self.name_of_station {
+            Some(name) => request_block(DcpBlockOption::NameOfStation, name.as_bytes())?,
+            None => request_block(DcpBlockOption::All, &[])?,
+        };
+
+        let mut v: Vec<u8> = vec![];
+        v.extend(FRAME_ID_IDENTIFY_REQ.to_be_bytes());
+        v.push(SERVICE_ID_IDENTIFY);
+        v.push(SERVICE_TYPE_REQUEST);
+        v.extend(self.xid.to_be_bytes());
+        v.extend(self.response_delay.to_be_bytes());
+        v.extend(length::<u16>(blocks.len(), "DCP data")?.to_be_bytes());
+        v.extend(blocks);
+
+        buf.extend(ethernet_frame(DCP_MULTICAST_MAC, self.src_mac, &v));
+        Ok(())
+    }
+}
+
+impl Decode for DcpIdentifyRequest {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let (_, src_mac, mut payload) = ethernet_payload(cursor)?;
+        let (xid, response_delay, blocks) =
+            dcp_blocks(&mut payload, FRAME_ID_IDENTIFY_REQ, SERVICE_TYPE_REQUEST)?;
+        let name_of_station = parse_blocks(blocks)?
+            .into_iter()
+            .find(|(o, _)| *o == Some(DcpBlockOption::NameOfStation))
+            .map(|(_, data)| String::from_utf8_lossy(data).to_string());
+
+        Ok(Self {
+            src_mac,
+            xid,
+            response_delay,
+            name_of_station,
+        })
+    }
+}
+
+/// DcpStation is what a station tells about itself in the Identify response
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DcpStation {
+    #[serde(serialize_with = "serialize_mac")]
+    pub mac_address: [u8; 6],
+    pub name_of_station: String,
+    /// type_of_station is the vendor's name for the station, e.g. `ET200SP`
+    pub type_of_station: String,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub ip_address: Ipv4Addr,
+    pub subnet_mask: Ipv4Addr,
+    pub gateway: Ipv4Addr,
+}
+
+/// DcpIdentifyResponse is the answer of a single station to an Identify request
+#[derive(Debug, PartialEq)]
+pub struct DcpIdentifyResponse {
+    pub dst_mac: [u8; 6],
+    pub xid: u32,
+    pub station: DcpStation,
+}
+
+impl DcpIdentifyResponse {
+    pub fn new(request: &DcpIdentifyRequest, station: DcpStation) -> Self {
+        Self {
+            dst_mac: request.src_mac,
+            xid: request.xid,
+            station,
+        }
+    }
+}
+
+impl Encode for DcpIdentifyResponse {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        let station = &self.station;
+        let mut ip_parameter: Vec<u8> = vec![];
+        ip_parameter.extend(station.ip_address.octets());
+        ip_parameter.extend(station.subnet_mask.octets());
+        ip_parameter.extend(station.gateway.octets());
+        let mut device_id = station.vendor_id.to_be_bytes().to_vec();
+        device_id.extend(station.device_id.to_be_bytes());
+
+        let mut blocks: Vec<u8> = vec![];
+        blocks.extend(response_block(
+            DcpBlockOption::IpParameter,
+            BLOCK_INFO_IP_SET,
+            &ip_parameter,
+        )?);
+        blocks.extend(response_block(
+            DcpBlockOption::TypeOfStation,
+            0,
+            station.type_of_station.as_bytes(),
+        )?);
+        blocks.extend(response_block(
+            DcpBlockOption::NameOfStation,
+            0,
+            station.name_of_station.as_bytes(),
+        )?);
+        blocks.extend(response_block(DcpBlockOption::DeviceId, 0, &device_id)?);
+        blocks.extend(response_block(
+            DcpBlockOption::DeviceRole,
+            0,
+            &[DEVICE_ROLE_IO_DEVICE, 0x00],
+        )?);
+
+        let mut v: Vec<u8> = vec![];
+        v.extend(FRAME_ID_IDENTIFY_RES.to_be_bytes());
+        v.push(SERVICE_ID_IDENTIFY);
+        v.push(SERVICE_TYPE_RESPONSE_SUCCESS);
+        v.extend(self.xid.to_be_bytes());
+        v.extend([0x00; 2]);
+        v.extend(length::<u16>(blocks.len(), "DCP data")?.to_be_bytes());
+        v.extend(blocks);
+
+        buf.extend(ethernet_frame(self.dst_mac, station.mac_address, &v));
+        Ok(())
+    }
+}
+
+impl Decode for DcpIdentifyResponse {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let (dst_mac, src_mac, mut payload) = ethernet_payload(cursor)?;
+        let (xid, _, blocks) = dcp_blocks(
+            &mut payload,
+            FRAME_ID_IDENTIFY_RES,
+            SERVICE_TYPE_RESPONSE_SUCCESS,
+        )?;
+
+        let mut station = DcpStation {
+            mac_address: src_mac,
+            name_of_station: String::new(),
+            type_of_station: String::new(),
+            vendor_id: 0,
+            device_id: 0,
+            ip_address: Ipv4Addr::UNSPECIFIED,
+            subnet_mask: Ipv4Addr::UNSPECIFIED,
+            gateway: Ipv4Addr::UNSPECIFIED,
+        };
+        for (option, data) in parse_blocks(blocks)? {
+            // the data of a response block starts with the BlockInfo
+            let mut data = Cursor::new(data);
+            data.skip(BLOCK_INFO_LEN, "DCP BlockInfo")?;
+            match option {
+                Some(DcpBlockOption::IpParameter) => {
+                    station.ip_address = Ipv4Addr::from(data.array::<4>("DCP IP address")?);
+                    station.subnet_mask = Ipv4Addr::from(data.array::<4>("DCP subnet mask")?);
+                    station.gateway = Ipv4Addr::from(data.array::<4>("DCP gateway")?);
+                }
+                Some(DcpBlockOption::TypeOfStation) => {
+                    station.type_of_station = String::from_utf8_lossy(data.rest()).to_string();
+                }
+                Some(DcpBlockOption::NameOfStation) => {
+                    station.name_of_station = String::from_utf8_lossy(data.rest()).to_string();
+                }
+                Some(DcpBlockOption::DeviceId) => {
+                    station.vendor_id = data.u16_be("DCP vendor id")?;
+                    station.device_id = data.u16_be("DCP device id")?;
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            dst_mac,
+            xid,
+            station,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::codec::test::{assert_decode_never_panics, assert_roundtrip};
+    use proptest::prelude::*;
+
+    const ETHERNET_HEADER_LEN: usize = 14;
+    const SRC_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+
+    fn get_station() -> DcpStation {
+        DcpStation {
+            mac_address: [0x28, 0x63, 0x36, 0x8a, 0x1f, 0x0c],
+            name_of_station: "et200sp-1".to_string(),
+            type_of_station: "ET200SP".to_string(),
+            vendor_id: 0x002a,
+            device_id: 0x0313,
+            ip_address: Ipv4Addr::new(192, 168, 0, 10),
+            subnet_mask: Ipv4Addr::new(255, 255, 255, 0),
+            gateway: Ipv4Addr::new(192, 168, 0, 1),
+        }
+    }
+
+    #[test]
+    fn encode_identify_request_should_ok() {
+        let request = DcpIdentifyRequest::new(SRC_MAC, 0x01020304, Some("et200sp-1"));
+        let frame = request.to_bytes().unwrap();
+
+        assert_eq!(
+            hex::encode(&frame[..ETHERNET_HEADER_LEN]),
+            "010ecf0000000200000000018892"
+        );
+        // header, then NameOfStation padded to an even length
+        assert_eq!(
+            hex::encode(&frame[ETHERNET_HEADER_LEN..ETHERNET_HEADER_LEN + 26]),
+            format!(
+                "fefe0500010203040080000e02020009{}00",
+                hex::encode("et200sp-1")
+            )
+        );
+        assert_eq!(frame.len(), ETHERNET_MIN_FRAME_LEN);
+        assert_eq!(DcpIdentifyRequest::from_bytes(&frame).unwrap(), request);
+
+        let frame = DcpIdentifyRequest::new(SRC_MAC, 1, None)
+            .to_bytes()
+            .unwrap();
+        assert_eq!(
+            hex::encode(&frame[ETHERNET_HEADER_LEN + 10..ETHERNET_HEADER_LEN + 16]),
+            "0004ffff0000"
+        );
+    }
+
+    #[test]
+    fn decode_identify_response_should_return_correctly() {
+        let request = DcpIdentifyRequest::new(SRC_MAC, 7, None);
+        let response = DcpIdentifyResponse::new(&request, get_station());
+        let frame = response.to_bytes().unwrap();
+
+        let parsed = DcpIdentifyResponse::from_bytes(&frame).unwrap();
+        assert_eq!(parsed, response);
+        assert_eq!(parsed.dst_mac, SRC_MAC);
+        assert_eq!(
+            serde_json::to_value(&parsed.station).unwrap()["mac_address"],
+            "28:63:36:8a:1f:0c"
+        );
+
+        // the same frame with a VLAN tag
+        let mut tagged = frame[..12].to_vec();
+        tagged.extend([0x81, 0x00, 0x00, 0x00]);
+        tagged.extend(&frame[12..]);
+        assert_eq!(
+            DcpIdentifyResponse::from_bytes(&tagged[..]).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn decode_identify_response_should_return_error() {
+        let request = DcpIdentifyRequest::new(SRC_MAC, 7, None);
+        let frame = request.to_bytes().unwrap();
+        // a request is not a response
+        assert!(DcpIdentifyResponse::from_bytes(&frame).is_err());
+
+        let frame = DcpIdentifyResponse::new(&request, get_station())
+            .to_bytes()
+            .unwrap();
+        // truncated
+        assert!(DcpIdentifyResponse::from_bytes(&frame[..30]).is_err());
+        // not PNIO
+        let mut other = frame.clone();
+        other[12..14].copy_from_slice(&[0x08, 0x00]);
+        assert!(DcpIdentifyResponse::from_bytes(&other[..]).is_err());
+    }
+
+    fn dcp_station() -> impl Strategy<Value = DcpStation> {
+        (
+            any::<[u8; 6]>(),
+            "[a-z0-9.-]{0,32}",
+            "[A-Za-z0-9 ]{0,32}",
+            any::<(u16, u16)>(),
+            any::<([u8; 4], [u8; 4], [u8; 4])>(),
+        )
+            .prop_map(|(mac_address, name_of_station, type_of_station, ids, ip)| {
+                DcpStation {
+                    mac_address,
+                    name_of_station,
+                    type_of_station,
+                    vendor_id: ids.0,
+                    device_id: ids.1,
+                    ip_address: Ipv4Addr::from(ip.0),
+                    subnet_mask: Ipv4Addr::from(ip.1),
+                    gateway: Ipv4Addr::from(ip.2),
+                }
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn identify_should_roundtrip(
+            src_mac in any::<[u8; 6]>(),
+            xid in any::<u32>(),
+            name in proptest::option::of("[a-z0-9.-]{1,64}"),
+            station in dcp_station(),
+        ) {
+            let request = DcpIdentifyRequest::new(src_mac, xid, name.as_deref());
+            assert_roundtrip(&request);
+            assert_roundtrip(&DcpIdentifyResponse::new(&request, station));
+        }
+
+        #[test]
+        fn decode_identify_should_never_panic(
+            station in dcp_station(),
+            flip in any::<usize>(),
+        ) {
+            let request = DcpIdentifyRequest::new(SRC_MAC, 7, None);
+            let frame = DcpIdentifyResponse::new(&request, station).to_bytes().unwrap();
+            assert_decode_never_panics::<DcpIdentifyResponse>(&frame, flip);
+            assert_decode_never_panics::<DcpIdentifyRequest>(&frame, flip);
+        }
+    }
+}
@@ -1,32 +1,70 @@
-use crate::protocol::util;
+use crate::protocol::{length, util, HartFrame, ProtocolError};
+
+/// HartAddress is the address of the field device in the HART frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HartAddress {
+    /// polling address of a short frame, only command 0 is sent with it
+    Polling(u8),
+    /// unique identifier of a long frame, obtained through command 0, 11 or 21
+    Unique([u8; 5]),
+}
+
+impl HartAddress {
+    /// BROADCAST is the unique identifier every device listens to, e.g. for
+    /// command 11 and 21 only the device with the tag answers
+    pub const BROADCAST: Self = Self::Unique([0x00; 5]);
+    pub const MAX_POLLING_ADDRESS: u8 = 63;
+}
 
 pub struct HartCommand {}
 impl HartCommand {
     const TRANSPARENT_MESSAGE_FORMAT: u8 = 0x00;
 
     pub fn construct_write_request(
-        device_id: [u8; 5],
-        command: u8,
+        address: HartAddress,
+        command: u16,
         write_payload: Option<&[u8]>,
     ) -> anyhow::Result<Box<[u8]>> {
-        let mut data: Vec<u8>;
+        // a command above 255 is carried by command 31, its number leads the
+        // payload
+        let (command, expansion) = HartFrame::expand(command);
+        let expanded_payload: Vec<u8>;
+        let write_payload = match (expansion, write_payload) {
+            (Some(expansion), payload) => {
+                expanded_payload = [&expansion[..], payload.unwrap_or_default()].concat();
+                Some(&expanded_payload[..])
+            }
+            (None, payload) => payload,
+        };
 
-        // command 0 with short device address is the special one
-        // its purpose is to get device_id from the response
-        // for other HART commands
-        if command == 0 && device_id == [0x00; 5] {
-            data = vec![
-                Self::TRANSPARENT_MESSAGE_FORMAT, // transparent message format
-                0x14,                             // number of preamble bytes
-                0x02,                             // short frame with command 0
-                0x00,                             // with command 0, this is 0x00
-                0x00,                             // command 0
-                0x00,                             // length in bytes
-            ];
-        } else {
+        let mut data: Vec<u8> = match address {
+            // command 0 with short device address is the special one
+            // its purpose is to get device_id from the response
+            // for other HART commands
+            HartAddress::Polling(polling_address) => {
+                if polling_address > HartAddress::MAX_POLLING_ADDRESS {
+                    return Err(ProtocolError::OutOfRange {
+                        field: "polling address",
+                        value: polling_address.into(),
+                    }
+                    .into());
+                }
+                vec![
+                    Self::TRANSPARENT_MESSAGE_FORMAT, // transparent message format
+                    0x14,                             // number of preamble bytes
+                    0x02,                             // short frame
+                    polling_address,                  // polling address 0-63
+                    command,                          // command 0
+                    // length in bytes
+                    match write_payload {
+                        Some(w) => length(w.len(), "HART payload")?,
+                        None => 0x00,
+                    },
+                ]
+            }
             // the rest of HART commands can use long device address (device_id)
             // obtain through command 0
-            data = vec![
+            HartAddress::Unique(device_id) => vec![
                 Self::TRANSPARENT_MESSAGE_FORMAT, // transparent message format
                 0x05,                             // number of preamble bytes
                 0x82,                             // long frame with command other than command 0
@@ -36,18 +74,21 @@ impl HartCommand {
                 device_id[3],
                 device_id[4],
                 command, // HART command, for example 48
-            ];
+                // TODO: uncertain
+                // length in bytes
+                // number of bytes to follow in the status and data bytes
+                // I think set it to 0x01 should be fine without payload,
+                // a write carries more than that
+                match write_payload {
+                    Some(w) => length(w.len(), "HART payload")?,
+                    None => 0x01,
+                },
+            ],
+        };
 
-            // TODO: uncertain
-            // length in bytes
-            // number of bytes to follow in the status and data bytes
-            // I think set it to 0x01 should be fine
-            data.push(0x01);
-
-            // payload (if any)
-            if let Some(w) = write_payload {
-                data.extend(w.as_ref());
-            }
+        // payload (if any)
+        if let Some(w) = write_payload {
+            data.extend(w);
         }
 
         // checksum
@@ -0,0 +1,265 @@
+use super::{util, Cursor, Decode, Encode, ProtocolError};
+
+/// HartFrame is a HART frame from its delimiter to its checksum, as carried by
+/// the request record (after the message format and the number of preambles)
+/// and the response record (after the response control and the message
+/// format) of the AI module, see `HartCommand::construct_write_request`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HartFrame {
+    pub delimiter: u8,
+    /// address is the polling address of a short frame or the 5 bytes unique
+    /// identifier of a long frame
+    pub address: Box<[u8]>,
+    pub command: u8,
+    pub byte_count: u8,
+    /// data is everything between the byte count and the checksum, i.e. the
+    /// statuses and the command specific data of a response
+    pub data: Box<[u8]>,
+    pub checksum: u8,
+}
+
+impl HartFrame {
+    /// RECORD_OFFSET is where the frame starts in the request and the response
+    /// records
+    pub const RECORD_OFFSET: usize = 2;
+    /// EXPANSION_COMMAND carries a command above 255 (HART 7), the 16 bits
+    /// number of the command leads the data, after the statuses of a response
+    pub const EXPANSION_COMMAND: u8 = 31;
+
+    /// expand splits the number of a command into the command of the frame
+    /// and the expansion bytes leading the data, if any
+    pub fn expand(command: u16) -> (u8, Option<[u8; 2]>) {
+        match u8::try_from(command) {
+            Ok(command) => (command, None),
+            Err(_) => (Self::EXPANSION_COMMAND, Some(command.to_be_bytes())),
+        }
+    }
+
+    /// split_expansion takes the number of the command out of the statuses
+    /// and the data of a command 31 response, the statuses are followed by
+    /// the data of the command
+    pub fn split_expansion(status_and_data: &[u8]) -> Result<(u16, Box<[u8]>), ProtocolError> {
+        let mut cursor = Cursor::new(status_and_data);
+        let statuses = cursor.take(2, "HART statuses")?;
+        let command = cursor.u16_be("HART expanded command")?;
+
+        Ok((command, [statuses, cursor.rest()].concat().into()))
+    }
+
+    /// command_number is the command of the frame, the one of the expansion
+    /// bytes for command 31
+    pub fn command_number(&self) -> Result<u16, ProtocolError> {
+        if self.command != Self::EXPANSION_COMMAND {
+            return Ok(self.command.into());
+        }
+
+        let mut cursor = Cursor::new(&self.data);
+        if self.is_response() {
+            cursor.skip(2, "HART statuses")?;
+        }
+        cursor.u16_be("HART expanded command")
+    }
+
+    pub fn is_long_frame(&self) -> bool {
+        self.delimiter & 0x80 == 0x80
+    }
+
+    /// is_response tells if the frame is sent by the field device (ACK), it's
+    /// sent by the master (STX) otherwise
+    pub fn is_response(&self) -> bool {
+        self.delimiter & 0x07 == 0x06
+    }
+
+    pub fn is_checksum_valid(&self) -> bool {
+        let mut frame = vec![self.delimiter];
+        frame.extend(self.address.iter());
+        frame.extend([self.command, self.byte_count]);
+        frame.extend(self.data.iter());
+        util::generate_xor_checksum(&frame).is_ok_and(|c| c == self.checksum)
+    }
+
+    /// device_type_code is the manufacturer id and the device type of the
+    /// unique identifier, `None` for a short frame
+    pub fn device_type_code(&self) -> Option<[u8; 2]> {
+        match self.is_long_frame() {
+            // primary master and burst mode bits are not part of the id
+            true => Some([self.address[0] & 0x3f, self.address[1]]),
+            false => None,
+        }
+    }
+}
+
+impl Encode for HartFrame {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        let address_len = match self.is_long_frame() {
+            true => 5,
+            false => 1,
+        };
+        if self.address.len() != address_len {
+            return Err(ProtocolError::InvalidLength {
+                field: "HART address",
+                len: self.address.len(),
+            });
+        }
+        if self.data.len() != self.byte_count as usize {
+            return Err(ProtocolError::InvalidLength {
+                field: "HART byte count",
+                len: self.data.len(),
+            });
+        }
+
+        buf.push(self.delimiter);
+        buf.extend(self.address.iter());
+        buf.extend([self.command, self.byte_count]);
+        buf.extend(self.data.iter());
+        buf.push(self.checksum);
+        Ok(())
+    }
+}
+
+impl Decode for HartFrame {
+    /// decode parses the frame starting with the delimiter, the byte count
+    /// tells where it ends, anything after the checksum is left in the cursor
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let delimiter = cursor.u8("HART delimiter")?;
+        // frame types STX, ACK and BACK
+        if !matches!(delimiter & 0x07, 0x01 | 0x02 | 0x06) {
+            return Err(ProtocolError::Invalid {
+                field: "HART delimiter",
+                value: delimiter.into(),
+            });
+        }
+        let address_len = match delimiter & 0x80 == 0x80 {
+            true => 5,
+            false => 1,
+        };
+
+        let address = cursor.take(address_len, "HART address")?;
+        let command = cursor.u8("HART command")?;
+        let byte_count = cursor.u8("HART byte count")?;
+        let data = cursor.take(byte_count as usize, "HART data")?;
+        let checksum = cursor.u8("HART checksum")?;
+
+        Ok(HartFrame {
+            delimiter,
+            address: address.into(),
+            command,
+            byte_count,
+            data: data.into(),
+            checksum,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::codec::test::{assert_decode_never_panics, assert_roundtrip};
+    use crate::protocol::{HartAddress, HartCommand};
+    use proptest::{collection, prelude::*};
+
+    #[test]
+    fn decode_should_parse_request_and_response() {
+        let request = HartCommand::construct_write_request(
+            HartAddress::Unique([0x2a, 0x0b, 0x0c, 0x78, 0x05]),
+            9,
+            Some(&[0x00]),
+        )
+        .unwrap();
+        let frame = HartFrame::from_bytes(&request[HartFrame::RECORD_OFFSET..]).unwrap();
+        assert!(frame.is_long_frame() && !frame.is_response());
+        assert_eq!(frame.command, 9);
+        assert_eq!(&frame.data[..], &[0x00]);
+        assert!(frame.is_checksum_valid());
+
+        // short frame of command 0, response code and device status, then
+        // the rest of the record
+        let response = [0x06, 0x00, 0x00, 0x02, 0x00, 0x00, 0x04, 0x00, 0x00];
+        let frame = HartFrame::from_bytes(&response).unwrap();
+        assert!(frame.is_response());
+        assert_eq!(frame.device_type_code(), None);
+        assert_eq!(&frame.data[..], &[0x00, 0x00]);
+        assert!(frame.is_checksum_valid());
+
+        // command 0 to polling address 5
+        let request =
+            HartCommand::construct_write_request(HartAddress::Polling(5), 0, None).unwrap();
+        let frame = HartFrame::from_bytes(&request[HartFrame::RECORD_OFFSET..]).unwrap();
+        assert!(!frame.is_long_frame());
+        assert_eq!((&frame.address[..], frame.byte_count), (&[0x05][..], 0));
+        assert!(frame.is_checksum_valid());
+        assert!(HartCommand::construct_write_request(HartAddress::Polling(64), 0, None).is_err());
+
+        assert!(HartFrame::from_bytes(&[0x82, 0x2a]).is_err());
+        assert!(HartFrame::from_bytes(&[0x06, 0x00, 0x00, 0x02, 0x00]).is_err());
+    }
+
+    #[test]
+    fn expanded_command_should_be_carried_by_command_31() {
+        assert_eq!(HartFrame::expand(48), (48, None));
+        assert_eq!(HartFrame::expand(1024), (31, Some([0x04, 0x00])));
+
+        let request = HartCommand::construct_write_request(
+            HartAddress::Unique([0x2a, 0x0b, 0x0c, 0x78, 0x05]),
+            523,
+            Some(&[0x00, 0x10]),
+        )
+        .unwrap();
+        let frame = HartFrame::from_bytes(&request[HartFrame::RECORD_OFFSET..]).unwrap();
+        assert_eq!((frame.command, frame.byte_count), (31, 4));
+        assert_eq!(&frame.data[..], &[0x02, 0x0b, 0x00, 0x10]);
+        assert_eq!(frame.command_number(), Ok(523));
+        assert!(frame.is_checksum_valid());
+
+        // statuses, expanded command 1024 and its data
+        let response = [
+            0x86, 0x2a, 0x0b, 0x0c, 0x78, 0x05, 0x1f, 0x05, 0x00, 0x00, 0x04, 0x00, 0x01, 0xc9,
+        ];
+        let frame = HartFrame::from_bytes(&response).unwrap();
+        assert!(frame.is_checksum_valid());
+        assert_eq!(frame.command_number(), Ok(1024));
+        assert_eq!(
+            HartFrame::split_expansion(&frame.data),
+            Ok((1024, [0x00, 0x00, 0x01].into()))
+        );
+        // "command not implemented" of a device before HART 7
+        assert!(HartFrame::split_expansion(&[0x40, 0x00]).is_err());
+    }
+
+    fn hart_frame() -> impl Strategy<Value = HartFrame> {
+        (
+            prop_oneof![Just(0x02u8), Just(0x06u8), Just(0x82u8), Just(0x86u8)],
+            any::<[u8; 5]>(),
+            any::<u8>(),
+            collection::vec(any::<u8>(), 0..=255),
+        )
+            .prop_map(|(delimiter, address, command, data)| {
+                let address_len = if delimiter & 0x80 == 0x80 { 5 } else { 1 };
+                let mut frame = vec![delimiter];
+                frame.extend(&address[..address_len]);
+                frame.extend([command, data.len() as u8]);
+                frame.extend(&data);
+                HartFrame {
+                    delimiter,
+                    address: address[..address_len].into(),
+                    command,
+                    byte_count: data.len() as u8,
+                    data: data.into(),
+                    checksum: util::generate_xor_checksum(&frame).unwrap(),
+                }
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn hart_frame_should_roundtrip(frame in hart_frame()) {
+            assert_roundtrip(&frame);
+            prop_assert!(frame.is_checksum_valid());
+        }
+
+        #[test]
+        fn decode_hart_frame_should_never_panic(frame in hart_frame(), flip in any::<usize>()) {
+            assert_decode_never_panics::<HartFrame>(&frame.to_bytes().unwrap(), flip);
+        }
+    }
+}
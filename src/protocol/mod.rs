@@ -1,26 +1,38 @@
+mod codec;
 mod constant;
 mod dcerpc;
 mod dcerpc_epm_req;
 mod dcerpc_epm_res;
+mod dcerpc_status;
+mod dcp;
 mod hart_command;
-mod packet;
+mod hart_frame;
 mod pnio;
 mod pnio_header;
 mod pnio_header_arblock_req;
 mod pnio_header_arblock_res;
 mod pnio_header_iod_req;
 mod pnio_header_iod_res;
+mod pnio_header_release_block;
+mod pnio_im;
+mod pnio_status;
 pub mod util;
 
+pub use self::codec::*;
 pub use self::constant::*;
 pub use self::dcerpc::*;
 pub use self::dcerpc_epm_req::*;
 pub use self::dcerpc_epm_res::*;
+pub use self::dcerpc_status::*;
+pub use self::dcp::*;
 pub use self::hart_command::*;
-pub use self::packet::*;
+pub use self::hart_frame::*;
 pub use self::pnio::*;
 pub use self::pnio_header::*;
 pub use self::pnio_header_arblock_req::*;
 pub use self::pnio_header_arblock_res::*;
 pub use self::pnio_header_iod_req::*;
 pub use self::pnio_header_iod_res::*;
+pub use self::pnio_header_release_block::*;
+pub use self::pnio_im::*;
+pub use self::pnio_status::*;
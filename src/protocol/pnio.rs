@@ -1,11 +1,9 @@
 use super::{
-    BlockHeaderType, Packet, PnioHeader, ArBlockRes,
-    PnioHeaderEnum, IodRes,
+    BlockHeaderType, Cursor, Decode, Encode, PnioError, PnioHeader, PnioHeaderEnum, ProtocolError,
+    READ_MAX_COUNT,
 };
-use anyhow::anyhow;
-use std::mem;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Pnio {
     pub args_max: Option<u32>,   // only available in request
     pub status: Option<[u8; 4]>, // only available in response
@@ -25,12 +23,9 @@ impl Pnio {
         pnio_header: PnioHeaderEnum,
         pnio_data: Option<Box<[u8]>>,
     ) -> Self {
-        let max_count_u32 = pnio_header.get_max_count();
-        let max_count = TryInto::<[u8; 4]>::try_into(max_count_u32.to_le_bytes()).unwrap();
-        let args_length_u32 = pnio_header.get_args_length();
-        let args_length = TryInto::<[u8; 4]>::try_into(args_length_u32.to_le_bytes()).unwrap();
-        let actual_count_u32 = pnio_header.get_actual_count();
-        let actual_count = TryInto::<[u8; 4]>::try_into(actual_count_u32.to_le_bytes()).unwrap();
+        let max_count = pnio_header.get_max_count().to_le_bytes();
+        let args_length = pnio_header.get_args_length().to_le_bytes();
+        let actual_count = pnio_header.get_actual_count().to_le_bytes();
         let args_max = pnio_header.get_args_max();
 
         Pnio {
@@ -44,134 +39,207 @@ impl Pnio {
             pnio_data,
         }
     }
-}
-
-impl Packet for Pnio {
-    fn concat(&self) -> anyhow::Result<Vec<u8>> {
-        let mut v: Vec<u8> = vec![];
-
-        v.extend(&self.args_max.unwrap().to_le_bytes());
-        v.extend(&self.args_length);
-        v.extend(&self.max_count);
-        v.extend(&self.offset);
-        v.extend(&self.actual_count);
-        v.extend(&self.pnio_header.concat().unwrap());
-
-        let data = match &self.pnio_data {
-            Some(d) => &d[..],
-            None => &[],
-        };
-        v.extend(data);
 
-        Ok(v)
+    /// error decodes the status of a response, `None` if the request succeeded
+    pub fn error(&self) -> Option<PnioError> {
+        self.status.and_then(PnioError::from_status)
     }
 
-    fn size(&self) -> usize {
-        mem::size_of::<Self>()
+    /// mismatch tells what of the response does not echo the request, `None`
+    /// if the response belongs to the request
+    pub fn mismatch(&self, request: &Pnio) -> Option<&'static str> {
+        match (&request.pnio_header, &self.pnio_header) {
+            (PnioHeaderEnum::ArBlockReq(req), PnioHeaderEnum::ArBlockRes(res)) => {
+                if req.ar_uuid != res.ar_uuid {
+                    Some("AR UUID")
+                } else if req.session_key != res.session_key {
+                    Some("session key")
+                } else {
+                    None
+                }
+            }
+            (PnioHeaderEnum::ReleaseBlock(req), PnioHeaderEnum::ReleaseBlock(res)) => {
+                if u16::from_be_bytes(res.block_header_type)
+                    != u16::from_be_bytes(req.block_header_type) | 0x8000
+                {
+                    Some("block type")
+                } else if req.ar_uuid != res.ar_uuid {
+                    Some("AR UUID")
+                } else if req.session_key != res.session_key {
+                    Some("session key")
+                } else {
+                    None
+                }
+            }
+            // the response block type is the one of the request with the
+            // response bit set
+            (PnioHeaderEnum::IodReq(req), PnioHeaderEnum::IodRes(res)) => {
+                if u16::from_be_bytes(res.block_header_type)
+                    != u16::from_be_bytes(req.block_header_type) | 0x8000
+                {
+                    Some("block type")
+                } else if req.seq_num != res.seq_num {
+                    Some("sequence number")
+                } else if req.ar_uuid != res.ar_uuid {
+                    Some("AR UUID")
+                } else if req.slot_num != res.slot_num || req.subslot_num != res.subslot_num {
+                    Some("slot/subslot")
+                } else if req.index != res.index {
+                    Some("index")
+                } else {
+                    None
+                }
+            }
+            _ => Some("block type"),
+        }
     }
 }
 
-impl TryFrom<Vec<u8>> for Pnio {
-    type Error = anyhow::Error;
-
-    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-        // actual_count includes size for pnio_header and data
-        let actual_count = match value.get(16..20) {
-            Some(a) => u32::from_le_bytes(*TryInto::<&[u8; 4]>::try_into(a).unwrap()),
-            None => return Err(anyhow!("failed to match pnio actual count")),
-        };
-
-        // header type
-        let block_header_type = match value.get(20..22) {
-            Some(b) => BlockHeaderType::from_u16(u16::from_be_bytes(<[u8; 2]>::try_from(b)?)),
-            None => return Err(anyhow!("failed to match pnio block header type")),
+impl Encode for Pnio {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        // request starts with args_max, while response starts with status
+        match (self.status, self.args_max) {
+            (Some(status), _) => buf.extend(status),
+            (None, Some(args_max)) => buf.extend(args_max.to_le_bytes()),
+            (None, None) => {
+                return Err(ProtocolError::Missing {
+                    field: "PNIO status or args_max",
+                })
+            }
         };
-
-        // invalid header type
-        if block_header_type.is_none() {
-            return Err(anyhow!("pnio block header type is invalid"));
+        buf.extend(self.args_length);
+        buf.extend(self.max_count);
+        buf.extend(self.offset);
+        buf.extend(self.actual_count);
+        self.pnio_header.encode(buf)?;
+        if let Some(data) = &self.pnio_data {
+            buf.extend(&data[..]);
         }
 
+        Ok(())
+    }
+}
+
+impl Decode for Pnio {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
         // response type has this status type
         // request type has args_max
-        let mut status: Option<[u8; 4]> = None;
-        if block_header_type.unwrap() == BlockHeaderType::IodReadResType
-            || block_header_type.unwrap() == BlockHeaderType::IodWriteResType
-        {
-            status = match value.get(0..4) {
-                Some(s) => Some(TryInto::<[u8; 4]>::try_into(s)?),
-                None => return Err(anyhow!("failed to match pnio status")),
-            };
-        };
-
-        // block length + 4 = pnio_header size
-        let block_length = match value.get(22..24) {
-            Some(b) => u16::from_be_bytes(TryInto::<[u8; 2]>::try_into(b)?),
-            None => return Err(anyhow!("failed to match pnio block length")),
-        };
+        let status_or_args_max = cursor.array("PNIO status or args_max")?;
+        let args_length = cursor.array("PNIO args_length")?;
+        let max_count = cursor.array("PNIO max_count")?;
+        let offset = cursor.array("PNIO offset")?;
+        // actual_count includes size for pnio_header and data
+        let actual_count = cursor.array("PNIO actual_count")?;
 
-        // pnio header, could be IODHeader or ARBlock
-        // TODO: what is this 4 bytes?
-        let pnio_header_end = 20 + block_length as usize + 4;
-        let pnio_header_bytes = match value.get(20..pnio_header_end) {
-            Some(bytes) => bytes,
-            None => return Err(anyhow!("failed to match pnio header")),
-        };
+        // pnio header, could be IODHeader or ARBlock, the block length does
+        // not count the block type and the block length
+        let mut ahead = *cursor;
+        ahead.skip(2, "PNIO block_header_type")?;
+        let pnio_header_len = ahead.u16_be("PNIO block_header_len")? as usize + 4;
+        let pnio_header = PnioHeaderEnum::decode(&mut cursor.sub(pnio_header_len, "PNIO header")?)?;
 
-        // only deserialize pnio_header response type
-        let pnio_header: PnioHeaderEnum = match block_header_type.unwrap() {
-            BlockHeaderType::ArBlockResType => {
-                let pnio_header_arblock_resp =
-                    TryInto::<ArBlockRes>::try_into(pnio_header_bytes)?;
-                PnioHeaderEnum::ArBlockRes(pnio_header_arblock_resp)
-            }
-            BlockHeaderType::IodReadResType | BlockHeaderType::IodWriteResType => {
-                let pnio_header_iod_resp =
-                    TryInto::<IodRes>::try_into(pnio_header_bytes.to_vec())?;
-                PnioHeaderEnum::IodRes(pnio_header_iod_resp)
-            }
-            _ => panic!(
-                "deserialization of pnio_header not working for header type other than response"
-            ),
+        let (status, args_max) = match pnio_header.block_header_type() {
+            Some(b) if b.is_response() => (Some(status_or_args_max), None),
+            _ => (None, Some(u32::from_le_bytes(status_or_args_max))),
         };
 
         // pnio data, only available in read response type and write request type
         let mut pnio_data: Option<Box<[u8]>> = None;
-        if block_header_type.unwrap() == BlockHeaderType::IodReadResType
-            || block_header_type.unwrap() == BlockHeaderType::IodWriteReqType
-        {
-            // TODO: what is this 4 bytes?
+        if matches!(
+            pnio_header.block_header_type(),
+            Some(BlockHeaderType::IodReadResType | BlockHeaderType::IodWriteReqType)
+        ) {
             // this is supposed to be the size of the pnio_data
-            let pnio_data_length = actual_count as usize - (block_length as usize + 4);
-            // let pnio_data_end = pnio_header_end + pnio_data_length;
-            // this is the real size of the pnio_data
-            // let real_data_length = value.len() - pnio_header_end;
-            // if the real size is fewer than target size
-            // then append 0x00 to the end
-            pnio_data = match value.get(pnio_header_end..) {
-                Some(p) => {
-                    let mut new_p = p.to_vec();
-                    // TODO: why size of the data coming back is not pnio_data_length?
-                    new_p.resize(pnio_data_length, 0x00);
-                    Some(new_p.into_boxed_slice())
-                }
-                None => return Err(anyhow!("failed to match pnio_data")),
-            };
+            let actual_count = u32::from_le_bytes(actual_count) as usize;
+            let pnio_data_len = actual_count
+                .checked_sub(pnio_header_len)
+                .filter(|len| *len <= READ_MAX_COUNT as usize)
+                .ok_or(ProtocolError::InvalidLength {
+                    field: "PNIO actual_count",
+                    len: actual_count,
+                })?;
+            let mut data = cursor
+                .take(pnio_data_len.min(cursor.remaining()), "PNIO data")?
+                .to_vec();
+            // TODO: why size of the data coming back is not pnio_data_length?
+            // if the real size is fewer than target size, then append 0x00 to the end
+            data.resize(pnio_data_len, 0x00);
+            pnio_data = Some(data.into_boxed_slice());
         }
 
-        let pnio = Pnio::new(status, pnio_header, pnio_data);
-
-        Ok(pnio)
+        Ok(Pnio {
+            args_max,
+            status,
+            args_length,
+            max_count,
+            offset,
+            actual_count,
+            pnio_header,
+            pnio_data,
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::protocol::{
+        codec::test::{assert_decode_never_panics, assert_roundtrip},
+        ArBlockReq, ArBlockRes, IodReq, IodRes, ReleaseBlock,
+    };
+    use proptest::{collection, prelude::*};
     use uuid::Uuid;
 
     #[test]
-    fn try_from_pnio_connect_response() {
+    fn mismatch_should_tell_what_does_not_echo_the_request() {
+        let ar_uuid = Uuid::new_v4();
+        let request = |index: u16| {
+            let iod_req = IodReq::new(BlockHeaderType::IodReadReqType, 7, ar_uuid, 1, 1, index, 0);
+            Pnio::new(None, PnioHeaderEnum::IodReq(iod_req), None)
+        };
+        let response = |block_header_type, seq_num, ar_uuid, subslot_num| {
+            let iod_res = IodRes::new(
+                block_header_type,
+                seq_num,
+                ar_uuid,
+                1,
+                subslot_num,
+                81,
+                [0x00; 4],
+                0,
+            );
+            Pnio::new(Some([0x00; 4]), PnioHeaderEnum::IodRes(iod_res), None)
+        };
+        let read_res = BlockHeaderType::IodReadResType;
+
+        assert_eq!(
+            response(read_res, 7, ar_uuid, 1).mismatch(&request(81)),
+            None
+        );
+        assert_eq!(
+            response(BlockHeaderType::IodWriteResType, 7, ar_uuid, 1).mismatch(&request(81)),
+            Some("block type")
+        );
+        assert_eq!(
+            response(read_res, 6, ar_uuid, 1).mismatch(&request(81)),
+            Some("sequence number")
+        );
+        assert_eq!(
+            response(read_res, 7, Uuid::nil(), 1).mismatch(&request(81)),
+            Some("AR UUID")
+        );
+        assert_eq!(
+            response(read_res, 7, ar_uuid, 2).mismatch(&request(81)),
+            Some("slot/subslot")
+        );
+        assert_eq!(
+            response(read_res, 7, ar_uuid, 1).mismatch(&request(80)),
+            Some("index")
+        );
+    }
+
+    #[test]
+    fn decode_pnio_connect_response() {
         let bytes = hex::decode(
             "00000000220000003d00\
              000000000000220000008\
@@ -182,7 +250,7 @@ mod test {
         )
         .unwrap();
 
-        let pnio_result = TryInto::<Pnio>::try_into(bytes);
+        let pnio_result = Pnio::from_bytes(&bytes);
         assert_eq!(pnio_result.is_ok(), true);
 
         let pnio = pnio_result.unwrap();
@@ -195,7 +263,7 @@ mod test {
     }
 
     #[test]
-    fn try_from_pnio_read_response() {
+    fn decode_pnio_read_response() {
         let bytes = hex::decode(
             "0000000030010000300001000000000030010000\
              8009003c01000023f4162dbe951d4041b5839b57\
@@ -217,7 +285,7 @@ mod test {
         )
         .unwrap();
 
-        let pnio_result = TryInto::<Pnio>::try_into(bytes);
+        let pnio_result = Pnio::from_bytes(&bytes);
         assert_eq!(pnio_result.is_ok(), true);
 
         let pnio = pnio_result.unwrap();
@@ -230,7 +298,7 @@ mod test {
     }
 
     #[test]
-    fn try_from_pnio_write_response() {
+    fn decode_pnio_write_response() {
         let bytes = hex::decode(
             "00000000400000004b00\
             00000000000040000000\
@@ -244,7 +312,7 @@ mod test {
         )
         .unwrap();
 
-        let pnio_result = TryInto::<Pnio>::try_into(bytes);
+        let pnio_result = Pnio::from_bytes(&bytes);
         assert_eq!(pnio_result.is_ok(), true);
 
         let pnio = pnio_result.unwrap();
@@ -257,4 +325,170 @@ mod test {
             )
         };
     }
+
+    #[test]
+    fn concat_pnio_response_should_write_the_status() {
+        let bytes = hex::decode(
+            "df80e000400000004b00\
+            00000000000040000000\
+            8008003c01000024f416\
+            2dbe951d4041b5839b57\
+            a3bed95e000000000001\
+            00010000005000000000\
+            00000000000000000000\
+            00000000000000000000\
+            00000000",
+        )
+        .unwrap();
+
+        let pnio = Pnio::from_bytes(&bytes).unwrap();
+
+        assert_eq!(pnio.status, Some([0xdf, 0x80, 0xe0, 0x00]));
+        let concat = pnio.to_bytes().unwrap();
+        assert_eq!(concat[..4], bytes[..4]);
+        assert_eq!(concat[20..], bytes[20..]);
+    }
+
+    #[test]
+    fn decode_pnio_should_return_error() {
+        let iod_req = IodReq::new(
+            BlockHeaderType::IodWriteReqType,
+            1,
+            Uuid::nil(),
+            1,
+            1,
+            80,
+            4,
+        );
+        let pnio = Pnio::new(
+            None,
+            PnioHeaderEnum::IodReq(iod_req),
+            Some([0x01; 4].into()),
+        );
+        let mut bytes = pnio.to_bytes().unwrap();
+
+        // the actual count is shorter than the header
+        bytes[16..20].copy_from_slice(&10u32.to_le_bytes());
+        assert_eq!(
+            Pnio::from_bytes(&bytes).unwrap_err(),
+            ProtocolError::InvalidLength {
+                field: "PNIO actual_count",
+                len: 10
+            }
+        );
+        // the actual count is far beyond any record
+        bytes[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Pnio::from_bytes(&bytes).is_err());
+        // the block length exceeds the datagram
+        let block_len: [u8; 2] = bytes[22..24].try_into().unwrap();
+        bytes[22..24].copy_from_slice(&0xffffu16.to_be_bytes());
+        assert!(matches!(
+            Pnio::from_bytes(&bytes).unwrap_err(),
+            ProtocolError::Truncated { .. }
+        ));
+        // unknown block type
+        bytes[22..24].copy_from_slice(&block_len);
+        bytes[20..22].copy_from_slice(&0x0102u16.to_be_bytes());
+        assert_eq!(
+            Pnio::from_bytes(&bytes).unwrap_err(),
+            ProtocolError::Invalid {
+                field: "PNIO block_header_type",
+                value: 0x0102
+            }
+        );
+    }
+
+    fn pnio() -> impl Strategy<Value = Pnio> {
+        let uuid = any::<u128>().prop_map(Uuid::from_u128);
+        let data = collection::vec(any::<u8>(), 0..300);
+        (
+            0..5u8,
+            uuid,
+            any::<[u16; 4]>(),
+            any::<[u8; 4]>(),
+            any::<bool>(),
+            data,
+        )
+            .prop_map(
+                |(header, ar_uuid, [seq, slot, subslot, index], status, write, data)| {
+                    let data_len = data.len() as u32;
+                    match header {
+                        0 => Pnio::new(
+                            None,
+                            PnioHeaderEnum::ArBlockReq(ArBlockReq::new(ar_uuid, seq, ar_uuid)),
+                            None,
+                        ),
+                        1 => Pnio::new(
+                            Some(status),
+                            PnioHeaderEnum::ArBlockRes(ArBlockRes::new(
+                                BlockHeaderType::ArBlockResType,
+                                ar_uuid,
+                                seq,
+                                [0x02; 6],
+                                slot.to_be_bytes(),
+                            )),
+                            None,
+                        ),
+                        2 => {
+                            let (block_header_type, data) = match write {
+                                true => (BlockHeaderType::IodWriteReqType, Some(data.into())),
+                                false => (BlockHeaderType::IodReadReqType, None),
+                            };
+                            let iod_req = IodReq::new(
+                                block_header_type,
+                                seq,
+                                ar_uuid,
+                                slot,
+                                subslot,
+                                index,
+                                data_len,
+                            );
+                            Pnio::new(None, PnioHeaderEnum::IodReq(iod_req), data)
+                        }
+                        3 => {
+                            let (block_header_type, data) = match write {
+                                true => (BlockHeaderType::IodWriteResType, None),
+                                false => (BlockHeaderType::IodReadResType, Some(data.into())),
+                            };
+                            let iod_res = IodRes::new(
+                                block_header_type,
+                                seq,
+                                ar_uuid,
+                                slot,
+                                subslot,
+                                index,
+                                status,
+                                data_len,
+                            );
+                            Pnio::new(Some(status), PnioHeaderEnum::IodRes(iod_res), data)
+                        }
+                        _ => {
+                            let (block_header_type, status) = match write {
+                                true => (BlockHeaderType::ReleaseBlockResType, Some(status)),
+                                false => (BlockHeaderType::ReleaseBlockReqType, None),
+                            };
+                            let release_block = ReleaseBlock::new(block_header_type, ar_uuid, seq);
+                            Pnio::new(status, PnioHeaderEnum::ReleaseBlock(release_block), None)
+                        }
+                    }
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn pnio_should_roundtrip(pnio in pnio()) {
+            assert_roundtrip(&pnio);
+        }
+
+        #[test]
+        fn decode_pnio_should_not_panic(
+            pnio in pnio(),
+            garbage in collection::vec(any::<u8>(), 0..128),
+            flip in any::<usize>(),
+        ) {
+            assert_decode_never_panics::<Pnio>(&pnio.to_bytes().unwrap(), flip);
+            assert_decode_never_panics::<Pnio>(&garbage, flip);
+        }
+    }
 }
@@ -1,7 +1,9 @@
-use super::{ArBlockReq, ArBlockRes, IodReq, IodRes};
+use super::{
+    ArBlockReq, ArBlockRes, BlockHeaderType, Cursor, Decode, Encode, IodReq, IodRes, ProtocolError,
+    ReleaseBlock,
+};
 
-pub trait PnioHeader {
-    fn concat(&self) -> anyhow::Result<Vec<u8>>;
+pub trait PnioHeader: Encode {
     fn size(&self) -> usize;
     fn get_max_count(&self) -> u32;
     fn get_actual_count(&self) -> u32;
@@ -9,30 +11,82 @@ pub trait PnioHeader {
     fn get_args_max(&self) -> Option<u32>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum PnioHeaderEnum {
     ArBlockReq(ArBlockReq),
     ArBlockRes(ArBlockRes),
     IodReq(IodReq),
     IodRes(IodRes),
+    ReleaseBlock(ReleaseBlock),
 }
 
-impl PnioHeader for PnioHeaderEnum {
-    fn concat(&self) -> anyhow::Result<Vec<u8>> {
+impl PnioHeaderEnum {
+    pub fn block_header_type(&self) -> Option<BlockHeaderType> {
+        let block_header_type = match self {
+            PnioHeaderEnum::ArBlockReq(p) => p.block_header_type,
+            PnioHeaderEnum::ArBlockRes(p) => p.block_header_type,
+            PnioHeaderEnum::IodReq(p) => p.block_header_type,
+            PnioHeaderEnum::IodRes(p) => p.block_header_type,
+            PnioHeaderEnum::ReleaseBlock(p) => p.block_header_type,
+        };
+        BlockHeaderType::from_u16(u16::from_be_bytes(block_header_type))
+    }
+}
+
+impl Encode for PnioHeaderEnum {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
         match self {
-            PnioHeaderEnum::ArBlockReq(p) => p.concat(),
-            PnioHeaderEnum::ArBlockRes(p) => p.concat(),
-            PnioHeaderEnum::IodReq(p) => p.concat(),
-            PnioHeaderEnum::IodRes(p) => p.concat(),
+            PnioHeaderEnum::ArBlockReq(p) => p.encode(buf),
+            PnioHeaderEnum::ArBlockRes(p) => p.encode(buf),
+            PnioHeaderEnum::IodReq(p) => p.encode(buf),
+            PnioHeaderEnum::IodRes(p) => p.encode(buf),
+            PnioHeaderEnum::ReleaseBlock(p) => p.encode(buf),
         }
     }
+}
+
+impl Decode for PnioHeaderEnum {
+    /// decode tells the header by its block type
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        // the block type is read ahead, the header reads it again
+        let mut ahead = *cursor;
+        let block_type = ahead.u16_be("PNIO block_header_type")?;
+        let header = match BlockHeaderType::from_u16(block_type) {
+            Some(BlockHeaderType::ArBlockReqType) => {
+                PnioHeaderEnum::ArBlockReq(ArBlockReq::decode(cursor)?)
+            }
+            Some(BlockHeaderType::ArBlockResType) => {
+                PnioHeaderEnum::ArBlockRes(ArBlockRes::decode(cursor)?)
+            }
+            Some(BlockHeaderType::IodReadReqType | BlockHeaderType::IodWriteReqType) => {
+                PnioHeaderEnum::IodReq(IodReq::decode(cursor)?)
+            }
+            Some(BlockHeaderType::IodReadResType | BlockHeaderType::IodWriteResType) => {
+                PnioHeaderEnum::IodRes(IodRes::decode(cursor)?)
+            }
+            Some(BlockHeaderType::ReleaseBlockReqType | BlockHeaderType::ReleaseBlockResType) => {
+                PnioHeaderEnum::ReleaseBlock(ReleaseBlock::decode(cursor)?)
+            }
+            None => {
+                return Err(ProtocolError::Invalid {
+                    field: "PNIO block_header_type",
+                    value: block_type.into(),
+                })
+            }
+        };
+
+        Ok(header)
+    }
+}
 
+impl PnioHeader for PnioHeaderEnum {
     fn size(&self) -> usize {
         match self {
             PnioHeaderEnum::ArBlockReq(p) => p.size(),
             PnioHeaderEnum::ArBlockRes(p) => p.size(),
             PnioHeaderEnum::IodReq(p) => p.size(),
             PnioHeaderEnum::IodRes(p) => p.size(),
+            PnioHeaderEnum::ReleaseBlock(p) => p.size(),
         }
     }
 
@@ -42,6 +96,7 @@ impl PnioHeader for PnioHeaderEnum {
             PnioHeaderEnum::ArBlockRes(p) => p.get_max_count(),
             PnioHeaderEnum::IodReq(p) => p.get_max_count(),
             PnioHeaderEnum::IodRes(p) => p.get_max_count(),
+            PnioHeaderEnum::ReleaseBlock(p) => p.get_max_count(),
         }
     }
 
@@ -51,6 +106,7 @@ impl PnioHeader for PnioHeaderEnum {
             PnioHeaderEnum::ArBlockRes(p) => p.get_actual_count(),
             PnioHeaderEnum::IodReq(p) => p.get_actual_count(),
             PnioHeaderEnum::IodRes(p) => p.get_actual_count(),
+            PnioHeaderEnum::ReleaseBlock(p) => p.get_actual_count(),
         }
     }
 
@@ -60,6 +116,7 @@ impl PnioHeader for PnioHeaderEnum {
             PnioHeaderEnum::ArBlockRes(p) => p.get_args_length(),
             PnioHeaderEnum::IodReq(p) => p.get_args_length(),
             PnioHeaderEnum::IodRes(p) => p.get_args_length(),
+            PnioHeaderEnum::ReleaseBlock(p) => p.get_args_length(),
         }
     }
 
@@ -69,6 +126,7 @@ impl PnioHeader for PnioHeaderEnum {
             PnioHeaderEnum::ArBlockRes(p) => p.get_args_max(),
             PnioHeaderEnum::IodReq(p) => p.get_args_max(),
             PnioHeaderEnum::IodRes(p) => p.get_args_max(),
+            PnioHeaderEnum::ReleaseBlock(p) => p.get_args_max(),
         }
     }
 }
@@ -1,11 +1,11 @@
 use super::{
     constant::{self, BlockHeaderType},
-    PnioHeader,
+    Cursor, Decode, Encode, PnioHeader, ProtocolError,
 };
 use std::mem;
 use uuid::Uuid;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ArBlockReq {
     pub block_header_type: [u8; 2],
     pub block_header_len: [u8; 2],
@@ -28,7 +28,7 @@ impl ArBlockReq {
         // TODO: what is this 4 bytes about?
         let block_header_len = (mem::size_of::<Self>() as u16 - 4).to_be_bytes();
         // TODO: how to get this value: "TBL"?
-        let cm_initiator_station_name = TryInto::<[u8; 3]>::try_into("TBL".as_bytes()).unwrap();
+        let cm_initiator_station_name = *b"TBL";
 
         ArBlockReq {
             block_header_type: (BlockHeaderType::ArBlockReqType as u16).to_be_bytes(),
@@ -51,27 +51,6 @@ impl ArBlockReq {
 }
 
 impl PnioHeader for ArBlockReq {
-    fn concat(&self) -> anyhow::Result<Vec<u8>> {
-        let mut v: Vec<u8> = vec![];
-
-        v.extend(self.block_header_type);
-        v.extend(self.block_header_len);
-        v.extend(self.block_header_version_high);
-        v.extend(self.block_header_version_low);
-        v.extend(self.ar_type);
-        v.extend(self.ar_uuid);
-        v.extend(self.session_key);
-        v.extend(self.cm_initiator_mac);
-        v.extend(self.cm_initiator_obj_uuid);
-        v.extend(self.ar_props);
-        v.extend(self.cm_initiator_act_timeout_factor);
-        v.extend(self.cm_initiator_udprt_port);
-        v.extend(self.station_name_len);
-        v.extend(self.cm_initiator_station_name);
-
-        Ok(v)
-    }
-
     fn size(&self) -> usize {
         mem::size_of::<Self>()
     }
@@ -92,3 +71,77 @@ impl PnioHeader for ArBlockReq {
         Some(self.get_actual_count())
     }
 }
+
+impl Encode for ArBlockReq {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        buf.extend(self.block_header_type);
+        buf.extend(self.block_header_len);
+        buf.extend(self.block_header_version_high);
+        buf.extend(self.block_header_version_low);
+        buf.extend(self.ar_type);
+        buf.extend(self.ar_uuid);
+        buf.extend(self.session_key);
+        buf.extend(self.cm_initiator_mac);
+        buf.extend(self.cm_initiator_obj_uuid);
+        buf.extend(self.ar_props);
+        buf.extend(self.cm_initiator_act_timeout_factor);
+        buf.extend(self.cm_initiator_udprt_port);
+        buf.extend(self.station_name_len);
+        buf.extend(self.cm_initiator_station_name);
+
+        Ok(())
+    }
+}
+
+impl Decode for ArBlockReq {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let block_header_type = cursor.array("ARBlockReq block_header_type")?;
+        let block_type = u16::from_be_bytes(block_header_type);
+        if BlockHeaderType::from_u16(block_type) != Some(BlockHeaderType::ArBlockReqType) {
+            return Err(ProtocolError::Invalid {
+                field: "ARBlockReq block_header_type",
+                value: block_type.into(),
+            });
+        }
+
+        let block_header_len = cursor.array("ARBlockReq block_header_len")?;
+        let block_header_version_high = cursor.array("ARBlockReq block_header_version_high")?;
+        let block_header_version_low = cursor.array("ARBlockReq block_header_version_low")?;
+        let ar_type = cursor.array("ARBlockReq ar_type")?;
+        let ar_uuid = cursor.array("ARBlockReq ARUUID")?;
+        let session_key = cursor.array("ARBlockReq session_key")?;
+        let cm_initiator_mac = cursor.array("ARBlockReq cm_initiator_mac")?;
+        let cm_initiator_obj_uuid = cursor.array("ARBlockReq cm_initiator_obj_uuid")?;
+        let ar_props = cursor.array("ARBlockReq ar_props")?;
+        let cm_initiator_act_timeout_factor =
+            cursor.array("ARBlockReq cm_initiator_act_timeout_factor")?;
+        let cm_initiator_udprt_port = cursor.array("ARBlockReq cm_initiator_udprt_port")?;
+        let station_name_len = cursor.array("ARBlockReq station_name_len")?;
+        // the station name of the initiator is the one of `new`
+        let len = u16::from_be_bytes(station_name_len) as usize;
+        if len != 3 {
+            return Err(ProtocolError::InvalidLength {
+                field: "ARBlockReq cm_initiator_station_name",
+                len,
+            });
+        }
+        let cm_initiator_station_name = cursor.array("ARBlockReq cm_initiator_station_name")?;
+
+        Ok(ArBlockReq {
+            block_header_type,
+            block_header_len,
+            block_header_version_high,
+            block_header_version_low,
+            ar_type,
+            ar_uuid,
+            session_key,
+            cm_initiator_mac,
+            cm_initiator_obj_uuid,
+            ar_props,
+            cm_initiator_act_timeout_factor,
+            cm_initiator_udprt_port,
+            station_name_len,
+            cm_initiator_station_name,
+        })
+    }
+}
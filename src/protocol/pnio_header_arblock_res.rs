@@ -1,11 +1,11 @@
 use super::{
-    constant::BlockHeaderType, PnioHeader, AR_TYPE, BLOCK_VERSION_HIGH, BLOCK_VERSION_LOW,
+    constant::BlockHeaderType, Cursor, Decode, Encode, PnioHeader, ProtocolError, AR_TYPE,
+    BLOCK_VERSION_HIGH, BLOCK_VERSION_LOW,
 };
-use anyhow::anyhow;
 use std::mem;
 use uuid::Uuid;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ArBlockRes {
     pub block_header_type: [u8; 2],
     pub block_header_len: [u8; 2],
@@ -42,22 +42,6 @@ impl ArBlockRes {
 }
 
 impl PnioHeader for ArBlockRes {
-    fn concat(&self) -> anyhow::Result<Vec<u8>> {
-        let mut v: Vec<u8> = vec![];
-
-        v.extend(self.block_header_type);
-        v.extend(self.block_header_len);
-        v.extend(self.block_header_version_high);
-        v.extend(self.block_header_version_low);
-        v.extend(self.ar_type);
-        v.extend(self.ar_uuid);
-        v.extend(self.session_key);
-        v.extend(self.cm_responder_mac_address);
-        v.extend(self.cm_responder_udpport);
-
-        Ok(v)
-    }
-
     fn size(&self) -> usize {
         mem::size_of::<Self>()
     }
@@ -81,50 +65,43 @@ impl PnioHeader for ArBlockRes {
     }
 }
 
-impl TryFrom<&[u8]> for ArBlockRes {
-    type Error = anyhow::Error;
-
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let block_header_type_u16 =
-            u16::from_be_bytes(*TryInto::<&[u8; 2]>::try_into(value.get(0..2).unwrap()).unwrap());
-        let block_header_type = match BlockHeaderType::from_u16(block_header_type_u16) {
-            Some(b) => b,
-            None => return Err(anyhow!("failed to match block_header_type")),
-        };
-
-        let _: [u8; 2] = match value.get(6..8) {
-            Some(a) => a.try_into()?,
-            None => return Err(anyhow!("failed to match ar_type")),
-        };
-
-        let ar_uuid = match value.get(8..24) {
-            Some(a) => Uuid::from_slice(a)?,
-            None => return Err(anyhow!("failed to match ARUUID")),
-        };
-
-        let session_key: u16 = match value.get(24..26) {
-            Some(s) => u16::from_be_bytes(TryInto::<[u8; 2]>::try_into(s)?),
-            None => return Err(anyhow!("failed to match session_key")),
-        };
-
-        let cm_responder_mac_address: [u8; 6] = match value.get(26..32) {
-            Some(c) => c.try_into()?,
-            None => return Err(anyhow!("failed to match cm_responder_mac_address")),
-        };
+impl Encode for ArBlockRes {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        buf.extend(self.block_header_type);
+        buf.extend(self.block_header_len);
+        buf.extend(self.block_header_version_high);
+        buf.extend(self.block_header_version_low);
+        buf.extend(self.ar_type);
+        buf.extend(self.ar_uuid);
+        buf.extend(self.session_key);
+        buf.extend(self.cm_responder_mac_address);
+        buf.extend(self.cm_responder_udpport);
+
+        Ok(())
+    }
+}
 
-        let cm_responder_udpport = match value.get(32..34) {
-            Some(c) => c.try_into()?,
-            None => return Err(anyhow!("failed to match cm_responder_udpport")),
-        };
+impl Decode for ArBlockRes {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let block_header_type = cursor.array("ARBlockRes block_header_type")?;
+        let block_type = u16::from_be_bytes(block_header_type);
+        if BlockHeaderType::from_u16(block_type) != Some(BlockHeaderType::ArBlockResType) {
+            return Err(ProtocolError::Invalid {
+                field: "ARBlockRes block_header_type",
+                value: block_type.into(),
+            });
+        }
 
-        let arblock_request = Self::new(
+        Ok(Self {
             block_header_type,
-            ar_uuid,
-            session_key,
-            cm_responder_mac_address,
-            cm_responder_udpport,
-        );
-
-        Ok(arblock_request)
+            block_header_len: cursor.array("ARBlockRes block_header_len")?,
+            block_header_version_high: cursor.array("ARBlockRes block_header_version_high")?,
+            block_header_version_low: cursor.array("ARBlockRes block_header_version_low")?,
+            ar_type: cursor.array("ARBlockRes ar_type")?,
+            ar_uuid: cursor.array("ARBlockRes ARUUID")?,
+            session_key: cursor.array("ARBlockRes session_key")?,
+            cm_responder_mac_address: cursor.array("ARBlockRes cm_responder_mac_address")?,
+            cm_responder_udpport: cursor.array("ARBlockRes cm_responder_udpport")?,
+        })
     }
 }
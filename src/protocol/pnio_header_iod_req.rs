@@ -1,12 +1,11 @@
 use super::{
-    BlockHeaderType, PnioHeader, BLOCK_VERSION_HIGH, BLOCK_VERSION_LOW, IOD_PADDING, IOD_REQ_API,
-    READ_MAX_COUNT,
+    BlockHeaderType, Cursor, Decode, Encode, PnioHeader, ProtocolError, BLOCK_VERSION_HIGH,
+    BLOCK_VERSION_LOW, IOD_PADDING, IOD_REQ_API, READ_MAX_COUNT,
 };
-use anyhow::anyhow;
 use std::mem;
 use uuid::Uuid;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct IodReq {
     pub block_header_type: [u8; 2],
     pub block_header_len: [u8; 2],
@@ -58,26 +57,6 @@ impl IodReq {
 }
 
 impl PnioHeader for IodReq {
-    fn concat(&self) -> anyhow::Result<Vec<u8>> {
-        let mut v: Vec<u8> = vec![];
-
-        v.extend(self.block_header_type);
-        v.extend(self.block_header_len);
-        v.extend(self.block_header_version_high);
-        v.extend(self.block_header_version_low);
-        v.extend(self.seq_num);
-        v.extend(self.ar_uuid);
-        v.extend(self.api);
-        v.extend(self.slot_num);
-        v.extend(self.subslot_num);
-        v.extend(self.padding1);
-        v.extend(self.index);
-        v.extend(self.record_data_len);
-        v.extend(self.padding2);
-
-        Ok(v)
-    }
-
     fn size(&self) -> usize {
         mem::size_of::<Self>()
     }
@@ -86,9 +65,9 @@ impl PnioHeader for IodReq {
         let block_header_type =
             BlockHeaderType::from_u16(u16::from_be_bytes(self.block_header_type));
 
-        match block_header_type.unwrap() {
-            BlockHeaderType::IodReadReqType => READ_MAX_COUNT,
-            BlockHeaderType::IodWriteReqType => {
+        match block_header_type {
+            Some(BlockHeaderType::IodReadReqType) => READ_MAX_COUNT,
+            Some(BlockHeaderType::IodWriteReqType) => {
                 self.size() as u32 + u32::from_be_bytes(self.record_data_len)
             }
             _ => 0,
@@ -99,9 +78,9 @@ impl PnioHeader for IodReq {
         let block_header_type =
             BlockHeaderType::from_u16(u16::from_be_bytes(self.block_header_type));
 
-        match block_header_type.unwrap() {
-            BlockHeaderType::IodReadReqType => self.size() as u32,
-            BlockHeaderType::IodWriteReqType => {
+        match block_header_type {
+            Some(BlockHeaderType::IodReadReqType) => self.size() as u32,
+            Some(BlockHeaderType::IodWriteReqType) => {
                 self.size() as u32 + u32::from_be_bytes(self.record_data_len)
             }
             _ => 0,
@@ -116,69 +95,86 @@ impl PnioHeader for IodReq {
         let block_header_type =
             BlockHeaderType::from_u16(u16::from_be_bytes(self.block_header_type));
 
-        match block_header_type.unwrap() {
-            BlockHeaderType::IodReadReqType => Some(READ_MAX_COUNT),
-            BlockHeaderType::IodWriteReqType => Some(self.get_actual_count()),
+        match block_header_type {
+            Some(BlockHeaderType::IodReadReqType) => Some(READ_MAX_COUNT),
+            Some(BlockHeaderType::IodWriteReqType) => Some(self.get_actual_count()),
             _ => None,
         }
     }
 }
 
-impl TryFrom<Vec<u8>> for IodReq {
-    type Error = anyhow::Error;
-
-    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-        // TODO: handle error properly
-        let block_header_type_u16 =
-            u16::from_be_bytes(*TryInto::<&[u8; 2]>::try_into(value.get(0..2).unwrap()).unwrap());
+impl Encode for IodReq {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        buf.extend(self.block_header_type);
+        buf.extend(self.block_header_len);
+        buf.extend(self.block_header_version_high);
+        buf.extend(self.block_header_version_low);
+        buf.extend(self.seq_num);
+        buf.extend(self.ar_uuid);
+        buf.extend(self.api);
+        buf.extend(self.slot_num);
+        buf.extend(self.subslot_num);
+        buf.extend(self.padding1);
+        buf.extend(self.index);
+        buf.extend(self.record_data_len);
+        buf.extend(self.padding2);
+
+        Ok(())
+    }
+}
 
-        let block_header_type = match BlockHeaderType::from_u16(block_header_type_u16) {
-            Some(b) => b,
-            None => return Err(anyhow!("failed to match block_header_type")),
-        };
+impl Decode for IodReq {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let block_header_type = cursor.array("IOD block_header_type")?;
+        let block_type = u16::from_be_bytes(block_header_type);
+        if !matches!(
+            BlockHeaderType::from_u16(block_type),
+            Some(BlockHeaderType::IodReadReqType | BlockHeaderType::IodWriteReqType)
+        ) {
+            return Err(ProtocolError::Invalid {
+                field: "IOD request block_header_type",
+                value: block_type.into(),
+            });
+        }
 
-        let seq_num_u16 = u16::from_be_bytes(value[6..8].try_into().unwrap());
-        let ar_uuid = Uuid::from_bytes(value[8..24].try_into().unwrap());
-        let slot_num_u16 = u16::from_be_bytes(value[28..30].try_into().unwrap());
-        let subslot_num_u16 = u16::from_be_bytes(value[30..32].try_into().unwrap());
-        let index = u16::from_be_bytes(value[34..36].try_into().unwrap());
+        Ok(IodReq {
+            block_header_type,
+            block_header_len: cursor.array("IOD block_header_len")?,
+            block_header_version_high: cursor.array("IOD block_header_version_high")?,
+            block_header_version_low: cursor.array("IOD block_header_version_low")?,
+            seq_num: cursor.array("IOD seq_num")?,
+            ar_uuid: cursor.array("IOD ARUUID")?,
+            api: cursor.array("IOD API")?,
+            slot_num: cursor.array("IOD slot_num")?,
+            subslot_num: cursor.array("IOD subslot_num")?,
+            padding1: cursor.array("IOD padding")?,
+            index: cursor.array("IOD index")?,
+            record_data_len: cursor.array("IOD record_data_len")?,
+            padding2: cursor.array("IOD padding")?,
+        })
+    }
+}
 
-        let record_data_len_u16 = u32::from_be_bytes(value[37..41].try_into().unwrap());
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        let iod_request = IodReq::new(
-            block_header_type,
-            seq_num_u16,
+    #[test]
+    fn decode_iod_request_should_read_the_record_data_length() {
+        let ar_uuid = Uuid::parse_str("b63dbc71-b545-9246-b8c5-0761aeb88cde").unwrap();
+        let iod_req = IodReq::new(
+            BlockHeaderType::IodWriteReqType,
+            1,
             ar_uuid,
-            slot_num_u16,
-            subslot_num_u16,
-            index,
-            record_data_len_u16,
+            1,
+            1,
+            80,
+            0x0102,
         );
 
-        Ok(iod_request)
-    }
-}
+        let parsed = IodReq::from_bytes(&iod_req.to_bytes().unwrap()).unwrap();
 
-impl TryInto<Vec<u8>> for IodReq {
-    type Error = String;
-
-    fn try_into(self) -> Result<Vec<u8>, Self::Error> {
-        let mut v: Vec<u8> = vec![];
-
-        v.extend(self.block_header_type);
-        v.extend(self.block_header_len);
-        v.extend(self.block_header_version_high);
-        v.extend(self.block_header_version_low);
-        v.extend(self.seq_num);
-        v.extend(self.ar_uuid);
-        v.extend(self.api);
-        v.extend(self.slot_num);
-        v.extend(self.subslot_num);
-        v.extend(self.padding1);
-        v.extend(self.index);
-        v.extend(self.record_data_len);
-        v.extend(self.padding2);
-
-        Ok(v)
+        assert_eq!(parsed.index, 80u16.to_be_bytes());
+        assert_eq!(parsed.record_data_len, 0x0102u32.to_be_bytes());
     }
 }
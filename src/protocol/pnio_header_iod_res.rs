@@ -1,12 +1,11 @@
 use super::{
-    BlockHeaderType, PnioHeader, BLOCK_VERSION_HIGH, BLOCK_VERSION_LOW, IOD_PADDING, IOD_REQ_API,
-    READ_MAX_COUNT,
+    BlockHeaderType, Cursor, Decode, Encode, PnioHeader, ProtocolError, BLOCK_VERSION_HIGH,
+    BLOCK_VERSION_LOW, IOD_PADDING, IOD_REQ_API, READ_MAX_COUNT,
 };
-use anyhow::anyhow;
 use std::mem;
 use uuid::Uuid;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct IodRes {
     pub block_header_type: [u8; 2],
     pub block_header_len: [u8; 2],
@@ -61,29 +60,6 @@ impl IodRes {
 }
 
 impl PnioHeader for IodRes {
-    fn concat(&self) -> anyhow::Result<Vec<u8>> {
-        let mut v: Vec<u8> = vec![];
-
-        v.extend(self.block_header_type);
-        v.extend(self.block_header_len);
-        v.extend(self.block_header_version_high);
-        v.extend(self.block_header_version_low);
-        v.extend(self.seq_num);
-        v.extend(self.ar_uuid);
-        v.extend(self.api);
-        v.extend(self.slot_num);
-        v.extend(self.subslot_num);
-        v.extend(self.padding1);
-        v.extend(self.index);
-        v.extend(self.record_data_len);
-        v.extend(self.additional_value1);
-        v.extend(self.additional_value2);
-        v.extend(self.status);
-        v.extend(self.padding2);
-
-        Ok(v)
-    }
-
     fn size(&self) -> usize {
         mem::size_of::<Self>()
     }
@@ -92,9 +68,9 @@ impl PnioHeader for IodRes {
         let block_header_type =
             BlockHeaderType::from_u16(u16::from_be_bytes(self.block_header_type));
 
-        match block_header_type.unwrap() {
-            BlockHeaderType::IodReadResType => READ_MAX_COUNT,
-            BlockHeaderType::IodWriteResType => {
+        match block_header_type {
+            Some(BlockHeaderType::IodReadResType) => READ_MAX_COUNT,
+            Some(BlockHeaderType::IodWriteResType) => {
                 // TODO: this is incorrect, should return corresponded
                 // request's max count
                 self.size() as u32 + u32::from_be_bytes(self.record_data_len)
@@ -107,11 +83,11 @@ impl PnioHeader for IodRes {
         let block_header_type =
             BlockHeaderType::from_u16(u16::from_be_bytes(self.block_header_type));
 
-        match block_header_type.unwrap() {
-            BlockHeaderType::IodReadResType => {
+        match block_header_type {
+            Some(BlockHeaderType::IodReadResType) => {
                 self.size() as u32 + u32::from_be_bytes(self.record_data_len)
             }
-            BlockHeaderType::IodWriteResType => self.size() as u32,
+            Some(BlockHeaderType::IodWriteResType) => self.size() as u32,
             _ => 0,
         }
     }
@@ -121,59 +97,64 @@ impl PnioHeader for IodRes {
     }
 
     fn get_args_max(&self) -> Option<u32> {
-        let block_header_type =
-            BlockHeaderType::from_u16(u16::from_be_bytes(self.block_header_type));
-
-        match block_header_type.unwrap() {
-            BlockHeaderType::IodReadResType => None,
-            BlockHeaderType::IodWriteResType => None,
-            _ => None,
-        }
+        None
     }
 }
 
-impl TryFrom<Vec<u8>> for IodRes {
-    type Error = anyhow::Error;
-
-    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-        let block_header_type_u16 =
-            u16::from_be_bytes(*TryInto::<&[u8; 2]>::try_into(value.get(0..2).unwrap())?);
-
-        let block_header_type = match BlockHeaderType::from_u16(block_header_type_u16) {
-            Some(b) => b,
-            None => return Err(anyhow!("failed to match block_header_type")),
-        };
-
-        let seq_num_u16 = u16::from_be_bytes(value[6..8].try_into()?);
-        let ar_uuid = Uuid::from_bytes(value[8..24].try_into()?);
-        let slot_num_u16 = u16::from_be_bytes(value[28..30].try_into()?);
-        let subslot_num_u16 = u16::from_be_bytes(value[30..32].try_into()?);
-        let index = match value.get(34..36) {
-            Some(i) => u16::from_be_bytes(TryInto::<[u8; 2]>::try_into(i)?),
-            None => return Err(anyhow!("failed to match IOD header index")),
-        };
-
-        let record_data_len_u16 = u32::from_be_bytes(value[36..40].try_into()?);
+impl Encode for IodRes {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        buf.extend(self.block_header_type);
+        buf.extend(self.block_header_len);
+        buf.extend(self.block_header_version_high);
+        buf.extend(self.block_header_version_low);
+        buf.extend(self.seq_num);
+        buf.extend(self.ar_uuid);
+        buf.extend(self.api);
+        buf.extend(self.slot_num);
+        buf.extend(self.subslot_num);
+        buf.extend(self.padding1);
+        buf.extend(self.index);
+        buf.extend(self.record_data_len);
+        buf.extend(self.additional_value1);
+        buf.extend(self.additional_value2);
+        buf.extend(self.status);
+        buf.extend(self.padding2);
+
+        Ok(())
+    }
+}
 
-        let mut status = [0x00; 4];
-        if block_header_type == BlockHeaderType::IodWriteResType {
-            status = match value.get(45..49) {
-                Some(s) => TryInto::<[u8; 4]>::try_into(s)?,
-                None => return Err(anyhow!("failed to match status for IODWriteRes")),
-            };
-        };
+impl Decode for IodRes {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let block_header_type = cursor.array("IOD block_header_type")?;
+        let block_type = u16::from_be_bytes(block_header_type);
+        if !matches!(
+            BlockHeaderType::from_u16(block_type),
+            Some(BlockHeaderType::IodReadResType | BlockHeaderType::IodWriteResType)
+        ) {
+            return Err(ProtocolError::Invalid {
+                field: "IOD response block_header_type",
+                value: block_type.into(),
+            });
+        }
 
-        let iod_request = IodRes::new(
+        Ok(IodRes {
             block_header_type,
-            seq_num_u16,
-            ar_uuid,
-            slot_num_u16,
-            subslot_num_u16,
-            index,
-            status,
-            record_data_len_u16,
-        );
-
-        Ok(iod_request)
+            block_header_len: cursor.array("IOD block_header_len")?,
+            block_header_version_high: cursor.array("IOD block_header_version_high")?,
+            block_header_version_low: cursor.array("IOD block_header_version_low")?,
+            seq_num: cursor.array("IOD seq_num")?,
+            ar_uuid: cursor.array("IOD ARUUID")?,
+            api: cursor.array("IOD API")?,
+            slot_num: cursor.array("IOD slot_num")?,
+            subslot_num: cursor.array("IOD subslot_num")?,
+            padding1: cursor.array("IOD padding")?,
+            index: cursor.array("IOD index")?,
+            record_data_len: cursor.array("IOD record_data_len")?,
+            additional_value1: cursor.array("IOD additional_value1")?,
+            additional_value2: cursor.array("IOD additional_value2")?,
+            status: cursor.array("IOD status")?,
+            padding2: cursor.array("IOD padding")?,
+        })
     }
 }
@@ -0,0 +1,139 @@
+use super::{
+    constant::{self, BlockHeaderType},
+    Cursor, Decode, Encode, PnioHeader, ProtocolError,
+};
+use std::mem;
+use uuid::Uuid;
+
+/// ReleaseBlock is the control block of IODReleaseReq and IODReleaseRes,
+/// the response echoes the request with its own block type
+#[derive(Debug, PartialEq)]
+pub struct ReleaseBlock {
+    pub block_header_type: [u8; 2],
+    pub block_header_len: [u8; 2],
+    pub block_header_version_high: [u8; 1],
+    pub block_header_version_low: [u8; 1],
+    pub reserved1: [u8; 2],
+    pub ar_uuid: [u8; 16],
+    pub session_key: [u8; 2],
+    pub reserved2: [u8; 2],
+    pub control_command: [u8; 2],
+    pub control_block_properties: [u8; 2],
+}
+
+impl ReleaseBlock {
+    pub fn new(block_header_type: BlockHeaderType, ar_uuid: Uuid, session_key: u16) -> Self {
+        Self {
+            block_header_type: (block_header_type as u16).to_be_bytes(),
+            // block length does not count the block type and the block length
+            block_header_len: (mem::size_of::<Self>() as u16 - 4).to_be_bytes(),
+            block_header_version_high: constant::BLOCK_VERSION_HIGH.to_be_bytes(),
+            block_header_version_low: constant::BLOCK_VERSION_LOW.to_be_bytes(),
+            reserved1: [0x00; 2],
+            ar_uuid: *ar_uuid.as_bytes(),
+            session_key: session_key.to_be_bytes(),
+            reserved2: [0x00; 2],
+            control_command: constant::CONTROL_COMMAND_RELEASE.to_be_bytes(),
+            control_block_properties: [0x00; 2],
+        }
+    }
+
+    fn is_request(&self) -> bool {
+        self.block_header_type == (BlockHeaderType::ReleaseBlockReqType as u16).to_be_bytes()
+    }
+}
+
+impl PnioHeader for ReleaseBlock {
+    fn size(&self) -> usize {
+        mem::size_of::<Self>()
+    }
+
+    fn get_max_count(&self) -> u32 {
+        self.size() as u32
+    }
+
+    fn get_actual_count(&self) -> u32 {
+        self.size() as u32
+    }
+
+    fn get_args_length(&self) -> u32 {
+        self.size() as u32
+    }
+
+    fn get_args_max(&self) -> Option<u32> {
+        match self.is_request() {
+            true => Some(self.get_actual_count()),
+            false => None,
+        }
+    }
+}
+
+impl Encode for ReleaseBlock {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        buf.extend(self.block_header_type);
+        buf.extend(self.block_header_len);
+        buf.extend(self.block_header_version_high);
+        buf.extend(self.block_header_version_low);
+        buf.extend(self.reserved1);
+        buf.extend(self.ar_uuid);
+        buf.extend(self.session_key);
+        buf.extend(self.reserved2);
+        buf.extend(self.control_command);
+        buf.extend(self.control_block_properties);
+
+        Ok(())
+    }
+}
+
+impl Decode for ReleaseBlock {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let block_header_type = cursor.array("ReleaseBlock block_header_type")?;
+        let block_type = u16::from_be_bytes(block_header_type);
+        if !matches!(
+            BlockHeaderType::from_u16(block_type),
+            Some(BlockHeaderType::ReleaseBlockReqType | BlockHeaderType::ReleaseBlockResType)
+        ) {
+            return Err(ProtocolError::Invalid {
+                field: "ReleaseBlock block_header_type",
+                value: block_type.into(),
+            });
+        }
+
+        Ok(Self {
+            block_header_type,
+            block_header_len: cursor.array("ReleaseBlock block_header_len")?,
+            block_header_version_high: cursor.array("ReleaseBlock block_header_version_high")?,
+            block_header_version_low: cursor.array("ReleaseBlock block_header_version_low")?,
+            reserved1: cursor.array("ReleaseBlock reserved")?,
+            ar_uuid: cursor.array("ReleaseBlock ARUUID")?,
+            session_key: cursor.array("ReleaseBlock session_key")?,
+            reserved2: cursor.array("ReleaseBlock reserved")?,
+            control_command: cursor.array("ReleaseBlock control_command")?,
+            control_block_properties: cursor.array("ReleaseBlock control_block_properties")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encode_release_block_should_ok() {
+        let ar_uuid = Uuid::parse_str("6f4162dd-be95-1d40-41b5-839b57a3bed9").unwrap();
+        let release_block = ReleaseBlock::new(BlockHeaderType::ReleaseBlockReqType, ar_uuid, 1);
+
+        let bytes = release_block.to_bytes().unwrap();
+        assert_eq!(
+            hex::encode(&bytes),
+            "0114001c01000000\
+             6f4162ddbe951d4041b5839b57a3bed9\
+             0001000000040000"
+        );
+        assert_eq!(release_block.get_args_max(), Some(32));
+
+        let release_block = ReleaseBlock::from_bytes(&bytes).unwrap();
+        assert_eq!(Uuid::from_bytes(release_block.ar_uuid), ar_uuid);
+        assert_eq!(release_block.session_key, [0x00, 0x01]);
+    }
+}
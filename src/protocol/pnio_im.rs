@@ -0,0 +1,239 @@
+use super::{Cursor, Decode, ProtocolError};
+use serde::{Serialize, Serializer};
+
+// I&M records, see PROFINET "Identification & Maintenance Functions"
+pub const IM0_INDEX: u16 = 0xaff0;
+pub const IM1_INDEX: u16 = 0xaff1;
+pub const IM2_INDEX: u16 = 0xaff2;
+pub const IM3_INDEX: u16 = 0xaff3;
+pub const IM4_INDEX: u16 = 0xaff4;
+// block type of I&M0, I&M1 to I&M4 follow in order
+const IM0_BLOCK_TYPE: u16 = 0x0020;
+const BLOCK_HEADER_LEN: usize = 6;
+
+/// im_block checks the block header of I&M`n` and returns the block content
+fn im_block<'a>(cursor: &mut Cursor<'a>, n: u16, len: usize) -> Result<Cursor<'a>, ProtocolError> {
+    let block_header_type = cursor.u16_be("I&M block_header_type")?;
+    if block_header_type != IM0_BLOCK_TYPE + n {
+        return Err(ProtocolError::Invalid {
+            field: "I&M block_header_type",
+            value: block_header_type.into(),
+        });
+    }
+    cursor.skip(BLOCK_HEADER_LEN - 2, "I&M block header")?;
+
+    cursor.sub(len, "I&M block")
+}
+
+// visible strings are padded with blanks
+fn visible_string(value: &[u8]) -> String {
+    String::from_utf8_lossy(value)
+        .trim_end_matches([' ', '\0'])
+        .to_string()
+}
+
+/// Im0 is the mandatory identification of a module or station
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Im0 {
+    pub vendor_id: u16,
+    pub order_id: String,
+    pub serial_number: String,
+    pub hardware_revision: u16,
+    /// software revision, e.g. `V4.2.0`
+    pub software_revision: String,
+    pub revision_counter: u16,
+    pub profile_id: u16,
+    pub profile_specific_type: u16,
+    /// I&M version, e.g. `1.1`
+    pub im_version: String,
+    /// im_supported has bit `n` set if I&M`n` is supported
+    pub im_supported: u16,
+}
+
+impl Im0 {
+    pub fn supports(&self, n: u16) -> bool {
+        self.im_supported & (1 << n) != 0
+    }
+}
+
+impl Decode for Im0 {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let mut b = im_block(cursor, 0, 54)?;
+        let vendor_id = b.u16_be("I&M0 vendor_id")?;
+        let order_id = visible_string(b.take(20, "I&M0 order_id")?);
+        let serial_number = visible_string(b.take(16, "I&M0 serial_number")?);
+        let hardware_revision = b.u16_be("I&M0 hardware_revision")?;
+        let [prefix, major, minor, patch] = b.array("I&M0 software_revision")?;
+        let revision_counter = b.u16_be("I&M0 revision_counter")?;
+        let profile_id = b.u16_be("I&M0 profile_id")?;
+        let profile_specific_type = b.u16_be("I&M0 profile_specific_type")?;
+        let [im_major, im_minor] = b.array("I&M0 im_version")?;
+        let im_supported = b.u16_be("I&M0 im_supported")?;
+
+        Ok(Self {
+            vendor_id,
+            order_id,
+            serial_number,
+            hardware_revision,
+            software_revision: format!("{}{major}.{minor}.{patch}", prefix as char),
+            revision_counter,
+            profile_id,
+            profile_specific_type,
+            im_version: format!("{im_major}.{im_minor}"),
+            im_supported,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Im1 {
+    pub tag_function: String,
+    pub tag_location: String,
+}
+
+impl Decode for Im1 {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let mut b = im_block(cursor, 1, 54)?;
+
+        Ok(Self {
+            tag_function: visible_string(b.take(32, "I&M1 tag_function")?),
+            tag_location: visible_string(b.take(22, "I&M1 tag_location")?),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Im2 {
+    /// installation date, `YYYY-MM-DD HH:MM`
+    pub date: String,
+}
+
+impl Decode for Im2 {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let b = im_block(cursor, 2, 16)?.rest();
+
+        Ok(Self {
+            date: visible_string(b),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Im3 {
+    pub descriptor: String,
+}
+
+impl Decode for Im3 {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let b = im_block(cursor, 3, 54)?.rest();
+
+        Ok(Self {
+            descriptor: visible_string(b),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Im4 {
+    /// signature is binary, it's serialized as hex
+    #[serde(serialize_with = "serialize_hex")]
+    pub signature: Vec<u8>,
+}
+
+impl Decode for Im4 {
+    fn decode(cursor: &mut Cursor<'_>) -> Result<Self, ProtocolError> {
+        let b = im_block(cursor, 4, 54)?.rest();
+
+        Ok(Self {
+            signature: b.to_vec(),
+        })
+    }
+}
+
+fn serialize_hex<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(value))
+}
+
+/// Identification holds the I&M records of a single slot/subslot, I&M1 to
+/// I&M4 are only there if I&M0 tells they are supported
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Identification {
+    pub slot_number: u16,
+    pub subslot_number: u16,
+    pub im0: Im0,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub im1: Option<Im1>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub im2: Option<Im2>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub im3: Option<Im3>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub im4: Option<Im4>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::codec::test::assert_decode_never_panics;
+    use proptest::{collection, prelude::*};
+
+    // I&M0 of an ET200SP interface module
+    fn get_im0() -> Vec<u8> {
+        let mut bytes = hex::decode("002000380100002a").unwrap();
+        bytes.extend(format!("{:<20}{:<16}", "6ES7 155-6AU01-0BN0", "S C-J9UE6092").as_bytes());
+        // hardware revision, software revision V4.2.0, revision counter,
+        // profile id, profile specific type, I&M version 1.1, I&M1-4 supported
+        bytes.extend(hex::decode("0003560402000001f60000040101001e").unwrap());
+        bytes
+    }
+
+    #[test]
+    fn decode_im0_should_return_correctly() {
+        let bytes = get_im0();
+        let im0 = Im0::from_bytes(&bytes).unwrap();
+
+        assert_eq!(im0.vendor_id, 0x002a);
+        assert_eq!(im0.order_id, "6ES7 155-6AU01-0BN0");
+        assert_eq!(im0.serial_number, "S C-J9UE6092");
+        assert_eq!(im0.hardware_revision, 3);
+        assert_eq!(im0.software_revision, "V4.2.0");
+        assert_eq!(im0.revision_counter, 1);
+        assert_eq!(im0.profile_id, 0xf600);
+        assert_eq!(im0.profile_specific_type, 4);
+        assert_eq!(im0.im_version, "1.1");
+        assert!(im0.supports(1) && im0.supports(4));
+        assert!(!im0.supports(0));
+    }
+
+    #[test]
+    fn decode_im1_should_return_correctly() {
+        let mut bytes = hex::decode("002100380100").unwrap();
+        bytes.extend(format!("{:<32}{:<22}", "PT-4711", "tank 3").as_bytes());
+        let im1 = Im1::from_bytes(&bytes).unwrap();
+
+        assert_eq!(im1.tag_function, "PT-4711");
+        assert_eq!(im1.tag_location, "tank 3");
+    }
+
+    #[test]
+    fn decode_im_should_return_error() {
+        let bytes = get_im0();
+        // I&M0 is not I&M1
+        assert!(Im1::from_bytes(&bytes).is_err());
+        // truncated
+        assert!(Im0::from_bytes(&bytes[..40]).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn decode_im_should_not_panic(
+            garbage in collection::vec(any::<u8>(), 0..80),
+            flip in any::<usize>(),
+        ) {
+            assert_decode_never_panics::<Im0>(&get_im0(), flip);
+            assert_decode_never_panics::<Im0>(&garbage, flip);
+            assert_decode_never_panics::<Im1>(&garbage, flip);
+            assert_decode_never_panics::<Im4>(&garbage, flip);
+        }
+    }
+}
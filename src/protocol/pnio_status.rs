@@ -0,0 +1,385 @@
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::fmt;
+
+// PNIO status, see IEC 61158-6-10 "Coding of the field PNIOStatus"
+pub const PNIO_STATUS_OK: [u8; 4] = [0x00; 4];
+const ERROR_DECODE_PNIORW: u8 = 0x80;
+const ERROR_DECODE_PNIO: u8 = 0x81;
+const ERROR_DECODE_MANUFACTURER_SPECIFIC: u8 = 0x82;
+// ErrorCode1 of the context management protocol machine, and its ErrorCode2
+// for a request to an AR the station does not know
+const ERROR_CODE1_CMRPC: u8 = 0x40;
+const ERROR_CODE2_AR_UUID_UNKNOWN: u8 = 0x05;
+
+/// PnioService is the ErrorCode of the PNIO status, i.e. the service that failed
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PnioService {
+    Connect,
+    Release,
+    Control,
+    Read,
+    Write,
+    Other(u8),
+}
+
+impl PnioService {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0xdb => Self::Connect,
+            0xdc => Self::Release,
+            0xdd => Self::Control,
+            0xde => Self::Read,
+            0xdf => Self::Write,
+            v => Self::Other(v),
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Connect => 0xdb,
+            Self::Release => 0xdc,
+            Self::Control => 0xdd,
+            Self::Read => 0xde,
+            Self::Write => 0xdf,
+            Self::Other(v) => *v,
+        }
+    }
+}
+
+impl fmt::Display for PnioService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect => write!(f, "IODConnectRes"),
+            Self::Release => write!(f, "IODReleaseRes"),
+            Self::Control => write!(f, "IODControlRes"),
+            Self::Read => write!(f, "IODReadRes"),
+            Self::Write => write!(f, "IODWriteRes"),
+            Self::Other(v) => write!(f, "error code {v:#04x}"),
+        }
+    }
+}
+
+/// PnioRwErrorCode is the ErrorCode1 of a record read or write, i.e. error
+/// class in the high nibble and error code in the low nibble
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PnioRwErrorCode {
+    // application
+    ReadError,
+    WriteError,
+    ModuleFailure,
+    Busy,
+    VersionConflict,
+    FeatureNotSupported,
+    // access
+    InvalidIndex,
+    WriteLengthError,
+    InvalidSlotSubslot,
+    TypeConflict,
+    InvalidArea,
+    StateConflict,
+    AccessDenied,
+    InvalidRange,
+    InvalidParameter,
+    InvalidType,
+    Backup,
+    // resource
+    ReadConstrainConflict,
+    WriteConstrainConflict,
+    ResourceBusy,
+    ResourceUnavailable,
+    UserSpecific(u8),
+    Other(u8),
+}
+
+impl PnioRwErrorCode {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0xa0 => Self::ReadError,
+            0xa1 => Self::WriteError,
+            0xa2 => Self::ModuleFailure,
+            0xa7 => Self::Busy,
+            0xa8 => Self::VersionConflict,
+            0xa9 => Self::FeatureNotSupported,
+            0xb0 => Self::InvalidIndex,
+            0xb1 => Self::WriteLengthError,
+            0xb2 => Self::InvalidSlotSubslot,
+            0xb3 => Self::TypeConflict,
+            0xb4 => Self::InvalidArea,
+            0xb5 => Self::StateConflict,
+            0xb6 => Self::AccessDenied,
+            0xb7 => Self::InvalidRange,
+            0xb8 => Self::InvalidParameter,
+            0xb9 => Self::InvalidType,
+            0xba => Self::Backup,
+            0xc0 => Self::ReadConstrainConflict,
+            0xc1 => Self::WriteConstrainConflict,
+            0xc2 => Self::ResourceBusy,
+            0xc3 => Self::ResourceUnavailable,
+            0xaa..=0xaf | 0xbb..=0xbf | 0xc8..=0xcf => Self::UserSpecific(value),
+            v => Self::Other(v),
+        }
+    }
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            Self::ReadError => "application: read error",
+            Self::WriteError => "application: write error",
+            Self::ModuleFailure => "application: module failure",
+            Self::Busy => "application: busy",
+            Self::VersionConflict => "application: version conflict",
+            Self::FeatureNotSupported => "application: feature not supported",
+            Self::InvalidIndex => "access: invalid index",
+            Self::WriteLengthError => "access: write length error",
+            Self::InvalidSlotSubslot => "access: invalid slot/subslot",
+            Self::TypeConflict => "access: type conflict",
+            Self::InvalidArea => "access: invalid area/api",
+            Self::StateConflict => "access: state conflict",
+            Self::AccessDenied => "access: access denied",
+            Self::InvalidRange => "access: invalid range",
+            Self::InvalidParameter => "access: invalid parameter",
+            Self::InvalidType => "access: invalid type",
+            Self::Backup => "access: backup",
+            Self::ReadConstrainConflict => "resource: read constrain conflict",
+            Self::WriteConstrainConflict => "resource: write constrain conflict",
+            Self::ResourceBusy => "resource: resource busy",
+            Self::ResourceUnavailable => "resource: resource unavailable",
+            Self::UserSpecific(_) => "user specific",
+            Self::Other(_) => "reserved",
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::ReadError => 0xa0,
+            Self::WriteError => 0xa1,
+            Self::ModuleFailure => 0xa2,
+            Self::Busy => 0xa7,
+            Self::VersionConflict => 0xa8,
+            Self::FeatureNotSupported => 0xa9,
+            Self::InvalidIndex => 0xb0,
+            Self::WriteLengthError => 0xb1,
+            Self::InvalidSlotSubslot => 0xb2,
+            Self::TypeConflict => 0xb3,
+            Self::InvalidArea => 0xb4,
+            Self::StateConflict => 0xb5,
+            Self::AccessDenied => 0xb6,
+            Self::InvalidRange => 0xb7,
+            Self::InvalidParameter => 0xb8,
+            Self::InvalidType => 0xb9,
+            Self::Backup => 0xba,
+            Self::ReadConstrainConflict => 0xc0,
+            Self::WriteConstrainConflict => 0xc1,
+            Self::ResourceBusy => 0xc2,
+            Self::ResourceUnavailable => 0xc3,
+            Self::UserSpecific(v) | Self::Other(v) => *v,
+        }
+    }
+}
+
+/// PnioError is a PNIO status other than ok, decoded by its ErrorDecode
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PnioError {
+    /// record read or write failed, ErrorCode2 is user specific
+    ReadWrite {
+        service: PnioService,
+        code: PnioRwErrorCode,
+        error_code2: u8,
+    },
+    /// connect, release or control failed, ErrorCode1 tells the faulty block
+    /// or the protocol machine, ErrorCode2 the reason
+    Pnio {
+        service: PnioService,
+        error_code1: u8,
+        error_code2: u8,
+    },
+    ManufacturerSpecific {
+        service: PnioService,
+        error_code1: u8,
+        error_code2: u8,
+    },
+    Unknown([u8; 4]),
+}
+
+impl PnioError {
+    /// from_status decodes the PNIO status, `None` if the status is ok
+    pub fn from_status(status: [u8; 4]) -> Option<Self> {
+        if status == PNIO_STATUS_OK {
+            return None;
+        }
+
+        let [error_code, error_decode, error_code1, error_code2] = status;
+        let service = PnioService::from_u8(error_code);
+        let error = match error_decode {
+            ERROR_DECODE_PNIORW => Self::ReadWrite {
+                service,
+                code: PnioRwErrorCode::from_u8(error_code1),
+                error_code2,
+            },
+            ERROR_DECODE_PNIO => Self::Pnio {
+                service,
+                error_code1,
+                error_code2,
+            },
+            ERROR_DECODE_MANUFACTURER_SPECIFIC => Self::ManufacturerSpecific {
+                service,
+                error_code1,
+                error_code2,
+            },
+            _ => Self::Unknown(status),
+        };
+
+        Some(error)
+    }
+
+    pub fn status(&self) -> [u8; 4] {
+        match *self {
+            Self::ReadWrite {
+                service,
+                code,
+                error_code2,
+            } => [
+                service.code(),
+                ERROR_DECODE_PNIORW,
+                code.code(),
+                error_code2,
+            ],
+            Self::Pnio {
+                service,
+                error_code1,
+                error_code2,
+            } => [service.code(), ERROR_DECODE_PNIO, error_code1, error_code2],
+            Self::ManufacturerSpecific {
+                service,
+                error_code1,
+                error_code2,
+            } => [
+                service.code(),
+                ERROR_DECODE_MANUFACTURER_SPECIFIC,
+                error_code1,
+                error_code2,
+            ],
+            Self::Unknown(status) => status,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        match self {
+            Self::ReadWrite { code, .. } => code.text().to_string(),
+            Self::Pnio {
+                error_code1,
+                error_code2,
+                ..
+            } => format!("PNIO error {error_code1:#04x}, reason {error_code2:#04x}"),
+            Self::ManufacturerSpecific { .. } => "manufacturer specific".to_string(),
+            Self::Unknown(_) => "unknown error decode".to_string(),
+        }
+    }
+
+    /// is_ar_unknown tells if the station does not know the AR of the request,
+    /// e.g. it restarted, the AR has to be established again
+    pub fn is_ar_unknown(&self) -> bool {
+        matches!(
+            self,
+            Self::Pnio {
+                error_code1: ERROR_CODE1_CMRPC,
+                error_code2: ERROR_CODE2_AR_UUID_UNKNOWN,
+                ..
+            }
+        )
+    }
+
+    /// is_retryable tells if the same request may succeed later on, e.g. the
+    /// station is busy, while an invalid index will fail whenever it's sent
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::ReadWrite {
+                code: PnioRwErrorCode::Busy
+                    | PnioRwErrorCode::ResourceBusy
+                    | PnioRwErrorCode::ResourceUnavailable,
+                ..
+            }
+        )
+    }
+}
+
+impl fmt::Display for PnioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let service = match self {
+            Self::ReadWrite { service, .. }
+            | Self::Pnio { service, .. }
+            | Self::ManufacturerSpecific { service, .. } => service.to_string(),
+            Self::Unknown(_) => "PNIO".to_string(),
+        };
+        write!(
+            f,
+            "{service}: {} (status {})",
+            self.text(),
+            hex::encode(self.status())
+        )
+    }
+}
+
+impl std::error::Error for PnioError {}
+
+impl Serialize for PnioError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("PnioError", 3)?;
+        s.serialize_field("status", &hex::encode(self.status()))?;
+        s.serialize_field("text", &self.to_string())?;
+        s.serialize_field("retryable", &self.is_retryable())?;
+        s.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_status_should_decode_read_write_error() {
+        assert_eq!(PnioError::from_status([0x00; 4]), None);
+
+        let error = PnioError::from_status([0xde, 0x80, 0xb0, 0x00]).unwrap();
+        assert_eq!(
+            error,
+            PnioError::ReadWrite {
+                service: PnioService::Read,
+                code: PnioRwErrorCode::InvalidIndex,
+                error_code2: 0x00,
+            }
+        );
+        assert!(!error.is_retryable());
+        assert_eq!(
+            error.to_string(),
+            "IODReadRes: access: invalid index (status de80b000)"
+        );
+
+        let error = PnioError::from_status([0xdf, 0x80, 0xc2, 0x00]).unwrap();
+        assert!(error.is_retryable());
+        assert_eq!(error.status(), [0xdf, 0x80, 0xc2, 0x00]);
+    }
+
+    #[test]
+    fn from_status_should_decode_other_error_decode() {
+        let error = PnioError::from_status([0xdb, 0x81, 0x40, 0x05]).unwrap();
+        assert_eq!(
+            error,
+            PnioError::Pnio {
+                service: PnioService::Connect,
+                error_code1: 0x40,
+                error_code2: 0x05,
+            }
+        );
+        assert!(error.is_ar_unknown());
+
+        let error = PnioError::from_status([0xde, 0x12, 0x34, 0x56]).unwrap();
+        assert_eq!(error, PnioError::Unknown([0xde, 0x12, 0x34, 0x56]));
+        assert_eq!(error.status(), [0xde, 0x12, 0x34, 0x56]);
+
+        assert_eq!(
+            serde_json::to_string(&PnioError::from_status([0xde, 0x80, 0xa7, 0x01])).unwrap(),
+            r#"{"status":"de80a701","text":"IODReadRes: application: busy (status de80a701)","retryable":true}"#
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{self, Deserialize, Serialize};
+use uuid::{uuid, Uuid};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SimulatorConfig {
+    /// bind_address is the udp socket the simulator listens on, both the endpoint
+    /// mapper lookup and the subsequent PNIO requests are served on it
+    pub bind_address: String,
+    /// interfaces are the endpoint mapper entries returned to the lookup,
+    /// in the order they are enumerated
+    pub interfaces: Vec<SimulatedInterface>,
+    pub channels: Vec<SimulatedChannel>,
+    /// records are served as they are to record reads, e.g. the I&M records
+    #[serde(default)]
+    pub records: Vec<SimulatedRecord>,
+}
+
+impl SimulatorConfig {
+    pub fn deserialize(content: &str) -> anyhow::Result<Self> {
+        let config = serde_yaml::from_str::<Self>(content)?;
+        Ok(config)
+    }
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: "127.0.0.1:34964".to_string(),
+            interfaces: vec![Default::default()],
+            channels: vec![Default::default()],
+            records: vec![],
+        }
+    }
+}
+
+/// SimulatedInterface is a single tower registered in the endpoint mapper.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SimulatedInterface {
+    pub object_uuid: Uuid,
+    pub interface_uuid: Uuid,
+    /// annotation is padded with spaces to 63 characters and null terminated,
+    /// for example `ET200SP  6ES7 155-6AU01-0BN0  4 V  4  2  0`
+    pub annotation: String,
+    /// udp_port is the port announced in the tower, the simulator's own port
+    /// is announced if not set
+    #[serde(default)]
+    pub udp_port: Option<u16>,
+}
+
+impl Default for SimulatedInterface {
+    fn default() -> Self {
+        Self {
+            object_uuid: uuid!("dea00000-6c97-11d1-8271-00010313002a"),
+            interface_uuid: uuid!("dea00001-6c97-11d1-8271-00a02442df7d"),
+            annotation: "ET200SP                   6ES7 155-6AU01-0BN0   4 V  4  2  0".to_string(),
+            udp_port: None,
+        }
+    }
+}
+
+/// SimulatedChannel is a HART field device connected to a channel of an AI module.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SimulatedChannel {
+    pub slot_number: u16,
+    pub subslot_number: u16,
+    pub request_data_record_number: u16,
+    pub response_data_record_number: u16,
+    /// command0_data is the command 0 response data without the statuses,
+    /// the long address is taken from it
+    pub command0_data: Vec<u8>,
+    /// data_ready_delay is the number of reads answered with "data not ready"
+    /// after each HART request
+    #[serde(default)]
+    pub data_ready_delay: u8,
+    #[serde(default)]
+    pub commands: Vec<SimulatedHartCommand>,
+    /// polling_address is the short address answering command 0
+    #[serde(default)]
+    pub polling_address: u8,
+    /// tag and long_tag are matched by command 11 and 21 respectively
+    #[serde(default)]
+    pub tag: String,
+    #[serde(default)]
+    pub long_tag: String,
+}
+
+impl SimulatedChannel {
+    /// long_address is the 5 bytes unique identifier derived from command 0,
+    /// i.e. manufacturer id, device type and the 3 bytes device id.
+    pub fn long_address(&self) -> [u8; 5] {
+        let byte = |i: usize| self.command0_data.get(i).copied().unwrap_or_default();
+        [byte(1), byte(2), byte(9), byte(10), byte(11)]
+    }
+}
+
+impl Default for SimulatedChannel {
+    fn default() -> Self {
+        Self {
+            slot_number: 1,
+            subslot_number: 1,
+            request_data_record_number: 80,
+            response_data_record_number: 81,
+            // SITRANS P DS III captured response
+            command0_data: vec![
+                0xfe, 0x2a, 0x0b, 0x05, 0x05, 0x03, 0x06, 0x38, 0x00, 0x3f, 0xcc, 0x78, 0x05, 0x0c,
+                0x02, 0x69, 0x00,
+            ],
+            data_ready_delay: 0,
+            commands: vec![],
+            polling_address: 0,
+            tag: String::new(),
+            long_tag: String::new(),
+        }
+    }
+}
+
+/// SimulatedHartCommand holds the scripted responses of a HART command, the
+/// responses are served one after another and the last one is repeated.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SimulatedHartCommand {
+    pub number: u16,
+    /// responses are the status bytes followed by the command specific data
+    pub responses: Vec<Vec<u8>>,
+}
+
+/// SimulatedRecord is a record of a slot/subslot with fixed content.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SimulatedRecord {
+    pub slot_number: u16,
+    pub subslot_number: u16,
+    pub index: u16,
+    pub data: Vec<u8>,
+}
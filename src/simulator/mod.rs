@@ -0,0 +1,831 @@
+mod config;
+mod server;
+mod station;
+
+pub use config::*;
+pub use server::Simulator;
+pub use station::{SimulatorFault, Station};
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        client::{sender::Sender, worker::Worker},
+        config::{Config, ConfigHartDevice, HartCommand, WritePolicy},
+        device::health::DeviceHealth,
+        hart::{HartAddressing, HartWrite},
+    };
+    use std::{
+        net::Ipv4Addr,
+        sync::{Mutex, RwLock},
+        time::Duration,
+    };
+
+    struct InMemorySender {
+        config: RwLock<Vec<Config>>,
+        messages: Mutex<Vec<String>>,
+    }
+
+    impl Sender for InMemorySender {
+        fn setup(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn send(&self, data: String) -> anyhow::Result<()> {
+            self.messages.lock().unwrap().push(data);
+            Ok(())
+        }
+
+        fn get_config(&self) -> &RwLock<Vec<Config>> {
+            &self.config
+        }
+    }
+
+    fn get_simulator_config() -> SimulatorConfig {
+        SimulatorConfig {
+            bind_address: "127.0.0.1:0".to_string(),
+            interfaces: vec![Default::default()],
+            channels: vec![SimulatedChannel {
+                commands: vec![SimulatedHartCommand {
+                    number: 9,
+                    // statuses, extended device status, device variable code,
+                    // classification, unit, value 1.5, variable status, timestamp
+                    responses: vec![vec![
+                        0x00, 0x00, 0x00, 0x00, 0x41, 0x08, 0x3f, 0xc0, 0x00, 0x00, 0xc0, 0x00,
+                        0x00, 0x00, 0x00,
+                    ]],
+                }],
+                ..Default::default()
+            }],
+            records: vec![],
+        }
+    }
+
+    fn get_config(port: u16) -> Config {
+        Config {
+            ip_address: "127.0.0.1".to_string(),
+            name_of_station: None,
+            port,
+            device_name: "6ES7 155-6AU01-0BN0".to_string(),
+            hart_devices: vec![ConfigHartDevice {
+                slot_number: 1,
+                subslot_number: 1,
+                hart_commands: vec![HartCommand {
+                    number: 9,
+                    data: Some(Box::new([0x00])),
+                }],
+                request_data_record_number: 80,
+                response_data_record_number: 81,
+                hart_device_name: "7MF4233-1FA10-2AB6-Z".to_string(),
+                hart_writes: vec![],
+                write_policy: WritePolicy::default(),
+                addressing: HartAddressing::default(),
+            }],
+            capture: false,
+        }
+    }
+
+    #[test]
+    fn worker_should_poll_simulated_station() {
+        let simulator = Simulator::start(get_simulator_config()).unwrap();
+        let sender = InMemorySender {
+            config: RwLock::new(vec![get_config(simulator.local_addr().port())]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::LOCALHOST);
+        assert_eq!(worker.store.len(), 1);
+
+        // first cycle obtains the long address through command 0
+        worker.read();
+        // second cycle polls the configured commands
+        worker.read();
+
+        let messages = sender.messages.lock().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains(r#""hart_command":9"#));
+        assert!(messages[0].contains(r#""value":1.5"#));
+    }
+
+    #[test]
+    fn worker_should_poll_stations_concurrently() {
+        let simulators: Vec<Simulator> = [8, 9]
+            .iter()
+            .map(|i| {
+                Simulator::start(SimulatorConfig {
+                    bind_address: format!("127.0.0.{i}:0"),
+                    ..get_simulator_config()
+                })
+                .unwrap()
+            })
+            .collect();
+        let sender = InMemorySender {
+            config: RwLock::new(
+                simulators
+                    .iter()
+                    .map(|s| Config {
+                        ip_address: s.local_addr().ip().to_string(),
+                        ..get_config(s.local_addr().port())
+                    })
+                    .collect(),
+            ),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.set_concurrency(2);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 8));
+        worker.read();
+        let stats = worker.read();
+
+        assert_eq!(stats.devices, 2);
+        assert_eq!(stats.stations.len(), 2);
+        assert!(stats.stations.iter().all(|(_, d)| *d <= stats.duration));
+        assert!(stats.slowest_station().is_some());
+        assert_eq!(sender.messages.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn worker_should_poll_stations_concurrently_with_slow_output() {
+        // an output taking its time for every message, e.g. waiting for the
+        // acknowledgement of the broker
+        struct SlowSender {
+            sender: InMemorySender,
+            delay: Duration,
+        }
+
+        impl Sender for SlowSender {
+            fn setup(&self) -> anyhow::Result<()> {
+                Ok(())
+            }
+
+            fn send(&self, data: String) -> anyhow::Result<()> {
+                std::thread::sleep(self.delay);
+                self.sender.send(data)
+            }
+
+            fn get_config(&self) -> &RwLock<Vec<Config>> {
+                self.sender.get_config()
+            }
+        }
+
+        let simulators: Vec<Simulator> = [21, 22]
+            .iter()
+            .map(|i| {
+                Simulator::start(SimulatorConfig {
+                    bind_address: format!("127.0.0.{i}:0"),
+                    ..get_simulator_config()
+                })
+                .unwrap()
+            })
+            .collect();
+        let sender = SlowSender {
+            sender: InMemorySender {
+                config: RwLock::new(
+                    simulators
+                        .iter()
+                        .map(|s| Config {
+                            ip_address: s.local_addr().ip().to_string(),
+                            ..get_config(s.local_addr().port())
+                        })
+                        .collect(),
+                ),
+                messages: Mutex::new(vec![]),
+            },
+            delay: Duration::from_millis(500),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.set_concurrency(2);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 21));
+        worker.read();
+        let stats = worker.read();
+
+        // the stations are polled while the messages are sent, neither waits
+        // for the output
+        assert_eq!(stats.stations.len(), 2);
+        assert!(stats.stations.iter().all(|(_, d)| *d < sender.delay));
+        // the cycle ends once the messages are out
+        assert!(stats.duration >= 2 * sender.delay);
+        assert_eq!(sender.sender.messages.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn worker_should_retry_busy_station_and_egress_pnio_errors() {
+        let simulator = Simulator::start(get_simulator_config()).unwrap();
+        let sender = InMemorySender {
+            config: RwLock::new(vec![get_config(simulator.local_addr().port())]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 3));
+        worker.read();
+
+        // "resource busy" is written again, the command gets through
+        simulator.inject_fault(SimulatorFault::PnioStatus([0xdf, 0x80, 0xc2, 0x00]));
+        worker.read();
+        // "invalid index" is not
+        simulator.inject_fault(SimulatorFault::PnioStatus([0xdf, 0x80, 0xb0, 0x00]));
+        let requests_received = simulator.requests_received();
+        worker.read();
+        assert_eq!(simulator.requests_received(), requests_received + 1);
+
+        let messages = sender.messages.lock().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains(r#""value":1.5"#));
+        assert!(messages[1].contains(r#""status":"df80b000""#));
+        assert!(messages[1].contains("IODWriteRes: access: invalid index"));
+    }
+
+    #[test]
+    fn worker_should_retry_busy_device_and_egress_hart_errors() {
+        let simulator = Simulator::start(get_simulator_config()).unwrap();
+        let sender = InMemorySender {
+            config: RwLock::new(vec![get_config(simulator.local_addr().port())]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 15));
+        worker.read();
+
+        // "busy" is sent again, the command gets through
+        simulator.inject_fault(SimulatorFault::HartResponseCode(0x20));
+        worker.read();
+        // "command not implemented" is not
+        simulator.inject_fault(SimulatorFault::HartResponseCode(0x40));
+        worker.read();
+        let health = |worker: &Worker| worker.store.values().next().unwrap().0.health.get();
+        assert_eq!(health(&worker), DeviceHealth::Degraded);
+        worker.read();
+        assert_eq!(health(&worker), DeviceHealth::Healthy);
+
+        let messages = sender.messages.lock().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].contains(r#""value":1.5"#));
+        assert!(messages[1].contains(r#""health":"degraded""#));
+        assert!(messages[1].contains(r#""kind":"hart""#));
+        assert!(messages[1].contains(r#""response_code":64"#));
+        assert!(messages[2].contains(r#""value":1.5"#));
+    }
+
+    #[test]
+    fn worker_should_egress_decode_errors() {
+        let mut simulator_config = get_simulator_config();
+        // statuses and extended device status, no device variable
+        simulator_config.channels[0].commands[0].responses = vec![vec![0x00, 0x00, 0x00]];
+        let simulator = Simulator::start(simulator_config).unwrap();
+        let sender = InMemorySender {
+            config: RwLock::new(vec![get_config(simulator.local_addr().port())]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 15));
+        // command 0, then command 9
+        worker.read();
+        worker.read();
+
+        let messages = sender.messages.lock().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains(r#""kind":"decode""#));
+        assert!(messages[0].contains("device variable is missing"));
+    }
+
+    #[test]
+    fn worker_should_send_allowed_writes_once_and_audit_them() {
+        let mut simulator_config = get_simulator_config();
+        simulator_config.channels[0].commands.extend([
+            SimulatedHartCommand {
+                number: 34,
+                // statuses, damping 2.0 echoed
+                responses: vec![vec![0x00, 0x00, 0x40, 0x00, 0x00, 0x00]],
+            },
+            SimulatedHartCommand {
+                number: 44,
+                // write protected
+                responses: vec![vec![0x07, 0x00]],
+            },
+            SimulatedHartCommand {
+                number: 40,
+                // statuses, current 16.0 mA echoed
+                responses: vec![vec![0x00, 0x00, 0x41, 0x80, 0x00, 0x00]],
+            },
+        ]);
+        let simulator = Simulator::start(simulator_config).unwrap();
+        let mut config = get_config(simulator.local_addr().port());
+        let hart_device = &mut config.hart_devices[0];
+        hart_device.hart_writes = vec![
+            HartWrite::damping(2.0).unwrap(),
+            HartWrite::pv_units(12).unwrap(),
+            HartWrite::range_values(12, 100.0, 0.0).unwrap(),
+        ];
+        hart_device.write_policy = WritePolicy {
+            allowed_commands: vec![34, 40, 44],
+            ..Default::default()
+        };
+        // enter fixed current mode, not a write of the configuration but not
+        // a read either
+        hart_device.hart_commands.push(HartCommand {
+            number: 40,
+            data: Some(Box::new([0x41, 0x80, 0x00, 0x00])),
+        });
+        let sender = InMemorySender {
+            config: RwLock::new(vec![config]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 16));
+        // command 0, then the writes and the commands
+        worker.read();
+        worker.read();
+        // the writes are sent once
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 16));
+        worker.read();
+
+        let messages = sender.messages.lock().unwrap();
+        assert_eq!(messages.len(), 6);
+        assert!(messages[0].contains(r#""hart_command":34"#));
+        assert!(messages[0].contains(r#""write":"damping""#));
+        assert!(messages[0].contains(r#""data":"40000000""#));
+        assert!(messages[0].contains(r#""outcome":"written""#));
+        assert!(messages[1].contains(r#""outcome":"rejected""#));
+        assert!(messages[1].contains(r#""response_code":7"#));
+        assert!(messages[2].contains(r#""write":"range_values""#));
+        assert!(messages[2].contains(r#""outcome":"denied""#));
+        // the raw write follows the typed ones and is sent once as well
+        assert!(messages[3].contains(r#""hart_command":40"#));
+        assert!(messages[3].contains(r#""data":"41800000""#));
+        assert!(messages[3].contains(r#""outcome":"written""#));
+        assert!(!messages[3].contains(r#""write""#));
+        assert!(messages[4].contains(r#""value":1.5"#));
+        assert!(messages[5].contains(r#""value":1.5"#));
+        assert_eq!(
+            worker.store.values().next().unwrap().0.health.get(),
+            DeviceHealth::Healthy
+        );
+    }
+
+    #[test]
+    fn worker_should_not_send_commands_again_to_silent_device() {
+        let mut simulator_config = get_simulator_config();
+        simulator_config.channels[0]
+            .commands
+            .push(SimulatedHartCommand {
+                number: 34,
+                // statuses, damping 2.0 echoed
+                responses: vec![vec![0x00, 0x00, 0x40, 0x00, 0x00, 0x00]],
+            });
+        let simulator = Simulator::start(simulator_config).unwrap();
+        let mut config = get_config(simulator.local_addr().port());
+        config.hart_devices[0].hart_writes = vec![HartWrite::damping(2.0).unwrap()];
+        config.hart_devices[0].write_policy = WritePolicy {
+            allowed_commands: vec![34],
+            ..Default::default()
+        };
+        let sender = InMemorySender {
+            config: RwLock::new(vec![config]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::LOCALHOST);
+        for (pnio_device, _) in worker.store.values_mut() {
+            pnio_device.data_ready_interval = Duration::from_millis(1);
+        }
+        // command 0
+        worker.read();
+
+        // the write is left without response, it's sent once and the
+        // commands polled are not sent after it
+        simulator.inject_fault(SimulatorFault::NoHartResponse);
+        let requests_received = simulator.requests_received();
+        worker.read();
+        // the write of the request record and the reads of the response record
+        assert_eq!(simulator.requests_received(), requests_received + 11);
+        let health = |worker: &Worker| worker.store.values().next().unwrap().0.health.get();
+        assert_eq!(health(&worker), DeviceHealth::Unreachable);
+
+        // the device answers again, the write may have been executed and is
+        // not sent again
+        worker.read();
+        assert_eq!(health(&worker), DeviceHealth::Healthy);
+
+        let messages = sender.messages.lock().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains(r#""outcome":"failed""#));
+        assert!(messages[0].contains("HART command 34: no response from the device"));
+        assert!(messages[1].contains(r#""value":1.5"#));
+    }
+
+    #[test]
+    fn worker_should_find_devices_by_polling_address_and_tag() {
+        let channel = get_simulator_config().channels.remove(0);
+        let channel =
+            |subslot_number: u16, polling_address, tag: &str, long_tag: &str| SimulatedChannel {
+                subslot_number,
+                request_data_record_number: 78 + 2 * subslot_number,
+                response_data_record_number: 79 + 2 * subslot_number,
+                polling_address,
+                tag: tag.to_string(),
+                long_tag: long_tag.to_string(),
+                ..channel.clone()
+            };
+        let simulator = Simulator::start(SimulatorConfig {
+            channels: vec![
+                channel(1, 5, "", ""),
+                channel(2, 0, "PT-101", ""),
+                channel(3, 0, "", "inlet pressure"),
+            ],
+            ..get_simulator_config()
+        })
+        .unwrap();
+        let mut config = get_config(simulator.local_addr().port());
+        let hart_device = config.hart_devices.remove(0);
+        let hart_device = |subslot_number: u16, addressing| ConfigHartDevice {
+            subslot_number,
+            request_data_record_number: 78 + 2 * subslot_number,
+            response_data_record_number: 79 + 2 * subslot_number,
+            addressing,
+            ..hart_device.clone()
+        };
+        config.hart_devices = vec![
+            hart_device(1, HartAddressing::PollingAddress { polling_address: 5 }),
+            hart_device(
+                2,
+                HartAddressing::Tag {
+                    tag: "PT-101".to_string(),
+                },
+            ),
+            hart_device(
+                3,
+                HartAddressing::LongTag {
+                    long_tag: "inlet pressure".to_string(),
+                },
+            ),
+        ];
+        let sender = InMemorySender {
+            config: RwLock::new(vec![config]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 17));
+        worker.read();
+        // all the devices are addressed by their unique identifier
+        assert!(worker
+            .store
+            .values()
+            .all(|(pnio_device, _)| *pnio_device.device_id.borrow()
+                == [0x2a, 0x0b, 0x3f, 0xcc, 0x78]));
+        worker.read();
+
+        let messages = sender.messages.lock().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().all(|m| m.contains(r#""value":1.5"#)));
+    }
+
+    #[test]
+    fn worker_should_poll_extended_commands_through_command_31() {
+        let mut simulator_config = get_simulator_config();
+        simulator_config.channels[0]
+            .commands
+            .push(SimulatedHartCommand {
+                number: 1024,
+                responses: vec![vec![0x00, 0x00, 0x01, 0x02]],
+            });
+        let simulator = Simulator::start(simulator_config).unwrap();
+        let mut config = get_config(simulator.local_addr().port());
+        // the device does not implement command 1025
+        config.hart_devices[0].hart_commands = [1024, 1025]
+            .map(|number| HartCommand { number, data: None })
+            .to_vec();
+        config.hart_devices[0].write_policy.read_commands = vec![1024, 1025];
+        let sender = InMemorySender {
+            config: RwLock::new(vec![config]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 18));
+        worker.read();
+        worker.read();
+
+        let messages = sender.messages.lock().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains(r#""hart_command":1024"#));
+        assert!(messages[0].contains(r#""bytes":[1,2]"#));
+        assert!(messages[1].contains(r#""hart_command":1025"#));
+        assert!(messages[1].contains(r#""response_code":64"#));
+    }
+
+    #[test]
+    fn worker_should_release_ar() {
+        let simulator = Simulator::start(get_simulator_config()).unwrap();
+        let sender = InMemorySender {
+            config: RwLock::new(vec![get_config(simulator.local_addr().port())]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 4));
+        let ar_uuid = simulator.ar_uuid().unwrap();
+
+        // reconnecting releases the AR established before
+        let (pnio_device, _) = worker.store.values().next().unwrap();
+        worker.block_on(pnio_device.session.connect_req()).unwrap();
+        assert_ne!(simulator.ar_uuid().unwrap(), ar_uuid);
+        assert_eq!(
+            simulator.ar_uuid(),
+            Some(*pnio_device.session.ar_uuid.borrow())
+        );
+
+        // the device removed from the config is released
+        sender.config.write().unwrap().clear();
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 4));
+        assert!(worker.store.is_empty());
+        assert!(worker.sessions.is_empty());
+        assert_eq!(simulator.ar_uuid(), None);
+    }
+
+    #[test]
+    fn worker_should_not_connect_when_station_refuses_ar() {
+        let simulator = Simulator::start(get_simulator_config()).unwrap();
+        let sender = InMemorySender {
+            config: RwLock::new(vec![get_config(simulator.local_addr().port())]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        simulator.inject_fault(SimulatorFault::PnioStatus([0xdb, 0x81, 0x40, 0x04]));
+        worker.evaluate(Ipv4Addr::LOCALHOST);
+        assert!(worker.store.is_empty());
+        assert!(worker.sessions.is_empty());
+        assert_eq!(simulator.ar_uuid(), None);
+
+        // the next evaluation connects again
+        worker.evaluate(Ipv4Addr::LOCALHOST);
+        assert_eq!(worker.store.len(), 1);
+        assert!(simulator.ar_uuid().is_some());
+    }
+
+    #[test]
+    fn worker_should_reconnect_restarted_station() {
+        let simulator = Simulator::start(get_simulator_config()).unwrap();
+        let sender = InMemorySender {
+            config: RwLock::new(vec![get_config(simulator.local_addr().port())]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::LOCALHOST);
+        worker.read();
+        worker.read();
+
+        // the station answers with "AR UUID unknown" after its restart
+        simulator.restart();
+        worker.read();
+        let session = worker.sessions.values().next().unwrap().clone();
+        assert!(session.is_stale());
+        // the station is not polled until it's connected again
+        let requests_received = simulator.requests_received();
+        worker.read();
+        assert_eq!(simulator.requests_received(), requests_received);
+
+        worker.evaluate(Ipv4Addr::LOCALHOST);
+        assert!(!session.is_stale());
+        assert_eq!(simulator.ar_uuid(), Some(*session.ar_uuid.borrow()));
+        worker.read();
+
+        let messages = sender.messages.lock().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].contains(r#""value":1.5"#));
+        assert!(messages[1].contains(r#""status":"df814005""#));
+        assert!(messages[2].contains(r#""value":1.5"#));
+    }
+
+    #[test]
+    fn worker_should_share_station_session_across_channels() {
+        let channel = get_simulator_config().channels.remove(0);
+        let simulator = Simulator::start(SimulatorConfig {
+            bind_address: "127.0.0.10:0".to_string(),
+            channels: vec![
+                channel.clone(),
+                SimulatedChannel {
+                    subslot_number: 2,
+                    request_data_record_number: 82,
+                    response_data_record_number: 83,
+                    ..channel
+                },
+            ],
+            ..get_simulator_config()
+        })
+        .unwrap();
+        let mut config = Config {
+            ip_address: "127.0.0.10".to_string(),
+            ..get_config(simulator.local_addr().port())
+        };
+        config.hart_devices.push(ConfigHartDevice {
+            subslot_number: 2,
+            request_data_record_number: 82,
+            response_data_record_number: 83,
+            ..config.hart_devices[0].clone()
+        });
+        let sender = InMemorySender {
+            config: RwLock::new(vec![config]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 10));
+        assert_eq!(worker.store.len(), 2);
+        assert_eq!(worker.sessions.len(), 1);
+
+        // both channels are polled within the single AR of the station
+        let session = worker.sessions.get("127.0.0.10").unwrap().clone();
+        assert!(worker
+            .store
+            .values()
+            .all(|(pnio_device, _)| std::rc::Rc::ptr_eq(&pnio_device.session, &session)));
+        assert_eq!(simulator.ar_uuid(), Some(*session.ar_uuid.borrow()));
+        worker.read();
+        worker.read();
+        assert_eq!(sender.messages.lock().unwrap().len(), 2);
+
+        // the station is released with its last channel only
+        sender.config.write().unwrap()[0].hart_devices.pop();
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 10));
+        assert_eq!(worker.store.len(), 1);
+        assert!(simulator.ar_uuid().is_some());
+        drop(session);
+        sender.config.write().unwrap()[0].hart_devices.clear();
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 10));
+        assert!(worker.sessions.is_empty());
+        assert_eq!(simulator.ar_uuid(), None);
+    }
+
+    #[test]
+    fn worker_should_egress_identification() {
+        // I&M0 of the interface module telling that I&M1 is supported
+        let mut im0 = vec![0x00, 0x20, 0x00, 0x38, 0x01, 0x00, 0x00, 0x2a];
+        im0.extend(format!("{:<20}{:<16}", "6ES7 155-6AU01-0BN0", "S C-J9UE6092").as_bytes());
+        im0.extend([
+            0x00, 0x03, b'V', 4, 2, 0, 0, 1, 0xf6, 0, 0, 4, 1, 1, 0x00, 0x02,
+        ]);
+        let mut im1 = vec![0x00, 0x21, 0x00, 0x38, 0x01, 0x00];
+        im1.extend(format!("{:<32}{:<22}", "PT-4711", "tank 3").as_bytes());
+        let record = |index: u16, data: &Vec<u8>| SimulatedRecord {
+            slot_number: 0,
+            subslot_number: 1,
+            index,
+            data: data.clone(),
+        };
+
+        let simulator = Simulator::start(SimulatorConfig {
+            records: vec![record(0xaff0, &im0), record(0xaff1, &im1)],
+            ..get_simulator_config()
+        })
+        .unwrap();
+        let mut config = get_config(simulator.local_addr().port());
+        config.hart_devices.push(ConfigHartDevice {
+            subslot_number: 2,
+            ..config.hart_devices[0].clone()
+        });
+        let sender = InMemorySender {
+            config: RwLock::new(vec![config]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 6));
+        let requests_received = simulator.requests_received();
+        worker.identify();
+
+        // I&M0 and I&M1 of the station are read once, I&M0 of each module
+        assert_eq!(simulator.requests_received(), requests_received + 4);
+        // the module of slot 1 has no I&M records, only the station is sent
+        let messages = sender.messages.lock().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains(r#""order_id":"6ES7 155-6AU01-0BN0""#));
+        assert!(messages[0].contains(r#""tag_function":"PT-4711""#));
+        assert!(!messages[0].contains(r#""im2""#));
+        assert!(!messages[0].contains(r#""module""#));
+    }
+
+    #[test]
+    fn worker_should_resolve_name_of_station() {
+        use crate::{
+            client::dcp::DcpClient,
+            protocol::{DcpIdentifyRequest, DcpIdentifyResponse, DcpStation, Decode, Encode},
+            transport::MemoryFrameTransport,
+        };
+
+        let simulator = Simulator::start(SimulatorConfig {
+            bind_address: "127.0.0.7:0".to_string(),
+            ..get_simulator_config()
+        })
+        .unwrap();
+        // the station answers to its name with the simulator's address
+        let transport = MemoryFrameTransport::new(
+            [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+            Box::new(|frame| {
+                let request = DcpIdentifyRequest::from_bytes(frame).unwrap();
+                if request.name_of_station.as_deref() != Some("et200sp-1") {
+                    return vec![];
+                }
+                let station = DcpStation {
+                    mac_address: [0x28, 0x63, 0x36, 0x8a, 0x1f, 0x0c],
+                    name_of_station: "et200sp-1".to_string(),
+                    type_of_station: "ET200SP".to_string(),
+                    vendor_id: 0x002a,
+                    device_id: 0x0313,
+                    ip_address: Ipv4Addr::new(127, 0, 0, 7),
+                    subnet_mask: Ipv4Addr::new(255, 0, 0, 0),
+                    gateway: Ipv4Addr::UNSPECIFIED,
+                };
+                vec![DcpIdentifyResponse::new(&request, station)
+                    .to_bytes()
+                    .unwrap()]
+            }),
+        );
+        let sender = InMemorySender {
+            config: RwLock::new(vec![Config {
+                ip_address: String::new(),
+                name_of_station: Some("et200sp-1".to_string()),
+                ..get_config(simulator.local_addr().port())
+            }]),
+            messages: Mutex::new(vec![]),
+        };
+
+        let mut worker = Worker::new(&sender);
+        // there's no way to resolve the name without DCP
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 7));
+        assert!(worker.store.is_empty());
+
+        worker.set_dcp_client(DcpClient::new(Box::new(transport)));
+        worker.evaluate(Ipv4Addr::new(127, 0, 0, 7));
+        let (pnio_device, _) = worker.store.get("et200sp-1-1-1").unwrap();
+        assert_eq!(pnio_device.session.ip_address, Ipv4Addr::new(127, 0, 0, 7));
+        assert!(simulator.ar_uuid().is_some());
+    }
+
+    #[test]
+    fn station_should_apply_injected_faults() {
+        use crate::protocol::{
+            DceRpcEpmRequest, DceRpcPacket, Decode, Encode, InterfaceVersion, OpNum, PacketType,
+            INTERFACE,
+        };
+        use uuid::Uuid;
+
+        let mut station = Station::new(get_simulator_config(), 34964);
+        let lookup = DceRpcPacket::new(
+            PacketType::Request,
+            Uuid::nil(),
+            Uuid::parse_str(INTERFACE).unwrap(),
+            InterfaceVersion::Lookup,
+            Uuid::new_v4(),
+            0,
+            OpNum::Read,
+            DceRpcEpmRequest::new([0x00; 20])
+                .to_bytes()
+                .unwrap()
+                .into_boxed_slice(),
+        )
+        .to_bytes()
+        .unwrap();
+
+        station.inject_fault(SimulatorFault::DropResponse);
+        assert!(station.handle(&lookup).unwrap().is_none());
+
+        station.inject_fault(SimulatorFault::Reject(0x1c010003));
+        let reject = station.handle(&lookup).unwrap().unwrap();
+        assert_eq!(reject[1], PacketType::Reject as u8);
+        assert_eq!(reject[80..], 0x1c010003u32.to_le_bytes());
+
+        let response = station.handle(&lookup).unwrap().unwrap();
+        assert_eq!(response[1], PacketType::Response as u8);
+        assert_eq!(station.requests_received, 3);
+
+        // the held back response is sent after "working"
+        station.inject_fault(SimulatorFault::Working(1));
+        assert!(station.handle(&lookup).unwrap().is_none());
+        let ping = DceRpcPacket::from_bytes(&lookup)
+            .unwrap()
+            .ping()
+            .to_bytes()
+            .unwrap();
+        let working = station.handle(&ping).unwrap().unwrap();
+        assert_eq!(working[1], PacketType::Working as u8);
+        let response = station.handle(&ping).unwrap().unwrap();
+        assert_eq!(response[1], PacketType::Response as u8);
+
+        // a dropped request is unknown to the station
+        station.inject_fault(SimulatorFault::DropResponse);
+        assert!(station.handle(&lookup).unwrap().is_none());
+        let nocall = station.handle(&ping).unwrap().unwrap();
+        assert_eq!(nocall[1], PacketType::Nocall as u8);
+    }
+}
@@ -0,0 +1,128 @@
+use super::{
+    config::SimulatorConfig,
+    station::{SimulatorFault, Station},
+};
+use std::{
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// Simulator answers the DCE/RPC endpoint mapper lookup and the PNIO connect,
+/// release, record write and record read requests of a simulated IO station
+/// with HART field devices, so that the whole worker loop can run without
+/// hardware.
+pub struct Simulator {
+    local_addr: SocketAddr,
+    station: Arc<Mutex<Station>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Simulator {
+    const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+    pub fn start(config: SimulatorConfig) -> anyhow::Result<Self> {
+        let socket = UdpSocket::bind(config.bind_address.as_str())?;
+        socket.set_read_timeout(Some(Self::POLL_TIMEOUT))?;
+        let local_addr = socket.local_addr()?;
+
+        let station = Arc::new(Mutex::new(Station::new(config, local_addr.port())));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let station = station.clone();
+            let stop = stop.clone();
+            thread::spawn(move || Self::serve(socket, station, stop))
+        };
+
+        log::info!("simulator listening on {local_addr}");
+
+        Ok(Self {
+            local_addr,
+            station,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    fn serve(socket: UdpSocket, station: Arc<Mutex<Station>>, stop: Arc<AtomicBool>) {
+        let mut buf = vec![0; 65535];
+        while !stop.load(Ordering::Relaxed) {
+            let (size, src) = match socket.recv_from(&mut buf) {
+                Ok(r) => r,
+                // read timeout, check the stop flag again
+                Err(_) => continue,
+            };
+
+            let response = match station.lock() {
+                Ok(mut s) => s.handle(&buf[..size]),
+                Err(_) => break,
+            };
+
+            match response {
+                Ok(Some(r)) => {
+                    if let Err(err) = socket.send_to(&r, src) {
+                        log::error!("simulator failed to answer {src}: {err}");
+                    }
+                }
+                Ok(None) => (),
+                Err(err) => log::error!("simulator failed to handle request from {src}: {err}"),
+            }
+        }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn inject_fault(&self, fault: SimulatorFault) {
+        if let Ok(mut s) = self.station.lock() {
+            s.inject_fault(fault);
+        }
+    }
+
+    /// ar_uuid is the application relationship currently established
+    pub fn ar_uuid(&self) -> Option<Uuid> {
+        self.station.lock().ok().and_then(|s| s.ar_uuid())
+    }
+
+    /// restart forgets the AR, see `Station::restart`
+    pub fn restart(&self) {
+        if let Ok(mut s) = self.station.lock() {
+            s.restart();
+        }
+    }
+
+    pub fn requests_received(&self) -> usize {
+        self.station
+            .lock()
+            .map(|s| s.requests_received)
+            .unwrap_or_default()
+    }
+
+    /// wait blocks until the simulator is stopped
+    pub fn wait(mut self) {
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,634 @@
+use super::config::{SimulatedChannel, SimulatedInterface, SimulatorConfig};
+use crate::{
+    hart::HartAddressing,
+    protocol::{
+        ArBlockRes, BlockHeaderType, DceRpcPacket, Decode, Encode, HartFrame, InterfaceVersion,
+        IodReq, IodRes, OpNum, PacketType, Pnio, PnioHeaderEnum, ReleaseBlock, TowerFloorProtocol,
+        DCERPC_EPM_STATUS_NOT_REGISTERED, INTERFACE,
+    },
+};
+use anyhow::anyhow;
+use std::collections::{HashMap, VecDeque};
+use uuid::{uuid, Uuid};
+
+/// SimulatorFault is injected into the simulator to exercise the error paths,
+/// each fault is consumed by the next request it applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulatorFault {
+    /// drop the next response, the client runs into its receive timeout
+    DropResponse,
+    /// answer the next DCE/RPC request with a reject carrying this status
+    Reject(u32),
+    /// hold back the next response, the following pings are answered with
+    /// this number of "working" before the response is sent
+    Working(u8),
+    /// answer the next connect, record read or write with this PNIO status
+    PnioStatus([u8; 4]),
+    /// answer the next HART request with this response code (1st status byte)
+    HartResponseCode(u8),
+    /// leave the next HART request without response, as a field device does
+    /// which is disconnected from the loop
+    NoHartResponse,
+}
+
+// PNIO status for an access to a record index the station does not know,
+// i.e. ErrorCode IODReadRes/IODWriteRes, ErrorDecode PNIORW, "invalid index"
+const PNIO_STATUS_READ_INVALID_INDEX: [u8; 4] = [0xde, 0x80, 0xb0, 0x00];
+const PNIO_STATUS_WRITE_INVALID_INDEX: [u8; 4] = [0xdf, 0x80, 0xb0, 0x00];
+// PNIO status for the release of an AR the station does not know,
+// i.e. ErrorCode IODReleaseRes, ErrorDecode PNIO, ErrorCode1 CMRPC
+const PNIO_STATUS_RELEASE_UNKNOWN_AR: [u8; 4] = [0xdc, 0x81, 0x40, 0x05];
+const PNIO_STATUS_READ_UNKNOWN_AR: [u8; 4] = [0xde, 0x81, 0x40, 0x05];
+const PNIO_STATUS_WRITE_UNKNOWN_AR: [u8; 4] = [0xdf, 0x81, 0x40, 0x05];
+
+// "response control" byte of the HART response record, see the AI module manual
+const RESPONSE_CONTROL_DATA_READY: u8 = 0x04;
+const RESPONSE_CONTROL_NOT_READY: u8 = 0x00;
+
+// DCE/RPC endpoint mapper
+const NDR_TRANSFER_SYNTAX: Uuid = uuid!("8a885d04-1ceb-11c9-9fe8-08002b104860");
+const EPM_STATUS_OK: u32 = 0x0000_0000;
+
+#[derive(Debug, Default)]
+struct ChannelState {
+    // HART response record waiting to be read, with the number of reads
+    // still to be answered with "data not ready"
+    pending_response: Option<(Box<[u8]>, u8)>,
+    // index of the next scripted response per command
+    script_positions: HashMap<u16, usize>,
+}
+
+/// Station is the request/response logic of the simulated IO station, it is
+/// independent of the socket so that it can be driven directly.
+#[derive(Debug)]
+pub struct Station {
+    config: SimulatorConfig,
+    local_port: u16,
+    channels: Vec<ChannelState>,
+    faults: VecDeque<SimulatorFault>,
+    ar_uuid: Option<Uuid>,
+    last_call: Option<Call>,
+    pub requests_received: usize,
+}
+
+// the last call, kept to answer the pings of the client
+#[derive(Debug)]
+struct Call {
+    activity: [u8; 16],
+    seq_num: [u8; 4],
+    response: Option<Vec<u8>>,
+    working: u8,
+}
+
+impl Station {
+    pub fn new(config: SimulatorConfig, local_port: u16) -> Self {
+        let channels = config.channels.iter().map(|_| Default::default()).collect();
+
+        Self {
+            config,
+            local_port,
+            channels,
+            faults: VecDeque::new(),
+            ar_uuid: None,
+            last_call: None,
+            requests_received: 0,
+        }
+    }
+
+    pub fn inject_fault(&mut self, fault: SimulatorFault) {
+        self.faults.push_back(fault);
+    }
+
+    /// ar_uuid is the application relationship established by the last connect
+    pub fn ar_uuid(&self) -> Option<Uuid> {
+        self.ar_uuid
+    }
+
+    /// restart forgets the AR as a station does when it's power cycled
+    pub fn restart(&mut self) {
+        self.ar_uuid = None;
+    }
+
+    /// handle processes one datagram, returns the datagram to be answered if any.
+    pub fn handle(&mut self, datagram: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.requests_received += 1;
+
+        let request = DceRpcPacket::from_bytes(datagram)?;
+        match request.get_packet_type() {
+            Some(PacketType::Request) => (),
+            Some(PacketType::Ping) => return self.handle_ping(&request),
+            _ => return Err(anyhow!("only DCE/RPC requests and pings are served")),
+        }
+
+        let mut call = Call {
+            activity: request.activity,
+            seq_num: request.seq_num,
+            response: None,
+            working: 0,
+        };
+
+        if self.take_fault(|f| matches!(f, SimulatorFault::DropResponse)) {
+            log::debug!("simulator dropping response");
+            self.last_call = Some(call);
+            return Ok(None);
+        }
+
+        if let Some(SimulatorFault::Reject(status)) =
+            self.take_fault_value(|f| matches!(f, SimulatorFault::Reject(_)))
+        {
+            log::debug!("simulator rejecting request with status {status:#010x}");
+            let reject = Self::response(&request, PacketType::Reject, status.to_le_bytes().into())?;
+            return Ok(Some(reject.to_bytes()?));
+        }
+
+        let interface = Uuid::from_bytes_le(request.interface);
+        let opnum = OpNum::from_u16(u16::from_le_bytes(request.opnum))
+            .ok_or(anyhow!("unknown DCE/RPC opnum"))?;
+
+        let stub_data = if interface == Uuid::parse_str(INTERFACE)? {
+            self.handle_lookup(&request.data)?
+        } else {
+            let pnio = Pnio::from_bytes(&request.data)?;
+            match (opnum, pnio.pnio_header) {
+                (OpNum::Connect, PnioHeaderEnum::ArBlockReq(ar_block_req)) => {
+                    let ar_uuid = Uuid::from_bytes(ar_block_req.ar_uuid);
+                    let status = match self
+                        .take_fault_value(|f| matches!(f, SimulatorFault::PnioStatus(_)))
+                    {
+                        Some(SimulatorFault::PnioStatus(status)) => status,
+                        _ => {
+                            self.ar_uuid = Some(ar_uuid);
+                            log::debug!("simulator established AR {ar_uuid}");
+                            [0x00; 4]
+                        }
+                    };
+
+                    let ar_block_res = ArBlockRes::new(
+                        BlockHeaderType::ArBlockResType,
+                        ar_uuid,
+                        u16::from_be_bytes(ar_block_req.session_key),
+                        [0x00; 6],
+                        self.local_port.to_be_bytes(),
+                    );
+                    Pnio::new(Some(status), PnioHeaderEnum::ArBlockRes(ar_block_res), None)
+                        .to_bytes()?
+                }
+                (OpNum::Release, PnioHeaderEnum::ReleaseBlock(release_block)) => {
+                    self.handle_release(release_block)?
+                }
+                // the records are read and written explicitly within the AR only
+                (OpNum::Read | OpNum::Write, PnioHeaderEnum::IodReq(iod_req))
+                    if self.ar_uuid != Some(Uuid::from_bytes(iod_req.ar_uuid)) =>
+                {
+                    let (block_header_type, status) = match opnum {
+                        OpNum::Write => (
+                            BlockHeaderType::IodWriteResType,
+                            PNIO_STATUS_WRITE_UNKNOWN_AR,
+                        ),
+                        _ => (BlockHeaderType::IodReadResType, PNIO_STATUS_READ_UNKNOWN_AR),
+                    };
+                    self.iod_response(block_header_type, &iod_req, status, None)?
+                }
+                (OpNum::Write, PnioHeaderEnum::IodReq(iod_req)) => {
+                    self.handle_write(iod_req, pnio.pnio_data.unwrap_or_default())?
+                }
+                // the records are read the same with or without an AR
+                (OpNum::Read | OpNum::ReadImplicit, PnioHeaderEnum::IodReq(iod_req)) => {
+                    self.handle_read(iod_req)?
+                }
+                (opnum, _) => return Err(anyhow!("unsupported PNIO request {opnum:?}")),
+            }
+        };
+
+        let response =
+            Self::response(&request, PacketType::Response, stub_data.into())?.to_bytes()?;
+        call.response = Some(response.clone());
+
+        if let Some(SimulatorFault::Working(working)) =
+            self.take_fault_value(|f| matches!(f, SimulatorFault::Working(_)))
+        {
+            log::debug!("simulator holding back response");
+            call.working = working;
+            self.last_call = Some(call);
+            return Ok(None);
+        }
+
+        self.last_call = Some(call);
+        Ok(Some(response))
+    }
+
+    // a ping is answered with "working" while the call is held back, then with
+    // the response, and with "nocall" if the call is unknown
+    fn handle_ping(&mut self, ping: &DceRpcPacket) -> anyhow::Result<Option<Vec<u8>>> {
+        let call = self
+            .last_call
+            .as_mut()
+            .filter(|c| c.activity == ping.activity && c.seq_num == ping.seq_num);
+
+        let reply = match call {
+            Some(c) if c.working > 0 => {
+                c.working -= 1;
+                ping.reply(PacketType::Working, Box::new([]))
+            }
+            Some(Call {
+                response: Some(response),
+                ..
+            }) => return Ok(Some(response.clone())),
+            _ => ping.reply(PacketType::Nocall, Box::new([])),
+        };
+
+        Ok(Some(reply.to_bytes()?))
+    }
+
+    fn response(
+        request: &DceRpcPacket,
+        packet_type: PacketType,
+        data: Box<[u8]>,
+    ) -> anyhow::Result<DceRpcPacket> {
+        let interface_ver = InterfaceVersion::from_u32(u32::from_le_bytes(request.interface_ver))
+            .ok_or(anyhow!("unknown DCE/RPC interface version"))?;
+        let opnum = OpNum::from_u16(u16::from_le_bytes(request.opnum))
+            .ok_or(anyhow!("unknown DCE/RPC opnum"))?;
+
+        Ok(DceRpcPacket::new(
+            packet_type,
+            Uuid::from_bytes_le(request.obj_uuid),
+            Uuid::from_bytes_le(request.interface),
+            interface_ver,
+            Uuid::from_bytes_le(request.activity),
+            u32::from_le_bytes(request.seq_num),
+            opnum,
+            data,
+        ))
+    }
+
+    fn take_fault(&mut self, predicate: impl Fn(&SimulatorFault) -> bool) -> bool {
+        self.take_fault_value(predicate).is_some()
+    }
+
+    fn take_fault_value(
+        &mut self,
+        predicate: impl Fn(&SimulatorFault) -> bool,
+    ) -> Option<SimulatorFault> {
+        let position = self.faults.iter().position(predicate)?;
+        self.faults.remove(position)
+    }
+
+    // endpoint mapper ---------------------------------------------------------
+
+    fn handle_lookup(&self, stub_data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        // the handle is the last 24 bytes but max_entries, the simulator keeps
+        // the index of the next entry inside the handle
+        let handle_start = stub_data
+            .len()
+            .checked_sub(24)
+            .ok_or(anyhow!("endpoint mapper request too short"))?;
+        let handle = &stub_data[handle_start..handle_start + 20];
+        let max_entries =
+            u32::from_le_bytes(stub_data[handle_start + 20..handle_start + 24].try_into()?);
+        let next = u32::from_le_bytes(handle[4..8].try_into()?) as usize;
+
+        let entries: Vec<&SimulatedInterface> = self
+            .config
+            .interfaces
+            .iter()
+            .skip(next)
+            .take(max_entries.max(1) as usize)
+            .collect();
+        let following = next + entries.len();
+
+        let mut v: Vec<u8> = vec![];
+
+        // handle, nil once there are no more entries
+        let mut response_handle = [0x00; 20];
+        if !entries.is_empty() && following < self.config.interfaces.len() {
+            response_handle[4..8].copy_from_slice(&(following as u32).to_le_bytes());
+        }
+        v.extend(response_handle);
+
+        // num_entries, max_count, offset, actual_count
+        let num_entries = entries.len() as u32;
+        v.extend(num_entries.to_le_bytes());
+        v.extend(max_entries.to_le_bytes());
+        v.extend(0u32.to_le_bytes());
+        v.extend(num_entries.to_le_bytes());
+
+        // entries, the towers are deferred after all the entries
+        for (i, entry) in entries.iter().enumerate() {
+            let annotation = Self::annotation(&entry.annotation);
+            v.extend(entry.object_uuid.to_bytes_le());
+            v.extend((3 + i as u32).to_le_bytes()); // tower referent id
+            v.extend(0u32.to_le_bytes()); // annotation offset
+            v.extend((annotation.len() as u32).to_le_bytes());
+            v.extend(&annotation);
+            Self::align(&mut v);
+        }
+        for entry in entries.iter() {
+            let tower = self.tower(entry);
+            v.extend((tower.len() as u32).to_le_bytes());
+            v.extend((tower.len() as u32).to_le_bytes());
+            v.extend(&tower);
+            Self::align(&mut v);
+        }
+
+        let status = match entries.is_empty() {
+            true => DCERPC_EPM_STATUS_NOT_REGISTERED,
+            false => EPM_STATUS_OK,
+        };
+        v.extend(status.to_le_bytes());
+
+        Ok(v)
+    }
+
+    fn annotation(annotation: &str) -> Vec<u8> {
+        let mut a = format!("{annotation:<63}").into_bytes();
+        a.truncate(63);
+        a.push(0x00);
+        a
+    }
+
+    fn align(v: &mut Vec<u8>) {
+        while !v.len().is_multiple_of(4) {
+            v.push(0x00);
+        }
+    }
+
+    fn tower(&self, entry: &SimulatedInterface) -> Vec<u8> {
+        let udp_port = entry.udp_port.unwrap_or(self.local_port);
+        let uuid_floor = |uuid: Uuid, version_major: u16, version_minor: u16| {
+            let mut f: Vec<u8> = vec![];
+            f.extend(19u16.to_le_bytes());
+            f.push(TowerFloorProtocol::Uuid as u8);
+            f.extend(uuid.to_bytes_le());
+            f.extend(version_major.to_le_bytes());
+            f.extend(2u16.to_le_bytes());
+            f.extend(version_minor.to_le_bytes());
+            f
+        };
+        let floor = |protocol: TowerFloorProtocol, rhs: &[u8]| {
+            let mut f: Vec<u8> = vec![];
+            f.extend(1u16.to_le_bytes());
+            f.push(protocol as u8);
+            f.extend((rhs.len() as u16).to_le_bytes());
+            f.extend(rhs);
+            f
+        };
+
+        let mut t: Vec<u8> = vec![];
+        t.extend(5u16.to_le_bytes());
+        t.extend(uuid_floor(entry.interface_uuid, 1, 0));
+        t.extend(uuid_floor(NDR_TRANSFER_SYNTAX, 2, 0));
+        t.extend(floor(
+            TowerFloorProtocol::RpcConnectionlessProtocol,
+            &[0x00; 2],
+        ));
+        t.extend(floor(TowerFloorProtocol::Udp, &udp_port.to_be_bytes()));
+        t.extend(floor(TowerFloorProtocol::Ip, &[0x00; 4]));
+        t
+    }
+
+    fn handle_release(&mut self, release_block: ReleaseBlock) -> anyhow::Result<Vec<u8>> {
+        let ar_uuid = Uuid::from_bytes(release_block.ar_uuid);
+        let status = match self.ar_uuid == Some(ar_uuid) {
+            true => {
+                log::debug!("simulator released AR {ar_uuid}");
+                self.ar_uuid = None;
+                [0x00; 4]
+            }
+            false => PNIO_STATUS_RELEASE_UNKNOWN_AR,
+        };
+
+        let release_block = ReleaseBlock::new(
+            BlockHeaderType::ReleaseBlockResType,
+            ar_uuid,
+            u16::from_be_bytes(release_block.session_key),
+        );
+        Ok(Pnio::new(
+            Some(status),
+            PnioHeaderEnum::ReleaseBlock(release_block),
+            None,
+        )
+        .to_bytes()?)
+    }
+
+    // records -----------------------------------------------------------------
+
+    fn find_channel(&self, iod_req: &IodReq) -> Option<usize> {
+        let slot = u16::from_be_bytes(iod_req.slot_num);
+        let subslot = u16::from_be_bytes(iod_req.subslot_num);
+        let index = u16::from_be_bytes(iod_req.index);
+
+        self.config.channels.iter().position(|c| {
+            c.slot_number == slot
+                && c.subslot_number == subslot
+                && (c.request_data_record_number == index || c.response_data_record_number == index)
+        })
+    }
+
+    fn iod_response(
+        &self,
+        block_header_type: BlockHeaderType,
+        iod_req: &IodReq,
+        status: [u8; 4],
+        data: Option<Box<[u8]>>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let record_data_len = match &data {
+            Some(d) => d.len() as u32,
+            None => u32::from_be_bytes(iod_req.record_data_len),
+        };
+        let iod_res = IodRes::new(
+            block_header_type,
+            u16::from_be_bytes(iod_req.seq_num),
+            Uuid::from_bytes(iod_req.ar_uuid),
+            u16::from_be_bytes(iod_req.slot_num),
+            u16::from_be_bytes(iod_req.subslot_num),
+            u16::from_be_bytes(iod_req.index),
+            status,
+            record_data_len,
+        );
+
+        Ok(Pnio::new(Some(status), PnioHeaderEnum::IodRes(iod_res), data).to_bytes()?)
+    }
+
+    fn handle_write(&mut self, iod_req: IodReq, data: Box<[u8]>) -> anyhow::Result<Vec<u8>> {
+        let block_header_type = BlockHeaderType::IodWriteResType;
+
+        if let Some(SimulatorFault::PnioStatus(status)) =
+            self.take_fault_value(|f| matches!(f, SimulatorFault::PnioStatus(_)))
+        {
+            return self.iod_response(block_header_type, &iod_req, status, None);
+        }
+
+        let index = u16::from_be_bytes(iod_req.index);
+        let channel_index = match self.find_channel(&iod_req) {
+            Some(c) if self.config.channels[c].request_data_record_number == index => c,
+            _ => {
+                let status = PNIO_STATUS_WRITE_INVALID_INDEX;
+                return self.iod_response(block_header_type, &iod_req, status, None);
+            }
+        };
+
+        let response_code =
+            match self.take_fault_value(|f| matches!(f, SimulatorFault::HartResponseCode(_))) {
+                Some(SimulatorFault::HartResponseCode(code)) => Some(code),
+                _ => None,
+            };
+
+        let no_response = self
+            .take_fault_value(|f| *f == SimulatorFault::NoHartResponse)
+            .is_some();
+
+        let channel = &self.config.channels[channel_index];
+        let state = &mut self.channels[channel_index];
+        state.pending_response = Self::hart_response(channel, state, &data, response_code)
+            .filter(|_| !no_response)
+            .map(|response| (response, channel.data_ready_delay));
+
+        self.iod_response(block_header_type, &iod_req, [0x00; 4], None)
+    }
+
+    fn handle_read(&mut self, iod_req: IodReq) -> anyhow::Result<Vec<u8>> {
+        let block_header_type = BlockHeaderType::IodReadResType;
+
+        if let Some(SimulatorFault::PnioStatus(status)) =
+            self.take_fault_value(|f| matches!(f, SimulatorFault::PnioStatus(_)))
+        {
+            return self.iod_response(block_header_type, &iod_req, status, None);
+        }
+
+        let index = u16::from_be_bytes(iod_req.index);
+        let slot = u16::from_be_bytes(iod_req.slot_num);
+        let subslot = u16::from_be_bytes(iod_req.subslot_num);
+        if let Some(record) = self
+            .config
+            .records
+            .iter()
+            .find(|r| r.slot_number == slot && r.subslot_number == subslot && r.index == index)
+        {
+            let record = record.data.clone().into_boxed_slice();
+            return self.iod_response(block_header_type, &iod_req, [0x00; 4], Some(record));
+        }
+
+        let channel_index = match self.find_channel(&iod_req) {
+            Some(c) if self.config.channels[c].response_data_record_number == index => c,
+            _ => {
+                let status = PNIO_STATUS_READ_INVALID_INDEX;
+                return self.iod_response(block_header_type, &iod_req, status, None);
+            }
+        };
+
+        let state = &mut self.channels[channel_index];
+        let record = match state.pending_response.as_mut() {
+            Some((response, 0)) => response.clone(),
+            Some((_, delay)) => {
+                *delay -= 1;
+                vec![RESPONSE_CONTROL_NOT_READY; 2].into_boxed_slice()
+            }
+            None => vec![RESPONSE_CONTROL_NOT_READY; 2].into_boxed_slice(),
+        };
+
+        self.iod_response(block_header_type, &iod_req, [0x00; 4], Some(record))
+    }
+
+    // HART --------------------------------------------------------------------
+
+    /// hart_response builds the response record for the HART request written into
+    /// the request record, `None` if the field device would not answer.
+    fn hart_response(
+        channel: &SimulatedChannel,
+        state: &mut ChannelState,
+        request: &[u8],
+        response_code: Option<u8>,
+    ) -> Option<Box<[u8]>> {
+        // request: message format, number of preambles, delimiter, address, command,
+        // byte count, data and checksum
+        let delimiter = *request.get(2)?;
+        let (address, command) = match delimiter {
+            0x02 => (request.get(3..4)?, *request.get(4)?),
+            0x82 => (request.get(3..8)?, *request.get(8)?),
+            _ => return None,
+        };
+
+        if address.len() == 5 {
+            // primary/secondary master and burst mode bits are not part of the id
+            let long_address = channel.long_address();
+            let is_broadcast = address[0] & 0x3f == 0 && address[1..] == [0x00; 4];
+            if is_broadcast {
+                // only the device with the tag answers command 11 and 21
+                let addressing = match command {
+                    11 => HartAddressing::Tag {
+                        tag: channel.tag.clone(),
+                    },
+                    21 => HartAddressing::LongTag {
+                        long_tag: channel.long_tag.clone(),
+                    },
+                    _ => return None,
+                };
+                let data = request.get(10..10 + *request.get(9)? as usize)?;
+                if addressing.identity_payload().ok()?.as_deref() != Some(data) {
+                    return None;
+                }
+            } else if address[0] & 0x3f != long_address[0] & 0x3f
+                || address[1..] != long_address[1..]
+            {
+                return None;
+            }
+        } else if address[0] & 0x3f != channel.polling_address {
+            return None;
+        }
+
+        // command 31 carries the number of the command ahead of its data
+        let expansion = match (command, address.len()) {
+            (HartFrame::EXPANSION_COMMAND, 5) => Some(request.get(10..12)?),
+            _ => None,
+        };
+        let number = match expansion {
+            Some(&[high, low]) => u16::from_be_bytes([high, low]),
+            _ => command.into(),
+        };
+
+        let mut status_and_data: Vec<u8> = match number {
+            // command 11 and 21 are answered like command 0
+            0 | 11 | 21 => [&[0x00, 0x00], &channel.command0_data[..]].concat(),
+            _ => {
+                let scripted = channel.commands.iter().find(|c| c.number == number);
+                match scripted {
+                    Some(c) if !c.responses.is_empty() => {
+                        let position = state.script_positions.entry(number).or_default();
+                        let mut response =
+                            c.responses[(*position).min(c.responses.len() - 1)].clone();
+                        *position += 1;
+                        // the number of the command follows the statuses
+                        if let Some(expansion) = expansion.filter(|_| response.len() >= 2) {
+                            response.splice(2..2, expansion.iter().copied());
+                        }
+                        response
+                    }
+                    // command not implemented
+                    _ => vec![0x40, 0x00],
+                }
+            }
+        };
+
+        if let Some(code) = response_code {
+            status_and_data.resize(status_and_data.len().max(2), 0x00);
+            status_and_data[0] = code;
+            status_and_data.truncate(2);
+        }
+
+        // response: response control, message format, delimiter, address, command,
+        // byte count, statuses, data and checksum
+        let mut frame: Vec<u8> = vec![delimiter | 0x04];
+        frame.extend(address);
+        frame.push(command);
+        frame.push(status_and_data.len() as u8);
+        frame.extend(&status_and_data);
+        let checksum = frame.iter().fold(0, |acc, b| acc ^ b);
+        frame.push(checksum);
+
+        let mut record = vec![RESPONSE_CONTROL_DATA_READY, 0x00];
+        record.extend(frame);
+
+        Some(record.into_boxed_slice())
+    }
+}
@@ -0,0 +1,157 @@
+use super::{PcapngWriter, TransportClient};
+use crate::error;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::{
+    cell::{Cell, RefCell},
+    convert::Infallible,
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
+    path::PathBuf,
+};
+
+/// CaptureSettings tells where the captures of the stations are written and
+/// how they are rotated
+#[derive(Debug, Clone)]
+pub struct CaptureSettings {
+    pub dir: PathBuf,
+    pub max_file_size: u64,
+    pub max_files: usize,
+}
+
+impl CaptureSettings {
+    /// writer creates the capture of the station, e.g. `<dir>/192.168.0.20.pcapng`
+    pub fn writer(&self, station: &str) -> anyhow::Result<PcapngWriter> {
+        let path = self.dir.join(format!("{station}.pcapng"));
+        PcapngWriter::create(&path, self.max_file_size, self.max_files)
+    }
+}
+
+/// CaptureClient writes every datagram sent and received through the client
+/// it wraps into a pcapng file, the traffic goes on if the capture fails.
+#[derive(Debug)]
+pub struct CaptureClient {
+    inner: Box<dyn TransportClient>,
+    src_addr: SocketAddrV4,
+    writer: RefCell<PcapngWriter>,
+}
+
+impl CaptureClient {
+    /// new wraps the client, `src_addr` is the local address of its socket
+    pub fn new(
+        inner: Box<dyn TransportClient>,
+        src_addr: SocketAddr,
+        writer: PcapngWriter,
+    ) -> anyhow::Result<Self> {
+        let SocketAddr::V4(src_addr) = src_addr else {
+            return Err(anyhow!("only ipv4 traffic can be captured"));
+        };
+
+        Ok(Self {
+            inner,
+            src_addr,
+            writer: RefCell::new(writer),
+        })
+    }
+
+    fn dst_addr(&self) -> SocketAddrV4 {
+        match self.inner.get_dst_conn_details() {
+            Ok((IpAddr::V4(ip), port)) => SocketAddrV4::new(ip, port),
+            _ => SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+        }
+    }
+
+    fn capture(&self, src: SocketAddrV4, dst: SocketAddrV4, datagram: &[u8]) {
+        if let Err(err) = self.writer.borrow_mut().write_datagram(src, dst, datagram) {
+            log::warn!("failed to capture datagram of {}: {err}", self.debug());
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl TransportClient for CaptureClient {
+    async fn send(&self, data: Box<[u8]>) -> error::Result<usize> {
+        let datagram = data.clone();
+        let size = self.inner.send(data).await?;
+        self.capture(self.src_addr, self.dst_addr(), &datagram);
+
+        Ok(size)
+    }
+
+    async fn receive(&self) -> error::Result<Box<[u8]>> {
+        let datagram = self.inner.receive().await?;
+        self.capture(self.dst_addr(), self.src_addr, &datagram);
+
+        Ok(datagram)
+    }
+
+    fn get_dst_conn_details(&self) -> anyhow::Result<(IpAddr, u16), Infallible> {
+        self.inner.get_dst_conn_details()
+    }
+
+    fn update_dest(&self, dst_ip: Ipv4Addr, dst_udpport: u16) -> anyhow::Result<()> {
+        self.inner.update_dest(dst_ip, dst_udpport)
+    }
+
+    fn debug(&self) -> String {
+        format!("{} (captured)", self.inner.debug())
+    }
+
+    fn dropped_packets(&self) -> &Cell<u64> {
+        self.inner.dropped_packets()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transport::UdpClient;
+    use std::fs;
+    use tokio::net::UdpSocket;
+
+    #[tokio::test]
+    async fn capture_client_should_write_sent_and_received_datagrams() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = CaptureSettings {
+            dir: dir.path().to_path_buf(),
+            max_file_size: 1 << 20,
+            max_files: 2,
+        };
+
+        let station = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let station_addr = station.local_addr().unwrap();
+        let udp_client = UdpClient::new(
+            Ipv4Addr::LOCALHOST,
+            Ipv4Addr::LOCALHOST,
+            station_addr.port(),
+        )
+        .await
+        .unwrap();
+        let src_addr = udp_client.local_addr().unwrap();
+        let client = CaptureClient::new(
+            Box::new(udp_client),
+            src_addr,
+            settings.writer("127.0.0.1").unwrap(),
+        )
+        .unwrap();
+
+        client.send(Box::new([0x04, 0x00])).await.unwrap();
+        let mut buf = [0x00; 16];
+        let (_, client_addr) = station.recv_from(&mut buf).await.unwrap();
+        station
+            .send_to(&[0x04, 0x02, 0x01], client_addr)
+            .await
+            .unwrap();
+        client.receive().await.unwrap();
+
+        // header blocks, then the request and the response of 64 bytes each
+        let bytes = fs::read(dir.path().join("127.0.0.1.pcapng")).unwrap();
+        assert_eq!(bytes.len(), 48 + 2 * 64);
+        let request = &bytes[48 + 28..][..30];
+        assert_eq!(request[20..22], src_addr.port().to_be_bytes());
+        assert_eq!(request[22..24], station_addr.port().to_be_bytes());
+        assert_eq!(request[28..], [0x04, 0x00]);
+        let response = &bytes[48 + 64 + 28..][..31];
+        assert_eq!(response[20..22], station_addr.port().to_be_bytes());
+        assert_eq!(response[28..], [0x04, 0x02, 0x01]);
+    }
+}
@@ -0,0 +1,9 @@
+/// FrameTransport sends and receives raw ethernet frames, i.e. the layer 2
+/// protocols of PROFINET like DCP, which are not carried over udp
+pub trait FrameTransport {
+    fn send_frame(&self, frame: &[u8]) -> anyhow::Result<usize>;
+    /// receive_frame returns the next frame, a receive timeout is returned as
+    /// an io error so that it can be told apart with `is_timeout`
+    fn receive_frame(&self) -> anyhow::Result<Box<[u8]>>;
+    fn mac_address(&self) -> [u8; 6];
+}
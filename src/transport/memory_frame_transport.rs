@@ -0,0 +1,53 @@
+use super::FrameTransport;
+use std::{cell::RefCell, collections::VecDeque, io, thread, time::Duration};
+
+type Responder = Box<dyn Fn(&[u8]) -> Vec<Vec<u8>>>;
+
+/// MemoryFrameTransport is a frame transport without a network, each frame sent
+/// is handed to the responder and the frames it answers with are received,
+/// e.g. to play the stations answering DCP requests in tests.
+pub struct MemoryFrameTransport {
+    mac_address: [u8; 6],
+    responder: Responder,
+    received: RefCell<VecDeque<Vec<u8>>>,
+    /// sent holds every frame sent so far
+    pub sent: RefCell<Vec<Vec<u8>>>,
+}
+
+impl MemoryFrameTransport {
+    const RECEIVE_TIMEOUT: Duration = Duration::from_millis(10);
+
+    pub fn new(mac_address: [u8; 6], responder: Responder) -> Self {
+        Self {
+            mac_address,
+            responder,
+            received: RefCell::new(VecDeque::new()),
+            sent: RefCell::new(vec![]),
+        }
+    }
+}
+
+impl FrameTransport for MemoryFrameTransport {
+    fn send_frame(&self, frame: &[u8]) -> anyhow::Result<usize> {
+        self.sent.borrow_mut().push(frame.to_vec());
+        self.received.borrow_mut().extend((self.responder)(frame));
+
+        Ok(frame.len())
+    }
+
+    fn receive_frame(&self) -> anyhow::Result<Box<[u8]>> {
+        match self.received.borrow_mut().pop_front() {
+            Some(frame) => Ok(frame.into_boxed_slice()),
+            None => {
+                // behave like a socket running into its receive timeout
+                thread::sleep(Self::RECEIVE_TIMEOUT);
+                let err = io::Error::from(io::ErrorKind::TimedOut);
+                Err(anyhow::Error::new(err).context("failed to receive frame"))
+            }
+        }
+    }
+
+    fn mac_address(&self) -> [u8; 6] {
+        self.mac_address
+    }
+}
@@ -1,5 +1,17 @@
+mod capture_client;
+mod frame_transport;
+mod memory_frame_transport;
+mod packet_socket;
+mod pcapng;
+mod replay_client;
 mod transport_client;
 mod udp_client;
 
-pub use transport_client::TransportClient;
+pub use capture_client::{CaptureClient, CaptureSettings};
+pub use frame_transport::FrameTransport;
+pub use memory_frame_transport::MemoryFrameTransport;
+pub use packet_socket::PacketSocket;
+pub use pcapng::{read_datagrams, Datagram, PcapngWriter};
+pub use replay_client::{Direction, Exchange, ReplayClient, Transcript};
+pub use transport_client::{is_timeout, TransportClient};
 pub use udp_client::*;
@@ -0,0 +1,139 @@
+use super::FrameTransport;
+use crate::protocol::ETHER_TYPE_PNIO;
+use anyhow::anyhow;
+use std::{
+    ffi::CString,
+    fs, io, mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    time::Duration,
+};
+
+/// PacketSocket is a linux AF_PACKET socket bound to a network interface, it
+/// receives the PNIO frames (ether type 0x8892) of that interface only. Opening
+/// it needs the CAP_NET_RAW capability.
+#[derive(Debug)]
+pub struct PacketSocket {
+    fd: OwnedFd,
+    interface: String,
+    mac_address: [u8; 6],
+}
+
+impl PacketSocket {
+    pub const RECEIVE_TIMEOUT: Duration = Duration::from_millis(100);
+    // large enough for a VLAN tagged frame
+    pub const MAX_FRAME_SIZE: usize = 1522;
+
+    pub fn new(interface: &str) -> anyhow::Result<Self> {
+        let protocol = ETHER_TYPE_PNIO.to_be() as libc::c_int;
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, protocol) };
+        if fd < 0 {
+            return Err(anyhow::Error::new(io::Error::last_os_error())
+                .context("failed to open packet socket"));
+        }
+        // closed on drop from now on
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let if_name = CString::new(interface)?;
+        let if_index = unsafe { libc::if_nametoindex(if_name.as_ptr()) };
+        if if_index == 0 {
+            return Err(anyhow!("network interface `{interface}` not found"));
+        }
+
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as libc::c_ushort;
+        addr.sll_protocol = ETHER_TYPE_PNIO.to_be();
+        addr.sll_ifindex = if_index as libc::c_int;
+        let result = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(anyhow::Error::new(io::Error::last_os_error())
+                .context(format!("failed to bind packet socket to `{interface}`")));
+        }
+
+        let timeout = libc::timeval {
+            tv_sec: Self::RECEIVE_TIMEOUT.as_secs() as libc::time_t,
+            tv_usec: Self::RECEIVE_TIMEOUT.subsec_micros() as libc::suseconds_t,
+        };
+        let result = unsafe {
+            libc::setsockopt(
+                fd.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const libc::timeval as *const libc::c_void,
+                mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(anyhow::Error::new(io::Error::last_os_error())
+                .context("failed to set the receive timeout"));
+        }
+
+        Ok(Self {
+            fd,
+            interface: interface.to_string(),
+            mac_address: Self::read_mac_address(interface)?,
+        })
+    }
+
+    // the address is given as `28:63:36:8a:1f:0c`
+    fn read_mac_address(interface: &str) -> anyhow::Result<[u8; 6]> {
+        let content = fs::read_to_string(format!("/sys/class/net/{interface}/address"))?;
+        let bytes = content
+            .trim()
+            .split(':')
+            .map(|b| u8::from_str_radix(b, 16))
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        bytes
+            .try_into()
+            .map_err(|_| anyhow!("invalid mac address `{}` of `{interface}`", content.trim()))
+    }
+}
+
+impl FrameTransport for PacketSocket {
+    fn send_frame(&self, frame: &[u8]) -> anyhow::Result<usize> {
+        let size = unsafe {
+            libc::send(
+                self.fd.as_raw_fd(),
+                frame.as_ptr() as *const libc::c_void,
+                frame.len(),
+                0,
+            )
+        };
+        if size < 0 {
+            return Err(anyhow::Error::new(io::Error::last_os_error())
+                .context(format!("failed to send frame on `{}`", self.interface)));
+        }
+
+        Ok(size as usize)
+    }
+
+    fn receive_frame(&self) -> anyhow::Result<Box<[u8]>> {
+        let mut buf: Vec<u8> = vec![0; Self::MAX_FRAME_SIZE];
+        let size = unsafe {
+            libc::recv(
+                self.fd.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+            )
+        };
+        // the io error is kept so that a receive timeout can be told apart
+        if size < 0 {
+            return Err(anyhow::Error::new(io::Error::last_os_error())
+                .context(format!("failed to receive frame on `{}`", self.interface)));
+        }
+
+        buf.truncate(size as usize);
+        Ok(buf.into_boxed_slice())
+    }
+
+    fn mac_address(&self) -> [u8; 6] {
+        self.mac_address
+    }
+}
@@ -0,0 +1,548 @@
+use anyhow::anyhow;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    net::{Ipv4Addr, SocketAddrV4},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECTION_HEADER_BLOCK: u32 = 0x0a0d0d0a;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x00000001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x00000006;
+const SIMPLE_PACKET_BLOCK: u32 = 0x00000003;
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+// classic pcap, with microsecond or nanosecond timestamps
+const PCAP_MAGIC: u32 = 0xa1b2c3d4;
+const PCAP_MAGIC_NS: u32 = 0xa1b23c4d;
+const PCAP_HEADER_LEN: usize = 24;
+const PCAP_RECORD_HEADER_LEN: usize = 16;
+const LINKTYPE_ETHERNET: u16 = 1;
+const LINKTYPE_RAW: u16 = 101;
+const LINKTYPE_LINUX_SLL: u16 = 113;
+// raw IPv4 packets, there's no link layer header
+const LINKTYPE_IPV4: u16 = 228;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const SNAP_LEN: u32 = 65535;
+const IPV4_HEADER_LEN: usize = 20;
+const UDP_HEADER_LEN: usize = 8;
+
+/// PcapngWriter writes udp datagrams as IPv4 packets into a pcapng file, the
+/// IPv4 and UDP headers are synthesized so that Wireshark dissects the
+/// datagrams as it would on the wire. The file is rotated when it exceeds
+/// `max_file_size`, `path.1` is the most recent of the rotated files.
+#[derive(Debug)]
+pub struct PcapngWriter {
+    path: PathBuf,
+    max_file_size: u64,
+    max_files: usize,
+    file: BufWriter<File>,
+    file_size: u64,
+    ip_id: u16,
+}
+
+impl PcapngWriter {
+    pub fn create(path: &Path, max_file_size: u64, max_files: usize) -> anyhow::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // the capture of the previous session of the station is kept
+        if path.exists() {
+            shift(path, max_files.max(1))?;
+        }
+        let (file, file_size) = Self::open(path)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_file_size,
+            max_files: max_files.max(1),
+            file,
+            file_size,
+            ip_id: 0,
+        })
+    }
+
+    // a new file starts with the section header and the interface description
+    fn open(path: &Path) -> anyhow::Result<(BufWriter<File>, u64)> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        let mut shb = vec![];
+        shb.extend(BYTE_ORDER_MAGIC.to_le_bytes());
+        // version 1.0
+        shb.extend(1u16.to_le_bytes());
+        shb.extend(0u16.to_le_bytes());
+        // section length is not known
+        shb.extend((-1i64).to_le_bytes());
+        let mut header = block(SECTION_HEADER_BLOCK, &shb);
+
+        let mut idb = vec![];
+        idb.extend(LINKTYPE_IPV4.to_le_bytes());
+        idb.extend(0u16.to_le_bytes());
+        idb.extend(SNAP_LEN.to_le_bytes());
+        header.extend(block(INTERFACE_DESCRIPTION_BLOCK, &idb));
+
+        file.write_all(&header)?;
+        file.flush()?;
+        Ok((file, header.len() as u64))
+    }
+
+    /// write_datagram writes the udp datagram sent from `src` to `dst`
+    pub fn write_datagram(
+        &mut self,
+        src: SocketAddrV4,
+        dst: SocketAddrV4,
+        payload: &[u8],
+    ) -> anyhow::Result<()> {
+        self.ip_id = self.ip_id.wrapping_add(1);
+        let packet = ipv4_udp_packet(src, dst, self.ip_id, payload);
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_micros() as u64;
+        let mut epb = vec![];
+        // interface 0, the only one of the section
+        epb.extend(0u32.to_le_bytes());
+        epb.extend(((timestamp >> 32) as u32).to_le_bytes());
+        epb.extend((timestamp as u32).to_le_bytes());
+        epb.extend((packet.len() as u32).to_le_bytes());
+        epb.extend((packet.len() as u32).to_le_bytes());
+        epb.extend(&packet);
+        epb.resize(epb.len().next_multiple_of(4), 0x00);
+        let epb = block(ENHANCED_PACKET_BLOCK, &epb);
+
+        if self.file_size + epb.len() as u64 > self.max_file_size {
+            self.rotate()?;
+        }
+        self.file.write_all(&epb)?;
+        // flushed right away, the capture is read while the gateway is running
+        self.file.flush()?;
+        self.file_size += epb.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> anyhow::Result<()> {
+        shift(&self.path, self.max_files)?;
+
+        let (file, file_size) = Self::open(&self.path)?;
+        (self.file, self.file_size) = (file, file_size);
+        Ok(())
+    }
+}
+
+// path.1 becomes path.2 and so on, the oldest file is dropped, path is left
+// to be created again
+fn shift(path: &Path, max_files: usize) -> anyhow::Result<()> {
+    let rotated = |n: usize| PathBuf::from(format!("{}.{n}", path.display()));
+
+    if max_files == 1 {
+        return Ok(());
+    }
+    for n in (1..max_files - 1).rev() {
+        if rotated(n).exists() {
+            fs::rename(rotated(n), rotated(n + 1))?;
+        }
+    }
+    fs::rename(path, rotated(1))?;
+
+    Ok(())
+}
+
+// a block is framed by its type and its total length, which is repeated at
+// the end so that the file can be read backwards
+fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+    let total_len = (body.len() + 12) as u32;
+    let mut b = Vec::with_capacity(total_len as usize);
+    b.extend(block_type.to_le_bytes());
+    b.extend(total_len.to_le_bytes());
+    b.extend(body);
+    b.extend(total_len.to_le_bytes());
+    b
+}
+
+fn ipv4_udp_packet(src: SocketAddrV4, dst: SocketAddrV4, id: u16, payload: &[u8]) -> Vec<u8> {
+    let udp_len = (UDP_HEADER_LEN + payload.len()) as u16;
+    let total_len = IPV4_HEADER_LEN as u16 + udp_len;
+
+    let mut p = Vec::with_capacity(total_len as usize);
+    // version 4, header length 5 words
+    p.push(0x45);
+    p.push(0x00);
+    p.extend(total_len.to_be_bytes());
+    p.extend(id.to_be_bytes());
+    // don't fragment
+    p.extend(0x4000u16.to_be_bytes());
+    // ttl, protocol udp
+    p.push(64);
+    p.push(17);
+    p.extend([0x00, 0x00]);
+    p.extend(src.ip().octets());
+    p.extend(dst.ip().octets());
+    let checksum = ipv4_checksum(&p);
+    p[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    p.extend(src.port().to_be_bytes());
+    p.extend(dst.port().to_be_bytes());
+    p.extend(udp_len.to_be_bytes());
+    // the udp checksum is optional over IPv4
+    p.extend([0x00, 0x00]);
+    p.extend(payload);
+    p
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|w| u16::from_be_bytes([w[0], *w.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Datagram is an udp datagram read from a capture
+#[derive(Debug, Clone, PartialEq)]
+pub struct Datagram {
+    pub src: SocketAddrV4,
+    pub dst: SocketAddrV4,
+    pub payload: Box<[u8]>,
+}
+
+/// read_datagrams reads the udp datagrams over IPv4 of a pcapng file, such as
+/// the one of `PcapngWriter`, or of a classic pcap file. Anything else, e.g.
+/// DCP frames or IP fragments, is left out.
+pub fn read_datagrams(bytes: &[u8]) -> anyhow::Result<Vec<Datagram>> {
+    let packets = match bytes.get(0..4) {
+        Some(magic) if u32::from_le_bytes(magic.try_into()?) == SECTION_HEADER_BLOCK => {
+            read_pcapng(bytes)?
+        }
+        Some(_) => read_pcap(bytes)?,
+        None => return Err(anyhow!("capture is too short")),
+    };
+
+    let mut datagrams = vec![];
+    for (link_type, packet) in packets {
+        let ipv4_packet = match link_type {
+            LINKTYPE_ETHERNET => ethernet_payload(packet),
+            LINKTYPE_LINUX_SLL => packet
+                .get(16..)
+                .filter(|_| packet.get(14..16) == Some(&ETHERTYPE_IPV4.to_be_bytes())),
+            LINKTYPE_RAW | LINKTYPE_IPV4 => Some(packet),
+            _ => return Err(anyhow!("link type {link_type} is not supported")),
+        };
+        if let Some(datagram) = ipv4_packet.and_then(udp_datagram) {
+            datagrams.push(datagram);
+        }
+    }
+
+    Ok(datagrams)
+}
+
+// the packets of the capture with the link type of their interface
+fn read_pcapng(bytes: &[u8]) -> anyhow::Result<Vec<(u16, &[u8])>> {
+    let mut packets = vec![];
+    let mut link_types: Vec<u16> = vec![];
+    let mut big_endian = false;
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let block_type = read_u32(bytes, offset, big_endian)?;
+        if block_type == SECTION_HEADER_BLOCK {
+            // a new section, which may be of another byte order
+            big_endian = match read_u32(bytes, offset + 8, false)? {
+                BYTE_ORDER_MAGIC => false,
+                m if m.swap_bytes() == BYTE_ORDER_MAGIC => true,
+                _ => return Err(anyhow!("invalid byte order magic at {offset}")),
+            };
+            link_types.clear();
+        }
+        let total_len = read_u32(bytes, offset + 4, big_endian)? as usize;
+        let block = bytes
+            .get(offset..offset + total_len)
+            .filter(|_| total_len >= 12)
+            .ok_or(anyhow!("truncated block at {offset}"))?;
+
+        match block_type {
+            INTERFACE_DESCRIPTION_BLOCK => link_types.push(read_u16(block, 8, big_endian)?),
+            ENHANCED_PACKET_BLOCK => {
+                let interface = read_u32(block, 8, big_endian)? as usize;
+                let captured_len = read_u32(block, 20, big_endian)? as usize;
+                let link_type = *link_types
+                    .get(interface)
+                    .ok_or(anyhow!("packet of unknown interface {interface}"))?;
+                let packet = block
+                    .get(28..28 + captured_len)
+                    .ok_or(anyhow!("truncated packet at {offset}"))?;
+                packets.push((link_type, packet));
+            }
+            SIMPLE_PACKET_BLOCK => {
+                let original_len = read_u32(block, 8, big_endian)? as usize;
+                let link_type = *link_types
+                    .first()
+                    .ok_or(anyhow!("packet of unknown interface 0"))?;
+                // the packet is truncated to the snap length, if any
+                let end = (12 + original_len).min(block.len().saturating_sub(4));
+                let packet = block
+                    .get(12..end)
+                    .ok_or(anyhow!("truncated packet at {offset}"))?;
+                packets.push((link_type, packet));
+            }
+            _ => (),
+        }
+        offset += total_len;
+    }
+
+    Ok(packets)
+}
+
+fn read_pcap(bytes: &[u8]) -> anyhow::Result<Vec<(u16, &[u8])>> {
+    let big_endian = match read_u32(bytes, 0, false)? {
+        PCAP_MAGIC | PCAP_MAGIC_NS => false,
+        m if m.swap_bytes() == PCAP_MAGIC || m.swap_bytes() == PCAP_MAGIC_NS => true,
+        _ => return Err(anyhow!("neither a pcap nor a pcapng capture")),
+    };
+    let link_type = read_u32(bytes, 20, big_endian)? as u16;
+
+    let mut packets = vec![];
+    let mut offset = PCAP_HEADER_LEN;
+    while offset < bytes.len() {
+        let captured_len = read_u32(bytes, offset + 8, big_endian)? as usize;
+        let start = offset + PCAP_RECORD_HEADER_LEN;
+        let packet = bytes
+            .get(start..start + captured_len)
+            .ok_or(anyhow!("truncated packet at {offset}"))?;
+        packets.push((link_type, packet));
+        offset = start + captured_len;
+    }
+
+    Ok(packets)
+}
+
+fn read_u32(bytes: &[u8], at: usize, big_endian: bool) -> anyhow::Result<u32> {
+    let b: [u8; 4] = bytes
+        .get(at..at + 4)
+        .ok_or(anyhow!("truncated capture at {at}"))?
+        .try_into()?;
+    Ok(match big_endian {
+        true => u32::from_be_bytes(b),
+        false => u32::from_le_bytes(b),
+    })
+}
+
+fn read_u16(bytes: &[u8], at: usize, big_endian: bool) -> anyhow::Result<u16> {
+    let b: [u8; 2] = bytes
+        .get(at..at + 2)
+        .ok_or(anyhow!("truncated capture at {at}"))?
+        .try_into()?;
+    Ok(match big_endian {
+        true => u16::from_be_bytes(b),
+        false => u16::from_le_bytes(b),
+    })
+}
+
+// the IPv4 packet of the frame, a VLAN tag is skipped
+fn ethernet_payload(frame: &[u8]) -> Option<&[u8]> {
+    let mut ethertype_at = 12;
+    if frame.get(12..14)? == ETHERTYPE_VLAN.to_be_bytes() {
+        ethertype_at += 4;
+    }
+    if frame.get(ethertype_at..ethertype_at + 2)? != ETHERTYPE_IPV4.to_be_bytes() {
+        return None;
+    }
+    frame.get(ethertype_at + 2..)
+}
+
+fn udp_datagram(packet: &[u8]) -> Option<Datagram> {
+    // version 4 and protocol udp only
+    if packet.first()? >> 4 != 4 || *packet.get(9)? != 17 {
+        return None;
+    }
+    // a fragment can't be told apart without reassembling the packet
+    let fragment = u16::from_be_bytes(packet.get(6..8)?.try_into().ok()?);
+    if fragment & 0x3fff != 0 {
+        log::debug!("leaving out IP fragment of the capture");
+        return None;
+    }
+    let header_len = ((packet[0] & 0x0f) as usize) * 4;
+    let total_len = u16::from_be_bytes(packet.get(2..4)?.try_into().ok()?) as usize;
+    // the ethernet padding is not part of the packet
+    let packet = packet.get(..total_len)?;
+    let udp = packet.get(header_len..)?;
+    let udp_len = u16::from_be_bytes(udp.get(4..6)?.try_into().ok()?) as usize;
+
+    let ip = |at: usize| -> Option<Ipv4Addr> {
+        Some(Ipv4Addr::from(
+            <[u8; 4]>::try_from(packet.get(at..at + 4)?).ok()?,
+        ))
+    };
+    let port = |at: usize| -> Option<u16> {
+        Some(u16::from_be_bytes(udp.get(at..at + 2)?.try_into().ok()?))
+    };
+    Some(Datagram {
+        src: SocketAddrV4::new(ip(12)?, port(0)?),
+        dst: SocketAddrV4::new(ip(16)?, port(2)?),
+        payload: udp.get(UDP_HEADER_LEN..udp_len)?.into(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::{collection, prelude::*};
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn write_datagram_should_synthesize_ipv4_udp_packet() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("station.pcapng");
+        let mut writer = PcapngWriter::create(&path, 1 << 20, 1).unwrap();
+        let src = SocketAddrV4::new(Ipv4Addr::new(192, 168, 0, 10), 50000);
+        let dst = SocketAddrV4::new(Ipv4Addr::new(192, 168, 0, 20), 34964);
+        writer
+            .write_datagram(src, dst, &[0x04, 0x00, 0x20])
+            .unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        // section header, interface description and a single packet
+        assert_eq!(bytes[0..4], SECTION_HEADER_BLOCK.to_le_bytes());
+        assert_eq!(bytes[28..32], INTERFACE_DESCRIPTION_BLOCK.to_le_bytes());
+        assert_eq!(bytes[36..38], LINKTYPE_IPV4.to_le_bytes());
+        let epb = &bytes[48..];
+        assert_eq!(epb[0..4], ENHANCED_PACKET_BLOCK.to_le_bytes());
+        assert_eq!(epb.len(), 32 + 32);
+        assert_eq!(epb[20..24], 31u32.to_le_bytes());
+
+        let packet = &epb[28..28 + 31];
+        assert_eq!(ipv4_checksum(&packet[..IPV4_HEADER_LEN]), 0);
+        assert_eq!(packet[12..16], [192, 168, 0, 10]);
+        assert_eq!(packet[22..24], 34964u16.to_be_bytes());
+        assert_eq!(packet[28..], [0x04, 0x00, 0x20]);
+    }
+
+    #[test]
+    fn write_datagram_should_rotate_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("station.pcapng");
+        // room for 2 packets per file
+        let mut writer = PcapngWriter::create(&path, 48 + 2 * 64, 3).unwrap();
+        let src = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 50000);
+        let dst = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 34964);
+        for i in 0..7 {
+            writer.write_datagram(src, dst, &[i; 3]).unwrap();
+        }
+
+        let mut files: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            ["station.pcapng", "station.pcapng.1", "station.pcapng.2"]
+        );
+        // the latest packet is in the current file, the one before in the
+        // most recent of the rotated files
+        assert_eq!(fs::read(&path).unwrap().len(), 48 + 64);
+        let rotated = fs::read(dir.path().join("station.pcapng.1")).unwrap();
+        assert_eq!(rotated[48 + 64 + 56..][..3], [5; 3]);
+
+        // the capture of the previous session is rotated as well
+        drop(writer);
+        PcapngWriter::create(&path, 48 + 2 * 64, 3).unwrap();
+        assert_eq!(fs::read(&path).unwrap().len(), 48);
+        assert_eq!(
+            fs::read(dir.path().join("station.pcapng.1")).unwrap().len(),
+            48 + 64
+        );
+    }
+
+    #[test]
+    fn read_datagrams_should_read_pcapng_and_pcap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("station.pcapng");
+        let mut writer = PcapngWriter::create(&path, 1 << 20, 1).unwrap();
+        let src = SocketAddrV4::new(Ipv4Addr::new(192, 168, 0, 10), 50000);
+        let dst = SocketAddrV4::new(Ipv4Addr::new(192, 168, 0, 20), 34964);
+        writer.write_datagram(src, dst, &[0x04, 0x00]).unwrap();
+        writer
+            .write_datagram(dst, src, &[0x04, 0x02, 0x01])
+            .unwrap();
+
+        let datagrams = read_datagrams(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(datagrams.len(), 2);
+        assert_eq!((datagrams[0].src, datagrams[0].dst), (src, dst));
+        assert_eq!(&datagrams[1].payload[..], &[0x04, 0x02, 0x01]);
+
+        // the same datagram in an ethernet frame, VLAN tagged and padded
+        let mut frame = vec![0x00; 12];
+        frame.extend(ETHERTYPE_VLAN.to_be_bytes());
+        frame.extend([0x00, 0x00]);
+        frame.extend(ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend(ipv4_udp_packet(src, dst, 1, &[0x04, 0x00]));
+        frame.resize(64, 0x00);
+        let mut pcap = vec![];
+        pcap.extend(PCAP_MAGIC.to_be_bytes());
+        pcap.extend(2u16.to_be_bytes());
+        pcap.extend(4u16.to_be_bytes());
+        pcap.extend([0x00; 8]);
+        pcap.extend(SNAP_LEN.to_be_bytes());
+        pcap.extend((LINKTYPE_ETHERNET as u32).to_be_bytes());
+        pcap.extend([0x00; 8]);
+        pcap.extend((frame.len() as u32).to_be_bytes());
+        pcap.extend((frame.len() as u32).to_be_bytes());
+        pcap.extend(&frame);
+
+        let datagrams = read_datagrams(&pcap).unwrap();
+        assert_eq!(datagrams.len(), 1);
+        assert_eq!((datagrams[0].src, datagrams[0].dst), (src, dst));
+        assert_eq!(&datagrams[0].payload[..], &[0x04, 0x00]);
+    }
+
+    // section header and interface description as written by the writer
+    fn get_header() -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("station.pcapng");
+        PcapngWriter::create(&path, 1 << 20, 1).unwrap();
+        fs::read(&path).unwrap()
+    }
+
+    #[test]
+    fn read_datagrams_should_reject_short_simple_packet_block() {
+        for total_len in 12u32..16 {
+            let mut capture = get_header();
+            capture.extend(SIMPLE_PACKET_BLOCK.to_le_bytes());
+            capture.extend(total_len.to_le_bytes());
+            capture.extend(vec![0xff; total_len as usize - 8]);
+
+            assert!(read_datagrams(&capture).is_err());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn read_datagrams_should_not_panic(
+            block_type in prop_oneof![
+                Just(SIMPLE_PACKET_BLOCK),
+                Just(ENHANCED_PACKET_BLOCK),
+                Just(INTERFACE_DESCRIPTION_BLOCK),
+                any::<u32>(),
+            ],
+            total_len in 0u32..64,
+            body in collection::vec(any::<u8>(), 0..64),
+            flip in any::<usize>(),
+        ) {
+            let mut capture = get_header();
+            capture.extend(block_type.to_le_bytes());
+            capture.extend(total_len.to_le_bytes());
+            capture.extend(body);
+
+            let _ = read_datagrams(&capture);
+            for len in 0..capture.len() {
+                let _ = read_datagrams(&capture[..len]);
+            }
+            let i = flip % capture.len();
+            capture[i] = !capture[i];
+            let _ = read_datagrams(&capture);
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use super::{pcapng, TransportClient};
+use crate::error::{self, TransportError};
+use crate::protocol::{BlockHeaderType, PacketType, DCERPC_VERSION, INTERFACE};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    convert::Infallible,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    ops::Range,
+};
+use uuid::Uuid;
+
+// DCE/RPC header fields, see `DceRpcPacket`
+const DCERPC_HEADER_LEN: usize = 80;
+const DCERPC_OBJECT: Range<usize> = 8..24;
+const DCERPC_INTERFACE: Range<usize> = 24..40;
+const DCERPC_ACTIVITY: Range<usize> = 40..56;
+const DCERPC_SEQ_NUM: Range<usize> = 64..68;
+const DCERPC_FRAGMENT_NUM: Range<usize> = 76..78;
+// context handle of the endpoint mapper request, see `DceRpcEpmRequest`
+const EPM_HANDLE: Range<usize> = 52..72;
+// PNIO block header type, then the fields of the blocks relative to the body
+const PNIO_BLOCK_TYPE: Range<usize> = 20..22;
+const PNIO_SEQ_NUM: Range<usize> = 26..28;
+const PNIO_AR_UUID: Range<usize> = 28..44;
+const PNIO_SESSION_KEY: Range<usize> = 44..46;
+const PNIO_INITIATOR_OBJECT: Range<usize> = 52..68;
+
+/// Direction tells if the datagram was sent to or received from the station
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Exchange is a datagram of the transcript, hex encoded in JSON
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Exchange {
+    pub direction: Direction,
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
+    pub data: Box<[u8]>,
+}
+
+fn serialize_hex<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(value))
+}
+
+fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Box<[u8]>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    hex::decode(s.replace(' ', ""))
+        .map(Vec::into_boxed_slice)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Transcript is the traffic between the gateway and a station, in the order
+/// it was sent and received
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Transcript {
+    pub exchanges: Vec<Exchange>,
+}
+
+impl Transcript {
+    pub fn from_json(content: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// from_capture takes the udp datagrams between the station and its
+    /// client out of a pcap or pcapng capture, e.g. the one written by
+    /// `CaptureClient`. The client is the sender of the first datagram to the
+    /// station, the traffic of any other host is left out.
+    pub fn from_capture(bytes: &[u8], station: Ipv4Addr) -> anyhow::Result<Self> {
+        let datagrams = pcapng::read_datagrams(bytes)?;
+        let client = datagrams
+            .iter()
+            .find(|d| *d.dst.ip() == station)
+            .map(|d| d.src)
+            .ok_or(anyhow!("no traffic of station {station} in the capture"))?;
+
+        let exchanges: Vec<Exchange> = datagrams
+            .into_iter()
+            .filter_map(|datagram| {
+                let direction = match (datagram.src, datagram.dst) {
+                    (src, dst) if src == client && *dst.ip() == station => Direction::Sent,
+                    (src, dst) if *src.ip() == station && dst == client => Direction::Received,
+                    _ => return None,
+                };
+                Some(Exchange {
+                    direction,
+                    data: datagram.payload,
+                })
+            })
+            .collect();
+
+        Ok(Self { exchanges })
+    }
+}
+
+// the recorded value of a field and the value sent in its place
+type Replaced = HashMap<(Field, Box<[u8]>), Box<[u8]>>;
+
+/// ReplayClient plays the station of a transcript. The requests sent must be
+/// those of the transcript, apart from the UUIDs and the sequence numbers
+/// which differ from one run to another, the recorded responses are returned
+/// with them replaced by those of the requests sent. Nothing to receive
+/// before the next recorded request is a receive timeout.
+#[derive(Debug)]
+pub struct ReplayClient {
+    exchanges: Vec<Exchange>,
+    next: Cell<usize>,
+    dst_socket_addr: RefCell<SocketAddr>,
+    replaced: RefCell<Replaced>,
+    dropped_packets: Cell<u64>,
+}
+
+impl ReplayClient {
+    pub fn new(transcript: Transcript, dst_socket_addr: SocketAddr) -> Self {
+        Self {
+            exchanges: transcript.exchanges,
+            next: Cell::new(0),
+            dst_socket_addr: RefCell::new(dst_socket_addr),
+            replaced: RefCell::new(HashMap::new()),
+            dropped_packets: Cell::new(0),
+        }
+    }
+
+    /// remaining is the number of datagrams of the transcript not replayed yet
+    pub fn remaining(&self) -> usize {
+        self.exchanges.len() - self.next.get()
+    }
+}
+
+#[async_trait(?Send)]
+impl TransportClient for ReplayClient {
+    async fn send(&self, data: Box<[u8]>) -> error::Result<usize> {
+        let n = self.next.get();
+        let recorded = match self.exchanges.get(n) {
+            Some(e) if e.direction == Direction::Sent => &e.data,
+            Some(_) => {
+                return Err(anyhow!(
+                    "datagram {n} of the transcript is a response, not the request {}",
+                    hex::encode(&data)
+                )
+                .into())
+            }
+            None => {
+                return Err(anyhow!(
+                    "transcript is over, the request {} is not recorded",
+                    hex::encode(&data)
+                )
+                .into())
+            }
+        };
+
+        let fields = volatile_fields(recorded);
+        if fields != volatile_fields(&data) || mask(recorded, &fields) != mask(&data, &fields) {
+            return Err(anyhow!(
+                "request does not match datagram {n} of the transcript\nrecorded: {}\nsent:     {}",
+                hex::encode(recorded),
+                hex::encode(&data)
+            )
+            .into());
+        }
+
+        let mut replaced = self.replaced.borrow_mut();
+        for (field, range) in fields {
+            replaced.insert((field, recorded[range.clone()].into()), data[range].into());
+        }
+        self.next.set(n + 1);
+
+        Ok(data.len())
+    }
+
+    async fn receive(&self) -> error::Result<Box<[u8]>> {
+        let n = self.next.get();
+        let recorded = match self.exchanges.get(n) {
+            Some(e) if e.direction == Direction::Received => &e.data,
+            // the station was waiting for a request, as a real one would
+            _ => return Err(TransportError::Timeout.into()),
+        };
+        self.next.set(n + 1);
+
+        let mut data = recorded.clone();
+        let replaced = self.replaced.borrow();
+        for (field, range) in volatile_fields(recorded) {
+            if let Some(value) = replaced.get(&(field, recorded[range.clone()].into())) {
+                data[range].copy_from_slice(value);
+            }
+        }
+
+        Ok(data)
+    }
+
+    fn get_dst_conn_details(&self) -> anyhow::Result<(IpAddr, u16), Infallible> {
+        Ok((
+            self.dst_socket_addr.borrow().ip(),
+            self.dst_socket_addr.borrow().port(),
+        ))
+    }
+
+    fn update_dest(&self, dst_ip: Ipv4Addr, dst_udpport: u16) -> anyhow::Result<()> {
+        self.dst_socket_addr
+            .replace(SocketAddr::from((dst_ip, dst_udpport)));
+
+        Ok(())
+    }
+
+    fn debug(&self) -> String {
+        format!(
+            "replay of {} ({}/{})",
+            self.dst_socket_addr.borrow(),
+            self.next.get(),
+            self.exchanges.len()
+        )
+    }
+
+    fn dropped_packets(&self) -> &Cell<u64> {
+        &self.dropped_packets
+    }
+}
+
+// Field is a part of a datagram which differs from one run to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Field {
+    Object,
+    Activity,
+    SeqNum,
+    EpmHandle,
+    PnioSeqNum,
+    ArUuid,
+    SessionKey,
+    InitiatorObject,
+}
+
+// the UUIDs and the sequence numbers of a DCE/RPC packet and of the PNIO
+// blocks it carries, nothing if it's not a DCE/RPC packet
+fn volatile_fields(datagram: &[u8]) -> Vec<(Field, Range<usize>)> {
+    if datagram.len() < DCERPC_HEADER_LEN || datagram[0] != DCERPC_VERSION {
+        return vec![];
+    }
+    let mut fields = vec![
+        (Field::Object, DCERPC_OBJECT),
+        (Field::Activity, DCERPC_ACTIVITY),
+        (Field::SeqNum, DCERPC_SEQ_NUM),
+    ];
+    // the body of the following fragments is not told apart
+    if datagram[DCERPC_FRAGMENT_NUM] != [0x00, 0x00] {
+        return fields;
+    }
+
+    let body = |range: Range<usize>| DCERPC_HEADER_LEN + range.start..DCERPC_HEADER_LEN + range.end;
+    let epm_interface = Uuid::parse_str(INTERFACE).unwrap().to_bytes_le();
+    if datagram[DCERPC_INTERFACE] == epm_interface {
+        if PacketType::from_u8(datagram[1]) == Some(PacketType::Request) {
+            fields.push((Field::EpmHandle, body(EPM_HANDLE)));
+        }
+    } else {
+        let block_type = datagram
+            .get(body(PNIO_BLOCK_TYPE))
+            .and_then(|b| BlockHeaderType::from_u16(u16::from_be_bytes([b[0], b[1]])));
+        match block_type {
+            Some(
+                BlockHeaderType::IodReadReqType
+                | BlockHeaderType::IodReadResType
+                | BlockHeaderType::IodWriteReqType
+                | BlockHeaderType::IodWriteResType,
+            ) => {
+                fields.push((Field::PnioSeqNum, body(PNIO_SEQ_NUM)));
+                fields.push((Field::ArUuid, body(PNIO_AR_UUID)));
+            }
+            Some(BlockHeaderType::ArBlockReqType) => {
+                fields.push((Field::ArUuid, body(PNIO_AR_UUID)));
+                fields.push((Field::SessionKey, body(PNIO_SESSION_KEY)));
+                fields.push((Field::InitiatorObject, body(PNIO_INITIATOR_OBJECT)));
+            }
+            Some(
+                BlockHeaderType::ArBlockResType
+                | BlockHeaderType::ReleaseBlockReqType
+                | BlockHeaderType::ReleaseBlockResType,
+            ) => {
+                fields.push((Field::ArUuid, body(PNIO_AR_UUID)));
+                fields.push((Field::SessionKey, body(PNIO_SESSION_KEY)));
+            }
+            None => (),
+        }
+    }
+
+    fields.retain(|(_, range)| range.end <= datagram.len());
+    fields
+}
+
+// the datagram with the fields zeroed, to be compared with another one
+fn mask(datagram: &[u8], fields: &[(Field, Range<usize>)]) -> Vec<u8> {
+    let mut masked = datagram.to_vec();
+    for (_, range) in fields {
+        masked[range.clone()].fill(0x00);
+    }
+    masked
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        client::lookup::LookupClient,
+        device::{pnio_device::PnioDevice, station_session::StationSession},
+        hart::HartDecoderRegistry,
+        simulator::{SimulatedChannel, SimulatedHartCommand, Simulator, SimulatorConfig},
+        transport::CaptureSettings,
+    };
+    use std::{fs, rc::Rc};
+
+    // reads command 9 of the channel of the station the way the worker does
+    async fn read_command_9(session: Rc<StationSession>) -> String {
+        let device = PnioDevice::new(session, 1, 1, 0x04, 80, 81, "device".to_string());
+        device.session.connect_req().await.unwrap();
+        device.send_common_write_req(80, 0, None).await.unwrap();
+        device.send_common_read_req(81, 0).await.unwrap();
+        device
+            .send_common_write_req(80, 9, Some(&[0x00]))
+            .await
+            .unwrap();
+        let (length, bytes) = device.send_common_read_req(81, 9).await.unwrap();
+        let response = HartDecoderRegistry::default()
+            .decode(9, device.device_type_code(), &bytes[..length as usize])
+            .unwrap();
+        device.session.release_req().await.unwrap();
+
+        serde_json::to_string(&response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn replay_should_repeat_the_captured_traffic() {
+        let dir = tempfile::tempdir().unwrap();
+        let simulator = Simulator::start(SimulatorConfig {
+            bind_address: "127.0.0.1:0".to_string(),
+            interfaces: vec![Default::default()],
+            channels: vec![SimulatedChannel {
+                commands: vec![SimulatedHartCommand {
+                    number: 9,
+                    responses: vec![vec![
+                        0x00, 0x00, 0x00, 0x00, 0x41, 0x08, 0x3f, 0xc0, 0x00, 0x00, 0xc0, 0x00,
+                        0x00, 0x00, 0x00,
+                    ]],
+                }],
+                ..Default::default()
+            }],
+            records: vec![],
+        })
+        .unwrap();
+        let target = (
+            "6ES7 155-6AU01-0BN0",
+            "127.0.0.1",
+            simulator.local_addr().port(),
+        );
+
+        let mut lookup_client = LookupClient::new();
+        lookup_client.capture = Some(CaptureSettings {
+            dir: dir.path().to_path_buf(),
+            max_file_size: 1 << 20,
+            max_files: 1,
+        });
+        let session = lookup_client
+            .lookup(Ipv4Addr::LOCALHOST, target)
+            .await
+            .unwrap();
+        let captured = read_command_9(Rc::new(session)).await;
+        drop(simulator);
+
+        // the station is gone, the transcript answers in its place
+        let capture = fs::read(dir.path().join("127.0.0.1.pcapng")).unwrap();
+        let transcript = Transcript::from_capture(&capture, Ipv4Addr::LOCALHOST).unwrap();
+        let transcript = Transcript::from_json(&transcript.to_json().unwrap()).unwrap();
+        let exchanges = transcript.exchanges.len();
+        let replay_client = ReplayClient::new(transcript, "127.0.0.1:34964".parse().unwrap());
+        let session = LookupClient::new()
+            .lookup_with(Box::new(replay_client), target)
+            .await
+            .unwrap();
+        let session = Rc::new(session);
+        let replayed = read_command_9(session.clone()).await;
+
+        assert_eq!(replayed, captured);
+        assert!(replayed.contains("1.5"));
+        assert_eq!(session.transport_client.dropped_packets().get(), 0);
+        // every datagram of the transcript is replayed
+        assert!(session
+            .transport_client
+            .debug()
+            .ends_with(&format!("({exchanges}/{exchanges})")));
+    }
+
+    #[tokio::test]
+    async fn replay_should_reject_other_requests() {
+        let request = |activity: u8, opnum: u8| {
+            let mut datagram = vec![DCERPC_VERSION, 0x00];
+            datagram.resize(DCERPC_HEADER_LEN, 0x01);
+            datagram[DCERPC_ACTIVITY].fill(activity);
+            datagram[DCERPC_FRAGMENT_NUM].fill(0x00);
+            datagram[68] = opnum;
+            datagram.into_boxed_slice()
+        };
+        let transcript = Transcript {
+            exchanges: vec![
+                Exchange {
+                    direction: Direction::Sent,
+                    data: request(0x01, 0x02),
+                },
+                Exchange {
+                    direction: Direction::Received,
+                    data: request(0x01, 0x02),
+                },
+            ],
+        };
+        let client = ReplayClient::new(transcript, "127.0.0.1:34964".parse().unwrap());
+
+        // another operation
+        let err = client.send(request(0x01, 0x03)).await.unwrap_err();
+        assert!(err.to_string().contains("does not match datagram 0"));
+
+        // the UUIDs and the sequence numbers are replaced by those sent
+        client.send(request(0x07, 0x02)).await.unwrap();
+        let response = client.receive().await.unwrap();
+        assert_eq!(response[DCERPC_ACTIVITY], [0x07; 16]);
+        assert_eq!(response[DCERPC_INTERFACE], [0x01; 16]);
+
+        // the transcript is over
+        assert!(client.receive().await.unwrap_err().is_timeout());
+        assert_eq!(client.remaining(), 0);
+    }
+}
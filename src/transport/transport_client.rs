@@ -1,11 +1,152 @@
+use crate::{
+    error,
+    protocol::{
+        DceRpcError, DceRpcFragments, DceRpcPacket, Decode, Encode, PacketType,
+        DCERPC_MAX_FRAGMENT_DATA_LEN, DCERPC_MAX_PINGS,
+    },
+};
+use anyhow::anyhow;
+use async_trait::async_trait;
 use core::fmt::Debug;
-use std::{convert::Infallible, net::IpAddr};
+use std::{
+    cell::Cell,
+    convert::Infallible,
+    io,
+    net::{IpAddr, Ipv4Addr},
+};
 
+/// TransportClient is async so that a single runtime can wait on the responses
+/// of many stations at once, the futures are not `Send` as the devices are
+/// polled on the thread of the runtime.
+#[async_trait(?Send)]
 pub trait TransportClient {
-    fn send(&self, data: Box<[u8]>) -> anyhow::Result<usize>;
-    fn receive(&self) -> anyhow::Result<Box<[u8]>>;
+    async fn send(&self, data: Box<[u8]>) -> error::Result<usize>;
+    /// receive fails with `TransportError::Timeout` if nothing arrives in time
+    async fn receive(&self) -> error::Result<Box<[u8]>>;
     fn get_dst_conn_details(&self) -> anyhow::Result<(IpAddr, u16), Infallible>;
+    /// update_dest changes the destination, e.g. from the endpoint mapper to
+    /// the PNIO port of the station
+    fn update_dest(&self, _dst_ip: Ipv4Addr, _dst_udpport: u16) -> anyhow::Result<()> {
+        Err(anyhow!(
+            "the destination of {} cannot be changed",
+            self.debug()
+        ))
+    }
     fn debug(&self) -> String;
+    /// dropped_packets counts the packets received but not belonging to the
+    /// call waited for, e.g. a late response or a packet of another host
+    fn dropped_packets(&self) -> &Cell<u64>;
+
+    /// drop_packet counts the packet as dropped instead of decoding it
+    fn drop_packet(&self, reason: &str) {
+        log::debug!("dropping packet, {reason}");
+        let dropped_packets = self.dropped_packets();
+        dropped_packets.set(dropped_packets.get() + 1);
+    }
+
+    /// send_dcerpc sends the packet, fragmented if the body does not fit
+    /// into a single datagram
+    async fn send_dcerpc(&self, packet: &DceRpcPacket) -> error::Result<()> {
+        for fragment in packet.fragment(DCERPC_MAX_FRAGMENT_DATA_LEN) {
+            self.send(fragment.to_bytes()?.into_boxed_slice()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// receive_dcerpc receives a packet, a fragmented body is reassembled
+    /// before it's returned, a datagram that is not a DCE/RPC packet is dropped
+    async fn receive_dcerpc(&self) -> error::Result<DceRpcPacket> {
+        let mut fragments = DceRpcFragments::default();
+        loop {
+            let packet = match DceRpcPacket::from_bytes(&self.receive().await?) {
+                Ok(packet) => packet,
+                Err(err) => {
+                    self.drop_packet(&format!("undecodable datagram, {err}"));
+                    continue;
+                }
+            };
+            if !packet.is_fragment() {
+                return Ok(packet);
+            }
+
+            log::debug!("received fragment {}", packet.get_fragment_num());
+            if !packet.is_no_fack() && !packet.is_last_fragment() {
+                self.send(packet.fack().to_bytes()?.into_boxed_slice())
+                    .await?;
+            }
+
+            if let Some(packet) = fragments.push(packet) {
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// call_dcerpc sends the request and waits for its response, the server is
+    /// pinged when the response is overdue instead of failing the call right
+    /// away. Reject and fault are returned as `Error::DceRpc`.
+    async fn call_dcerpc(&self, request: &DceRpcPacket) -> error::Result<DceRpcPacket> {
+        self.send_dcerpc(request).await?;
+
+        let mut pings = 0;
+        loop {
+            let packet = match self.receive_dcerpc().await {
+                Ok(p) => p,
+                Err(err) if err.is_timeout() => {
+                    if pings >= DCERPC_MAX_PINGS {
+                        return Err(DceRpcError::Timeout.into());
+                    }
+                    pings += 1;
+                    log::debug!("response overdue, pinging the server ({pings})");
+                    self.send(request.ping().to_bytes()?.into_boxed_slice())
+                        .await?;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            if packet.activity != request.activity {
+                self.drop_packet("activity of another call");
+                continue;
+            }
+            if packet.seq_num != request.seq_num {
+                self.drop_packet("sequence number of another call");
+                continue;
+            }
+
+            match packet.get_packet_type() {
+                // still working on the call, keep on waiting as long as the
+                // server answers the pings
+                Some(PacketType::Working) => {
+                    log::debug!("server is working on the call");
+                    pings = 0;
+                }
+                // the server lost the request, it's sent once again
+                Some(PacketType::Nocall) if pings > 0 => {
+                    log::debug!("server does not know the call, sending it again");
+                    self.send_dcerpc(request).await?;
+                }
+                Some(PacketType::Fack) | Some(PacketType::Ack) => (),
+                Some(PacketType::Response) if packet.opnum != request.opnum => {
+                    self.drop_packet("response to another operation");
+                }
+                _ => return Ok(packet.into_result()?),
+            }
+        }
+    }
+}
+
+/// is_timeout tells if the error is a receive timeout of the socket, e.g. of
+/// a `FrameTransport`
+pub fn is_timeout(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<io::Error>())
+        .any(|e| {
+            matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            )
+        })
 }
 
 impl Debug for dyn TransportClient {
@@ -13,3 +154,283 @@ impl Debug for dyn TransportClient {
         write!(f, "{}", self.debug())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        error::Error,
+        protocol::{InterfaceVersion, NcaStatus, OpNum},
+    };
+    use std::{cell::RefCell, collections::VecDeque};
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    struct InMemoryClient {
+        received: RefCell<VecDeque<Box<[u8]>>>,
+        sent: RefCell<Vec<Box<[u8]>>>,
+        dropped_packets: Cell<u64>,
+    }
+
+    #[async_trait(?Send)]
+    impl TransportClient for InMemoryClient {
+        async fn send(&self, data: Box<[u8]>) -> error::Result<usize> {
+            let len = data.len();
+            self.sent.borrow_mut().push(data);
+            Ok(len)
+        }
+
+        async fn receive(&self) -> error::Result<Box<[u8]>> {
+            self.received
+                .borrow_mut()
+                .pop_front()
+                .ok_or(io::Error::from(io::ErrorKind::WouldBlock).into())
+        }
+
+        fn get_dst_conn_details(&self) -> anyhow::Result<(IpAddr, u16), Infallible> {
+            Ok((IpAddr::from([127, 0, 0, 1]), 34964))
+        }
+
+        fn debug(&self) -> String {
+            "in memory".to_string()
+        }
+
+        fn dropped_packets(&self) -> &Cell<u64> {
+            &self.dropped_packets
+        }
+    }
+
+    fn get_packet(data_len: usize) -> DceRpcPacket {
+        DceRpcPacket::new(
+            PacketType::Response,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            InterfaceVersion::ReadWrite,
+            Uuid::new_v4(),
+            7,
+            OpNum::Read,
+            (0..data_len).map(|i| i as u8).collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn send_dcerpc_should_fragment_large_body() {
+        let client = InMemoryClient::default();
+        client.send_dcerpc(&get_packet(3000)).await.unwrap();
+
+        let sent = client.sent.borrow();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[0].len(), 80 + DCERPC_MAX_FRAGMENT_DATA_LEN);
+        assert_eq!(sent[2].len(), 80 + 3000 - 2 * DCERPC_MAX_FRAGMENT_DATA_LEN);
+        // fragment flag, last fragment only on the last one
+        assert_eq!(sent[0][2] & 0x06, 0x04);
+        assert_eq!(sent[2][2] & 0x06, 0x06);
+        // fragment numbers
+        assert_eq!(sent[1][76..78], [0x01, 0x00]);
+        assert_eq!(sent[2][76..78], [0x02, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn receive_dcerpc_should_reassemble_fragments() {
+        let client = InMemoryClient::default();
+        let packet = get_packet(3000);
+        let data = packet.data.clone();
+
+        // out of order
+        let mut fragments: Vec<Box<[u8]>> = packet
+            .fragment(DCERPC_MAX_FRAGMENT_DATA_LEN)
+            .iter()
+            .map(|f| f.to_bytes().unwrap().into_boxed_slice())
+            .collect();
+        fragments.swap(0, 1);
+        client.received.borrow_mut().extend(fragments);
+
+        let received = client.receive_dcerpc().await.unwrap();
+        assert_eq!(received.data, data);
+        assert!(!received.is_fragment());
+        // fragments were sent with "no fack"
+        assert!(client.sent.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn receive_dcerpc_should_acknowledge_fragment() {
+        let client = InMemoryClient::default();
+        let mut fragments = get_packet(2000).fragment(DCERPC_MAX_FRAGMENT_DATA_LEN);
+        // the sender wants the first fragment to be acknowledged
+        fragments[0].flags1[0] &= !0x08;
+        client.received.borrow_mut().extend(
+            fragments
+                .iter()
+                .map(|f| f.to_bytes().unwrap().into_boxed_slice()),
+        );
+
+        assert_eq!(client.receive_dcerpc().await.unwrap().data.len(), 2000);
+        let sent = client.sent.borrow();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0][1], PacketType::Fack as u8);
+    }
+
+    #[tokio::test]
+    async fn receive_dcerpc_should_fail_on_missing_fragment() {
+        let client = InMemoryClient::default();
+        let fragments = get_packet(3000).fragment(DCERPC_MAX_FRAGMENT_DATA_LEN);
+        client
+            .received
+            .borrow_mut()
+            .push_back(fragments[2].to_bytes().unwrap().into_boxed_slice());
+
+        assert!(client.receive_dcerpc().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn call_dcerpc_should_ping_overdue_response() {
+        let request = get_packet(0).reply(PacketType::Request, Box::new([0x00; 10]));
+        let working = request.reply(PacketType::Working, Box::new([]));
+        let response = request.reply(PacketType::Response, vec![0x01; 4].into());
+
+        // nothing is received for the request, the 1st ping is answered with
+        // "working" and the 2nd with the response
+        let client = PingingClient::default();
+        client.answers.borrow_mut().extend([
+            working.to_bytes().unwrap().into_boxed_slice(),
+            response.to_bytes().unwrap().into_boxed_slice(),
+        ]);
+
+        let received = client.call_dcerpc(&request).await.unwrap();
+        assert_eq!(received.data, vec![0x01; 4].into_boxed_slice());
+
+        let sent = client.client.sent.borrow();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[0][1], PacketType::Request as u8);
+        assert_eq!(sent[1][1], PacketType::Ping as u8);
+        assert_eq!(sent[2][1], PacketType::Ping as u8);
+    }
+
+    #[tokio::test]
+    async fn call_dcerpc_should_keep_waiting_while_server_is_working() {
+        let request = get_packet(0).reply(PacketType::Request, Box::new([0x00; 10]));
+        let working = request.reply(PacketType::Working, Box::new([]));
+        let response = request.reply(PacketType::Response, vec![0x01; 4].into());
+
+        // a long record operation, every ping is answered with "working"
+        // until the response is ready
+        let client = PingingClient::default();
+        let workings = DCERPC_MAX_PINGS as usize + 2;
+        client.answers.borrow_mut().extend(
+            std::iter::repeat_n(&working, workings)
+                .chain([&response])
+                .map(|p| p.to_bytes().unwrap().into_boxed_slice()),
+        );
+
+        let received = client.call_dcerpc(&request).await.unwrap();
+        assert_eq!(received.data, vec![0x01; 4].into_boxed_slice());
+        // the request and a ping per answer
+        assert_eq!(client.client.sent.borrow().len(), 1 + workings + 1);
+    }
+
+    #[tokio::test]
+    async fn call_dcerpc_should_return_typed_reject() {
+        let client = InMemoryClient::default();
+        let request = get_packet(10);
+        let reject = request.reply(PacketType::Reject, 0x1c010002u32.to_le_bytes().into());
+        client
+            .received
+            .borrow_mut()
+            .push_back(reject.to_bytes().unwrap().into_boxed_slice());
+
+        let err = client.call_dcerpc(&request).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DceRpc(DceRpcError::Reject(NcaStatus::OpRngError))
+        ));
+    }
+
+    #[tokio::test]
+    async fn call_dcerpc_should_drop_packets_of_other_calls() {
+        let client = InMemoryClient::default();
+        let request = get_packet(0).reply(PacketType::Request, Box::new([0x00; 10]));
+        let response = request.reply(PacketType::Response, vec![0x01; 4].into());
+
+        // a late response of the previous call, a response of another activity
+        // and a response to another operation arrive first
+        let mut late = request.reply(PacketType::Response, vec![0x02; 4].into());
+        late.seq_num = 6u32.to_le_bytes();
+        let mut foreign = request.reply(PacketType::Response, vec![0x03; 4].into());
+        foreign.activity = *Uuid::new_v4().as_bytes();
+        let mut other_operation = request.reply(PacketType::Response, vec![0x04; 4].into());
+        other_operation.opnum = (OpNum::Write as u16).to_le_bytes();
+        client.received.borrow_mut().extend(
+            [late, foreign, other_operation, response]
+                .iter()
+                .map(|p| p.to_bytes().unwrap().into_boxed_slice()),
+        );
+
+        let received = client.call_dcerpc(&request).await.unwrap();
+        assert_eq!(received.data, vec![0x01; 4].into_boxed_slice());
+        assert_eq!(client.dropped_packets.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn call_dcerpc_should_drop_undecodable_datagrams() {
+        let client = InMemoryClient::default();
+        let request = get_packet(0).reply(PacketType::Request, Box::new([0x00; 10]));
+        let response = request.reply(PacketType::Response, vec![0x01; 4].into());
+
+        // a stray datagram and a truncated response arrive first
+        let truncated: Box<[u8]> = response.to_bytes().unwrap()[..40].into();
+        client.received.borrow_mut().extend([
+            Box::new([0xffu8; 8]) as Box<[u8]>,
+            truncated,
+            response.to_bytes().unwrap().into_boxed_slice(),
+        ]);
+
+        let received = client.call_dcerpc(&request).await.unwrap();
+        assert_eq!(received.data, vec![0x01; 4].into_boxed_slice());
+        assert_eq!(client.dropped_packets.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn call_dcerpc_should_give_up_after_pings() {
+        let client = InMemoryClient::default();
+
+        let err = client.call_dcerpc(&get_packet(10)).await.unwrap_err();
+        assert!(matches!(err, Error::DceRpc(DceRpcError::Timeout)));
+        // the request and the pings
+        assert_eq!(client.sent.borrow().len(), 1 + DCERPC_MAX_PINGS as usize);
+    }
+
+    // PingingClient answers every ping with the next answer
+    #[derive(Default)]
+    struct PingingClient {
+        client: InMemoryClient,
+        answers: RefCell<VecDeque<Box<[u8]>>>,
+    }
+
+    #[async_trait(?Send)]
+    impl TransportClient for PingingClient {
+        async fn send(&self, data: Box<[u8]>) -> error::Result<usize> {
+            if data[1] == PacketType::Ping as u8 {
+                if let Some(answer) = self.answers.borrow_mut().pop_front() {
+                    self.client.received.borrow_mut().push_back(answer);
+                }
+            }
+            self.client.send(data).await
+        }
+
+        async fn receive(&self) -> error::Result<Box<[u8]>> {
+            self.client.receive().await
+        }
+
+        fn get_dst_conn_details(&self) -> anyhow::Result<(IpAddr, u16), Infallible> {
+            self.client.get_dst_conn_details()
+        }
+
+        fn debug(&self) -> String {
+            self.client.debug()
+        }
+
+        fn dropped_packets(&self) -> &Cell<u64> {
+            self.client.dropped_packets()
+        }
+    }
+}
@@ -1,36 +1,35 @@
-use crate::transport::transport_client::TransportClient;
-use anyhow::anyhow;
+use crate::{
+    error::{self, TransportError},
+    transport::transport_client::TransportClient,
+};
+use async_trait::async_trait;
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     convert::Infallible,
-    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     time::Duration,
 };
+use tokio::{net::UdpSocket, time};
 
 #[derive(Debug)]
 pub struct UdpClient {
     socket: UdpSocket,
     dst_socket_addr: RefCell<SocketAddr>,
-}
-
-impl Clone for UdpClient {
-    fn clone(&self) -> Self {
-        Self {
-            socket: self.socket.try_clone().unwrap(),
-            dst_socket_addr: self.dst_socket_addr.clone(),
-        }
-    }
+    dropped_packets: Cell<u64>,
 }
 
 impl UdpClient {
-    pub const SRC_UDP_PORT: u16 = 53212; // just arbitrary port number
-
-    pub fn new(src_ip: Ipv4Addr, dst_ip: Ipv4Addr, dst_udpport: u16) -> anyhow::Result<Self> {
-        let src_udpsocket = format!("{src_ip}:{}", Self::SRC_UDP_PORT);
-        let socket = UdpSocket::bind(src_udpsocket)?;
+    pub const MAX_DATAGRAM_SIZE: usize = 65535;
+    pub const RECEIVE_TIMEOUT: Duration = Duration::from_secs(3);
 
-        let _ = socket.set_read_timeout(Some(Duration::new(3, 0)));
-        let _ = socket.set_write_timeout(Some(Duration::new(3, 0)));
+    /// new binds an ephemeral port of the source ip, so that each station
+    /// session has its own socket shared by the HART channels of the station,
+    /// and the responses of one station are not mixed up with the others
+    pub async fn new(src_ip: Ipv4Addr, dst_ip: Ipv4Addr, dst_udpport: u16) -> error::Result<Self> {
+        let address = SocketAddr::from((src_ip, 0));
+        let socket = UdpSocket::bind(address)
+            .await
+            .map_err(|source| TransportError::Bind { address, source })?;
 
         let dst_ipv4 = IpAddr::V4(dst_ip); // only supports ipv4 for now
         let dst_socket_addr = RefCell::new(SocketAddr::from((dst_ipv4, dst_udpport)));
@@ -38,38 +37,42 @@ impl UdpClient {
         Ok(UdpClient {
             socket,
             dst_socket_addr,
+            dropped_packets: Cell::new(0),
         })
     }
 
-    pub fn update_dest(&self, dst_ip: Ipv4Addr, dst_udpport: u16) -> anyhow::Result<()> {
-        let dst_socket_addr = SocketAddr::from((dst_ip, dst_udpport));
-        self.dst_socket_addr.replace(dst_socket_addr);
-
-        Ok(())
+    pub fn local_addr(&self) -> error::Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
     }
 }
 
+#[async_trait(?Send)]
 impl TransportClient for UdpClient {
-    fn send(&self, data: Box<[u8]>) -> anyhow::Result<usize> {
-        match self
-            .socket
-            .send_to(&data, *self.dst_socket_addr.borrow())
-            .map_err(|err| Err(anyhow!("failed to send packet, error: {}", err)))
-        {
-            Ok(send_resp_size) => Ok(send_resp_size),
-            Err(err) => err,
-        }
+    async fn send(&self, data: Box<[u8]>) -> error::Result<usize> {
+        let dst_socket_addr = *self.dst_socket_addr.borrow();
+        Ok(self.socket.send_to(&data, dst_socket_addr).await?)
     }
 
-    fn receive(&self) -> anyhow::Result<Box<[u8]>> {
-        let mut buf: Vec<u8> = vec![0; 300]; // pre-allocate 300 bytes
-        match self
-            .socket
-            .recv_from(&mut buf)
-            .map_err(|err| Err(anyhow!("failed to receive packet, error: {}", err)))
-        {
-            Ok(_) => Ok(buf.into_boxed_slice()),
-            Err(err) => err,
+    /// receive returns the next datagram of the destination, the datagrams of
+    /// any other host or port are dropped
+    async fn receive(&self) -> error::Result<Box<[u8]>> {
+        // large enough for any udp datagram, trimmed to the received size
+        let mut buf: Vec<u8> = vec![0; Self::MAX_DATAGRAM_SIZE];
+        let deadline = time::Instant::now() + Self::RECEIVE_TIMEOUT;
+        loop {
+            let (size, src_socket_addr) =
+                match time::timeout_at(deadline, self.socket.recv_from(&mut buf)).await {
+                    Ok(received) => received?,
+                    Err(_) => return Err(TransportError::Timeout.into()),
+                };
+
+            if src_socket_addr != *self.dst_socket_addr.borrow() {
+                self.drop_packet(&format!("sent by {src_socket_addr}"));
+                continue;
+            }
+
+            buf.truncate(size);
+            return Ok(buf.into_boxed_slice());
         }
     }
 
@@ -80,6 +83,13 @@ impl TransportClient for UdpClient {
         ))
     }
 
+    fn update_dest(&self, dst_ip: Ipv4Addr, dst_udpport: u16) -> anyhow::Result<()> {
+        let dst_socket_addr = SocketAddr::from((dst_ip, dst_udpport));
+        self.dst_socket_addr.replace(dst_socket_addr);
+
+        Ok(())
+    }
+
     fn debug(&self) -> String {
         let socket = format!(
             "{}:{}",
@@ -89,4 +99,34 @@ impl TransportClient for UdpClient {
         let dst_socket_addr = self.dst_socket_addr.borrow().to_string();
         format!("{} {}", socket, dst_socket_addr)
     }
+
+    fn dropped_packets(&self) -> &Cell<u64> {
+        &self.dropped_packets
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn receive_should_drop_datagrams_of_other_hosts() {
+        let station = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let other = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let station_addr = station.local_addr().unwrap();
+        let client = UdpClient::new(
+            Ipv4Addr::LOCALHOST,
+            Ipv4Addr::LOCALHOST,
+            station_addr.port(),
+        )
+        .await
+        .unwrap();
+        let client_addr = client.socket.local_addr().unwrap();
+
+        other.send_to(&[0x01], client_addr).await.unwrap();
+        station.send_to(&[0x02], client_addr).await.unwrap();
+
+        assert_eq!(&client.receive().await.unwrap()[..], &[0x02]);
+        assert_eq!(client.dropped_packets().get(), 1);
+    }
 }